@@ -22,6 +22,7 @@ use crate::{
         export, import, Context,
     },
     common::{infra::config::USERS, meta, migration},
+    job,
     service::{compact, db, file_list, users},
 };
 
@@ -96,7 +97,7 @@ pub async fn cli() -> Result<bool, anyhow::Error> {
                         .long("to")
                         .value_name("to")
                         .required(true)
-                        .help("migrate to: sqlite, etcd, mysql, postgresql"),
+                        .help("migrate to: sled, sqlite, etcd, mysql, postgresql"),
                 ]),
             clap::Command::new("migrate-dashboards").about("migrate-dashboards"),
             clap::Command::new("migrate-pipeline").about("migrate pipelines")
@@ -116,6 +117,7 @@ pub async fn cli() -> Result<bool, anyhow::Error> {
                         .value_name("file")
                         .help("the parquet file name"),
                 ),
+            clap::Command::new("flush-wal").about("move all the local WAL parquet files to storage, the node should be stopped first"),
             clap::Command::new("migrate-schemas").about("migrate from single row to row per schema version"),
             clap::Command::new("seaorm-rollback").about("rollback SeaORM migration steps")
                 .subcommand(
@@ -273,6 +275,11 @@ pub async fn cli() -> Result<bool, anyhow::Error> {
                 }
             }
         }
+        "flush-wal" => {
+            println!("Running flush WAL files to storage");
+            db::schema::cache().await?;
+            job::files::parquet::move_all_files_to_storage().await?;
+        }
         "import" => {
             import::Import::operator(dataCli::arg_matches(command.clone())).await?;
         }
@@ -79,6 +79,13 @@ impl Context for Export {
             search_type,
             search_event_context,
             use_cache: None,
+            cursor: None,
+            use_cursor: false,
+            streams: vec![],
+            approximate_count: false,
+            use_fts: false,
+            params: Default::default(),
+            force: false,
         };
 
         match SearchService::search("", &c.org, stream_type, None, &req).await {
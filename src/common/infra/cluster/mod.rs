@@ -50,6 +50,7 @@ static QUERIER_BACKGROUND_CONSISTENT_HASH: Lazy<RwBTreeMap<u64, String>> =
 static COMPACTOR_CONSISTENT_HASH: Lazy<RwBTreeMap<u64, String>> = Lazy::new(Default::default);
 static FLATTEN_COMPACTOR_CONSISTENT_HASH: Lazy<RwBTreeMap<u64, String>> =
     Lazy::new(Default::default);
+static INGESTER_CONSISTENT_HASH: Lazy<RwBTreeMap<u64, String>> = Lazy::new(Default::default);
 static NODES_HEALTH_CHECK: Lazy<RwAHashMap<String, usize>> = Lazy::new(Default::default);
 
 pub async fn add_node_to_consistent_hash(node: &Node, role: &Role, group: Option<RoleGroup>) {
@@ -61,6 +62,7 @@ pub async fn add_node_to_consistent_hash(node: &Node, role: &Role, group: Option
         },
         Role::Compactor => COMPACTOR_CONSISTENT_HASH.write().await,
         Role::FlattenCompactor => FLATTEN_COMPACTOR_CONSISTENT_HASH.write().await,
+        Role::Ingester => INGESTER_CONSISTENT_HASH.write().await,
         _ => return,
     };
     let mut h = config::utils::hash::gxhash::new();
@@ -80,6 +82,7 @@ pub async fn remove_node_from_consistent_hash(node: &Node, role: &Role, group: O
         },
         Role::Compactor => COMPACTOR_CONSISTENT_HASH.write().await,
         Role::FlattenCompactor => FLATTEN_COMPACTOR_CONSISTENT_HASH.write().await,
+        Role::Ingester => INGESTER_CONSISTENT_HASH.write().await,
         _ => return,
     };
     let mut h = config::utils::hash::gxhash::new();
@@ -103,6 +106,7 @@ pub async fn get_node_from_consistent_hash(
         },
         Role::Compactor => COMPACTOR_CONSISTENT_HASH.read().await,
         Role::FlattenCompactor => FLATTEN_COMPACTOR_CONSISTENT_HASH.read().await,
+        Role::Ingester => INGESTER_CONSISTENT_HASH.read().await,
         _ => return None,
     };
     if nodes.is_empty() {
@@ -153,6 +157,14 @@ pub async fn print_consistent_hash() -> HashMap<String, HashMap<String, Vec<u64>
     }
     drop(r);
     map.insert("flatten_compactor".to_string(), node_map);
+    let r = INGESTER_CONSISTENT_HASH.read().await;
+    let mut node_map = HashMap::new();
+    for (k, v) in r.iter() {
+        let entry = node_map.entry(v.clone()).or_insert(Vec::new());
+        entry.push(*k);
+    }
+    drop(r);
+    map.insert("ingester".to_string(), node_map);
     map
 }
 
@@ -169,6 +181,7 @@ pub async fn register_and_keep_alive() -> Result<()> {
         add_node_to_consistent_hash(&node, &Role::Querier, Some(RoleGroup::Background)).await;
         add_node_to_consistent_hash(&node, &Role::Compactor, None).await;
         add_node_to_consistent_hash(&node, &Role::FlattenCompactor, None).await;
+        add_node_to_consistent_hash(&node, &Role::Ingester, None).await;
         NODES.write().await.insert(LOCAL_NODE.uuid.clone(), node);
         return Ok(());
     }
@@ -319,6 +332,9 @@ async fn watch_node_list() -> Result<()> {
                         )
                         .await;
                     }
+                    if item_value.is_ingester() {
+                        remove_node_from_consistent_hash(&item_value, &Role::Ingester, None).await;
+                    }
                     NODES.write().await.remove(item_key);
                     continue;
                 }
@@ -346,6 +362,9 @@ async fn watch_node_list() -> Result<()> {
                 if item_value.is_flatten_compactor() {
                     add_node_to_consistent_hash(&item_value, &Role::FlattenCompactor, None).await;
                 }
+                if item_value.is_ingester() {
+                    add_node_to_consistent_hash(&item_value, &Role::Ingester, None).await;
+                }
                 NODES.write().await.insert(item_key.to_string(), item_value);
             }
             Event::Delete(ev) => {
@@ -380,6 +399,9 @@ async fn watch_node_list() -> Result<()> {
                     remove_node_from_consistent_hash(&item_value, &Role::FlattenCompactor, None)
                         .await;
                 }
+                if item_value.is_ingester() {
+                    remove_node_from_consistent_hash(&item_value, &Role::Ingester, None).await;
+                }
                 NODES.write().await.remove(item_key);
             }
             Event::Empty => {}
@@ -443,6 +465,9 @@ async fn check_nodes_status(client: &reqwest::Client) -> Result<()> {
                 if node.is_flatten_compactor() {
                     remove_node_from_consistent_hash(&node, &Role::FlattenCompactor, None).await;
                 }
+                if node.is_ingester() {
+                    remove_node_from_consistent_hash(&node, &Role::Ingester, None).await;
+                }
                 NODES.write().await.remove(&node.uuid);
             }
         } else {
@@ -500,6 +525,14 @@ pub async fn get_cached_online_ingester_nodes() -> Option<Vec<Node>> {
     .await
 }
 
+#[inline]
+pub async fn get_cached_online_alert_manager_nodes() -> Option<Vec<Node>> {
+    get_cached_nodes(|node| {
+        node.status == NodeStatus::Online && node.scheduled && node.is_alert_manager()
+    })
+    .await
+}
+
 #[inline]
 pub async fn get_cached_online_querier_nodes(group: Option<RoleGroup>) -> Option<Vec<Node>> {
     let nodes = get_cached_nodes(|node| {
@@ -606,6 +639,7 @@ mod tests {
         remove_node_from_consistent_hash(&node, &Role::Querier, Some(RoleGroup::Background)).await;
         remove_node_from_consistent_hash(&node, &Role::Compactor, None).await;
         remove_node_from_consistent_hash(&node, &Role::FlattenCompactor, None).await;
+        remove_node_from_consistent_hash(&node, &Role::Ingester, None).await;
         for key in data {
             assert_eq!(
                 get_node_from_consistent_hash(key.first().unwrap(), &Role::Querier, None).await,
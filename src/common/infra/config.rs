@@ -34,7 +34,8 @@ use vector_enrichment::TableRegistry;
 
 use crate::{
     common::meta::{
-        maxmind::MaxmindClient, organization::OrganizationSetting, syslog::SyslogRoute, user::User,
+        maxmind::MaxmindClient, organization::OrganizationSetting, recording_rule::RuleGroup,
+        silence::Silence, syslog::SyslogRoute, user::User,
     },
     handler::http::request::websocket::session::WsSession,
     service::{
@@ -71,6 +72,12 @@ pub static DASHBOARD_REPORTS: Lazy<RwHashMap<String, reports::Report>> =
     Lazy::new(Default::default);
 pub static SYSLOG_ROUTES: Lazy<RwHashMap<String, SyslogRoute>> = Lazy::new(Default::default);
 pub static SYSLOG_ENABLED: Lazy<Arc<RwLock<bool>>> = Lazy::new(|| Arc::new(RwLock::new(false)));
+// {org_id}/{stream_type}/{alias} -> stream name
+pub static STREAM_ALIASES: Lazy<RwHashMap<String, String>> = Lazy::new(Default::default);
+// {org_id}/{group_name} -> recording rule group
+pub static RECORDING_RULES: Lazy<RwHashMap<String, RuleGroup>> = Lazy::new(Default::default);
+// {org_id}/{silence_id} -> silence
+pub static SILENCES: Lazy<RwHashMap<String, Silence>> = Lazy::new(Default::default);
 pub static ENRICHMENT_TABLES: Lazy<RwHashMap<String, StreamTable>> = Lazy::new(Default::default);
 pub static ENRICHMENT_REGISTRY: Lazy<Arc<TableRegistry>> =
     Lazy::new(|| Arc::new(TableRegistry::default()));
@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{path::Path, sync::Arc};
+use std::{path::Path, sync::Arc, time::Duration};
 
 use chrono::{DateTime, Datelike, TimeZone, Utc};
 use config::{
@@ -122,6 +122,36 @@ pub async fn check_in_use(stream: StreamParams, file_name: &str) -> bool {
     MANAGER.check_in_use(stream, file_name).await
 }
 
+/// Waits until the file is not the active file of the stream anymore, polls
+/// [`check_in_use`] with exponential back-off, eg: during the graceful
+/// shutdown the flush job waits for the file instead of skipping it.
+pub async fn wait_until_released(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    file_name: &str,
+    timeout: Duration,
+) -> Result<(), anyhow::Error> {
+    let start = std::time::Instant::now();
+    let mut interval = Duration::from_millis(10);
+    loop {
+        let stream = StreamParams::new(org_id, stream_name, stream_type);
+        if !check_in_use(stream, file_name).await {
+            return Ok(());
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(anyhow::anyhow!(
+                "wal file is still in use after {:?}: {}",
+                timeout,
+                file_name
+            ));
+        }
+        tokio::time::sleep(interval.min(timeout - elapsed)).await;
+        interval = (interval * 2).min(Duration::from_secs(1));
+    }
+}
+
 pub async fn flush_all_to_disk() {
     for data in MANAGER.data.iter() {
         for (_, file) in data.read().await.iter() {
@@ -444,6 +474,27 @@ mod tests {
         assert!(file.name().contains(&format!("{}/{}", thread_id, key)));
     }
 
+    #[tokio::test]
+    async fn test_wal_wait_until_released() {
+        let org_id = "test_org";
+        let stream_name = "test_stream_release";
+        let stream_type = StreamType::Logs;
+        let stream = StreamParams::new(org_id, stream_name, stream_type);
+        let file = get_or_create(1, stream, None, "test_key").await;
+        let timeout = Duration::from_millis(50);
+        assert!(
+            wait_until_released(org_id, stream_name, stream_type, file.name(), timeout)
+                .await
+                .is_err()
+        );
+        let other = file.name().replace(".json", "_old.json");
+        assert!(
+            wait_until_released(org_id, stream_name, stream_type, &other, timeout)
+                .await
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn test_wal_rw_file() {
         let thread_id = 1;
@@ -20,10 +20,13 @@ pub mod maxmind;
 pub mod middleware_data;
 pub mod organization;
 pub mod proxy;
+pub mod recording_rule;
 pub mod saved_view;
 pub mod search;
 pub mod service;
 pub mod service_account;
+pub mod silence;
+pub mod storage_key;
 pub mod stream;
 pub mod syslog;
 pub mod telemetry;
@@ -151,6 +151,12 @@ pub struct OrganizationSettingPayload {
     pub enable_websocket_search: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub min_auto_refresh_interval: Option<u32>,
+    /// Max ingested bytes per second, 0 means unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingestion_rate_limit_bytes: Option<u32>,
+    /// Max ingested records per second, 0 means unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingestion_rate_limit_records: Option<u32>,
 }
 
 #[derive(Serialize, ToSchema, Deserialize, Debug, Clone)]
@@ -169,6 +175,12 @@ pub struct OrganizationSetting {
     pub enable_websocket_search: bool,
     #[serde(default = "default_auto_refresh_interval")]
     pub min_auto_refresh_interval: u32,
+    /// Max ingested bytes per second, 0 means unlimited.
+    #[serde(default)]
+    pub ingestion_rate_limit_bytes: u32,
+    /// Max ingested records per second, 0 means unlimited.
+    #[serde(default)]
+    pub ingestion_rate_limit_records: u32,
 }
 
 impl Default for OrganizationSetting {
@@ -180,6 +192,8 @@ impl Default for OrganizationSetting {
             toggle_ingestion_logs: default_toggle_ingestion_logs(),
             enable_websocket_search: default_enable_websocket_search(),
             min_auto_refresh_interval: default_auto_refresh_interval(),
+            ingestion_rate_limit_bytes: 0,
+            ingestion_rate_limit_records: 0,
         }
     }
 }
@@ -0,0 +1,81 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A Prometheus rule file
+///
+/// https://prometheus.io/docs/prometheus/latest/configuration/recording_rules/
+/// https://prometheus.io/docs/prometheus/latest/configuration/alerting_rules/
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RuleFile {
+    pub groups: Vec<RuleGroup>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RuleGroup {
+    pub name: String,
+    /// How often the rules are evaluated, eg: `1m`, defaults to
+    /// `ZO_RECORDING_RULES_DEFAULT_INTERVAL` seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    pub rules: Vec<Rule>,
+}
+
+/// A recording rule when `record` is set, an alerting rule when `alert` is set
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Rule {
+    /// The metric the result of the expression is written to
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub record: String,
+    /// The name of the alert fired for every series of the result
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub alert: String,
+    pub expr: String,
+    /// How long a series must be in the result before the alert fires, eg:
+    /// `5m`, the alert is pending until then
+    #[serde(default, rename = "for", skip_serializing_if = "Option::is_none")]
+    pub for_duration: Option<String>,
+    /// Labels added to, or overwritten in, the result
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// Alert annotations, templates with `$labels`, `$value` and
+    /// `$externalLabels`, eg: `{{ $labels.job }} at {{ $value | humanize }}`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+    /// Where the alerts are sent when they fire and resolve
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub receivers: Vec<Receiver>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Receiver {
+    /// PagerDuty Events API v2
+    Pagerduty {
+        routing_key: String,
+        /// One of `critical`, `error`, `warning` and `info`, defaults to the
+        /// `severity` label of the alert or `error`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        severity: Option<String>,
+    },
+    /// Slack incoming webhook
+    Slack { webhook_url: String },
+    /// Email sent with the SMTP settings of the instance
+    Email { to: Vec<String> },
+}
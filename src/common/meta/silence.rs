@@ -0,0 +1,41 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Suppresses the notifications of the alerts matching all the matchers
+/// between `starts_at` and `ends_at`, in microseconds
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Silence {
+    #[serde(default)]
+    pub id: String,
+    pub matchers: Vec<SilenceMatcher>,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    #[serde(default)]
+    pub comment: String,
+    #[serde(default)]
+    pub created_by: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SilenceMatcher {
+    pub name: String,
+    pub value: String,
+    /// The value is a regular expression matching the whole label value
+    #[serde(default, rename = "isRegex")]
+    pub is_regex: bool,
+}
@@ -0,0 +1,115 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, TimeZone, Utc};
+use config::meta::stream::StreamType;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static STORAGE_KEY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(file_list|files)/[^/]+/[^/]+/[^/]+/(\d{4})/(\d{2})/(\d{2})/(\d{2})/").unwrap()
+});
+
+/// A storage key of a stream file or of a file list file which is known to
+/// be well formed, eg: `files/default/logs/olympics/2023/08/21/08/xxx.parquet`
+/// or the prefix of the hour `files/default/logs/olympics/2023/08/21/08/`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageKey(String);
+
+impl StorageKey {
+    pub fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        let caps = STORAGE_KEY_RE
+            .captures(s)
+            .ok_or_else(|| anyhow::anyhow!("invalid storage key: {s}"))?;
+        let num = |i: usize| caps[i].parse::<u32>().unwrap();
+        if Utc
+            .with_ymd_and_hms(num(2) as i32, num(3), num(4), num(5), 0, 0)
+            .single()
+            .is_none()
+        {
+            return Err(anyhow::anyhow!("invalid date in storage key: {s}"));
+        }
+        Ok(Self(s.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn org(&self) -> &str {
+        self.column(1)
+    }
+
+    pub fn stream_type(&self) -> StreamType {
+        StreamType::from(self.column(2))
+    }
+
+    pub fn stream_name(&self) -> &str {
+        self.column(3)
+    }
+
+    /// Returns the hour the file belongs to.
+    pub fn date(&self) -> DateTime<Utc> {
+        let num = |i: usize| self.column(i).parse::<u32>().unwrap();
+        Utc.with_ymd_and_hms(num(4) as i32, num(5), num(6), num(7), 0, 0)
+            .unwrap()
+    }
+
+    fn column(&self, i: usize) -> &str {
+        // the structure is validated by `parse`
+        self.0.split('/').nth(i).unwrap()
+    }
+}
+
+impl std::fmt::Display for StorageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for StorageKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_key_parse() {
+        let key = StorageKey::parse(
+            "files/default/logs/olympics/2023/08/21/08/7099303408192061440.parquet",
+        )
+        .unwrap();
+        assert_eq!(key.org(), "default");
+        assert_eq!(key.stream_type(), StreamType::Logs);
+        assert_eq!(key.stream_name(), "olympics");
+        assert_eq!(
+            key.date(),
+            Utc.with_ymd_and_hms(2023, 8, 21, 8, 0, 0).unwrap()
+        );
+        assert!(StorageKey::parse("file_list/default/logs/olympics/2023/08/21/08/").is_ok());
+
+        // thread id in the path
+        assert!(
+            StorageKey::parse("files/default/logs/olympics/0/2023/08/21/08/a.parquet").is_err()
+        );
+        assert!(StorageKey::parse("files/default/logs/olympics/2023/08/21/").is_err());
+        assert!(StorageKey::parse("data/default/logs/olympics/2023/08/21/08/").is_err());
+        assert!(StorageKey::parse("files/default/logs/olympics/2023/13/21/08/").is_err());
+    }
+}
@@ -82,6 +82,35 @@ pub struct StreamDeleteFields {
     pub fields: Vec<String>,
 }
 
+/// Another name of a stream. Searches and the logs and traces ingestion
+/// resolve it to the stream. The metrics ingestion doesn't, because the
+/// stream of a metric is its `__name__` label, so an alias of a metrics stream
+/// only works in searches.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct StreamAlias {
+    pub alias: String,
+}
+
+/// File statistics of a stream, summed from the file list by the stats job.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StreamFileStats {
+    pub total_files: i64,
+    pub total_records: i64,
+    pub total_compressed_bytes: i64,
+    pub total_uncompressed_bytes: i64,
+}
+
+impl From<&StreamStats> for StreamFileStats {
+    fn from(stats: &StreamStats) -> Self {
+        Self {
+            total_files: stats.file_num,
+            total_records: stats.doc_num,
+            total_compressed_bytes: stats.compressed_size as i64,
+            total_uncompressed_bytes: stats.storage_size as i64,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +122,24 @@ mod tests {
         let stats_frm_str = StreamStats::from(stats_str.as_str());
         assert_eq!(stats, stats_frm_str);
     }
+
+    #[test]
+    fn test_stream_file_stats() {
+        let stats = StreamStats {
+            doc_num: 1000,
+            file_num: 3,
+            storage_size: 4096.0,
+            compressed_size: 512.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            StreamFileStats::from(&stats),
+            StreamFileStats {
+                total_files: 3,
+                total_records: 1000,
+                total_compressed_bytes: 512,
+                total_uncompressed_bytes: 4096,
+            }
+        );
+    }
 }
@@ -41,6 +41,7 @@ pub async fn run(from: &str, to: &str) -> Result<(), anyhow::Error> {
 async fn migrate_meta(from: &str, to: &str) -> Result<(), anyhow::Error> {
     println!("load meta from {}", from);
     let src: Box<dyn infra_db::Db> = match from.to_lowercase().as_str().trim() {
+        "sled" => Box::<infra_db::sled::SledDb>::default(),
         "sqlite" => Box::<infra_db::sqlite::SqliteDb>::default(),
         "etcd" => Box::<infra_db::etcd::Etcd>::default(),
         "mysql" => Box::<infra_db::mysql::MysqlDb>::default(),
@@ -48,6 +49,7 @@ async fn migrate_meta(from: &str, to: &str) -> Result<(), anyhow::Error> {
         _ => panic!("invalid meta source"),
     };
     let dest: Box<dyn infra_db::Db> = match to.to_lowercase().as_str().trim() {
+        "sled" => Box::<infra_db::sled::SledDb>::default(),
         "sqlite" => Box::<infra_db::sqlite::SqliteDb>::default(),
         "etcd" => Box::<infra_db::etcd::Etcd>::default(),
         "mysql" => Box::<infra_db::mysql::MysqlDb>::default(),
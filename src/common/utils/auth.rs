@@ -32,7 +32,7 @@ use crate::common::{
     meta::{
         authz::Authz,
         organization::DEFAULT_ORG,
-        user::{AuthTokens, UserRole},
+        user::{AuthTokens, TokenValidationResponse, UserRole},
     },
 };
 
@@ -179,6 +179,37 @@ impl FromRequest for UserEmail {
     }
 }
 
+/// The user authenticated by the validators, set into the request extensions
+/// once the credentials (basic auth, token or jwt) are validated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CurrentUser {
+    pub email: String,
+    pub role: Option<UserRole>,
+    pub is_internal_user: bool,
+}
+
+impl From<&TokenValidationResponse> for CurrentUser {
+    fn from(res: &TokenValidationResponse) -> Self {
+        Self {
+            email: res.user_email.clone(),
+            role: res.user_role.clone(),
+            is_internal_user: res.is_internal_user,
+        }
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<CurrentUser>() {
+            Some(user) => ready(Ok(user.clone())),
+            None => ready(Err(actix_web::error::ErrorUnauthorized("No user found"))),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AuthExtractor {
     pub auth: String,
@@ -655,6 +686,26 @@ mod tests {
         assert_eq!(generated_url, expected_url);
     }
 
+    #[tokio::test]
+    async fn test_current_user_extractor() {
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        assert!(CurrentUser::extract(&req).await.is_err());
+
+        let res = TokenValidationResponse {
+            is_valid: true,
+            user_email: "root@example.com".to_string(),
+            is_internal_user: true,
+            user_role: Some(UserRole::Root),
+            ..Default::default()
+        };
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        req.extensions_mut().insert(CurrentUser::from(&res));
+        let user = CurrentUser::extract(&req).await.unwrap();
+        assert_eq!(user.email, "root@example.com");
+        assert_eq!(user.role, Some(UserRole::Root));
+        assert!(user.is_internal_user);
+    }
+
     #[tokio::test]
     async fn test_is_root_user() {
         assert!(!is_root_user("dummy"));
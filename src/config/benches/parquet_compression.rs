@@ -0,0 +1,66 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{Int64Array, StringArray},
+    record_batch::RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema};
+use config::utils::parquet::get_parquet_compression;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+
+const FIXTURE_SIZE: usize = 10 * 1024 * 1024; // 10 MB
+
+fn generate_fixture() -> RecordBatch {
+    let line = "2024-01-01T00:00:00Z INFO request completed method=GET path=/api/default/_search status=200";
+    let rows = FIXTURE_SIZE / line.len();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("_timestamp", DataType::Int64, false),
+        Field::new("log", DataType::Utf8, true),
+    ]));
+    let timestamps = Int64Array::from_iter_values(0..rows as i64);
+    let logs =
+        StringArray::from_iter_values((0..rows).map(|i| format!("{line} took={}ms", i % 1000)));
+    RecordBatch::try_new(schema, vec![Arc::new(timestamps), Arc::new(logs)]).unwrap()
+}
+
+pub fn compression_benchmark(c: &mut Criterion) {
+    let batch = generate_fixture();
+    let mut group = c.benchmark_group("parquet/zstd");
+    group.throughput(Throughput::Bytes(FIXTURE_SIZE as u64));
+    group.sample_size(10);
+    for level in [1, 3, 9] {
+        group.bench_function(BenchmarkId::from_parameter(level), |b| {
+            b.iter(|| {
+                let props = WriterProperties::builder()
+                    .set_compression(get_parquet_compression("zstd", level))
+                    .build();
+                let mut buf = Vec::with_capacity(FIXTURE_SIZE);
+                let mut writer =
+                    ArrowWriter::try_new(&mut buf, batch.schema(), Some(props)).unwrap();
+                writer.write(black_box(&batch)).unwrap();
+                writer.close().unwrap();
+                buf
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compression_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, Int64Array, StringArray},
+    record_batch::RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema};
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter, ProjectionMask};
+
+const NUM_ROWS: usize = 100_000;
+const NUM_FIELDS: usize = 50;
+
+// a wide schema like the one of a log stream: _timestamp and many string fields
+fn generate_fixture() -> Bytes {
+    let mut fields = vec![Field::new("_timestamp", DataType::Int64, false)];
+    let mut columns: Vec<ArrayRef> =
+        vec![Arc::new(Int64Array::from_iter_values(0..NUM_ROWS as i64))];
+    for i in 1..NUM_FIELDS {
+        fields.push(Field::new(format!("field_{i}"), DataType::Utf8, true));
+        columns.push(Arc::new(StringArray::from_iter_values(
+            (0..NUM_ROWS).map(|j| format!("value_{i}_{}", j % 1000)),
+        )));
+    }
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    Bytes::from(buf)
+}
+
+fn read(data: &Bytes, projection: Option<&[usize]>) -> usize {
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(data.clone()).unwrap();
+    if let Some(projection) = projection {
+        let mask = ProjectionMask::roots(builder.parquet_schema(), projection.iter().copied());
+        builder = builder.with_projection(mask);
+    }
+    builder
+        .build()
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .sum()
+}
+
+pub fn projection_benchmark(c: &mut Criterion) {
+    let data = generate_fixture();
+    let mut group = c.benchmark_group("parquet/projection");
+    group.sample_size(10);
+    group.bench_function("full_scan", |b| {
+        b.iter(|| read(black_box(&data), None));
+    });
+    // eg: SELECT _timestamp, field_1, field_2 FROM ...
+    group.bench_function("projected_scan", |b| {
+        b.iter(|| read(black_box(&data), Some(&[0, 1, 2])));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, projection_benchmark);
+criterion_main!(benches);
@@ -473,6 +473,12 @@ pub struct Auth {
     pub cookie_secure_only: bool,
     #[env_config(name = "ZO_EXT_AUTH_SALT", default = "openobserve")]
     pub ext_auth_salt: String,
+    #[env_config(
+        name = "ZO_AUTHZ_CACHE_TTL",
+        default = 10,
+        help = "Seconds to cache the result of a permission check, 0 to disable"
+    )]
+    pub authz_cache_ttl: i64,
 }
 
 #[derive(EnvConfig)]
@@ -521,6 +527,58 @@ pub struct Grpc {
     pub connect_timeout: u64,
     #[env_config(name = "ZO_GRPC_CHANNEL_CACHE_DISABLED", default = false)]
     pub channel_cache_disabled: bool,
+    #[env_config(
+        name = "ZO_FLIGHT_SQL_ENABLED",
+        default = false,
+        help = "Serve the Arrow Flight SQL protocol on the querier nodes for BI tools"
+    )]
+    pub flight_sql_enabled: bool,
+    #[env_config(name = "ZO_FLIGHT_SQL_PORT", default = 5083)]
+    pub flight_sql_port: u16,
+    #[env_config(
+        name = "ZO_FLIGHT_SQL_MAX_ROWS",
+        default = 100000,
+        help = "Reject the Flight SQL statements which return more rows than this"
+    )]
+    pub flight_sql_max_rows: i64,
+    #[env_config(
+        name = "ZO_VECTOR_ENABLED",
+        default = false,
+        help = "Serve the gRPC protocol of the Vector sink on the ingester nodes"
+    )]
+    pub vector_enabled: bool,
+    #[env_config(name = "ZO_VECTOR_PORT", default = 6000)]
+    pub vector_port: u16,
+    #[env_config(
+        name = "ZO_VECTOR_ORG",
+        default = "default",
+        help = "Organization of the logs received from the Vector sink"
+    )]
+    pub vector_org: String,
+    #[env_config(
+        name = "ZO_VECTOR_STREAM",
+        default = "vector",
+        help = "Stream of the logs received from the Vector sink"
+    )]
+    pub vector_stream: String,
+    #[env_config(
+        name = "ZO_VECTOR_TLS_CERT_PATH",
+        default = "",
+        help = "Server certificate of the Vector gRPC server, required with ZO_VECTOR_ENABLED"
+    )]
+    pub vector_tls_cert_path: String,
+    #[env_config(
+        name = "ZO_VECTOR_TLS_KEY_PATH",
+        default = "",
+        help = "Private key of the Vector gRPC server, required with ZO_VECTOR_ENABLED"
+    )]
+    pub vector_tls_key_path: String,
+    #[env_config(
+        name = "ZO_VECTOR_TLS_CLIENT_CA_PATH",
+        default = "",
+        help = "CA which signs the client certificates of the Vector sinks, the clients without a certificate signed by it are rejected. Required with ZO_VECTOR_ENABLED"
+    )]
+    pub vector_tls_client_ca_path: String,
     #[env_config(name = "ZO_GRPC_TLS_ENABLED", default = false)]
     pub tls_enabled: bool,
     #[env_config(name = "ZO_GRPC_TLS_CERT_DOMAIN", default = "")]
@@ -537,6 +595,20 @@ pub struct TCP {
     pub tcp_port: u16,
     #[env_config(name = "ZO_UDP_PORT", default = 5514)]
     pub udp_port: u16,
+    #[env_config(
+        name = "ZO_FLUENT_FORWARD_ENABLED",
+        default = false,
+        help = "Accept logs over the Fluentd forward protocol, eg: the forward output of Fluent Bit"
+    )]
+    pub fluent_forward_enabled: bool,
+    #[env_config(name = "ZO_FLUENT_FORWARD_PORT", default = 24224)]
+    pub fluent_forward_port: u16,
+    #[env_config(
+        name = "ZO_FLUENT_FORWARD_ORG",
+        default = "default",
+        help = "Organization of the logs received over the forward protocol, the tag is the stream name"
+    )]
+    pub fluent_forward_org: String,
 }
 
 #[derive(EnvConfig)]
@@ -545,6 +617,12 @@ pub struct Route {
     pub timeout: u64,
     #[env_config(name = "ZO_ROUTE_MAX_CONNECTIONS", default = 1024)]
     pub max_connections: usize,
+    #[env_config(
+        name = "ZO_ROUTE_INGEST_BY_STREAM",
+        default = false,
+        help = "Route the ingestion requests of a stream to the same ingester by consistent hash"
+    )]
+    pub ingest_by_stream: bool,
 }
 
 #[derive(EnvConfig)]
@@ -639,6 +717,24 @@ pub struct Common {
     pub ui_sql_base64_enabled: bool,
     #[env_config(name = "ZO_METRICS_DEDUP_ENABLED", default = true)]
     pub metrics_dedup_enabled: bool,
+    #[env_config(
+        name = "ZO_PROMQL_EXPERIMENTAL_ENABLED",
+        default = false,
+        help = "Enable the experimental PromQL operators limitk and limit_ratio"
+    )]
+    pub promql_experimental_enabled: bool,
+    #[env_config(
+        name = "ZO_RECORDING_RULES_DEFAULT_INTERVAL",
+        default = 60,
+        help = "Evaluation interval in seconds of the recording rule groups without an interval"
+    )]
+    pub recording_rules_default_interval: u64,
+    #[env_config(
+        name = "ZO_RECORDING_RULES_EXTERNAL_LABELS",
+        default = "",
+        help = "The $externalLabels of alert annotation templates, as name=value separated by commas"
+    )]
+    pub recording_rules_external_labels: String,
     #[env_config(name = "ZO_BLOOM_FILTER_ENABLED", default = true)]
     pub bloom_filter_enabled: bool,
     #[env_config(name = "ZO_BLOOM_FILTER_DISABLED_ON_SEARCH", default = false)]
@@ -651,6 +747,18 @@ pub struct Common {
         help = "Bloom filter ndv ratio, set to 100 means NDV = row_count / 100, if set to 1 means will use NDV = row_count"
     )]
     pub bloom_filter_ndv_ratio: u64,
+    #[env_config(
+        name = "ZO_PARQUET_COMPRESSION",
+        default = "zstd",
+        help = "Compression codec for parquet files, zstd(default), lz4, none"
+    )]
+    pub parquet_compression: String,
+    #[env_config(
+        name = "ZO_PARQUET_COMPRESSION_LEVEL",
+        default = 3,
+        help = "Zstd compression level for parquet files, valid range is 1-22"
+    )]
+    pub parquet_compression_level: i32,
     #[env_config(name = "ZO_WAL_FSYNC_DISABLED", default = false)]
     pub wal_fsync_disabled: bool,
     #[env_config(name = "ZO_TRACING_ENABLED", default = false)]
@@ -843,6 +951,18 @@ pub struct Common {
     pub bulk_api_response_errors_only: bool,
     #[env_config(name = "ZO_ALLOW_USER_DEFINED_SCHEMAS", default = false)]
     pub allow_user_defined_schemas: bool,
+    #[env_config(
+        name = "ZO_INGEST_STRICT_SCHEMA",
+        default = false,
+        help = "Reject the records which would change the data type of an existing field of the stream, only new fields are allowed"
+    )]
+    pub ingest_strict_schema: bool,
+    #[env_config(
+        name = "ZO_SEARCH_CURSOR_SECRET",
+        default = "",
+        help = "Secret to encrypt the search pagination cursors, default to the root user password"
+    )]
+    pub search_cursor_secret: String,
     #[env_config(
         name = "ZO_MEM_TABLE_STREAMS",
         default = "",
@@ -909,6 +1029,42 @@ pub struct Common {
         help = "Discard data of last n seconds from cached results"
     )]
     pub result_cache_discard_duration: i64,
+    #[env_config(
+        name = "ZO_QUERY_CACHE_TTL",
+        default = 0,
+        help = "Serve identical search requests from the query cache for n seconds, 0 disables it"
+    )]
+    pub query_cache_ttl: u64,
+    #[env_config(
+        name = "ZO_QUERY_CACHE_MAX_SIZE",
+        default = 1024,
+        help = "Max size in MB of the responses in the query cache on disk"
+    )]
+    pub query_cache_max_size: usize,
+    #[env_config(
+        name = "ZO_ICEBERG_CATALOG_ENABLED",
+        default = false,
+        help = "Write Apache Iceberg table metadata of the parquet files of every stream"
+    )]
+    pub iceberg_catalog_enabled: bool,
+    #[env_config(
+        name = "ZO_ICEBERG_COMMIT_INTERVAL",
+        default = 60,
+        help = "Seconds between the Iceberg commits, the files of a stream added or removed in between go into one snapshot"
+    )]
+    pub iceberg_commit_interval: u64,
+    #[env_config(
+        name = "ZO_ICEBERG_SNAPSHOT_MAX_AGE",
+        default = 432000, // 5 days
+        help = "Seconds an Iceberg snapshot is kept for time travel, older snapshots are expired at the next commit"
+    )]
+    pub iceberg_snapshot_max_age: i64,
+    #[env_config(
+        name = "ZO_ICEBERG_MANIFEST_MERGE_COUNT",
+        default = 100,
+        help = "Merge the manifests of an Iceberg table into one when a snapshot has this many, 0 disables merging"
+    )]
+    pub iceberg_manifest_merge_count: usize,
     #[env_config(
         name = "ZO_METRICS_CACHE_ENABLED",
         default = true,
@@ -988,10 +1144,28 @@ pub struct Limit {
     pub file_push_interval: u64,
     #[env_config(name = "ZO_FILE_PUSH_LIMIT", default = 0)] // files
     pub file_push_limit: usize,
+    #[env_config(
+        name = "ZO_FILE_PUSH_MAX_RETRIES",
+        default = 3,
+        help = "Max retries with exponential back-off when uploading a file to storage"
+    )]
+    pub file_push_max_retries: usize,
+    #[env_config(
+        name = "ZO_FILE_ORPHAN_AGE_HOURS",
+        default = 6,
+        help = "Delete the unfinished .par files in the WAL directory which are not in a lock file after n hours, 0 disables it"
+    )]
+    pub file_orphan_age_hours: u64,
+    #[env_config(name = "ZO_FILE_ORPHAN_CHECK_INTERVAL", default = 3600)] // seconds
+    pub file_orphan_check_interval: u64,
     // over this limit will skip merging on ingester
     #[env_config(name = "ZO_FILE_MOVE_FIELDS_LIMIT", default = 2000)]
     pub file_move_fields_limit: usize,
-    #[env_config(name = "ZO_FILE_MOVE_THREAD_NUM", default = 0)]
+    #[env_config(
+        name = "ZO_FILE_MOVE_THREAD_NUM",
+        default = 0,
+        help = "Number of workers moving WAL files to storage concurrently, each worker handles one partition at a time, default is CPU core"
+    )]
     pub file_move_thread_num: usize,
     #[env_config(name = "ZO_FILE_MERGE_THREAD_NUM", default = 0)]
     pub file_merge_thread_num: usize,
@@ -1005,6 +1179,12 @@ pub struct Limit {
     pub query_timeout: u64,
     #[env_config(name = "ZO_QUERY_DEFAULT_LIMIT", default = 1000)]
     pub query_default_limit: i64,
+    #[env_config(
+        name = "ZO_QUERY_MAX_SCAN_BYTES",
+        default = 0,
+        help = "Reject the queries which would scan more bytes than this, estimated from the file list, 0 means unlimited"
+    )]
+    pub query_max_scan_bytes: i64,
     #[env_config(name = "ZO_QUERY_PARTITION_BY_SECS", default = 1)] // seconds
     pub query_partition_by_secs: usize,
     #[env_config(name = "ZO_QUERY_GROUP_BASE_SPEED", default = 768)] // MB/s/core
@@ -1013,6 +1193,18 @@ pub struct Limit {
     pub ingest_allowed_upto: i64,
     #[env_config(name = "ZO_INGEST_FLATTEN_LEVEL", default = 3)] // default flatten level
     pub ingest_flatten_level: u32,
+    #[env_config(
+        name = "ZO_INGEST_LUA_MAX_INSTRUCTIONS",
+        default = 1000000,
+        help = "Maximum number of Lua instructions an ingest lua transform can run for one record."
+    )]
+    pub ingest_lua_max_instructions: u32,
+    #[env_config(
+        name = "ZO_INGEST_LUA_MEMORY_LIMIT",
+        default = 64,
+        help = "Maximum memory in MB an ingest lua transform can allocate."
+    )]
+    pub ingest_lua_memory_limit: usize,
     #[env_config(name = "ZO_IGNORE_FILE_RETENTION_BY_STREAM", default = false)]
     pub ignore_file_retention_by_stream: bool,
     #[env_config(name = "ZO_LOGS_FILE_RETENTION", default = "hourly")]
@@ -1045,6 +1237,12 @@ pub struct Limit {
     pub grpc_runtime_blocking_worker_num: usize, // equals to 512 if 0
     #[env_config(name = "ZO_GRPC_RUNTIME_SHUTDOWN_TIMEOUT", default = 10)] // seconds
     pub grpc_runtime_shutdown_timeout: u64,
+    #[env_config(
+        name = "ZO_GRPC_BATCH_SIZE",
+        default = 1000,
+        help = "Number of records the gRPC streaming logs ingestion buffers before writing them to the WAL."
+    )]
+    pub grpc_batch_size: usize,
     #[env_config(name = "ZO_JOB_RUNTIME_WORKER_NUM", default = 0)]
     pub job_runtime_worker_num: usize, // equals to cpu_num if 0
     #[env_config(name = "ZO_JOB_RUNTIME_BLOCKING_WORKER_NUM", default = 0)]
@@ -1065,6 +1263,12 @@ pub struct Limit {
     pub http_slow_log_threshold: u64,
     #[env_config(name = "ZO_ACTIX_SHUTDOWN_TIMEOUT", default = 5)] // seconds
     pub http_shutdown_timeout: u64,
+    #[env_config(
+        name = "ZO_SHUTDOWN_DRAIN_TIMEOUT",
+        default = 120,
+        help = "Maximum seconds the node spends draining (flushing memtables and moving wal files to storage) before it leaves the cluster on shutdown."
+    )] // seconds
+    pub shutdown_drain_timeout: u64,
     #[env_config(name = "ZO_ALERT_SCHEDULE_INTERVAL", default = 10)] // seconds
     pub alert_schedule_interval: i64,
     #[env_config(name = "ZO_ALERT_SCHEDULE_CONCURRENCY", default = 5)]
@@ -1183,6 +1387,18 @@ pub struct Limit {
         help = "Maximum number of entries in the file stat cache. Higher values increase memory usage but may improve query performance."
     )]
     pub datafusion_file_stat_cache_max_entries: usize,
+    #[env_config(
+        name = "ZO_DATAFUSION_PARQUET_META_CACHE_MAX_SIZE",
+        default = 128,
+        help = "Maximum memory in MB used by the parquet file footers kept in the parquet metadata cache, set to 0 to disable the cache."
+    )] // MB
+    pub datafusion_parquet_meta_cache_max_size: usize,
+    #[env_config(
+        name = "ZO_DATAFUSION_PARQUET_META_CACHE_TTL",
+        default = 3600,
+        help = "Seconds a cached parquet file footer is kept before it is fetched again."
+    )] // seconds
+    pub datafusion_parquet_meta_cache_ttl: u64,
     #[env_config(
         name = "ZO_DATAFUSION_STREAMING_AGGS_CACHE_MAX_ENTRIES",
         default = 100000,
@@ -1233,6 +1449,12 @@ pub struct Compact {
     pub sync_to_db_interval: u64,
     #[env_config(name = "ZO_COMPACT_MAX_FILE_SIZE", default = 512)] // MB
     pub max_file_size: usize,
+    #[env_config(
+        name = "ZO_COMPACT_MIN_FILES",
+        default = 2,
+        help = "Minimum number of small files in a partition before they are merged into a larger file"
+    )]
+    pub min_files: usize,
     #[env_config(name = "ZO_COMPACT_EXTENDED_DATA_RETENTION_DAYS", default = 3650)] // days
     pub extended_data_retention_days: i64,
     #[env_config(name = "ZO_COMPACT_DATA_RETENTION_DAYS", default = 3650)] // days
@@ -1271,6 +1493,26 @@ pub struct Compact {
     pub job_clean_wait_time: i64,
     #[env_config(name = "ZO_COMPACT_PENDING_JOBS_METRIC_INTERVAL", default = 300)] // seconds
     pub pending_jobs_metric_interval: u64,
+    #[env_config(
+        name = "ZO_COMPACT_DELTA_LOG_ENABLED",
+        default = false,
+        help = "Write a Delta Lake transaction log of the compacted files of every stream"
+    )]
+    pub delta_log_enabled: bool,
+    #[env_config(
+        name = "ZO_COMPACT_METRICS_ROLLUP_ENABLED",
+        default = false,
+        help = "Downsample metrics streams into {stream}_5m, {stream}_1h and {stream}_1d rollup streams"
+    )]
+    pub metrics_rollup_enabled: bool,
+    #[env_config(name = "ZO_COMPACT_METRICS_ROLLUP_INTERVAL", default = 3600)] // seconds
+    pub metrics_rollup_interval: u64,
+    #[env_config(
+        name = "ZO_COMPACT_METRICS_ROLLUP_DELAY",
+        default = 3600, // 1 hour
+        help = "Only roll up the data older than this, to leave time for late samples"
+    )]
+    pub metrics_rollup_delay: i64,
 }
 
 #[derive(EnvConfig)]
@@ -1473,6 +1715,18 @@ pub struct S3 {
     pub feature_http1_only: bool,
     #[env_config(name = "ZO_S3_FEATURE_HTTP2_ONLY", default = false)]
     pub feature_http2_only: bool,
+    #[env_config(
+        name = "ZO_S3_FEATURE_CHECKSUM",
+        default = false,
+        help = "Send a sha256 checksum with every upload and verify the etag of uploaded files, the etag must be the md5 of the object, eg: no SSE-KMS"
+    )]
+    pub feature_checksum: bool,
+    #[env_config(
+        name = "ZO_S3_FEATURE_CONDITIONAL_PUT",
+        default = true,
+        help = "Create objects with `If-None-Match: *` so concurrent writers can't overwrite each other, eg: the delta log commits. Disable it for the S3 compatible stores without conditional writes"
+    )]
+    pub feature_conditional_put: bool,
     #[env_config(name = "ZO_S3_ALLOW_INVALID_CERTIFICATES", default = false)]
     pub allow_invalid_certificates: bool,
     #[env_config(name = "ZO_S3_SYNC_TO_CACHE_INTERVAL", default = 600)] // seconds
@@ -1481,6 +1735,18 @@ pub struct S3 {
     pub max_retries: usize,
     #[env_config(name = "ZO_S3_MAX_IDLE_PER_HOST", default = 0)]
     pub max_idle_per_host: usize,
+    #[env_config(
+        name = "ZO_S3_GCP_SERVICE_ACCOUNT_KEY",
+        default = "",
+        help = "The content of the service account JSON key of GCS, if neither this nor ZO_S3_ACCESS_KEY is set, the application default credentials are used"
+    )]
+    pub gcp_service_account_key: String,
+    #[env_config(
+        name = "ZO_S3_AZURE_CONNECTION_STRING",
+        default = "",
+        help = "The connection string of the Azure storage account, if neither this nor ZO_S3_SECRET_KEY is set, the managed identity is used"
+    )]
+    pub azure_connection_string: String,
 }
 
 #[derive(Debug, EnvConfig)]
@@ -1535,6 +1801,12 @@ pub struct HealthCheck {
         help = "Health check timeout in seconds"
     )]
     pub timeout: u64,
+    #[env_config(
+        name = "ZO_HEALTH_CHECK_CACHE_TTL",
+        default = 5,
+        help = "Seconds the result of the readiness checks is reused before the subsystems are checked again"
+    )]
+    pub cache_ttl: u64,
     #[env_config(
         name = "ZO_HEALTH_CHECK_FAILED_TIMES",
         default = 5,
@@ -1570,6 +1842,9 @@ pub fn init() -> Config {
     if cfg.limit.grpc_runtime_blocking_worker_num == 0 {
         cfg.limit.grpc_runtime_blocking_worker_num = 512;
     }
+    if cfg.limit.grpc_batch_size == 0 {
+        cfg.limit.grpc_batch_size = 1000;
+    }
     if cfg.limit.job_runtime_worker_num == 0 {
         cfg.limit.job_runtime_worker_num = cpu_num;
     }
@@ -1732,6 +2007,9 @@ fn check_common_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     if cfg.limit.metrics_cache_max_entries == 0 {
         cfg.limit.metrics_cache_max_entries = 100_000;
     }
+    if cfg.common.recording_rules_default_interval == 0 {
+        cfg.common.recording_rules_default_interval = 60;
+    }
 
     // check search job retention
     if cfg.limit.search_job_retention == 0 {
@@ -1769,12 +2047,9 @@ fn check_common_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     cfg.common.local_mode_storage = cfg.common.local_mode_storage.to_lowercase();
 
     // format metadata storage
-    if cfg.common.meta_store.is_empty() {
-        if cfg.common.local_mode {
-            cfg.common.meta_store = "sqlite".to_string();
-        } else {
-            cfg.common.meta_store = "etcd".to_string();
-        }
+    // the default of local mode depends on the data dir, see check_path_config
+    if cfg.common.meta_store.is_empty() && !cfg.common.local_mode {
+        cfg.common.meta_store = "etcd".to_string();
     }
     cfg.common.meta_store = cfg.common.meta_store.to_lowercase();
     if cfg.common.local_mode
@@ -1814,6 +2089,28 @@ fn check_common_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.common.bloom_filter_ndv_ratio = 100;
     }
 
+    // check parquet compression codec
+    cfg.common.parquet_compression = cfg.common.parquet_compression.to_lowercase();
+    if cfg.common.parquet_compression.is_empty() {
+        cfg.common.parquet_compression = "zstd".to_string();
+    }
+    if !["zstd", "lz4", "none"].contains(&cfg.common.parquet_compression.as_str()) {
+        return Err(anyhow::anyhow!(
+            "ZO_PARQUET_COMPRESSION must be one of zstd, lz4, none."
+        ));
+    }
+    if !(1..=22).contains(&cfg.common.parquet_compression_level) {
+        return Err(anyhow::anyhow!(
+            "ZO_PARQUET_COMPRESSION_LEVEL must be between 1 and 22."
+        ));
+    }
+    if cfg.common.parquet_compression == "zstd" && cfg.common.parquet_compression_level > 9 {
+        log::warn!(
+            "ZO_PARQUET_COMPRESSION_LEVEL is set to {}, levels above 9 have a disproportionate CPU cost",
+            cfg.common.parquet_compression_level
+        );
+    }
+
     // check default inverted index search format
     cfg.common.inverted_index_store_format = cfg.common.inverted_index_store_format.to_lowercase();
     if cfg.common.inverted_index_store_format.is_empty() {
@@ -1844,6 +2141,13 @@ fn check_common_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.common.feature_join_right_side_max_rows = 50_000;
     }
 
+    if cfg.common.iceberg_commit_interval == 0 {
+        cfg.common.iceberg_commit_interval = 60;
+    }
+    if cfg.common.iceberg_snapshot_max_age <= 0 {
+        cfg.common.iceberg_snapshot_max_age = 432000;
+    }
+
     Ok(())
 }
 
@@ -1855,6 +2159,14 @@ fn check_grpc_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     {
         return Err(anyhow::anyhow!("ZO_GRPC_TLS_CERT_DOMAIN, ZO_GRPC_TLS_CERT_PATH and ZO_GRPC_TLS_KEY_PATH must be set when ZO_GRPC_TLS_ENABLED is true"));
     }
+    // the vector sink can't send credentials, the clients are authenticated by mTLS
+    if cfg.grpc.vector_enabled
+        && (cfg.grpc.vector_tls_cert_path.is_empty()
+            || cfg.grpc.vector_tls_key_path.is_empty()
+            || cfg.grpc.vector_tls_client_ca_path.is_empty())
+    {
+        return Err(anyhow::anyhow!("ZO_VECTOR_TLS_CERT_PATH, ZO_VECTOR_TLS_KEY_PATH and ZO_VECTOR_TLS_CLIENT_CA_PATH must be set when ZO_VECTOR_ENABLED is true"));
+    }
     Ok(())
 }
 
@@ -1903,6 +2215,17 @@ fn check_path_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     if !cfg.common.data_db_dir.ends_with('/') {
         cfg.common.data_db_dir = format!("{}/", cfg.common.data_db_dir);
     }
+    // local mode defaults to sled, but keeps using sqlite for the data dirs
+    // created before sled was the default
+    if cfg.common.meta_store.is_empty() {
+        let db_dir = std::path::Path::new(&cfg.common.data_db_dir);
+        cfg.common.meta_store =
+            if db_dir.join("metadata.sqlite").exists() && !db_dir.join("metadata.sled").exists() {
+                "sqlite".to_string()
+            } else {
+                "sled".to_string()
+            };
+    }
     if cfg.common.data_cache_dir.is_empty() {
         cfg.common.data_cache_dir = format!("{}cache/", cfg.common.data_dir);
     }
@@ -2013,6 +2336,8 @@ fn check_memory_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.limit.mem_table_bucket_num = 1;
     }
 
+    cfg.limit.datafusion_parquet_meta_cache_max_size *= 1024 * 1024;
+
     // wal
     if cfg.limit.wal_write_buffer_size < 4096 {
         cfg.limit.wal_write_buffer_size = 4096;
@@ -2097,6 +2422,7 @@ fn check_disk_cache_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     } else {
         cfg.disk_cache.gc_size *= 1024 * 1024;
     }
+    cfg.common.query_cache_max_size *= 1024 * 1024;
 
     if cfg.disk_cache.multi_dir.contains('/') {
         return Err(anyhow::anyhow!(
@@ -2148,6 +2474,9 @@ fn check_compact_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.compact.max_file_size = 512;
     }
     cfg.compact.max_file_size *= 1024 * 1024;
+    if cfg.compact.min_files < 2 {
+        cfg.compact.min_files = 2;
+    }
     if cfg.compact.delete_files_delay_hours < 1 {
         cfg.compact.delete_files_delay_hours = 2;
     }
@@ -2171,6 +2500,12 @@ fn check_compact_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     if cfg.compact.pending_jobs_metric_interval == 0 {
         cfg.compact.pending_jobs_metric_interval = 300;
     }
+    if cfg.compact.metrics_rollup_interval == 0 {
+        cfg.compact.metrics_rollup_interval = 3600;
+    }
+    if cfg.compact.metrics_rollup_delay < 0 {
+        cfg.compact.metrics_rollup_delay = 3600;
+    }
 
     Ok(())
 }
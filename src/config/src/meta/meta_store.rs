@@ -18,6 +18,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum MetaStore {
+    Sled,
     Sqlite,
     Etcd,
     Nats,
@@ -28,6 +29,7 @@ pub enum MetaStore {
 impl From<&str> for MetaStore {
     fn from(s: &str) -> Self {
         match s.to_lowercase().as_str() {
+            "sled" => MetaStore::Sled,
             "sqlite" => MetaStore::Sqlite,
             "etcd" => MetaStore::Etcd,
             "nats" => MetaStore::Nats,
@@ -41,6 +43,7 @@ impl From<&str> for MetaStore {
 impl From<String> for MetaStore {
     fn from(s: String) -> Self {
         match s.to_lowercase().as_str() {
+            "sled" => MetaStore::Sled,
             "sqlite" => MetaStore::Sqlite,
             "etcd" => MetaStore::Etcd,
             "nats" => MetaStore::Nats,
@@ -54,6 +57,7 @@ impl From<String> for MetaStore {
 impl std::fmt::Display for MetaStore {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            MetaStore::Sled => write!(f, "sled"),
             MetaStore::Sqlite => write!(f, "sqlite"),
             MetaStore::Etcd => write!(f, "etcd"),
             MetaStore::Nats => write!(f, "nats"),
@@ -117,6 +117,8 @@ pub struct RequestQuery {
     pub time: Option<String>,
     /// Evaluation timeout.
     pub timeout: Option<String>,
+    /// IANA timezone of the time functions. Defaults to UTC.
+    pub timezone: Option<String>,
 }
 
 /// Range query.
@@ -135,6 +137,8 @@ pub struct RequestRangeQuery {
     pub timeout: Option<String>,
     /// Do not use cache.
     pub no_cache: Option<bool>,
+    /// IANA timezone of the time functions. Defaults to UTC.
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
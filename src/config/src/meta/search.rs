@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use proto::cluster_rpc;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -21,7 +21,7 @@ use utoipa::ToSchema;
 
 use crate::{
     meta::sql::OrderBy,
-    utils::{base64, json},
+    utils::{base64, json, sql::bind_params},
 };
 
 pub const PARTIAL_ERROR_RESPONSE_MESSAGE: &str =
@@ -53,6 +53,8 @@ pub struct Request {
     pub regions: Vec<String>, // default query all regions, local: only query local region clusters
     #[serde(default)]
     pub clusters: Vec<String>, // default query all clusters, local: only query local cluster
+    /// Timeout of the query in seconds, `ZO_QUERY_TIMEOUT` is used when it's 0,
+    /// the query is canceled and responds `408 Request Timeout` after it
     #[serde(default)]
     pub timeout: i64,
     #[serde(default)]
@@ -64,6 +66,44 @@ pub struct Request {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub use_cache: Option<bool>, // used for search job,
+    /// The `next_cursor` of the previous page, replaces `from` for deep
+    /// pagination of the queries sorted by `_timestamp` desc. Only the streams
+    /// with `_o2_id`, which store the original data, get a cursor, and only
+    /// for the queries without aggregates
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Return a `next_cursor` for the first page, the sql is then ordered by
+    /// `_timestamp DESC, _o2_id DESC` to page with the cursor
+    #[serde(default)]
+    pub use_cursor: bool,
+    /// Query these streams together, the stream in the sql is replaced by the
+    /// `UNION ALL` of the streams
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<String>,
+    /// Return `total_hits_lower_bound` estimated from the file list and the
+    /// parquet statistics instead of counting the total hits
+    #[serde(default)]
+    pub approximate_count: bool,
+    /// Pick up the files by the full text search index before scanning them
+    /// for the `field LIKE '%keyword%'` filters on the full text search fields.
+    ///
+    /// This is lossy: the index skips the tokens longer than 40 chars, so the
+    /// rows where the keyword only appears inside such a token are missed,
+    /// e.g. `%abc%` doesn't find a long base64 or hex string containing `abc`.
+    #[serde(default)]
+    pub use_fts: bool,
+    /// Values of the `$name` placeholders in the sql, bound as escaped
+    /// literals when the request is decoded
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[schema(value_type = Object)]
+    pub params: HashMap<String, json::Value>,
+    /// Run the query even if it would scan more than
+    /// `ZO_QUERY_MAX_SCAN_BYTES`, only allowed for the admin users
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -166,6 +206,11 @@ impl Request {
             RequestEncoding::Empty => {}
         }
         self.encoding = RequestEncoding::Empty;
+        if !self.params.is_empty() {
+            self.query.sql = bind_params(&self.query.sql, &self.params)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            self.params.clear();
+        }
         Ok(())
     }
 }
@@ -216,6 +261,14 @@ pub struct Response {
     pub work_group: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub order_by: Option<OrderBy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_hits_lower_bound: Option<usize>,
+    #[serde(default)]
+    pub count_is_approximate: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
@@ -273,6 +326,9 @@ impl Response {
             result_cache_ratio: 0,
             work_group: None,
             order_by: None,
+            next_cursor: None,
+            total_hits_lower_bound: None,
+            count_is_approximate: false,
         }
     }
 
@@ -295,6 +351,11 @@ impl Response {
         // self.total = self.hits.len();
     }
 
+    pub fn set_total_hits_lower_bound(&mut self, val: usize) {
+        self.total_hits_lower_bound = Some(val);
+        self.count_is_approximate = true;
+    }
+
     pub fn add_hit(&mut self, hit: &json::Value) {
         self.hits.push(hit.to_owned());
         self.total += 1;
@@ -509,6 +570,13 @@ impl SearchHistoryRequest {
             search_type: Some(SearchEventType::Other),
             search_event_context: None,
             use_cache: None,
+            cursor: None,
+            use_cursor: false,
+            streams: vec![],
+            approximate_count: false,
+            use_fts: false,
+            params: Default::default(),
+            force: false,
         };
         Ok(search_req)
     }
@@ -1040,6 +1108,13 @@ impl MultiStreamRequest {
                 search_type: self.search_type,
                 search_event_context: self.search_event_context.clone(),
                 use_cache: None,
+                cursor: None,
+                use_cursor: false,
+                streams: vec![],
+                approximate_count: false,
+                use_fts: false,
+                params: Default::default(),
+                force: false,
             });
         }
         res
@@ -13,12 +13,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{cmp::max, fmt::Display};
+use std::{cmp::max, fmt::Display, ops::ControlFlow};
 
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use hashbrown::HashMap;
 use proto::cluster_rpc;
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use sqlparser::{
+    ast::{visit_expressions, Expr as SqlExpr},
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+    tokenizer::Token,
+};
 use utoipa::ToSchema;
 
 use super::bitvec::BitVec;
@@ -527,6 +533,92 @@ pub struct UpdateStreamSettings {
     pub approx_partition: Option<bool>,
     #[serde(default)]
     pub extended_retention_days: UpdateSettingsWrapper<TimeRange>,
+    #[serde(default)]
+    pub derived_fields: UpdateSettingsWrapper<DerivedField>,
+    /// VRL program applied to the records at ingestion, an empty program
+    /// removes the transform
+    #[serde(skip_serializing_if = "Option::None")]
+    #[serde(default)]
+    pub ingest_transform: Option<String>,
+    /// Lua script applied to the records at ingestion, an empty script
+    /// removes the transform
+    #[serde(skip_serializing_if = "Option::None")]
+    #[serde(default)]
+    pub ingest_lua_transform: Option<String>,
+    /// Field with the ip address to enrich with geo info at ingestion, an
+    /// empty field disables the enrichment
+    #[serde(skip_serializing_if = "Option::None")]
+    #[serde(default)]
+    pub geoip_field: Option<String>,
+    /// Fields added to `partition_keys` as value partitions
+    #[serde(default)]
+    pub custom_partition_key: Vec<String>,
+    /// Marks the `{stream}_5m`, `{stream}_1h` and `{stream}_1d` streams
+    /// written by the metrics rollup job, set by the job only
+    #[serde(default)]
+    pub rollup_stream: bool,
+}
+
+/// A virtual column computed from the stored columns of a stream at query
+/// time, eg: `response_time_ms` as `latency_us / 1000`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DerivedField {
+    pub name: String,
+    /// SQL expression over the columns of the stream
+    pub expression: String,
+}
+
+impl DerivedField {
+    /// Only columns, literals, operators and casts are allowed in the
+    /// expression, no function calls or subqueries.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("derived field name can't be empty".to_string());
+        }
+        let invalid = |e: String| format!("invalid derived field [{}]: {e}", self.name);
+        let mut parser = Parser::new(&PostgreSqlDialect {})
+            .try_with_sql(&self.expression)
+            .map_err(|e| invalid(e.to_string()))?;
+        let expr = parser.parse_expr().map_err(|e| invalid(e.to_string()))?;
+        if parser.peek_token().token != Token::EOF {
+            return Err(invalid("expected a single expression".to_string()));
+        }
+        let unsupported = visit_expressions(&expr, |e| match e {
+            SqlExpr::Function(_)
+            | SqlExpr::Subquery(_)
+            | SqlExpr::Exists { .. }
+            | SqlExpr::InSubquery { .. } => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        });
+        if unsupported.is_break() {
+            return Err(invalid(
+                "only columns, literals, operators and casts are supported".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the columns used by the expression.
+    pub fn columns(&self) -> Vec<String> {
+        let Ok(expr) = Parser::new(&PostgreSqlDialect {})
+            .try_with_sql(&self.expression)
+            .and_then(|mut p| p.parse_expr())
+        else {
+            return vec![];
+        };
+        let mut columns = Vec::new();
+        let _ = visit_expressions(&expr, |e| {
+            match e {
+                SqlExpr::Identifier(ident) => columns.push(ident.value.clone()),
+                SqlExpr::CompoundIdentifier(idents) => {
+                    columns.extend(idents.last().map(|ident| ident.value.clone()))
+                }
+                _ => {}
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        columns
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
@@ -654,6 +746,37 @@ pub struct StreamSettings {
     pub index_updated_at: i64,
     #[serde(default)]
     pub extended_retention_days: Vec<TimeRange>,
+    #[serde(default)]
+    pub derived_fields: Vec<DerivedField>,
+    /// VRL program applied to each record before it is written to the wal,
+    /// an `abort` in the program drops the record
+    #[serde(skip_serializing_if = "Option::None")]
+    pub ingest_transform: Option<String>,
+    /// Lua script applied to each record after the `ingest_transform`, the
+    /// script returns `nil` to drop the record
+    #[serde(skip_serializing_if = "Option::None")]
+    pub ingest_lua_transform: Option<String>,
+    /// Field with an ip address, the records are enriched with the
+    /// `_geo_country`, `_geo_city` and `_geo_asn` of the ip at ingestion
+    #[serde(skip_serializing_if = "Option::None")]
+    pub geoip_field: Option<String>,
+    /// Shorthand for value partition keys, the fields are moved into
+    /// `partition_keys` when the settings are saved and never stored
+    #[serde(default)]
+    pub custom_partition_key: Vec<String>,
+}
+
+impl StreamSettings {
+    /// Moves the fields of `custom_partition_key` into `partition_keys` as
+    /// value partitions, the fields which are already partition keys are
+    /// skipped.
+    pub fn apply_custom_partition_key(&mut self) {
+        for field in std::mem::take(&mut self.custom_partition_key) {
+            if !self.partition_keys.iter().any(|k| k.field == field) {
+                self.partition_keys.push(StreamPartition::new(&field));
+            }
+        }
+    }
 }
 
 impl Serialize for StreamSettings {
@@ -681,6 +804,28 @@ impl Serialize for StreamSettings {
         state.serialize_field("approx_partition", &self.approx_partition)?;
         state.serialize_field("index_updated_at", &self.index_updated_at)?;
         state.serialize_field("extended_retention_days", &self.extended_retention_days)?;
+        if !self.derived_fields.is_empty() {
+            state.serialize_field("derived_fields", &self.derived_fields)?;
+        } else {
+            state.skip_field("derived_fields")?;
+        }
+        match self.ingest_transform.as_ref() {
+            Some(func) => state.serialize_field("ingest_transform", func)?,
+            None => state.skip_field("ingest_transform")?,
+        }
+        match self.ingest_lua_transform.as_ref() {
+            Some(script) => state.serialize_field("ingest_lua_transform", script)?,
+            None => state.skip_field("ingest_lua_transform")?,
+        }
+        match self.geoip_field.as_ref() {
+            Some(field) => state.serialize_field("geoip_field", field)?,
+            None => state.skip_field("geoip_field")?,
+        }
+        if self.rollup_stream {
+            state.serialize_field("rollup_stream", &self.rollup_stream)?;
+        } else {
+            state.skip_field("rollup_stream")?;
+        }
 
         match self.defined_schema_fields.as_ref() {
             Some(fields) => {
@@ -825,6 +970,31 @@ impl From<&str> for StreamSettings {
             }
         }
 
+        let derived_fields = settings
+            .get("derived_fields")
+            .and_then(|v| json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+
+        let ingest_transform = settings
+            .get("ingest_transform")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let ingest_lua_transform = settings
+            .get("ingest_lua_transform")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let geoip_field = settings
+            .get("geoip_field")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+        let rollup_stream = settings
+            .get("rollup_stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or_default();
+
         Self {
             partition_time_level,
             partition_keys,
@@ -840,6 +1010,12 @@ impl From<&str> for StreamSettings {
             distinct_value_fields,
             index_updated_at,
             extended_retention_days,
+            derived_fields,
+            ingest_transform,
+            ingest_lua_transform,
+            geoip_field,
+            custom_partition_key: vec![],
+            rollup_stream,
         }
     }
 }
@@ -1195,4 +1371,81 @@ mod tests {
         let expected_res = vec![TimeRange::new(0, 199), TimeRange::new(200, 300)];
         assert_eq!(TimeRange::flatten_overlapping_ranges(ranges), expected_res);
     }
+
+    #[test]
+    fn test_derived_field() {
+        let field = |expression: &str| DerivedField {
+            name: "response_time_ms".to_string(),
+            expression: expression.to_string(),
+        };
+        assert!(field("latency_us / 1000").validate().is_ok());
+        assert!(field("CAST(latency_us AS DOUBLE) / 1000.0")
+            .validate()
+            .is_ok());
+        assert!(field("CASE WHEN code >= 500 THEN 'error' ELSE 'ok' END")
+            .validate()
+            .is_ok());
+        assert!(field("lower(name)").validate().is_err());
+        assert!(field("(SELECT 1)").validate().is_err());
+        assert!(field("a + 1; DROP TABLE t").validate().is_err());
+        assert!(field("a +").validate().is_err());
+        assert_eq!(
+            field("CAST(latency_us AS DOUBLE) / \"Scale\" + t.base").columns(),
+            vec!["latency_us", "Scale", "base"]
+        );
+
+        let settings = StreamSettings {
+            derived_fields: vec![field("latency_us / 1000")],
+            ..Default::default()
+        };
+        let settings = StreamSettings::from(json::to_string(&settings).unwrap().as_str());
+        assert_eq!(settings.derived_fields, vec![field("latency_us / 1000")]);
+    }
+
+    #[test]
+    fn test_stream_settings_custom_partition_key() {
+        let mut settings: StreamSettings = json::from_str(
+            r#"{"partition_keys": [{"field": "host", "types": {"hash": 8}}], "custom_partition_key": ["host", "level"]}"#,
+        )
+        .unwrap();
+        settings.apply_custom_partition_key();
+        assert!(settings.custom_partition_key.is_empty());
+        assert_eq!(
+            settings.partition_keys,
+            vec![
+                StreamPartition::new_hash("host", 8),
+                StreamPartition::new("level")
+            ]
+        );
+        let json = json::to_string(&settings).unwrap();
+        assert!(!json.contains("custom_partition_key"));
+    }
+
+    #[test]
+    fn test_stream_settings_ingest_transform() {
+        let settings = StreamSettings::from(r#"{"ingest_transform": ".a = 1"}"#);
+        assert_eq!(settings.ingest_transform.as_deref(), Some(".a = 1"));
+        let settings = StreamSettings::from(json::to_string(&settings).unwrap().as_str());
+        assert_eq!(settings.ingest_transform.as_deref(), Some(".a = 1"));
+        let settings = StreamSettings::from(r#"{"ingest_transform": ""}"#);
+        assert_eq!(settings.ingest_transform, None);
+        let settings = StreamSettings::from(r#"{"ingest_lua_transform": "return ..."}"#);
+        let settings = StreamSettings::from(json::to_string(&settings).unwrap().as_str());
+        assert_eq!(settings.ingest_lua_transform.as_deref(), Some("return ..."));
+    }
+
+    #[test]
+    fn test_stream_settings_rollup_stream() {
+        let settings = StreamSettings::from("{}");
+        assert!(!settings.rollup_stream);
+        assert!(!json::to_string(&settings)
+            .unwrap()
+            .contains("rollup_stream"));
+        let settings = StreamSettings {
+            rollup_stream: true,
+            ..Default::default()
+        };
+        let settings = StreamSettings::from(json::to_string(&settings).unwrap().as_str());
+        assert!(settings.rollup_stream);
+    }
 }
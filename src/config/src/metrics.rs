@@ -18,7 +18,7 @@ use std::collections::HashMap;
 use actix_web_prometheus::{PrometheusMetrics, PrometheusMetricsBuilder};
 use once_cell::sync::Lazy;
 use prometheus::{
-    CounterVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    CounterVec, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
 };
 
 pub const NAMESPACE: &str = "zo";
@@ -174,6 +174,80 @@ pub static INGEST_WAL_READ_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .expect("Metric created")
 });
+pub static INGEST_UNCOMPRESSED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ingest_uncompressed_bytes",
+            "Ingestor raw json bytes flushed from WAL. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization", "stream", "stream_type"],
+    )
+    .expect("Metric created")
+});
+pub static INGEST_COMPRESSED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ingest_compressed_bytes",
+            "Ingestor compressed parquet bytes flushed from WAL. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization", "stream", "stream_type"],
+    )
+    .expect("Metric created")
+});
+pub static COMPRESSION_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "compression_ratio_gauge",
+            "Ratio of uncompressed to compressed bytes flushed from WAL in the last minute. "
+                .to_owned()
+                + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization", "stream", "stream_type"],
+    )
+    .expect("Metric created")
+});
+pub static WAL_FILES_TOTAL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "openobserve_wal_files_total",
+            "Ingestor WAL files on disk, by type: wal for the memtable logs, parquet for the files waiting for upload. ".to_owned()
+                + HELP_SUFFIX,
+        )
+        .const_labels(create_const_labels()),
+        &["type"],
+    )
+    .expect("Metric created")
+});
+pub static WAL_BYTES_TOTAL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "openobserve_wal_bytes_total",
+            "Ingestor WAL files size on disk, by type: wal for the memtable logs, parquet for the files waiting for upload. ".to_owned()
+                + HELP_SUFFIX,
+        )
+        .const_labels(create_const_labels()),
+        &["type"],
+    )
+    .expect("Metric created")
+});
+pub static INGEST_WAL_UPLOAD_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ingest_wal_upload_failures",
+            "Ingestor WAL file upload failures. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization", "stream_type"],
+    )
+    .expect("Metric created")
+});
 pub static INGEST_MEMTABLE_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new(
@@ -365,6 +439,32 @@ pub static QUERY_METRICS_CACHE_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
     .expect("Metric created")
 });
 
+// query parquet metadata cache stats
+pub static QUERY_PARQUET_META_CACHE_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "query_parquet_meta_cache_hits",
+            "Querier parquet metadata cache hits. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &[],
+    )
+    .expect("Metric created")
+});
+pub static QUERY_PARQUET_META_CACHE_MISSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "query_parquet_meta_cache_misses",
+            "Querier parquet metadata cache misses. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &[],
+    )
+    .expect("Metric created")
+});
+
 // compactor stats
 pub static COMPACT_USED_TIME: Lazy<CounterVec> = Lazy::new(|| {
     CounterVec::new(
@@ -402,6 +502,18 @@ pub static COMPACT_MERGED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .expect("Metric created")
 });
+pub static FILES_DELETED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "files_deleted_total",
+            "Files deleted by the data retention. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization", "stream_type"],
+    )
+    .expect("Metric created")
+});
 pub static COMPACT_DELAY_HOURS: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new(
@@ -534,6 +646,18 @@ pub static STORAGE_TIME: Lazy<CounterVec> = Lazy::new(|| {
     )
     .expect("Metric created")
 });
+pub static STORAGE_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "storage_errors",
+            "Storage failed requests. ".to_owned() + HELP_SUFFIX,
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["method_type", "storage_type"],
+    )
+    .expect("Metric created")
+});
 
 // metadata stats
 pub static META_STORAGE_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
@@ -680,6 +804,18 @@ pub static QUERY_CANCELED_NUMS: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .expect("Metric created")
 });
+pub static QUERY_REJECTED_NUMS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "query_rejected_nums",
+            "Rejected query numbers for exceeding the scan budget",
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["organization"],
+    )
+    .expect("Metric created")
+});
 
 // This corresponds to mysql or pgsql queries, not sqlite as that is local and can be ignored
 pub static DB_QUERY_NUMS: Lazy<IntCounterVec> = Lazy::new(|| {
@@ -764,6 +900,24 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(INGEST_WAL_READ_BYTES.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(INGEST_UNCOMPRESSED_BYTES.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(INGEST_COMPRESSED_BYTES.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(COMPRESSION_RATIO.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(WAL_FILES_TOTAL.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(WAL_BYTES_TOTAL.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(INGEST_WAL_UPLOAD_FAILURES.clone()))
+        .expect("Metric registered");
     registry
         .register(Box::new(INGEST_MEMTABLE_BYTES.clone()))
         .expect("Metric registered");
@@ -811,6 +965,12 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(QUERY_METRICS_CACHE_HITS.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(QUERY_PARQUET_META_CACHE_HITS.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(QUERY_PARQUET_META_CACHE_MISSES.clone()))
+        .expect("Metric registered");
 
     // query manager
     registry
@@ -825,6 +985,9 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(QUERY_CANCELED_NUMS.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(QUERY_REJECTED_NUMS.clone()))
+        .expect("Metric registered");
 
     // compactor stats
     registry
@@ -836,6 +999,9 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(COMPACT_MERGED_BYTES.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(FILES_DELETED_TOTAL.clone()))
+        .expect("Metric registered");
     registry
         .register(Box::new(COMPACT_DELAY_HOURS.clone()))
         .expect("Metric registered");
@@ -871,6 +1037,9 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(STORAGE_WRITE_REQUESTS.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(STORAGE_ERRORS.clone()))
+        .expect("Metric registered");
     // metadata stats
     registry
         .register(Box::new(META_STORAGE_BYTES.clone()))
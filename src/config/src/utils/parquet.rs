@@ -28,7 +28,7 @@ use parquet::{
         arrow_reader::ArrowReaderMetadata, async_reader::ParquetRecordBatchStream,
         AsyncArrowWriter, ParquetRecordBatchStreamBuilder,
     },
-    basic::{Compression, Encoding},
+    basic::{Compression, Encoding, ZstdLevel},
     file::{metadata::KeyValue, properties::WriterProperties},
 };
 
@@ -45,7 +45,10 @@ pub fn new_parquet_writer<'a>(
         .set_write_batch_size(PARQUET_BATCH_SIZE) // in bytes
         .set_data_page_size_limit(PARQUET_PAGE_SIZE) // maximum size of a data page in bytes
         .set_max_row_group_size(PARQUET_MAX_ROW_GROUP_SIZE) // maximum number of rows in a row group
-        .set_compression(Compression::ZSTD(Default::default()))
+        .set_compression(get_parquet_compression(
+            &cfg.common.parquet_compression,
+            cfg.common.parquet_compression_level,
+        ))
         .set_column_dictionary_enabled(
             cfg.common.column_timestamp.as_str().into(),
             false,
@@ -86,6 +89,16 @@ pub fn new_parquet_writer<'a>(
     AsyncArrowWriter::try_new(buf, schema.clone(), Some(writer_props)).unwrap()
 }
 
+/// Get the parquet compression codec by name, fallback to zstd. The level is
+/// only used by zstd.
+pub fn get_parquet_compression(name: &str, level: i32) -> Compression {
+    match name {
+        "lz4" => Compression::LZ4_RAW,
+        "none" => Compression::UNCOMPRESSED,
+        _ => Compression::ZSTD(ZstdLevel::try_new(level).unwrap_or_default()),
+    }
+}
+
 pub async fn write_recordbatch_to_parquet(
     schema: Arc<Schema>,
     record_batches: &[RecordBatch],
@@ -212,3 +225,94 @@ pub fn parse_time_range_from_filename(mut name: &str) -> (i64, i64) {
     let max_ts = columns[1].parse::<i64>().unwrap_or(0);
     (min_ts, max_ts)
 }
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Int64Array, StringArray};
+    use arrow_schema::{DataType, Field};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parquet_compression_round_trip() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("log", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+            ],
+        )
+        .unwrap();
+
+        for codec in ["zstd", "lz4", "none"] {
+            let props = WriterProperties::builder()
+                .set_compression(get_parquet_compression(codec, 3))
+                .build();
+            let mut buf = Vec::new();
+            let mut writer =
+                AsyncArrowWriter::try_new(&mut buf, schema.clone(), Some(props)).unwrap();
+            writer.write(&batch).await.unwrap();
+            writer.close().await.unwrap();
+
+            let (_, batches) = read_recordbatch_from_bytes(&bytes::Bytes::from(buf))
+                .await
+                .unwrap();
+            assert_eq!(batches, vec![batch.clone()], "codec: {codec}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parquet_row_group_bloom_filter() {
+        use parquet::file::{
+            properties::ReaderProperties,
+            reader::{FileReader, SerializedFileReader},
+            serialized_reader::ReadOptionsBuilder,
+        };
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("trace_id", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["t1", "t2", "t3"])),
+            ],
+        )
+        .unwrap();
+        let meta = FileMeta {
+            records: 3,
+            ..Default::default()
+        };
+        let buf = write_recordbatch_to_parquet(
+            schema.clone(),
+            &[batch],
+            &["trace_id".to_string()],
+            &meta,
+        )
+        .await
+        .unwrap();
+
+        let options = ReadOptionsBuilder::new()
+            .with_reader_properties(
+                ReaderProperties::builder()
+                    .set_read_bloom_filter(true)
+                    .build(),
+            )
+            .build();
+        let reader =
+            SerializedFileReader::new_with_options(bytes::Bytes::from(buf), options).unwrap();
+        let row_group = reader.get_row_group(0).unwrap();
+        // the filter of the row group lets the search skip it for absent values
+        let bloom_filter = row_group.get_column_bloom_filter(1).unwrap();
+        assert!(bloom_filter.check("t2"));
+        assert!(!bloom_filter.check("t4"));
+        // no filter for the fields not configured
+        assert!(row_group.get_column_bloom_filter(0).is_none());
+    }
+}
@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::ops::ControlFlow;
+use std::{collections::HashMap, ops::ControlFlow};
 
 use sqlparser::{
     ast::{Expr, Function, GroupByExpr, Query, SelectItem, SetExpr, Statement, Visit, Visitor},
@@ -21,6 +21,8 @@ use sqlparser::{
     parser::Parser,
 };
 
+use crate::utils::{json, time::parse_str_to_timestamp_micros};
+
 pub const AGGREGATE_UDF_LIST: [&str; 9] = [
     "min",
     "max",
@@ -86,7 +88,7 @@ fn is_aggregate_in_select(query: &Query) -> bool {
     false
 }
 
-fn is_aggregate_expression(expr: &Expr) -> bool {
+pub fn is_aggregate_expression(expr: &Expr) -> bool {
     match expr {
         Expr::Function(Function { name, .. }) => {
             AGGREGATE_UDF_LIST.contains(&name.to_string().to_lowercase().as_str())
@@ -194,3 +196,108 @@ impl Visitor for SubqueryVisitor {
         ControlFlow::Continue(())
     }
 }
+
+/// Replaces the `$name` placeholders in the sql with the escaped literals of
+/// the params, the placeholders inside quoted strings or identifiers and the
+/// ones without a param are kept as they are.
+///
+/// Strings are single quoted, numbers and booleans are written as is, arrays
+/// become comma separated lists for `IN ($name)`, and `{"timestamp": "..."}`
+/// becomes the timestamp in microseconds.
+pub fn bind_params(sql: &str, params: &HashMap<String, json::Value>) -> Result<String, String> {
+    let mut result = String::with_capacity(sql.len());
+    let mut chars = sql.char_indices().peekable();
+    let mut quote = None;
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '$') => {
+                let start = i + 1;
+                let mut end = start;
+                while let Some((j, c)) = chars.peek().copied() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                let name = &sql[start..end];
+                match params.get(name) {
+                    Some(value) => result.push_str(&param_literal(name, value, true)?),
+                    None => result.push_str(&sql[i..end]),
+                }
+                continue;
+            }
+            _ => {}
+        }
+        result.push(c);
+    }
+    Ok(result)
+}
+
+fn param_literal(name: &str, value: &json::Value, allow_array: bool) -> Result<String, String> {
+    Ok(match value {
+        json::Value::Null => "NULL".to_string(),
+        json::Value::Bool(v) => v.to_string().to_uppercase(),
+        json::Value::Number(v) => v.to_string(),
+        json::Value::String(v) => format!("'{}'", v.replace('\'', "''")),
+        json::Value::Array(values) if allow_array && !values.is_empty() => values
+            .iter()
+            .map(|v| param_literal(name, v, false))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", "),
+        json::Value::Object(v) => match v.get("timestamp").and_then(|v| v.as_str()) {
+            Some(ts) if v.len() == 1 => parse_str_to_timestamp_micros(ts)
+                .map_err(|e| format!("Invalid timestamp of param {name}: {e}"))?
+                .to_string(),
+            _ => return Err(format!("Unsupported value of param {name}")),
+        },
+        _ => return Err(format!("Unsupported value of param {name}")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_params() {
+        let params: HashMap<String, json::Value> = json::from_str(
+            r#"{
+                "name": "o'brien",
+                "code": 500,
+                "ratio": 0.5,
+                "ok": true,
+                "hosts": ["a", "b'c"],
+                "since": {"timestamp": "2024-01-01T00:00:00Z"}
+            }"#,
+        )
+        .unwrap();
+        let sql = "SELECT * FROM logs WHERE name = $name AND code = $code AND ratio > $ratio \
+                   AND ok = $ok AND host IN ($hosts) AND _timestamp >= $since";
+        assert_eq!(
+            bind_params(sql, &params).unwrap(),
+            "SELECT * FROM logs WHERE name = 'o''brien' AND code = 500 AND ratio > 0.5 \
+             AND ok = TRUE AND host IN ('a', 'b''c') AND _timestamp >= 1704067200000000"
+        );
+
+        // the injected quote can't close the literal
+        let params: HashMap<String, json::Value> =
+            json::from_str(r#"{"name": "x' OR '1'='1"}"#).unwrap();
+        assert_eq!(
+            bind_params("SELECT * FROM logs WHERE name = $name", &params).unwrap(),
+            "SELECT * FROM logs WHERE name = 'x'' OR ''1''=''1'"
+        );
+
+        // placeholders in quotes and unknown placeholders are kept
+        let sql = "SELECT '$name', \"$name\" FROM logs WHERE a = $other";
+        assert_eq!(bind_params(sql, &params).unwrap(), sql);
+
+        let params: HashMap<String, json::Value> =
+            json::from_str(r#"{"nested": [[1]], "obj": {"a": 1}}"#).unwrap();
+        assert!(bind_params("SELECT $nested", &params).is_err());
+        assert!(bind_params("SELECT $obj", &params).is_err());
+    }
+}
@@ -36,7 +36,15 @@ pub fn check_auth(req: Request<()>) -> Result<Request<()>, Status> {
         .to_str()
         .unwrap()
         .to_string();
+    // the Flight SQL clients send the basic credentials of their handshake
+    // back as a bearer token
+    let token = match token.strip_prefix("Bearer ") {
+        Some(credentials) => format!("Basic {credentials}"),
+        None => token,
+    };
     if token.eq(get_internal_grpc_token().as_str()) {
+        // the internal token is only known to the nodes of the cluster, they
+        // forward the user they already authenticated
         Ok(req)
     } else {
         let org_id = metadata.get(&cfg.grpc.org_header_key);
@@ -68,17 +76,18 @@ pub fn check_auth(req: Request<()>) -> Result<Request<()>, Status> {
             return Err(Status::unauthenticated("No valid auth token"));
         };
 
-        if user.token.eq(&credentials.password) {
-            return Ok(req);
-        }
         let in_pass = get_hash(&credentials.password, &user.salt);
-        if user_id.eq(&user.email)
-            && (credentials.password.eq(&user.password) || in_pass.eq(&user.password))
+        if user.token.eq(&credentials.password)
+            || (user_id.eq(&user.email)
+                && (credentials.password.eq(&user.password) || in_pass.eq(&user.password)))
         {
+            let user_id_metadata = MetadataValue::try_from(&user_id)
+                .map_err(|_| Status::unauthenticated("No valid auth token"))?;
+            // the user id of the request is replaced by the verified one, the
+            // services trust it for the stream permissions
             let mut req = req;
-            let user_id_metadata = MetadataValue::try_from(&user_id).unwrap();
-            req.metadata_mut().append("user_id", user_id_metadata);
-
+            req.metadata_mut().remove("user_id");
+            req.metadata_mut().insert("user_id", user_id_metadata);
             Ok(req)
         } else {
             Err(Status::unauthenticated("No valid auth token"))
@@ -89,6 +98,7 @@ pub fn check_auth(req: Request<()>) -> Result<Request<()>, Status> {
 #[cfg(test)]
 mod tests {
     use config::{cache_instance_id, get_config};
+    use tonic::metadata::MetadataKey;
 
     use super::*;
     use crate::common::meta::user::User;
@@ -190,4 +200,49 @@ mod tests {
         let res = check_auth(request);
         assert!(res.is_err())
     }
+
+    #[tokio::test]
+    async fn test_check_auth_user_id() {
+        let user = User {
+            email: "root@example.com".to_string(),
+            password: "Complexpass#123".to_string(),
+            role: crate::common::meta::user::UserRole::Root,
+            salt: "Complexpass#123".to_string(),
+            first_name: "root".to_owned(),
+            last_name: "".to_owned(),
+            token: "token".to_string(),
+            rum_token: Some("rum_token".to_string()),
+            org: "default".to_owned(),
+            is_external: false,
+            password_ext: Some("Complexpass#123".to_string()),
+        };
+        USERS.insert("default/root@example.com".to_string(), user.clone());
+        ROOT_USER.insert("root".to_string(), user);
+
+        let mut request = tonic::Request::new(());
+        let meta = request.metadata_mut();
+        meta.insert(
+            get_config()
+                .grpc
+                .org_header_key
+                .as_str()
+                .parse::<MetadataKey<_>>()
+                .unwrap(),
+            "default".parse().unwrap(),
+        );
+        meta.insert(
+            "authorization",
+            "basic cm9vdEBleGFtcGxlLmNvbTp0b2tlbg==".parse().unwrap(),
+        );
+        meta.append("user_id", "admin@example.com".parse().unwrap());
+
+        let req = check_auth(request).unwrap();
+        let user_ids = req
+            .metadata()
+            .get_all("user_id")
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(user_ids, vec!["root@example.com"]);
+    }
 }
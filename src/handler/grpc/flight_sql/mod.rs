@@ -0,0 +1,517 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{pin::Pin, sync::Arc};
+
+use arrow::array::{RecordBatch, StringArray};
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    sql::{
+        server::FlightSqlService, Any, CommandGetTableTypes, CommandGetTables,
+        CommandStatementQuery, ProstMessageExt, SqlInfo, TicketStatementQuery,
+    },
+    FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse,
+    Ticket,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use config::{
+    get_config, ider,
+    meta::{
+        search,
+        sql::{resolve_stream_names_with_type, Sql, TableReferenceExt},
+        stream::StreamType,
+    },
+    metrics,
+    utils::{
+        json, record_batch_ext::convert_json_to_record_batch,
+        schema::infer_json_schema_from_values, time::now_micros,
+    },
+};
+use futures::{stream, Stream, TryStreamExt};
+use hashlink::lru_cache::LruCache;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tonic::{metadata::MetadataMap, Request, Response, Status, Streaming};
+
+#[cfg(feature = "enterprise")]
+use crate::handler::http::request::search::utils::check_stream_permissions;
+use crate::{
+    common::utils::stream::get_settings_max_query_range, service::search as SearchService,
+};
+
+/// The metadata key of the stream type of the unqualified streams in the sql,
+/// `logs` by default. Qualified streams, eg: `metrics.cpu`, use their schema.
+const STREAM_TYPE_HEADER_KEY: &str = "stream-type";
+
+/// The streams are listed as tables of the schemas of their stream types.
+const TABLE_TYPE: &str = "TABLE";
+const TABLE_SCHEMAS: [StreamType; 3] = [StreamType::Logs, StreamType::Metrics, StreamType::Traces];
+
+/// The number of statement results kept between their flight info and their
+/// fetch.
+const RESULTS_CACHE_SIZE: usize = 16;
+
+type DoGetStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send + 'static>>;
+
+/// The statements are executed by `get_flight_info_statement` for the schema
+/// of their results, which are taken by the `do_get_statement` of the ticket.
+static RESULTS: Lazy<Mutex<LruCache<String, StatementResult>>> =
+    Lazy::new(|| Mutex::new(LruCache::new(RESULTS_CACHE_SIZE)));
+
+struct StatementResult {
+    org_id: String,
+    user_id: String,
+    batch: RecordBatch,
+}
+
+/// The statement handle of the tickets, the sql is executed again when the
+/// result is not cached, eg: the ticket is fetched from another querier.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct StatementHandle {
+    id: String,
+    sql: String,
+}
+
+/// Arrow Flight SQL service for BI tools, the sql statements are executed
+/// by the search service of the organization of the request.
+#[derive(Default)]
+pub struct FlightSqlServiceImpl;
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServiceImpl {
+    type FlightService = FlightSqlServiceImpl;
+
+    /// The requests are authenticated by the grpc interceptor, so the basic
+    /// credentials are handed back as the bearer token of the following
+    /// requests, which the interceptor reads as basic credentials again.
+    async fn do_handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<
+        Response<Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>>,
+        Status,
+    > {
+        let credentials = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+            .map(|(_, credentials)| credentials.trim().to_string())
+            .ok_or_else(|| Status::unauthenticated("No valid auth token"))?;
+        let token = format!("Bearer {credentials}")
+            .parse()
+            .map_err(|_| Status::unauthenticated("No valid auth token"))?;
+        let output: Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>> =
+            Box::pin(stream::iter(vec![Ok(HandshakeResponse::default())]));
+        let mut resp = Response::new(output);
+        resp.metadata_mut().insert("authorization", token);
+        Ok(resp)
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let (org_id, user_id) = get_org_and_user(request.metadata())?;
+        let stream_type = get_stream_type(request.metadata())?;
+        let batch = execute_query(&org_id, &user_id, stream_type, &query.query).await?;
+        let schema = batch.schema();
+        let handle = StatementHandle {
+            id: ider::uuid(),
+            sql: query.query,
+        };
+        let ticket = TicketStatementQuery {
+            statement_handle: json::to_vec(&handle)
+                .map_err(|e| Status::internal(e.to_string()))?
+                .into(),
+        };
+        RESULTS.lock().insert(
+            handle.id,
+            StatementResult {
+                org_id,
+                user_id,
+                batch,
+            },
+        );
+        flight_info(ticket.as_any(), Some(&schema), request.into_inner())
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let handle: StatementHandle = json::from_slice(&ticket.statement_handle)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let (org_id, user_id) = get_org_and_user(request.metadata())?;
+        let stream_type = get_stream_type(request.metadata())?;
+        // the result is only handed to the user who executed the statement
+        let cached = RESULTS
+            .lock()
+            .remove(&handle.id)
+            .filter(|r| r.org_id == org_id && r.user_id == user_id);
+        let batch = match cached {
+            Some(r) => r.batch,
+            None => execute_query(&org_id, &user_id, stream_type, &handle.sql).await?,
+        };
+        Ok(Response::new(encode_batch(batch.schema(), batch)))
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(query.as_any(), Some(&schema), request.into_inner())
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let (org_id, _user_id) = get_org_and_user(request.metadata())?;
+        let include_schema = query.include_schema;
+        let mut builder = query.into_builder();
+        for stream_type in TABLE_SCHEMAS {
+            let streams =
+                crate::service::stream::get_streams(&org_id, Some(stream_type), false, None).await;
+            for s in streams {
+                #[cfg(feature = "enterprise")]
+                if check_stream_permissions(&s.name, &org_id, &_user_id, &stream_type)
+                    .await
+                    .is_some()
+                {
+                    continue;
+                }
+                let schema = if include_schema {
+                    infra::schema::get(&org_id, &s.name, stream_type)
+                        .await
+                        .map_err(|e| Status::internal(e.to_string()))?
+                } else {
+                    Schema::empty()
+                };
+                builder
+                    .append(
+                        &org_id,
+                        stream_type.to_string(),
+                        &s.name,
+                        TABLE_TYPE,
+                        &schema,
+                    )
+                    .map_err(|e| Status::internal(e.to_string()))?;
+            }
+        }
+        let schema = builder.schema();
+        let batch = builder
+            .build()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(encode_batch(schema, batch)))
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = table_types_batch().schema();
+        flight_info(query.as_any(), Some(&schema), request.into_inner())
+    }
+
+    async fn do_get_table_types(
+        &self,
+        _query: CommandGetTableTypes,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let batch = table_types_batch();
+        Ok(Response::new(encode_batch(batch.schema(), batch)))
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+/// A flight of one endpoint whose ticket is the command itself.
+fn flight_info(
+    command: Any,
+    schema: Option<&Schema>,
+    descriptor: FlightDescriptor,
+) -> Result<Response<FlightInfo>, Status> {
+    let ticket = Ticket::new(command.encode_to_vec());
+    let info = FlightInfo::new()
+        .with_endpoint(FlightEndpoint::new().with_ticket(ticket))
+        .with_descriptor(descriptor);
+    let info = match schema {
+        Some(schema) => info
+            .try_with_schema(schema)
+            .map_err(|e| Status::internal(e.to_string()))?,
+        None => info,
+    };
+    Ok(Response::new(info))
+}
+
+fn encode_batch(schema: SchemaRef, batch: RecordBatch) -> DoGetStream {
+    let stream = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(stream::iter(vec![Ok(batch)]))
+        .map_err(Status::from);
+    Box::pin(stream)
+}
+
+fn table_types_batch() -> RecordBatch {
+    let schema = Schema::new(vec![Field::new("table_type", DataType::Utf8, false)]);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Arc::new(StringArray::from(vec![TABLE_TYPE]))],
+    )
+    .unwrap()
+}
+
+fn get_org_and_user(metadata: &MetadataMap) -> Result<(String, String), Status> {
+    let org_header_key = &get_config().grpc.org_header_key;
+    let org_id = metadata
+        .get(org_header_key)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            Status::invalid_argument(format!(
+                "Please specify organization id with header key '{org_header_key}'"
+            ))
+        })?;
+    let user_id = metadata
+        .get("user_id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    Ok((org_id.to_string(), user_id.to_string()))
+}
+
+fn get_stream_type(metadata: &MetadataMap) -> Result<StreamType, Status> {
+    match metadata
+        .get(STREAM_TYPE_HEADER_KEY)
+        .and_then(|v| v.to_str().ok())
+    {
+        None => Ok(StreamType::Logs),
+        Some(v) => match StreamType::from(v) {
+            StreamType::Logs if !v.eq_ignore_ascii_case("logs") => Err(Status::invalid_argument(
+                format!("Unsupported stream type: {v}"),
+            )),
+            stream_type => Ok(stream_type),
+        },
+    }
+}
+
+/// Runs the sql in the time range of its `_timestamp` filters. Without a
+/// lower bound it covers the max query range of the streams, or all their data
+/// when they have none. The statements over the max query range or the scan
+/// budget, or returning more than `ZO_FLIGHT_SQL_MAX_ROWS` rows are rejected.
+async fn execute_query(
+    org_id: &str,
+    user_id: &str,
+    stream_type: StreamType,
+    sql: &str,
+) -> Result<RecordBatch, Status> {
+    let cfg = get_config();
+    let streams =
+        resolve_stream_names_with_type(sql).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+    let (mut start_time, end_time) = statement_time_range(sql);
+    let mut max_query_range = 0;
+    for stream in streams.iter() {
+        let stream_name = stream.stream_name();
+        let stream_type = stream.get_stream_type(stream_type);
+        #[cfg(feature = "enterprise")]
+        if check_stream_permissions(&stream_name, org_id, user_id, &stream_type)
+            .await
+            .is_some()
+        {
+            return Err(Status::permission_denied(format!(
+                "Unauthorized access to stream {stream_name}"
+            )));
+        }
+        if let Some(settings) = infra::schema::get_settings(org_id, &stream_name, stream_type).await
+        {
+            let range = get_settings_max_query_range(
+                settings.max_query_range,
+                org_id,
+                (!user_id.is_empty()).then_some(user_id),
+            )
+            .await;
+            if range > 0 && (max_query_range == 0 || range < max_query_range) {
+                max_query_range = range;
+            }
+        }
+    }
+    if max_query_range > 0 {
+        let max_range = max_query_range * 3600 * 1_000_000;
+        if start_time == 0 {
+            start_time = end_time - max_range;
+        } else if end_time - start_time > max_range {
+            return Err(Status::invalid_argument(format!(
+                "Query duration is over the query range restriction of {max_query_range} hours"
+            )));
+        }
+    }
+
+    // reject the statements which would scan too much
+    let max_scan_bytes = cfg.limit.query_max_scan_bytes;
+    if max_scan_bytes > 0 {
+        let mut scan_bytes = 0;
+        for stream in streams.iter() {
+            scan_bytes += SearchService::scan_budget::estimate_scan_bytes(
+                org_id,
+                stream.get_stream_type(stream_type),
+                &[stream.stream_name()],
+                start_time,
+                end_time,
+            )
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        }
+        if let Err(e) = SearchService::scan_budget::check_scan_budget(scan_bytes, max_scan_bytes) {
+            metrics::QUERY_REJECTED_NUMS
+                .with_label_values(&[org_id])
+                .inc();
+            return Err(Status::resource_exhausted(e));
+        }
+    }
+
+    // one more row than the limit tells apart the results which don't fit
+    let max_rows = cfg.grpc.flight_sql_max_rows;
+    let req = search::Request {
+        query: search::Query {
+            sql: sql.to_string(),
+            size: max_rows + 1,
+            start_time,
+            end_time,
+            ..Default::default()
+        },
+        encoding: search::RequestEncoding::Empty,
+        regions: vec![],
+        clusters: vec![],
+        timeout: 0,
+        search_type: None,
+        search_event_context: None,
+        use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
+    };
+    let user_id = (!user_id.is_empty()).then(|| user_id.to_string());
+    let res = SearchService::search("", org_id, stream_type, user_id, &req)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    if res.hits.len() as i64 > max_rows {
+        return Err(Status::resource_exhausted(format!(
+            "The statement returns more than {max_rows} rows, add a LIMIT or narrow down the time range"
+        )));
+    }
+    hits_to_batch(&res.hits).map_err(|e| Status::internal(e.to_string()))
+}
+
+/// The time range of the `_timestamp` filters of the sql, the end is now when
+/// unbounded and the start is 0.
+fn statement_time_range(sql: &str) -> (i64, i64) {
+    let now = now_micros();
+    let (start_time, end_time) = Sql::new(sql)
+        .ok()
+        .and_then(|s| s.time_range)
+        .unwrap_or_default();
+    // the end of the search is exclusive, `_timestamp <= t` needs t itself
+    let end_time = if end_time > 0 {
+        std::cmp::min(end_time + 1, now)
+    } else {
+        now
+    };
+    (start_time, end_time)
+}
+
+/// Converts the hits to a record batch, the columns are inferred from the
+/// values of the hits.
+fn hits_to_batch(hits: &[json::Value]) -> Result<RecordBatch, arrow_schema::ArrowError> {
+    if hits.is_empty() {
+        return Ok(RecordBatch::new_empty(Arc::new(Schema::empty())));
+    }
+    let schema = Arc::new(infer_json_schema_from_values(
+        hits.iter(),
+        StreamType::Logs,
+    )?);
+    let hits = hits.iter().cloned().map(Arc::new).collect::<Vec<_>>();
+    convert_json_to_record_batch(&schema, &hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_table_types_batch() {
+        let batch = table_types_batch();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.schema().field(0).name(), "table_type");
+    }
+
+    #[test]
+    fn test_hits_to_batch() {
+        let hits = vec![
+            json::json!({"_timestamp": 1, "level": "info"}),
+            json::json!({"_timestamp": 2, "level": "error", "code": 500}),
+        ];
+        let batch = hits_to_batch(&hits).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let code = batch.column_by_name("code").unwrap();
+        assert_eq!(code.null_count(), 1);
+        assert!(hits_to_batch(&[]).unwrap().num_rows() == 0);
+    }
+
+    #[test]
+    fn test_get_stream_type() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(get_stream_type(&metadata).unwrap(), StreamType::Logs);
+        metadata.insert(STREAM_TYPE_HEADER_KEY, "metrics".parse().unwrap());
+        assert_eq!(get_stream_type(&metadata).unwrap(), StreamType::Metrics);
+        metadata.insert(STREAM_TYPE_HEADER_KEY, "foo".parse().unwrap());
+        assert!(get_stream_type(&metadata).is_err());
+    }
+
+    #[test]
+    fn test_statement_time_range() {
+        let (start, end) = statement_time_range(
+            "SELECT * FROM t WHERE _timestamp >= 1666093521151350 AND _timestamp < 1666093521151351",
+        );
+        assert_eq!((start, end), (1666093521151350, 1666093521151352));
+        let (start, end) = statement_time_range("SELECT * FROM metrics.cpu");
+        assert_eq!(start, 0);
+        assert!(end > 1666093521151351);
+    }
+
+    #[test]
+    fn test_statement_handle() {
+        let handle = StatementHandle {
+            id: ider::uuid(),
+            sql: "SELECT * FROM t".to_string(),
+        };
+        let bytes = json::to_vec(&handle).unwrap();
+        assert_eq!(json::from_slice::<StatementHandle>(&bytes).unwrap(), handle);
+    }
+}
@@ -23,6 +23,7 @@ use crate::service::promql;
 
 pub mod auth;
 pub mod flight;
+pub mod flight_sql;
 pub mod request;
 
 pub struct MetadataMap<'a>(&'a tonic::metadata::MetadataMap);
@@ -54,6 +55,7 @@ impl From<promql::MetricsQueryRequest> for cluster_rpc::MetricsQueryRequest {
             end: req.end,
             step: req.step,
             query_exemplars: req.query_exemplars,
+            timezone: req.timezone.unwrap_or_default(),
         };
 
         let job = cluster_rpc::Job {
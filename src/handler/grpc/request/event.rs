@@ -29,7 +29,10 @@ use proto::cluster_rpc::{event_server::Event, EmptyResponse, FileList};
 use tonic::{Request, Response, Status};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::{common::infra::cluster::get_node_from_consistent_hash, handler::grpc::MetadataMap};
+use crate::{
+    common::infra::cluster::get_node_from_consistent_hash, handler::grpc::MetadataMap,
+    service::search::cache::query_cache::QUERY_CACHE,
+};
 
 pub struct Eventer;
 
@@ -88,6 +91,13 @@ impl Event for Eventer {
             }
         }
 
+        // drop the cached responses which don't include the new files
+        if LOCAL_NODE.is_querier() {
+            for item in put_items.iter() {
+                QUERY_CACHE.invalidate_file(&item.key, &item.meta).await;
+            }
+        }
+
         // cache latest files for querier
         if cfg.memory_cache.cache_latest_files && LOCAL_NODE.is_querier() {
             for item in put_items.iter() {
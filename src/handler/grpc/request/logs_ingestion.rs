@@ -0,0 +1,138 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::{metrics, utils::json};
+use proto::cluster_rpc::{
+    logs_ingestion_service_server::LogsIngestionService, LogsIngestionRequest,
+    LogsIngestionResponse,
+};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::common::meta::ingestion::IngestionRequest;
+
+#[derive(Default)]
+pub struct LogsIngester;
+
+#[tonic::async_trait]
+impl LogsIngestionService for LogsIngester {
+    /// Ingests a stream of log records into the org and stream given in the
+    /// request metadata, writing them to the WAL every
+    /// `ZO_GRPC_BATCH_SIZE` records.
+    async fn ingest_stream(
+        &self,
+        request: Request<Streaming<LogsIngestionRequest>>,
+    ) -> Result<Response<LogsIngestionResponse>, Status> {
+        let start = std::time::Instant::now();
+        let cfg = config::get_config();
+
+        let metadata = request.metadata();
+        let Some(org_id) = metadata
+            .get(&cfg.grpc.org_header_key)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+        else {
+            return Err(Status::invalid_argument(format!(
+                "Please specify organization id with header key '{}' ",
+                &cfg.grpc.org_header_key
+            )));
+        };
+        let stream_name = metadata
+            .get(&cfg.grpc.stream_header_key)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("default")
+            .to_string();
+        let user_email = metadata
+            .get("user_id")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let batch_size = cfg.limit.grpc_batch_size;
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut resp = LogsIngestionResponse::default();
+        let mut in_stream = request.into_inner();
+        while let Some(req) = in_stream.message().await? {
+            for record in req.records {
+                match json::from_slice::<json::Value>(&record) {
+                    Ok(v) => batch.push(v),
+                    Err(e) => {
+                        resp.failed += 1;
+                        resp.error = format!("invalid json record: {e}");
+                    }
+                }
+                if batch.len() >= batch_size {
+                    flush(&org_id, &stream_name, &user_email, &mut batch, &mut resp).await;
+                }
+            }
+        }
+        flush(&org_id, &stream_name, &user_email, &mut batch, &mut resp).await;
+
+        // metrics
+        let time = start.elapsed().as_secs_f64();
+        metrics::GRPC_RESPONSE_TIME
+            .with_label_values(&["/ingest/logs/stream", "200", "", "", ""])
+            .observe(time);
+        metrics::GRPC_INCOMING_REQUESTS
+            .with_label_values(&["/ingest/logs/stream", "200", "", "", ""])
+            .inc();
+
+        Ok(Response::new(resp))
+    }
+}
+
+async fn flush(
+    org_id: &str,
+    stream_name: &str,
+    user_email: &str,
+    batch: &mut Vec<json::Value>,
+    resp: &mut LogsIngestionResponse,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let records = batch.len() as u64;
+    let data = json::to_vec(batch).map(bytes::Bytes::from);
+    batch.clear();
+    let ret = match data {
+        Ok(data) => {
+            crate::service::logs::ingest::ingest(
+                0,
+                org_id,
+                stream_name,
+                IngestionRequest::JSON(&data),
+                user_email,
+                None,
+            )
+            .await
+        }
+        Err(e) => Err(e.into()),
+    };
+    match ret {
+        Ok(ret) => {
+            for stream in ret.status {
+                resp.successful += stream.status.successful as u64;
+                resp.failed += stream.status.failed as u64;
+                if !stream.status.error.is_empty() {
+                    resp.error = stream.status.error;
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("[gRPC] logs streaming ingestion error: {}", e);
+            resp.failed += records;
+            resp.error = e.to_string();
+        }
+    }
+}
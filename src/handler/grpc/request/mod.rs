@@ -16,7 +16,9 @@
 pub mod event;
 pub mod ingest;
 pub mod logs;
+pub mod logs_ingestion;
 pub mod metrics;
 pub mod query_cache;
 pub mod search;
 pub mod traces;
+pub mod vector;
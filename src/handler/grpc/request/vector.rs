@@ -0,0 +1,115 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! gRPC server of the Vector `vector` sink, enabled on the ingesters with
+//! `ZO_VECTOR_ENABLED=true`. The logs are ingested into `ZO_VECTOR_ORG`
+//! (`default`) / `ZO_VECTOR_STREAM` (`vector`).
+//!
+//! The sink can't send credentials, so the server listens on its own port,
+//! `ZO_VECTOR_PORT` (6000), and only accepts the clients with a certificate
+//! signed by `ZO_VECTOR_TLS_CLIENT_CA_PATH`. The server certificate is
+//! `ZO_VECTOR_TLS_CERT_PATH` / `ZO_VECTOR_TLS_KEY_PATH`. The matching Vector
+//! config:
+//!
+//! ```toml
+//! [sinks.openobserve]
+//! type = "vector"
+//! inputs = ["app_logs"]
+//! address = "ingester:6000"
+//! compression = true
+//! tls.enabled = true
+//! tls.ca_file = "/etc/vector/openobserve-ca.pem"
+//! tls.crt_file = "/etc/vector/client.pem"
+//! tls.key_file = "/etc/vector/client.key"
+//! ```
+
+use config::metrics;
+use proto::vector_rpc::{
+    vector_server::Vector, HealthCheckRequest, HealthCheckResponse, PushEventsRequest,
+    PushEventsResponse, ServingStatus,
+};
+use tonic::{Request, Response, Status};
+
+use crate::service::logs::vector;
+
+#[derive(Default)]
+pub struct VectorIngester;
+
+#[tonic::async_trait]
+impl Vector for VectorIngester {
+    /// Ingests the log events pushed by the Vector sink into `ZO_VECTOR_ORG` /
+    /// `ZO_VECTOR_STREAM`. A failed ingestion is returned to the sink as an
+    /// error, so the batch is not acknowledged.
+    async fn push_events(
+        &self,
+        request: Request<PushEventsRequest>,
+    ) -> Result<Response<PushEventsResponse>, Status> {
+        let start = std::time::Instant::now();
+        let cfg = config::get_config();
+        let records = request
+            .into_inner()
+            .events
+            .into_iter()
+            .filter_map(vector::to_record)
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            return Ok(Response::new(PushEventsResponse {}));
+        }
+
+        let ret = vector::ingest(&cfg.grpc.vector_org, &cfg.grpc.vector_stream, &records).await;
+        let (code, ret) = match ret {
+            Ok(resp) => match resp
+                .status
+                .into_iter()
+                .find(|stream| stream.status.failed > 0)
+            {
+                Some(stream) => (
+                    "400",
+                    Err(Status::invalid_argument(format!(
+                        "{} of {} events failed: {}",
+                        stream.status.failed,
+                        records.len(),
+                        stream.status.error
+                    ))),
+                ),
+                None => ("200", Ok(Response::new(PushEventsResponse {}))),
+            },
+            Err(e) => {
+                log::error!("[gRPC] vector ingestion error: {}", e);
+                ("500", Err(Status::internal(e.to_string())))
+            }
+        };
+
+        // metrics
+        let time = start.elapsed().as_secs_f64();
+        metrics::GRPC_RESPONSE_TIME
+            .with_label_values(&["/vector/push_events", code, "", "", ""])
+            .observe(time);
+        metrics::GRPC_INCOMING_REQUESTS
+            .with_label_values(&["/vector/push_events", code, "", "", ""])
+            .inc();
+
+        ret
+    }
+
+    async fn health_check(
+        &self,
+        _request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        Ok(Response::new(HealthCheckResponse {
+            status: ServingStatus::Serving.into(),
+        }))
+    }
+}
@@ -33,7 +33,7 @@ use crate::{
             },
         },
         utils::{
-            auth::{get_hash, is_root_user, AuthExtractor},
+            auth::{get_hash, is_root_user, AuthExtractor, CurrentUser},
             redirect_response::RedirectResponseBuilder,
         },
     },
@@ -82,6 +82,7 @@ pub async fn validator(
                     header::HeaderName::from_static("user_id"),
                     header::HeaderValue::from_str(&res.user_email).unwrap(),
                 );
+                req.extensions_mut().insert(CurrentUser::from(&res));
 
                 if auth_info.bypass_check
                     || check_permissions(
@@ -490,6 +491,7 @@ pub async fn validator_aws(
                                 header::HeaderName::from_static("user_id"),
                                 header::HeaderValue::from_str(&res.user_email).unwrap(),
                             );
+                            req.extensions_mut().insert(CurrentUser::from(&res));
                             Ok(req)
                         } else {
                             Err((ErrorUnauthorized("Unauthorized Access"), req))
@@ -534,6 +536,7 @@ pub async fn validator_gcp(
                             header::HeaderName::from_static("user_id"),
                             header::HeaderValue::from_str(&res.user_email).unwrap(),
                         );
+                        req.extensions_mut().insert(CurrentUser::from(&res));
                         Ok(req)
                     } else {
                         Err((ErrorUnauthorized("Unauthorized Access"), req))
@@ -780,15 +783,80 @@ pub(crate) async fn check_permissions(
         &auth_info.org_id
     };
 
-    o2_enterprise::enterprise::openfga::authorizer::authz::is_allowed(
+    let role = role.to_string();
+    let cache_key = format!(
+        "{org_id}/{user_id}/{}/{obj_str}/{}/{role}",
+        auth_info.method, auth_info.parent_id
+    );
+    if let Some(allowed) = PERMISSION_CACHE.get(&cache_key) {
+        return allowed;
+    }
+    let allowed = o2_enterprise::enterprise::openfga::authorizer::authz::is_allowed(
         org_id,
         user_id,
         &auth_info.method,
         &obj_str,
         &auth_info.parent_id,
-        &role.to_string(),
+        &role,
     )
-    .await
+    .await;
+    PERMISSION_CACHE.insert(cache_key, allowed);
+    allowed
+}
+
+#[cfg(feature = "enterprise")]
+static PERMISSION_CACHE: once_cell::sync::Lazy<PermissionCache> =
+    once_cell::sync::Lazy::new(|| PermissionCache::new(get_config().auth.authz_cache_ttl));
+
+/// Drops the cached permission checks of this node after the roles, the
+/// groups or the role of a user changed. The other nodes catch up when their
+/// entries expire after `ZO_AUTHZ_CACHE_TTL`.
+#[cfg(feature = "enterprise")]
+pub(crate) fn invalidate_permission_cache() {
+    PERMISSION_CACHE.clear();
+}
+
+/// Results of the permission checks for a short time, so the same request
+/// of a user doesn't hit the authorizer again and again. The least recently
+/// used entries are evicted beyond `MAX_ENTRIES`.
+#[cfg(feature = "enterprise")]
+struct PermissionCache {
+    data: parking_lot::Mutex<hashlink::LruCache<String, (bool, i64)>>,
+    ttl: i64,
+}
+
+#[cfg(feature = "enterprise")]
+impl PermissionCache {
+    const MAX_ENTRIES: usize = 100_000;
+
+    fn new(ttl_secs: i64) -> Self {
+        Self {
+            data: parking_lot::Mutex::new(hashlink::LruCache::new(Self::MAX_ENTRIES)),
+            ttl: ttl_secs * 1_000_000,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<bool> {
+        let mut data = self.data.lock();
+        let (allowed, expires_at) = *data.get(key)?;
+        if expires_at < config::utils::time::now_micros() {
+            data.remove(key);
+            return None;
+        }
+        Some(allowed)
+    }
+
+    fn insert(&self, key: String, allowed: bool) {
+        if self.ttl <= 0 {
+            return;
+        }
+        let expires_at = config::utils::time::now_micros() + self.ttl;
+        self.data.lock().insert(key, (allowed, expires_at));
+    }
+
+    fn clear(&self) {
+        self.data.lock().clear();
+    }
 }
 
 #[cfg(not(feature = "enterprise"))]
@@ -940,6 +1008,42 @@ mod tests {
         assert!(resp_from_builder.given_name.eq(&resp.given_name));
     }
 
+    #[cfg(feature = "enterprise")]
+    #[test]
+    fn test_permission_cache() {
+        let cache = PermissionCache::new(60);
+        assert_eq!(cache.get("a"), None);
+        cache.insert("a".to_string(), true);
+        cache.insert("b".to_string(), false);
+        assert_eq!(cache.get("a"), Some(true));
+        assert_eq!(cache.get("b"), Some(false));
+
+        // expired
+        cache.data.lock().insert("a".to_string(), (true, 0));
+        assert_eq!(cache.get("a"), None);
+        assert!(!cache.data.lock().contains_key("a"));
+
+        cache.clear();
+        assert_eq!(cache.get("b"), None);
+
+        let cache = PermissionCache::new(0);
+        cache.insert("a".to_string(), true);
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[cfg(feature = "enterprise")]
+    #[test]
+    fn test_permission_cache_bounded() {
+        let cache = PermissionCache::new(60);
+        for i in 0..=PermissionCache::MAX_ENTRIES {
+            cache.insert(i.to_string(), true);
+        }
+        assert_eq!(cache.data.lock().len(), PermissionCache::MAX_ENTRIES);
+        // the least recently used entry is evicted
+        assert_eq!(cache.get("0"), None);
+        assert_eq!(cache.get("1"), Some(true));
+    }
+
     #[tokio::test]
     async fn test_validation_response_default() {
         let actual = TokenValidationResponse {
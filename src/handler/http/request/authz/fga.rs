@@ -19,6 +19,8 @@ use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
 use o2_enterprise::enterprise::dex::meta::auth::RoleRequest;
 
 use crate::common::meta::user::{UserGroup, UserGroupRequest, UserRoleRequest};
+#[cfg(feature = "enterprise")]
+use crate::handler::http::auth::validator::invalidate_permission_cache;
 
 #[cfg(feature = "enterprise")]
 #[post("/{org_id}/roles")]
@@ -57,7 +59,10 @@ pub async fn delete_role(path: web::Path<(String, String)>) -> Result<HttpRespon
     match o2_enterprise::enterprise::openfga::authorizer::roles::delete_role(&org_id, &role_name)
         .await
     {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Ok(_) => {
+            invalidate_permission_cache();
+            Ok(HttpResponse::Ok().finish())
+        }
         Err(err) => Ok(HttpResponse::InternalServerError().body(err.to_string())),
     }
 }
@@ -145,7 +150,10 @@ pub async fn update_role(
     )
     .await
     {
-        Ok(res) => Ok(HttpResponse::Ok().json(res)),
+        Ok(res) => {
+            invalidate_permission_cache();
+            Ok(HttpResponse::Ok().json(res))
+        }
         Err(err) => Ok(HttpResponse::InternalServerError().body(err.to_string())),
     }
 }
@@ -252,7 +260,10 @@ pub async fn update_group(
     )
     .await
     {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Ok(_) => {
+            invalidate_permission_cache();
+            Ok(HttpResponse::Ok().finish())
+        }
         Err(err) => Ok(HttpResponse::InternalServerError().body(err.to_string())),
     }
 }
@@ -368,7 +379,10 @@ pub async fn delete_group(path: web::Path<(String, String)>) -> Result<HttpRespo
     match o2_enterprise::enterprise::openfga::authorizer::groups::delete_group(&org_id, &group_name)
         .await
     {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Ok(_) => {
+            invalidate_permission_cache();
+            Ok(HttpResponse::Ok().finish())
+        }
         Err(err) => Ok(HttpResponse::InternalServerError().body(err.to_string())),
     }
 }
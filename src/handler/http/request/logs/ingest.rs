@@ -139,7 +139,7 @@ pub async fn multi(
         ("org_id" = String, Path, description = "Organization name"),
         ("stream_name" = String, Path, description = "Stream name"),
     ),
-    request_body(content = String, description = "Ingest data (json array)", content_type = "application/json", example = json!([{"Year": 1896, "City": "Athens", "Sport": "Aquatics", "Discipline": "Swimming", "Athlete": "Alfred", "Country": "HUN"},{"Year": 1896, "City": "Athens", "Sport": "Aquatics", "Discipline": "Swimming", "Athlete": "HERSCHMANN", "Country":"CHN"}])),
+    request_body(content = String, description = "Ingest data (json array, or newline delimited json with content type application/x-ndjson)", content_type = "application/json", example = json!([{"Year": 1896, "City": "Athens", "Sport": "Aquatics", "Discipline": "Swimming", "Athlete": "Alfred", "Country": "HUN"},{"Year": 1896, "City": "Athens", "Sport": "Aquatics", "Discipline": "Swimming", "Athlete": "HERSCHMANN", "Country":"CHN"}])),
     responses(
         (status = 200, description = "Success", content_type = "application/json", body = IngestionResponse, example = json!({"code": 200,"status": [{"name": "olympics","successful": 3,"failed": 0}]})),
         (status = 500, description = "Failure", content_type = "application/json", body = HttpResponse),
@@ -154,16 +154,18 @@ pub async fn json(
 ) -> Result<HttpResponse, Error> {
     let (org_id, stream_name) = path.into_inner();
     let user_email = in_req.headers().get("user_id").unwrap().to_str().unwrap();
+    let is_ndjson = in_req
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-ndjson"));
+    let req = if is_ndjson {
+        IngestionRequest::Multi(&body)
+    } else {
+        IngestionRequest::JSON(&body)
+    };
     Ok(
-        match logs::ingest::ingest(
-            **thread_id,
-            &org_id,
-            &stream_name,
-            IngestionRequest::JSON(&body),
-            user_email,
-            None,
-        )
-        .await
+        match logs::ingest::ingest(**thread_id, &org_id, &stream_name, req, user_email, None).await
         {
             Ok(v) => match v.code {
                 503 => HttpResponse::ServiceUnavailable().json(v),
@@ -31,6 +31,7 @@ pub mod rum;
 pub mod search;
 pub mod service_accounts;
 pub mod short_url;
+pub mod silences;
 pub mod status;
 pub mod stream;
 pub mod syslog;
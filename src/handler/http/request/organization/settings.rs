@@ -101,6 +101,15 @@ async fn create(
         }
     }
 
+    if let Some(ingestion_rate_limit_bytes) = settings.ingestion_rate_limit_bytes {
+        field_found = true;
+        data.ingestion_rate_limit_bytes = ingestion_rate_limit_bytes;
+    }
+    if let Some(ingestion_rate_limit_records) = settings.ingestion_rate_limit_records {
+        field_found = true;
+        data.ingestion_rate_limit_records = ingestion_rate_limit_records;
+    }
+
     if !field_found {
         return Ok(MetaHttpResponse::bad_request("No valid field found"));
     }
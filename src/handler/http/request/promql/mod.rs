@@ -15,7 +15,7 @@
 
 use std::io::Error;
 
-use actix_web::{get, http, post, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, http, post, web, HttpRequest, HttpResponse};
 use config::utils::time::{parse_milliseconds, parse_str_to_timestamp_micros};
 use infra::errors;
 use promql_parser::parser;
@@ -26,8 +26,11 @@ use {
 };
 
 use crate::{
-    common::{meta::http::HttpResponse as MetaHttpResponse, utils::http::get_or_create_trace_id},
-    service::{metrics, promql},
+    common::{
+        meta::{http::HttpResponse as MetaHttpResponse, recording_rule::RuleFile},
+        utils::http::get_or_create_trace_id,
+    },
+    service::{metrics, promql, recording_rules},
 };
 
 /// prometheus remote-write endpoint for metrics
@@ -85,6 +88,7 @@ pub async fn remote_write(
         ("query" = String, Query, description = "Prometheus expression query string"),
         ("time" = Option<String>, Query, description = "<rfc3339 | unix_timestamp>: Evaluation timestamp. Optional"),
         ("timeout" = Option<String>, Query, description = "Evaluation timeout"),
+        ("timezone" = Option<String>, Query, description = "IANA timezone of the time functions, e.g. America/New_York. Defaults to UTC"),
     ),
     responses(
         (status = 200, description = "Success", content_type = "application/json", body = HttpResponse, example = json!({
@@ -215,6 +219,11 @@ async fn query(
     };
     let end = start;
     let timeout = search_timeout(req.timeout);
+    if let Err(e) = check_timezone(req.timezone.as_deref()) {
+        return Ok(
+            HttpResponse::BadRequest().json(promql::ApiFuncResponse::<()>::err_bad_data(e, None))
+        );
+    }
 
     let req = promql::MetricsQueryRequest {
         query: req.query.unwrap_or_default(),
@@ -223,6 +232,7 @@ async fn query(
         step: 300_000_000, // 5m
         query_exemplars: false,
         no_cache: None,
+        timezone: req.timezone,
     };
 
     search(&trace_id, org_id, &req, user_email, timeout).await
@@ -244,6 +254,7 @@ async fn query(
         ("end" = String, Query, description = "<rfc3339 | unix_timestamp>: End timestamp, inclusive"),
         ("step" = Option<String>, Query, description = "Query resolution step width in duration format or float number of seconds"),
         ("timeout" = Option<String>, Query, description = "Evaluation timeout"),
+        ("timezone" = Option<String>, Query, description = "IANA timezone of the time functions, e.g. America/New_York. Defaults to UTC"),
     ),
     responses(
         (status = 200, description = "Success", content_type = "application/json", body = HttpResponse, example = json!({
@@ -511,6 +522,14 @@ async fn query_range(
     }
 
     let timeout = search_timeout(req.timeout);
+    if let Err(e) = check_timezone(req.timezone.as_deref()) {
+        return Ok(
+            HttpResponse::BadRequest().json(promql::ApiFuncResponse::<()>::err_bad_data(
+                e,
+                Some(trace_id),
+            )),
+        );
+    }
 
     let req = promql::MetricsQueryRequest {
         query: req.query.unwrap_or_default(),
@@ -519,6 +538,7 @@ async fn query_range(
         step,
         query_exemplars,
         no_cache: req.no_cache,
+        timezone: req.timezone,
     };
     search(&trace_id, org_id, &req, user_email, timeout).await
 }
@@ -991,6 +1011,108 @@ fn format_query(_org_id: &str, query: &str, _in_req: HttpRequest) -> Result<Http
     Ok(HttpResponse::Ok().json(promql::ApiFuncResponse::ok(expr.prettify(), None)))
 }
 
+/// prometheus recording rules
+// refer: https://prometheus.io/docs/prometheus/latest/querying/api/#rules
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Metrics",
+    operation_id = "PrometheusRulesList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse, example = json!({
+            "status" : "success",
+            "data" : {
+                "groups": [
+                    {
+                        "name": "http",
+                        "interval": "1m",
+                        "rules": [
+                            {
+                                "record": "job:http_requests:rate5m",
+                                "expr": "sum by (job) (rate(http_requests_total[5m]))"
+                            }
+                        ]
+                    }
+                ]
+            }
+        })),
+    )
+)]
+#[get("/{org_id}/prometheus/api/v1/rules")]
+pub async fn rules_list(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let groups = recording_rules::list(&org_id);
+    Ok(HttpResponse::Ok().json(promql::ApiFuncResponse::ok(RuleFile { groups }, None)))
+}
+
+/// Saves the recording rules of a Prometheus rule file, a group replaces the
+/// group of the same name
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Metrics",
+    operation_id = "PrometheusRulesSave",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    request_body(content = String, description = "Prometheus rule file", content_type = "application/yaml"),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse, example = json!({
+            "status" : "success",
+            "data" : ["http"]
+        })),
+        (status = 400, description = "Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/prometheus/api/v1/rules")]
+pub async fn rules_save(
+    org_id: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, Error> {
+    Ok(match recording_rules::save(&org_id, &body).await {
+        Ok(names) => HttpResponse::Ok().json(promql::ApiFuncResponse::ok(names, None)),
+        Err(err) => {
+            HttpResponse::BadRequest().json(promql::ApiFuncResponse::<()>::err_bad_data(err, None))
+        }
+    })
+}
+
+/// Deletes a recording rule group
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Metrics",
+    operation_id = "PrometheusRulesDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("group_name" = String, Path, description = "Rule group name"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse),
+        (status = 500, description = "Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/prometheus/api/v1/rules/{group_name}")]
+pub async fn rules_delete(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, group_name) = path.into_inner();
+    Ok(match recording_rules::delete(&org_id, &group_name).await {
+        Ok(_) => HttpResponse::Ok().json(promql::ApiFuncResponse::ok(group_name, None)),
+        Err(err) => {
+            log::error!("delete recording rule group failed: {err}");
+            HttpResponse::InternalServerError()
+                .json(promql::ApiFuncResponse::<()>::err_internal(err, None))
+        }
+    })
+}
+
 fn search_timeout(timeout: Option<String>) -> i64 {
     match timeout {
         None => 0,
@@ -1004,6 +1126,13 @@ fn search_timeout(timeout: Option<String>) -> i64 {
     }
 }
 
+fn check_timezone(timezone: Option<&str>) -> Result<(), String> {
+    match timezone {
+        Some(tz) if tz.parse::<chrono_tz::Tz>().is_err() => Err(format!("Invalid timezone: {tz}")),
+        _ => Ok(()),
+    }
+}
+
 async fn search(
     trace_id: &str,
     org_id: &str,
@@ -28,17 +28,18 @@ use config::{
     },
     metrics,
     utils::{base64, json},
-    DISTINCT_FIELDS,
+    DISTINCT_FIELDS, ID_COL_NAME,
 };
-use infra::{cache::stats, errors};
+use infra::{cache::stats, errors, schema::get_stream_setting_fts_fields};
 use tracing::{Instrument, Span};
 #[cfg(feature = "enterprise")]
 use utils::check_stream_permissions;
 
 use crate::{
     common::{
-        meta::{self, http::HttpResponse as MetaHttpResponse},
+        meta::{self, http::HttpResponse as MetaHttpResponse, user::UserRole},
         utils::{
+            auth::is_root_user,
             functions,
             http::{
                 get_or_create_trace_id, get_search_event_context_from_request,
@@ -50,8 +51,13 @@ use crate::{
     },
     service::{
         metadata::distinct_values::DISTINCT_STREAM_PREFIX,
-        search as SearchService,
+        search::{
+            self as SearchService,
+            cursor::Cursor,
+            sql::{generate_cursor_sql, generate_federated_sql, generate_fts_sql},
+        },
         self_reporting::{http_report_metrics, report_request_usage_stats},
+        stream, users,
     },
 };
 
@@ -64,6 +70,37 @@ pub mod search_job;
 #[cfg(feature = "enterprise")]
 pub(crate) mod utils;
 
+/// Responds 429 to a canceled query, 408 to a query which timed out and 500
+/// to the other errors.
+fn search_error_response(err: errors::Error, trace_id: String) -> HttpResponse {
+    match err {
+        errors::Error::ErrorCode(code) => match code {
+            errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests().json(
+                meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
+            ),
+            errors::ErrorCodes::SearchTimeout(_) => HttpResponse::RequestTimeout().json(
+                meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
+            ),
+            _ => HttpResponse::InternalServerError().json(
+                meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
+            ),
+        },
+        _ => HttpResponse::InternalServerError().json(meta::http::HttpResponse::error(
+            StatusCode::INTERNAL_SERVER_ERROR.into(),
+            err.to_string(),
+        )),
+    }
+}
+
+/// The root user and the admins of the organization can force the queries
+/// over the scan budget.
+async fn is_admin_user(org_id: &str, user_id: &str) -> bool {
+    is_root_user(user_id)
+        || users::get_user(Some(org_id), user_id)
+            .await
+            .is_some_and(|u| u.role == UserRole::Admin)
+}
+
 async fn can_use_distinct_stream(
     org: &str,
     stream_name: &str,
@@ -221,6 +258,82 @@ pub async fn search(
             .and_then(|event_type| get_search_event_context_from_request(event_type, &query));
     }
 
+    // list the streams of the stream type which the user can access
+    if SearchService::sql::is_show_streams(&req.query.sql) {
+        #[allow(unused_mut)]
+        let mut streams = stream::get_streams(&org_id, Some(stream_type), false, None).await;
+        #[cfg(feature = "enterprise")]
+        {
+            let mut permitted = Vec::with_capacity(streams.len());
+            for s in streams {
+                if check_stream_permissions(&s.name, &org_id, &user_id, &stream_type)
+                    .await
+                    .is_none()
+                {
+                    permitted.push(s);
+                }
+            }
+            streams = permitted;
+        }
+        streams.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut res = stream::show_streams(&org_id, &streams).await;
+        res.took = start.elapsed().as_millis() as usize;
+        res.set_trace_id(trace_id);
+        return Ok(HttpResponse::Ok().json(res));
+    }
+
+    // return the schema of the stream
+    if let Some(stream_name) = SearchService::sql::get_describe_stream(&req.query.sql) {
+        let stream_name = stream::resolve_alias(&org_id, stream_type, &stream_name);
+        #[cfg(feature = "enterprise")]
+        if let Some(res) =
+            check_stream_permissions(&stream_name, &org_id, &user_id, &stream_type).await
+        {
+            return Ok(res);
+        }
+        let Some(mut res) = stream::describe_stream(&org_id, &stream_name, stream_type).await
+        else {
+            return Ok(MetaHttpResponse::bad_request(format!(
+                "Stream {stream_name} not found"
+            )));
+        };
+        res.took = start.elapsed().as_millis() as usize;
+        res.set_trace_id(trace_id);
+        return Ok(HttpResponse::Ok().json(res));
+    }
+
+    // replace the stream aliases first, the settings, the permissions and the
+    // scan estimates below are the ones of the streams they point to
+    req.query.sql =
+        match SearchService::sql::resolve_stream_aliases(&org_id, stream_type, &req.query.sql) {
+            Ok(v) => v,
+            Err(e) => return Ok(MetaHttpResponse::bad_request(e)),
+        };
+    for stream_name in req.streams.iter_mut() {
+        *stream_name = stream::resolve_alias(&org_id, stream_type, stream_name);
+    }
+
+    // query several streams together, the cursor is bound to the sql before it
+    let cursor_sql = req.query.sql.clone();
+    if !req.streams.is_empty() {
+        let mut schemas = Vec::with_capacity(req.streams.len());
+        for stream_name in req.streams.iter() {
+            let schema = infra::schema::get(&org_id, stream_name, stream_type)
+                .await
+                .unwrap_or_else(|_| Schema::empty());
+            if schema.fields().is_empty() {
+                return Ok(MetaHttpResponse::bad_request(format!(
+                    "Stream {stream_name} not found"
+                )));
+            }
+            schemas.push((stream_name.to_string(), schema));
+        }
+        req.query.sql = match generate_federated_sql(&req.query.sql, &schemas) {
+            Ok(v) => v,
+            Err(e) => return Ok(MetaHttpResponse::bad_request(e)),
+        };
+    }
+
     // get stream name
     let stream_names = match resolve_stream_names(&req.query.sql) {
         Ok(v) => v.clone(),
@@ -235,7 +348,7 @@ pub async fn search(
     };
 
     // get stream settings
-    for stream_name in stream_names {
+    for stream_name in stream_names.iter() {
         if let Some(settings) =
             infra::schema::get_settings(&org_id, &stream_name, stream_type).await
         {
@@ -262,6 +375,108 @@ pub async fn search(
         }
     }
 
+    // pick up the files by the full text search index before scanning them
+    if req.use_fts && stream_names.len() == 1 {
+        let settings = infra::schema::get_settings(&org_id, &stream_names[0], stream_type).await;
+        let fts_fields = get_stream_setting_fts_fields(&settings);
+        req.query.sql = match generate_fts_sql(&req.query.sql, &fts_fields) {
+            Ok(v) => v,
+            Err(e) => return Ok(MetaHttpResponse::bad_request(e)),
+        };
+    }
+
+    // continue from the cursor of the previous page, the cursor needs the
+    // sql ordered by `_timestamp DESC` and `_o2_id` in the streams to break ties
+    let cursor = match req.cursor.as_deref() {
+        Some(token) => match Cursor::decode(token, &cursor_sql, req.query.start_time) {
+            Ok(cursor) => Some(cursor),
+            Err(e) => return Ok(MetaHttpResponse::bad_request(e)),
+        },
+        None => None,
+    };
+    let cursor_start_time = req.query.start_time;
+    // the sql is only rewritten for the cursor when paging is requested
+    let mut can_page = req.use_cursor || cursor.is_some();
+    for stream_name in stream_names.iter() {
+        if !can_page {
+            break;
+        }
+        let schema = infra::schema::get(&org_id, stream_name, stream_type)
+            .await
+            .unwrap_or_else(|_| Schema::empty());
+        can_page = schema.field_with_name(ID_COL_NAME).is_ok();
+    }
+    let cursor_sql_rewritten = if can_page {
+        match generate_cursor_sql(&req.query.sql, cursor.as_ref()) {
+            Ok(v) => v,
+            Err(e) => return Ok(MetaHttpResponse::bad_request(e)),
+        }
+    } else {
+        None
+    };
+    let use_cursor = cursor_sql_rewritten.is_some();
+    match cursor_sql_rewritten {
+        Some(sql) => {
+            req.query.sql = sql;
+            if cursor.is_some() {
+                req.query.from = 0;
+            }
+        }
+        None if cursor.is_some() => {
+            return Ok(MetaHttpResponse::bad_request(
+                "Search cursor is only supported for the queries ordered by _timestamp DESC",
+            ));
+        }
+        None => {}
+    }
+
+    // estimate the total hits from the statistics instead of counting them
+    let total_hits_lower_bound = if req.approximate_count {
+        SearchService::approx_count::total_hits_lower_bound(
+            &org_id,
+            stream_type,
+            &req.query.sql,
+            req.query.start_time,
+            req.query.end_time,
+        )
+        .await
+    } else {
+        None
+    };
+    if total_hits_lower_bound.is_some() {
+        req.query.track_total_hits = false;
+    }
+
+    // reject the queries which would scan too much, unless an admin forces them
+    let max_scan_bytes = get_config().limit.query_max_scan_bytes;
+    if max_scan_bytes > 0 && !(req.force && is_admin_user(&org_id, &user_id).await) {
+        let scan_bytes = match SearchService::scan_budget::estimate_scan_bytes(
+            &org_id,
+            stream_type,
+            &stream_names,
+            req.query.start_time,
+            req.query.end_time,
+        )
+        .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                return Ok(HttpResponse::InternalServerError().json(
+                    meta::http::HttpResponse::error(
+                        StatusCode::INTERNAL_SERVER_ERROR.into(),
+                        e.to_string(),
+                    ),
+                ));
+            }
+        };
+        if let Err(e) = SearchService::scan_budget::check_scan_budget(scan_bytes, max_scan_bytes) {
+            metrics::QUERY_REJECTED_NUMS
+                .with_label_values(&[&org_id])
+                .inc();
+            return Ok(MetaHttpResponse::bad_request(e));
+        }
+    }
+
     // run search with cache
     let res = SearchService::cache::search(
         &trace_id,
@@ -274,26 +489,20 @@ pub async fn search(
     .instrument(http_span)
     .await;
     match res {
-        Ok(res) => Ok(HttpResponse::Ok().json(res)),
+        Ok(mut res) => {
+            if use_cursor {
+                res.next_cursor = Cursor::next(&res.hits, req.query.size)
+                    .map(|c| c.encode(&cursor_sql, cursor_start_time));
+            }
+            if let Some(total) = total_hits_lower_bound {
+                res.set_total_hits_lower_bound(total);
+            }
+            Ok(HttpResponse::Ok().json(res))
+        }
         Err(err) => {
             http_report_metrics(start, &org_id, stream_type, "", "500", "_search");
             log::error!("[trace_id {trace_id}] search error: {}", err);
-            Ok(match err {
-                errors::Error::ErrorCode(code) => match code {
-                    errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
-                        .json(meta::http::HttpResponse::error_code_with_trace_id(
-                            code,
-                            Some(trace_id),
-                        )),
-                    _ => HttpResponse::InternalServerError().json(
-                        meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
-                    ),
-                },
-                _ => HttpResponse::InternalServerError().json(meta::http::HttpResponse::error(
-                    StatusCode::INTERNAL_SERVER_ERROR.into(),
-                    err.to_string(),
-                )),
-            })
+            Ok(search_error_response(err, trace_id))
         }
     }
 }
@@ -489,6 +698,13 @@ pub async fn around(
         search_type: Some(SearchEventType::UI),
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     let search_res = SearchService::search(&trace_id, &org_id, stream_type, user_id.clone(), &req)
         .instrument(http_span.clone())
@@ -499,22 +715,7 @@ pub async fn around(
         Err(err) => {
             http_report_metrics(start, &org_id, stream_type, &stream_name, "500", "_around");
             log::error!("search around error: {:?}", err);
-            return Ok(match err {
-                errors::Error::ErrorCode(code) => match code {
-                    errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
-                        .json(meta::http::HttpResponse::error_code_with_trace_id(
-                            code,
-                            Some(trace_id),
-                        )),
-                    _ => HttpResponse::InternalServerError().json(
-                        meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
-                    ),
-                },
-                _ => HttpResponse::InternalServerError().json(meta::http::HttpResponse::error(
-                    StatusCode::INTERNAL_SERVER_ERROR.into(),
-                    err.to_string(),
-                )),
-            });
+            return Ok(search_error_response(err, trace_id));
         }
     };
 
@@ -543,6 +744,13 @@ pub async fn around(
         search_type: Some(SearchEventType::UI),
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     let search_res = SearchService::search(&trace_id, &org_id, stream_type, user_id.clone(), &req)
         .instrument(http_span)
@@ -553,22 +761,7 @@ pub async fn around(
         Err(err) => {
             http_report_metrics(start, &org_id, stream_type, &stream_name, "500", "_around");
             log::error!("search around error: {:?}", err);
-            return Ok(match err {
-                errors::Error::ErrorCode(code) => match code {
-                    errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
-                        .json(meta::http::HttpResponse::error_code_with_trace_id(
-                            code,
-                            Some(trace_id),
-                        )),
-                    _ => HttpResponse::InternalServerError().json(
-                        meta::http::HttpResponse::error_code_with_trace_id(code, Some(trace_id)),
-                    ),
-                },
-                _ => HttpResponse::InternalServerError().json(meta::http::HttpResponse::error(
-                    StatusCode::INTERNAL_SERVER_ERROR.into(),
-                    err.to_string(),
-                )),
-            });
+            return Ok(search_error_response(err, trace_id));
         }
     };
 
@@ -884,6 +1077,13 @@ async fn values_v1(
         search_type: Some(SearchEventType::Values),
         search_event_context: None,
         use_cache: Some(use_cache),
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
 
     // skip fields which aren't part of the schema
@@ -956,25 +1156,7 @@ async fn values_v1(
             Err(err) => {
                 http_report_metrics(start, org_id, stream_type, stream_name, "500", "_values/v1");
                 log::error!("search values error: {:?}", err);
-                return Ok(match err {
-                    errors::Error::ErrorCode(code) => match code {
-                        errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
-                            .json(meta::http::HttpResponse::error_code_with_trace_id(
-                                code,
-                                Some(trace_id),
-                            )),
-                        _ => HttpResponse::InternalServerError().json(
-                            meta::http::HttpResponse::error_code_with_trace_id(
-                                code,
-                                Some(trace_id),
-                            ),
-                        ),
-                    },
-                    _ => HttpResponse::InternalServerError().json(meta::http::HttpResponse::error(
-                        StatusCode::INTERNAL_SERVER_ERROR.into(),
-                        err.to_string(),
-                    )),
-                });
+                return Ok(search_error_response(err, trace_id));
             }
         };
         query_results.push((field.to_string(), resp_search));
@@ -1406,3 +1588,38 @@ pub async fn search_history(
 
     Ok(HttpResponse::Ok().json(search_res))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_timeout_response() {
+        // a slow query is aborted after the timeout like the flight search does
+        let timeout = 1;
+        let query_task = tokio::task::spawn(async {
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        });
+        let abort_handle = query_task.abort_handle();
+        let start = std::time::Instant::now();
+        let res = tokio::select! {
+            _ = query_task => Ok(()),
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(timeout)) => {
+                abort_handle.abort();
+                Err(errors::Error::ErrorCode(errors::ErrorCodes::SearchTimeout(format!(
+                    "search timeout after {timeout}s"
+                ))))
+            }
+        };
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        let resp = search_error_response(res.unwrap_err(), "trace_id".to_string());
+        assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let err = errors::Error::ErrorCode(errors::ErrorCodes::SearchCancelQuery("".to_string()));
+        let resp = search_error_response(err, "trace_id".to_string());
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let err = errors::Error::Message("failed".to_string());
+        let resp = search_error_response(err, "trace_id".to_string());
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -435,13 +435,24 @@ pub async fn search_multi(
                 log::error!("search error: {:?}", err);
                 multi_res.function_error = format!("{};{:?}", multi_res.function_error, err);
                 if let errors::Error::ErrorCode(code) = err {
-                    if let errors::ErrorCodes::SearchCancelQuery(_) = code {
-                        return Ok(HttpResponse::TooManyRequests().json(
-                            meta::http::HttpResponse::error_code_with_trace_id(
-                                code,
-                                Some(trace_id),
-                            ),
-                        ));
+                    match code {
+                        errors::ErrorCodes::SearchCancelQuery(_) => {
+                            return Ok(HttpResponse::TooManyRequests().json(
+                                meta::http::HttpResponse::error_code_with_trace_id(
+                                    code,
+                                    Some(trace_id),
+                                ),
+                            ));
+                        }
+                        errors::ErrorCodes::SearchTimeout(_) => {
+                            return Ok(HttpResponse::RequestTimeout().json(
+                                meta::http::HttpResponse::error_code_with_trace_id(
+                                    code,
+                                    Some(trace_id),
+                                ),
+                            ));
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -946,6 +957,13 @@ pub async fn around_multi(
             search_type: Some(search::SearchEventType::UI),
             search_event_context: None,
             use_cache: None,
+            cursor: None,
+            use_cursor: false,
+            streams: vec![],
+            approximate_count: false,
+            use_fts: false,
+            params: Default::default(),
+            force: false,
         };
         let search_res =
             SearchService::search(&trace_id, &org_id, stream_type, user_id.clone(), &req)
@@ -982,6 +1000,11 @@ pub async fn around_multi(
                                 code,
                                 Some(trace_id),
                             )),
+                        errors::ErrorCodes::SearchTimeout(_) => HttpResponse::RequestTimeout()
+                            .json(meta::http::HttpResponse::error_code_with_trace_id(
+                                code,
+                                Some(trace_id),
+                            )),
                         _ => HttpResponse::InternalServerError().json(
                             meta::http::HttpResponse::error_code_with_trace_id(
                                 code,
@@ -1022,6 +1045,13 @@ pub async fn around_multi(
             search_type: Some(search::SearchEventType::UI),
             search_event_context: None,
             use_cache: None,
+            cursor: None,
+            use_cursor: false,
+            streams: vec![],
+            approximate_count: false,
+            use_fts: false,
+            params: Default::default(),
+            force: false,
         };
         let search_res =
             SearchService::search(&trace_id, &org_id, stream_type, user_id.clone(), &req)
@@ -1058,6 +1088,11 @@ pub async fn around_multi(
                                 code,
                                 Some(trace_id),
                             )),
+                        errors::ErrorCodes::SearchTimeout(_) => HttpResponse::RequestTimeout()
+                            .json(meta::http::HttpResponse::error_code_with_trace_id(
+                                code,
+                                Some(trace_id),
+                            )),
                         _ => HttpResponse::InternalServerError().json(
                             meta::http::HttpResponse::error_code_with_trace_id(
                                 code,
@@ -0,0 +1,109 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Error;
+
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+
+use crate::{
+    common::meta::{http::HttpResponse as MetaHttpResponse, silence::Silence},
+    service::silences,
+};
+
+/// CreateSilence
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Silences",
+    operation_id = "CreateSilence",
+    security(
+        ("Authorization" = [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    request_body(content = Silence, description = "Silence details", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = Silence),
+        (status = 400, description = "Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/silences")]
+pub async fn create(
+    path: web::Path<String>,
+    body: web::Json<Silence>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let org_id = path.into_inner();
+    let user_email = req
+        .headers()
+        .get("user_id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    match silences::create(&org_id, body.into_inner(), user_email).await {
+        Ok(silence) => Ok(MetaHttpResponse::json(silence)),
+        Err(e) => Ok(MetaHttpResponse::bad_request(e)),
+    }
+}
+
+/// ListSilences
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Silences",
+    operation_id = "ListSilences",
+    security(
+        ("Authorization" = [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = Vec<Silence>),
+    )
+)]
+#[get("/{org_id}/silences")]
+pub async fn list(path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let org_id = path.into_inner();
+    Ok(MetaHttpResponse::json(silences::list(&org_id)))
+}
+
+/// DeleteSilence
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Silences",
+    operation_id = "DeleteSilence",
+    security(
+        ("Authorization" = [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("silence_id" = String, Path, description = "Silence id"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse),
+        (status = 404, description = "NotFound", content_type = "application/json", body = HttpResponse),
+        (status = 500, description = "Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/silences/{silence_id}")]
+pub async fn delete(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, silence_id) = path.into_inner();
+    if silences::get(&org_id, &silence_id).is_none() {
+        return Ok(MetaHttpResponse::not_found("Silence not found"));
+    }
+    match silences::delete(&org_id, &silence_id).await {
+        Ok(_) => Ok(MetaHttpResponse::ok("Silence deleted")),
+        Err(e) => Ok(MetaHttpResponse::internal_error(e)),
+    }
+}
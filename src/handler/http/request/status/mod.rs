@@ -13,7 +13,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{io::Error, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    io::Error,
+    sync::{atomic::Ordering, Arc},
+};
 
 use actix_web::{
     cookie,
@@ -68,8 +72,12 @@ use crate::{
     },
     service::{
         db,
+        health::{self, HealthStatus},
         search::{
-            datafusion::{storage::file_statistics_cache, udf::DEFAULT_FUNCTIONS},
+            datafusion::{
+                storage::{file_statistics_cache, parquet_meta_cache},
+                udf::DEFAULT_FUNCTIONS,
+            },
             tantivy::puffin_directory::reader_cache,
         },
     },
@@ -78,6 +86,17 @@ use crate::{
 #[derive(Serialize, ToSchema)]
 pub struct HealthzResponse {
     status: String,
+    #[serde(flatten)]
+    checks: BTreeMap<&'static str, HealthStatus>,
+}
+
+impl HealthzResponse {
+    fn new(status: &str) -> Self {
+        Self {
+            status: status.to_string(),
+            checks: BTreeMap::new(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -137,18 +156,59 @@ struct Rum {
 }
 
 /// Healthz
+///
+/// Health of the subsystems of the node, it returns 503 while a critical
+/// subsystem (storage, wal) is unhealthy. The report is cached for
+/// `ZO_HEALTH_CHECK_CACHE_TTL` seconds, so frequent liveness probes don't hit
+/// the subsystems on every request. See [`readyz`] for whether the node can
+/// serve requests.
 #[utoipa::path(
     path = "/healthz",
     tag = "Meta",
     responses(
-        (status = 200, description="Status OK", content_type = "application/json", body = HealthzResponse, example = json!({"status": "ok"}))
+        (status = 200, description="Status OK", content_type = "application/json", body = HealthzResponse, example = json!({"status": "ok", "storage": "ok", "wal": "ok", "cluster": "ok"})),
+        (status = 503, description="Status Not OK", content_type = "application/json", body = HealthzResponse, example = json!({"status": "not ok", "storage": "unhealthy", "wal": "ok", "cluster": "degraded"})),
     )
 )]
 #[get("/healthz")]
 pub async fn healthz() -> Result<HttpResponse, Error> {
-    Ok(HttpResponse::Ok().json(HealthzResponse {
-        status: "ok".to_string(),
-    }))
+    Ok(health_report().await)
+}
+
+/// Readyz
+///
+/// Unlike `/healthz`, this also returns 503 until the stream stats have been
+/// loaded from the file list after startup, so the node gets requests only
+/// once it is ready to serve them.
+#[utoipa::path(
+    path = "/readyz",
+    tag = "Meta",
+    responses(
+        (status = 200, description="Status OK", content_type = "application/json", body = HealthzResponse, example = json!({"status": "ok", "storage": "ok", "wal": "ok", "cluster": "ok"})),
+        (status = 503, description="Status Not OK", content_type = "application/json", body = HealthzResponse, example = json!({"status": "not ok", "storage": "unhealthy", "wal": "ok", "cluster": "degraded"})),
+    )
+)]
+#[get("/readyz")]
+pub async fn readyz() -> Result<HttpResponse, Error> {
+    if !db::file_list::FILE_LIST_LOADED.load(Ordering::Acquire) {
+        return Ok(HttpResponse::ServiceUnavailable().json(HealthzResponse::new("not ok")));
+    }
+    Ok(health_report().await)
+}
+
+async fn health_report() -> HttpResponse {
+    let (healthy, checks) = health::check_all().await;
+    if healthy {
+        HttpResponse::Ok().json(HealthzResponse {
+            status: "ok".to_string(),
+            checks,
+        })
+    } else {
+        HttpResponse::ServiceUnavailable().json(HealthzResponse {
+            status: "not ok".to_string(),
+            checks,
+        })
+    }
 }
 
 /// Healthz HEAD
@@ -171,18 +231,12 @@ pub async fn healthz_head() -> Result<HttpResponse, Error> {
 pub async fn schedulez() -> Result<HttpResponse, Error> {
     let node_id = LOCAL_NODE.uuid.clone();
     let Some(node) = cluster::get_node_by_uuid(&node_id).await else {
-        return Ok(HttpResponse::NotFound().json(HealthzResponse {
-            status: "not ok".to_string(),
-        }));
+        return Ok(HttpResponse::NotFound().json(HealthzResponse::new("not ok")));
     };
     Ok(if node.scheduled && node.status == NodeStatus::Online {
-        HttpResponse::Ok().json(HealthzResponse {
-            status: "ok".to_string(),
-        })
+        HttpResponse::Ok().json(HealthzResponse::new("ok"))
     } else {
-        HttpResponse::NotFound().json(HealthzResponse {
-            status: "not ok".to_string(),
-        })
+        HttpResponse::NotFound().json(HealthzResponse::new("not ok"))
     })
 }
 
@@ -355,7 +409,10 @@ pub async fn cache_status() -> Result<HttpResponse, Error> {
     );
     stats.insert(
         "DATAFUSION",
-        json::json!({"file_stat_cache": file_statistics_cache::GLOBAL_CACHE.clone().len()}),
+        json::json!({
+            "file_stat_cache": file_statistics_cache::GLOBAL_CACHE.clone().len(),
+            "parquet_meta_cache": parquet_meta_cache::GLOBAL_CACHE.clone().len(),
+        }),
     );
     stats.insert(
         "INVERTED_INDEX",
@@ -15,21 +15,26 @@
 
 use std::{
     collections::HashMap,
+    convert::Infallible,
     io::{Error, ErrorKind},
+    time::Duration,
 };
 
 use actix_web::{delete, get, http, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web_lab::sse;
 use config::{
     meta::stream::{StreamSettings, StreamType, UpdateStreamSettings},
-    utils::schema::format_stream_name,
+    utils::{json, schema::format_stream_name},
 };
+use futures::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
     common::{
         meta::{
             self,
             http::HttpResponse as MetaHttpResponse,
-            stream::{ListStream, StreamDeleteFields},
+            stream::{ListStream, StreamAlias, StreamDeleteFields, StreamFileStats},
         },
         utils::http::get_stream_type_from_request,
     },
@@ -76,6 +81,141 @@ async fn schema(
     stream::get_stream(&org_id, &stream_name, stream_type).await
 }
 
+/// GetStreamStats
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamStats",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("type" = String, Query, description = "Stream type"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = StreamFileStats),
+        (status = 400, description = "Failure", content_type = "application/json", body = HttpResponse),
+        (status = 404, description = "NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/streams/{stream_name}/stats")]
+async fn stats(path: web::Path<(String, String)>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v,
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    e.to_string(),
+                )),
+            );
+        }
+    };
+    let stream_type = stream_type.unwrap_or(StreamType::Logs);
+    stream::get_stream_file_stats(&org_id, &stream_name, stream_type).await
+}
+
+/// TailStream
+///
+/// Streams the records of a log stream as they are ingested by this node over
+/// a websocket, one json record per text message. The `filter` query param
+/// keeps only the records whose `_log` field contains it.
+#[get("/{org_id}/streams/{stream_name}/tail")]
+async fn tail(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let filter = query.get("filter").cloned().unwrap_or_default();
+    let (res, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let mut subscription = crate::service::logs::tail::subscribe(&org_id, &stream_name, None);
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                msg = msg_stream.next() => match msg {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                },
+                record = subscription.recv() => match record {
+                    Ok((_, record)) => {
+                        if !crate::service::logs::tail::matches(&record, &filter) {
+                            continue;
+                        }
+                        let Ok(text) = json::to_string(record.as_ref()) else {
+                            continue;
+                        };
+                        if session.text(text).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("live tail of {org_id}/{stream_name} skipped {n} records");
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+        let _ = session.close(None).await;
+    });
+    Ok(res)
+}
+
+/// TailStreamSse
+///
+/// The live tail over server-sent events for the environments which block
+/// websockets, every record is a json `data` event with the record id as the
+/// event id. A reconnecting client with `Last-Event-ID` gets the buffered
+/// records it missed first.
+#[get("/{org_id}/streams/{stream_name}/tail/sse")]
+async fn tail_sse(path: web::Path<(String, String)>, req: HttpRequest) -> impl Responder {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let filter = query.get("filter").cloned().unwrap_or_default();
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let subscription = crate::service::logs::tail::subscribe(&org_id, &stream_name, last_event_id);
+    let key = format!("{org_id}/{stream_name}");
+    let events = futures::stream::unfold(subscription, move |mut subscription| {
+        let filter = filter.clone();
+        let key = key.clone();
+        async move {
+            loop {
+                match subscription.recv().await {
+                    Ok((id, record)) => {
+                        if !crate::service::logs::tail::matches(&record, &filter) {
+                            continue;
+                        }
+                        let Ok(data) = sse::Data::new_json(record.as_ref()) else {
+                            continue;
+                        };
+                        let event = sse::Event::Data(data.id(id.to_string()));
+                        return Some((Ok::<_, Infallible>(event), subscription));
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("live tail of {key} skipped {n} records");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    sse::Sse::from_stream(events).with_keep_alive(Duration::from_secs(15))
+}
+
 /// CreateStreamSettings
 #[utoipa::path(
     context_path = "/api",
@@ -341,6 +481,95 @@ async fn delete(
     stream::delete_stream(&org_id, &stream_name, stream_type).await
 }
 
+/// CreateStreamAlias
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamAliasCreate",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("type" = String, Query, description = "Stream type"),
+    ),
+    request_body(content = StreamAlias, description = "Name the stream can also be queried and ingested as. Metrics are not ingested through aliases", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse),
+        (status = 400, description = "Failure", content_type = "application/json", body = HttpResponse),
+        (status = 404, description = "NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/streams/{stream_name}/alias")]
+async fn create_alias(
+    path: web::Path<(String, String)>,
+    body: web::Json<StreamAlias>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, mut stream_name) = path.into_inner();
+    let mut alias = body.into_inner().alias;
+    if !config::get_config().common.skip_formatting_stream_name {
+        stream_name = format_stream_name(&stream_name);
+        alias = format_stream_name(&alias);
+    }
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v,
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    e.to_string(),
+                )),
+            );
+        }
+    };
+    let stream_type = stream_type.unwrap_or(StreamType::Logs);
+    stream::create_alias(&org_id, &stream_name, stream_type, &alias).await
+}
+
+/// DeleteStreamAlias
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamAliasDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("alias" = String, Path, description = "Alias name"),
+        ("type" = String, Query, description = "Stream type"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = HttpResponse),
+        (status = 404, description = "NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/streams/{stream_name}/alias/{alias}")]
+async fn delete_alias(
+    path: web::Path<(String, String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, _stream_name, alias) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v,
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    e.to_string(),
+                )),
+            );
+        }
+    };
+    let stream_type = stream_type.unwrap_or(StreamType::Logs);
+    stream::delete_alias(&org_id, stream_type, &alias).await
+}
+
 /// ListStreams
 #[utoipa::path(
     context_path = "/api",
@@ -297,6 +297,13 @@ pub async fn get_latest_traces(
         search_type: None,
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     let stream_type = StreamType::Traces;
     let user_id = in_req
@@ -338,6 +345,8 @@ pub async fn get_latest_traces(
                 errors::Error::ErrorCode(code) => match code {
                     errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
                         .json(meta::http::HttpResponse::error_code(code)),
+                    errors::ErrorCodes::SearchTimeout(_) => HttpResponse::RequestTimeout()
+                        .json(meta::http::HttpResponse::error_code(code)),
                     _ => HttpResponse::InternalServerError()
                         .json(meta::http::HttpResponse::error_code(code)),
                 },
@@ -429,6 +438,8 @@ pub async fn get_latest_traces(
                     errors::Error::ErrorCode(code) => match code {
                         errors::ErrorCodes::SearchCancelQuery(_) => HttpResponse::TooManyRequests()
                             .json(meta::http::HttpResponse::error_code(code)),
+                        errors::ErrorCodes::SearchTimeout(_) => HttpResponse::RequestTimeout()
+                            .json(meta::http::HttpResponse::error_code(code)),
                         _ => HttpResponse::InternalServerError()
                             .json(meta::http::HttpResponse::error_code(code)),
                     },
@@ -0,0 +1,240 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    future::{ready, Ready},
+    io,
+    rc::Rc,
+};
+
+use actix_http::h1::Payload;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::{header, StatusCode},
+    web::Bytes,
+    Error, HttpResponse,
+};
+use async_compression::futures::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder};
+use futures::{AsyncRead, AsyncReadExt, Stream, TryStreamExt};
+use futures_util::future::LocalBoxFuture;
+
+use crate::common::meta::http::HttpResponse as MetaHttpResponse;
+
+/// Decompresses `gzip`, `deflate` and `br` encoded request bodies before they
+/// reach the handlers, bodies larger than `limit` once decompressed are
+/// rejected with `413 Payload Too Large`.
+pub struct DecompressLayer {
+    limit: usize,
+}
+
+impl DecompressLayer {
+    pub fn new(limit: usize) -> Self {
+        DecompressLayer { limit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for DecompressLayer
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = DecompressMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(DecompressMiddleware {
+            service: Rc::new(service),
+            limit: self.limit,
+        }))
+    }
+}
+
+pub struct DecompressMiddleware<S> {
+    service: Rc<S>,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for DecompressMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let encoding = req
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .and_then(Encoding::parse);
+        // other encodings are left to the payload extractors
+        let Some(encoding) = encoding else {
+            return Box::pin(self.service.call(req));
+        };
+
+        let service = self.service.clone();
+        let limit = self.limit;
+        Box::pin(async move {
+            let payload = req
+                .take_payload()
+                .map_err(|e| io::Error::other(e.to_string()));
+            let body = match decode(encoding, payload, limit).await {
+                Ok(body) => body,
+                Err(e) => return Err(error_response(e, limit)),
+            };
+
+            // the handlers see a plain body
+            let headers = req.headers_mut();
+            headers.remove(header::CONTENT_ENCODING);
+            headers.insert(header::CONTENT_LENGTH, body.len().into());
+            let (_, mut payload) = Payload::create(true);
+            payload.unread_data(body);
+            req.set_payload(payload.into());
+            service.call(req).await
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+impl Encoding {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            "br" => Some(Encoding::Brotli),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DecodeError {
+    TooLarge,
+    Invalid(io::Error),
+}
+
+async fn decode<St>(encoding: Encoding, body: St, limit: usize) -> Result<Bytes, DecodeError>
+where
+    St: Stream<Item = Result<Bytes, io::Error>> + Unpin,
+{
+    let reader = body.into_async_read();
+    match encoding {
+        Encoding::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            read_limited(decoder, limit).await
+        }
+        // http deflate is the zlib format
+        Encoding::Deflate => read_limited(ZlibDecoder::new(reader), limit).await,
+        Encoding::Brotli => read_limited(BrotliDecoder::new(reader), limit).await,
+    }
+}
+
+async fn read_limited(reader: impl AsyncRead + Unpin, limit: usize) -> Result<Bytes, DecodeError> {
+    let mut buf = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut buf)
+        .await
+        .map_err(DecodeError::Invalid)?;
+    if buf.len() > limit {
+        return Err(DecodeError::TooLarge);
+    }
+    Ok(buf.into())
+}
+
+fn error_response(err: DecodeError, limit: usize) -> Error {
+    let (status, message) = match err {
+        DecodeError::TooLarge => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("decompressed request body is larger than {limit} bytes"),
+        ),
+        DecodeError::Invalid(e) => (
+            StatusCode::BAD_REQUEST,
+            format!("failed to decompress request body: {e}"),
+        ),
+    };
+    let resp =
+        HttpResponse::build(status).json(MetaHttpResponse::error(status.into(), message.clone()));
+    InternalError::from_response(message, resp).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_compression::futures::bufread::{BrotliEncoder, GzipEncoder, ZlibEncoder};
+
+    use super::*;
+
+    async fn encode(encoding: Encoding, data: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match encoding {
+            Encoding::Gzip => GzipEncoder::new(data).read_to_end(&mut buf).await,
+            Encoding::Deflate => ZlibEncoder::new(data).read_to_end(&mut buf).await,
+            Encoding::Brotli => BrotliEncoder::new(data).read_to_end(&mut buf).await,
+        }
+        .unwrap();
+        buf
+    }
+
+    fn to_stream(data: Vec<u8>) -> impl Stream<Item = Result<Bytes, io::Error>> + Unpin {
+        let chunks = data
+            .chunks(7)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect::<Vec<_>>();
+        futures::stream::iter(chunks)
+    }
+
+    #[test]
+    fn test_encoding_parse() {
+        assert_eq!(Encoding::parse("gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::parse(" GZIP "), Some(Encoding::Gzip));
+        assert_eq!(Encoding::parse("deflate"), Some(Encoding::Deflate));
+        assert_eq!(Encoding::parse("br"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::parse("zstd"), None);
+        assert_eq!(Encoding::parse("identity"), None);
+    }
+
+    #[tokio::test]
+    async fn test_decode() {
+        let data = br#"{"log":"hello"}"#.repeat(1000);
+        for encoding in [Encoding::Gzip, Encoding::Deflate, Encoding::Brotli] {
+            let body = to_stream(encode(encoding, &data).await);
+            let decoded = decode(encoding, body, data.len()).await.unwrap();
+            assert_eq!(decoded.as_ref(), data.as_slice());
+
+            let body = to_stream(encode(encoding, &data).await);
+            let ret = decode(encoding, body, data.len() - 1).await;
+            assert!(matches!(ret, Err(DecodeError::TooLarge)));
+        }
+        let ret = decode(Encoding::Gzip, to_stream(data.clone()), data.len()).await;
+        assert!(matches!(ret, Err(DecodeError::Invalid(_))));
+    }
+}
@@ -14,7 +14,11 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 mod check_keep_alive;
+mod decompress;
+mod rate_limit;
 mod slow_log;
 
 pub use check_keep_alive::check_keep_alive;
+pub use decompress::DecompressLayer;
+pub use rate_limit::{ingestion_rate_limit, RateLimiter};
 pub use slow_log::SlowLog;
@@ -0,0 +1,366 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use actix_http::h1::Payload;
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method, StatusCode},
+    web::BytesMut,
+    HttpResponse,
+};
+use actix_web_lab::middleware::Next;
+use dashmap::DashMap;
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::{
+    common::{
+        infra::config::ORGANIZATION_SETTING,
+        meta::{http::HttpResponse as MetaHttpResponse, ingestion::INGESTION_EP},
+    },
+    service::db::organization::ORG_SETTINGS_KEY_PREFIX,
+};
+
+static LIMITERS: Lazy<DashMap<String, Arc<RateLimiter>>> = Lazy::new(DashMap::new);
+
+#[derive(Debug, PartialEq)]
+pub enum RateLimitError {
+    /// Not enough tokens yet, retry after the duration.
+    Wait(Duration),
+    /// The request is larger than the limit of one second and can never pass.
+    TooLarge,
+}
+
+/// A token bucket holding at most one second of tokens, a rate of 0 means
+/// unlimited.
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+    }
+
+    fn wait_time(&self, n: u32) -> Result<Duration, RateLimitError> {
+        if self.rate == 0.0 {
+            return Ok(Duration::ZERO);
+        }
+        let n = n as f64;
+        if n > self.rate {
+            return Err(RateLimitError::TooLarge);
+        }
+        Ok(Duration::from_secs_f64(
+            ((n - self.tokens) / self.rate).max(0.0),
+        ))
+    }
+
+    fn take(&mut self, n: u32) {
+        if self.rate > 0.0 {
+            self.tokens -= n as f64;
+        }
+    }
+}
+
+struct Buckets {
+    bytes: Bucket,
+    records: Bucket,
+    last: Instant,
+}
+
+/// Token bucket rate limiter of the ingestion of an organization, a limit of
+/// 0 means unlimited.
+pub struct RateLimiter {
+    bytes_per_sec: u32,
+    records_per_sec: u32,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u32, records_per_sec: u32) -> Self {
+        Self {
+            bytes_per_sec,
+            records_per_sec,
+            buckets: Mutex::new(Buckets {
+                bytes: Bucket::new(bytes_per_sec),
+                records: Bucket::new(records_per_sec),
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes `bytes` and `records` tokens. Both buckets are checked before
+    /// either is consumed, so a rejected request takes no tokens.
+    pub fn check(&self, bytes: u32, records: u32) -> Result<(), RateLimitError> {
+        let mut buckets = self.buckets.lock();
+        let now = Instant::now();
+        let elapsed = now.duration_since(buckets.last);
+        buckets.last = now;
+        buckets.bytes.refill(elapsed);
+        buckets.records.refill(elapsed);
+
+        let wait = buckets
+            .bytes
+            .wait_time(bytes)?
+            .max(buckets.records.wait_time(records)?);
+        if !wait.is_zero() {
+            return Err(RateLimitError::Wait(wait));
+        }
+        buckets.bytes.take(bytes);
+        buckets.records.take(records);
+        Ok(())
+    }
+}
+
+/// Returns the rate limiter of the organization, the limiter is recreated
+/// when the limits in the organization settings change.
+async fn get_limiter(org_id: &str) -> Option<Arc<RateLimiter>> {
+    // all the org settings are cached and watched, don't hit the db here
+    let key = format!("{ORG_SETTINGS_KEY_PREFIX}/{org_id}");
+    let (bytes_per_sec, records_per_sec) = ORGANIZATION_SETTING
+        .read()
+        .await
+        .get(&key)
+        .map(|s| (s.ingestion_rate_limit_bytes, s.ingestion_rate_limit_records))?;
+    if bytes_per_sec == 0 && records_per_sec == 0 {
+        LIMITERS.remove(org_id);
+        return None;
+    }
+    if let Some(limiter) = LIMITERS.get(org_id) {
+        if limiter.bytes_per_sec == bytes_per_sec && limiter.records_per_sec == records_per_sec {
+            return Some(limiter.clone());
+        }
+    }
+    let limiter = Arc::new(RateLimiter::new(bytes_per_sec, records_per_sec));
+    LIMITERS.insert(org_id.to_string(), limiter.clone());
+    Some(limiter)
+}
+
+/// Estimates the number of records in an ingestion request body, compressed
+/// or binary bodies are counted as one record.
+fn count_records(ep: &str, compressed: bool, body: &[u8]) -> u32 {
+    if compressed {
+        return 1;
+    }
+    let lines = || {
+        body.split(|c| *c == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .count()
+    };
+    let n = match ep {
+        "_json" => count_json_values(body),
+        "_multi" => lines(),
+        // every document follows an action line
+        "_bulk" => lines() / 2,
+        _ => 1,
+    };
+    n.clamp(1, u32::MAX as usize) as u32
+}
+
+/// Counts the elements of a top level json array, or the top level values
+/// of a (newline delimited) json body, without parsing the values.
+fn count_json_values(body: &[u8]) -> usize {
+    let is_array = body.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'[');
+    // values are counted at this nesting depth
+    let level = if is_array { 1 } else { 0 };
+    let mut depth = 0usize;
+    let mut count = 0;
+    let mut in_value = false;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &c in body {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            b'{' | b'[' => {
+                if depth == level && !in_value {
+                    in_value = true;
+                    count += 1;
+                }
+                depth += 1;
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == level {
+                    in_value = false;
+                }
+            }
+            b',' if depth == level => in_value = false,
+            _ if c.is_ascii_whitespace() => {
+                // scalar values are separated by whitespace in ndjson
+                if depth == level && level == 0 {
+                    in_value = false;
+                }
+            }
+            _ if depth < level => {}
+            _ => {
+                if c == b'"' {
+                    in_string = true;
+                }
+                if depth == level && !in_value {
+                    in_value = true;
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Rejects ingestion requests with `429 Too Many Requests` when the
+/// organization is over its ingestion rate limit, and with `413 Payload Too
+/// Large` when a single request exceeds the limit of one second.
+pub async fn ingestion_rate_limit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let prefix = format!("{}/api/", config::get_config().common.base_uri);
+    let path = req.path().strip_prefix(&prefix).unwrap_or_default();
+    let path_columns = path.split('/').collect::<Vec<&str>>();
+    let ep = path_columns.last().copied().unwrap_or_default();
+    if req.method() != Method::POST || path_columns.len() < 2 || !INGESTION_EP.contains(&ep) {
+        return next.call(req).await.map(|res| res.map_into_boxed_body());
+    }
+    let org_id = path_columns[0].to_string();
+    let ep = ep.to_string();
+    let Some(limiter) = get_limiter(&org_id).await else {
+        return next.call(req).await.map(|res| res.map_into_boxed_body());
+    };
+
+    // the body is buffered to count the records, stop at the payload limit
+    // like the handlers would
+    let max_size = config::get_config().limit.req_payload_limit;
+    let mut body = BytesMut::new();
+    let mut payload = req.take_payload();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > max_size {
+            return Ok(req.into_response(payload_too_large(format!(
+                "request body is larger than the payload limit of {max_size} bytes"
+            ))));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let compressed = req.headers().contains_key(header::CONTENT_ENCODING);
+    let records = count_records(&ep, compressed, &body);
+    let bytes = body.len().min(u32::MAX as usize) as u32;
+
+    match limiter.check(bytes, records) {
+        Ok(()) => {}
+        Err(RateLimitError::Wait(wait)) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let resp = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(MetaHttpResponse::error(
+                    StatusCode::TOO_MANY_REQUESTS.into(),
+                    format!("ingestion rate limit exceeded for organization {org_id}"),
+                ));
+            return Ok(req.into_response(resp));
+        }
+        // retrying can never succeed, the request has to be split
+        Err(RateLimitError::TooLarge) => {
+            return Ok(req.into_response(payload_too_large(format!(
+                "request is larger than the ingestion rate limit of organization {org_id}"
+            ))));
+        }
+    }
+
+    // put the payload back into the request
+    let (_, mut payload) = Payload::create(true);
+    payload.unread_data(body.freeze());
+    req.set_payload(payload.into());
+    next.call(req).await.map(|res| res.map_into_boxed_body())
+}
+
+fn payload_too_large(message: String) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().json(MetaHttpResponse::error(
+        StatusCode::PAYLOAD_TOO_LARGE.into(),
+        message,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(100, 2);
+        assert!(limiter.check(50, 1).is_ok());
+        assert!(limiter.check(50, 1).is_ok());
+        // records exhausted
+        assert!(matches!(limiter.check(0, 1), Err(RateLimitError::Wait(_))));
+
+        let limiter = RateLimiter::new(100, 0);
+        assert!(limiter.check(60, 1000).is_ok());
+        // bytes exhausted
+        assert!(matches!(
+            limiter.check(60, 1000),
+            Err(RateLimitError::Wait(_))
+        ));
+        // larger than the limit of one second
+        assert_eq!(limiter.check(1000, 1), Err(RateLimitError::TooLarge));
+    }
+
+    #[test]
+    fn test_rate_limiter_no_partial_take() {
+        let limiter = RateLimiter::new(100, 2);
+        assert!(limiter.check(10, 2).is_ok());
+        // rejected by the records bucket, the bytes must not be taken
+        assert!(limiter.check(90, 1).is_err());
+        assert!(limiter.buckets.lock().bytes.tokens >= 90.0);
+        assert_eq!(limiter.check(0, 3), Err(RateLimitError::TooLarge));
+    }
+
+    #[test]
+    fn test_count_records() {
+        let body = br#"[{"a":1},{"a":2},{"a":3}]"#;
+        assert_eq!(count_records("_json", false, body), 3);
+        assert_eq!(count_records("_json", true, body), 1);
+        let body = b"{\"a\":1}\n{\"a\":2}\n\n";
+        assert_eq!(count_records("_multi", false, body), 2);
+        assert_eq!(count_records("_json", false, body), 2);
+        assert_eq!(count_records("_json", false, b"{\n\"a\":1\n}"), 1);
+        let body = b"{\"index\":{}}\n{\"a\":1}\n{\"index\":{}}\n{\"a\":2}\n";
+        assert_eq!(count_records("_bulk", false, body), 2);
+        assert_eq!(count_records("logs", false, b"\x0a\x0b"), 1);
+        let body = br#"[{"a":"],[{"}, {"b":[1,2,{"c":"\""}]}, 3, "x"]"#;
+        assert_eq!(count_records("_json", false, body), 4);
+        assert_eq!(count_records("_json", false, b"[]"), 1);
+        assert_eq!(count_records("_json", false, b"1\n\"a\"\n[1,2]"), 3);
+    }
+}
@@ -199,6 +199,7 @@ pub fn get_basic_routes(svc: &mut web::ServiceConfig) {
     let cors = get_cors();
     svc.service(status::healthz)
         .service(status::healthz_head)
+        .service(status::readyz)
         .service(status::schedulez);
     svc.service(
         web::scope("/auth")
@@ -315,6 +316,8 @@ pub fn get_service_routes(svc: &mut web::ServiceConfig) {
     let server = cfg.common.instance_name_short.to_string();
 
     let service = web::scope("/api")
+        .wrap(from_fn(middlewares::ingestion_rate_limit))
+        .wrap(middlewares::DecompressLayer::new(cfg.limit.req_json_limit))
         .wrap(from_fn(audit_middleware))
         .wrap(HttpAuthentication::with_fn(
             super::auth::validator::oo_validator,
@@ -352,10 +355,15 @@ pub fn get_service_routes(svc: &mut web::ServiceConfig) {
         .service(organization::es::org_pipeline)
         .service(organization::es::org_pipeline_create)
         .service(stream::schema)
+        .service(stream::stats)
+        .service(stream::tail)
+        .service(stream::tail_sse)
         .service(stream::settings)
         .service(stream::update_settings)
         .service(stream::delete_fields)
         .service(stream::delete)
+        .service(stream::create_alias)
+        .service(stream::delete_alias)
         .service(stream::list)
         .service(logs::ingest::bulk)
         .service(logs::ingest::multi)
@@ -381,6 +389,9 @@ pub fn get_service_routes(svc: &mut web::ServiceConfig) {
         .service(promql::label_values)
         .service(promql::format_query_get)
         .service(promql::format_query_post)
+        .service(promql::rules_list)
+        .service(promql::rules_save)
+        .service(promql::rules_delete)
         .service(enrichment_table::save_enrichment_table)
         .service(search::search)
         .service(search::search_partition)
@@ -495,6 +506,9 @@ pub fn get_service_routes(svc: &mut web::ServiceConfig) {
         .service(stream::delete_stream_cache)
         .service(short_url::shorten)
         .service(short_url::retrieve)
+        .service(silences::create)
+        .service(silences::list)
+        .service(silences::delete)
         .service(service_accounts::list)
         .service(service_accounts::save)
         .service(service_accounts::delete)
@@ -22,6 +22,7 @@ use crate::{common::meta, handler::http::request};
 #[openapi(
     paths(
         request::status::healthz,
+        request::status::readyz,
         request::users::list,
         request::users::save,
         request::users::update,
@@ -38,10 +39,13 @@ use crate::{common::meta, handler::http::request};
         request::organization::settings::create,
         request::stream::list,
         request::stream::schema,
+        request::stream::stats,
         request::stream::settings,
         request::stream::update_settings,
         request::stream::delete_fields,
         request::stream::delete,
+        request::stream::create_alias,
+        request::stream::delete_alias,
         request::logs::ingest::bulk,
         request::logs::ingest::multi,
         request::logs::ingest::json,
@@ -56,6 +60,9 @@ use crate::{common::meta, handler::http::request};
         request::promql::labels_get,
         request::promql::label_values,
         request::promql::format_query_get,
+        request::promql::rules_list,
+        request::promql::rules_save,
+        request::promql::rules_delete,
         request::enrichment_table::save_enrichment_table,
         request::rum::ingest::log,
         request::rum::ingest::data,
@@ -131,6 +138,9 @@ use crate::{common::meta, handler::http::request};
         request::clusters::list_clusters,
         request::short_url::shorten,
         request::short_url::retrieve,
+        request::silences::create,
+        request::silences::list,
+        request::silences::delete,
     ),
     components(
         schemas(
@@ -139,6 +149,8 @@ use crate::{common::meta, handler::http::request};
             meta::stream::Stream,
             meta::stream::StreamProperty,
             meta::stream::StreamDeleteFields,
+            meta::stream::StreamAlias,
+            meta::stream::StreamFileStats,
             meta::stream::ListStream,
             config::meta::stream::StreamSettings,
             config::meta::stream::StreamPartition,
@@ -268,6 +280,8 @@ use crate::{common::meta, handler::http::request};
             meta::ingestion::BulkResponseError,
             meta::syslog::SyslogRoute,
             meta::syslog::SyslogRoutes,
+            meta::silence::Silence,
+            meta::silence::SilenceMatcher,
             config::meta::promql::Metadata,
             config::meta::promql::MetricType,
             // Functions
@@ -293,6 +307,7 @@ use crate::{common::meta, handler::http::request};
         (name = "Syslog Routes", description = "Syslog Routes retrieval & management operations"),
         (name = "Clusters", description = "Super cluster operations"),
         (name = "Short Url", description = "Short Url Service"),
+        (name = "Silences", description = "Alert silences retrieval & management operations"),
     ),
     info(
         description = "OpenObserve API documents [https://openobserve.ai/docs/](https://openobserve.ai/docs/)",
@@ -318,3 +333,76 @@ impl Modify for SecurityAddon {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use config::utils::json;
+
+    use super::*;
+
+    fn spec() -> json::Value {
+        json::from_str(&ApiDoc::openapi().to_json().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_openapi_alert_paths() {
+        let spec = spec();
+        for (path, methods) in [
+            ("/api/v2/{org_id}/alerts", vec!["get", "post"]),
+            (
+                "/api/v2/{org_id}/alerts/{alert_id}",
+                vec!["get", "put", "delete"],
+            ),
+            ("/api/v2/{org_id}/alerts/{alert_id}/enable", vec!["put"]),
+            ("/api/v2/{org_id}/alerts/{alert_id}/trigger", vec!["put"]),
+            ("/api/v2/{org_id}/alerts/move", vec!["put"]),
+            ("/api/{org_id}/silences", vec!["get", "post"]),
+            ("/api/{org_id}/silences/{silence_id}", vec!["delete"]),
+        ] {
+            for method in methods {
+                assert!(
+                    spec["paths"][path][method].is_object(),
+                    "missing {method} {path}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_openapi_alert_schemas() {
+        let spec = spec();
+        let schemas = &spec["components"]["schemas"];
+        for name in [
+            "Alert",
+            "TriggerCondition",
+            "QueryCondition",
+            "CreateAlertRequestBody",
+            "UpdateAlertRequestBody",
+            "GetAlertResponseBody",
+            "ListAlertsResponseBody",
+            "Destination",
+            "Template",
+            "Silence",
+            "SilenceMatcher",
+        ] {
+            assert!(schemas[name].is_object(), "missing schema {name}");
+        }
+        // the storage and the api alert models are both named `Alert` and must
+        // keep the same fields
+        let alert = &schemas["Alert"]["properties"];
+        for field in [
+            "id",
+            "name",
+            "stream_type",
+            "stream_name",
+            "is_real_time",
+            "query_condition",
+            "trigger_condition",
+            "destinations",
+            "enabled",
+            "last_edited_by",
+        ] {
+            assert!(alert[field].is_object(), "missing Alert.{field}");
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use bytes::BytesMut;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::service::logs::fluent_forward;
+
+pub async fn tcp_server(listener: TcpListener) {
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(val) => val,
+            Err(e) => {
+                log::error!("Error while accepting forward protocol connection: {}", e);
+                continue;
+            }
+        };
+        tokio::task::spawn(async move {
+            if let Err(e) = handle_connection(stream).await {
+                log::error!(
+                    "Error while reading forward protocol from {}: {}",
+                    peer_addr,
+                    e
+                );
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream) -> Result<(), anyhow::Error> {
+    let cfg = config::get_config();
+    let max_size = cfg.limit.req_payload_limit;
+    let mut buf = BytesMut::with_capacity(64 * 1024);
+    let mut scanner = fluent_forward::FrameScanner::default();
+    loop {
+        while let Some(n) = scanner.next_frame(&buf, max_size)? {
+            let frame = buf.split_to(n);
+            let message = fluent_forward::decode(&frame, max_size)?;
+            let chunk = message.chunk.clone();
+            let tag = message.tag.clone();
+            // don't ack the chunk if it fails, so the client retries it
+            if let Err(e) = fluent_forward::ingest(&cfg.tcp.fluent_forward_org, message).await {
+                log::error!("Error while ingesting forward protocol tag {}: {}", tag, e);
+                continue;
+            }
+            if let Some(chunk) = chunk {
+                stream
+                    .write_all(&fluent_forward::encode_ack(&chunk))
+                    .await?;
+            }
+        }
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Ok(());
+        }
+    }
+}
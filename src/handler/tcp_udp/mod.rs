@@ -21,6 +21,8 @@ use tokio::{
 
 use crate::{job::syslog_server::BROADCASTER, service::logs::syslog};
 
+pub mod fluent_forward;
+
 pub static STOP_SRV: &str = "ZO_STOP_TCP_UDP";
 
 pub async fn udp_server(socket: UdpSocket) {
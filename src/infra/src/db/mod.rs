@@ -28,6 +28,7 @@ pub mod etcd;
 pub mod mysql;
 pub mod nats;
 pub mod postgres;
+pub mod sled;
 pub mod sqlite;
 
 pub static NEED_WATCH: bool = true;
@@ -88,10 +89,11 @@ async fn default() -> Box<dyn Db> {
     if !cfg.common.local_mode
         && (cfg.common.meta_store == "sled" || cfg.common.meta_store == "sqlite")
     {
-        panic!("cluster mode is not supported for ZO_META_STORE=sqlite");
+        panic!("cluster mode is not supported for ZO_META_STORE=sled or sqlite");
     }
 
     match cfg.common.meta_store.as_str().into() {
+        MetaStore::Sled => Box::<sled::SledDb>::default(),
         MetaStore::Sqlite => Box::<sqlite::SqliteDb>::default(),
         MetaStore::Etcd => Box::<etcd::Etcd>::default(),
         MetaStore::Nats => Box::<nats::NatsDb>::default(),
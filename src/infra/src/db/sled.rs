@@ -0,0 +1,544 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use config::{cluster, FxIndexMap};
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
+use tokio::sync::{mpsc, Mutex, RwLock};
+
+use crate::{
+    db::{Event, EventData},
+    errors::*,
+};
+
+pub const SLED_DIR: &str = "metadata.sled";
+
+pub static CLIENT: Lazy<sled::Db> = Lazy::new(connect);
+pub static CHANNEL: Lazy<SledDbChannel> = Lazy::new(SledDbChannel::new);
+
+/// Serializes the read-modify-write of `get_for_update` with other writes.
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+static WATCHERS: Lazy<RwLock<FxIndexMap<String, EventChannel>>> =
+    Lazy::new(|| RwLock::new(Default::default()));
+
+type EventChannel = Arc<mpsc::Sender<Event>>;
+
+fn connect() -> sled::Db {
+    let cfg = config::get_config();
+    let path = format!("{}{}", cfg.common.data_db_dir, SLED_DIR);
+    sled::open(path).expect("sled open failed")
+}
+
+impl From<sled::Error> for Error {
+    fn from(e: sled::Error) -> Self {
+        Error::Message(format!("[SLED] {e}"))
+    }
+}
+
+pub struct SledDbChannel {
+    pub watch_tx: EventChannel,
+}
+
+impl SledDbChannel {
+    pub fn new() -> Self {
+        Self {
+            watch_tx: SledDbChannel::handle_watch_channel(),
+        }
+    }
+
+    fn handle_watch_channel() -> EventChannel {
+        let (tx, mut rx) = mpsc::channel::<Event>(10000);
+        tokio::task::spawn(async move {
+            loop {
+                if cluster::is_offline() {
+                    break;
+                }
+                let event = match rx.recv().await {
+                    Some(v) => v,
+                    None => {
+                        log::info!("[SLED] watch event channel closed");
+                        break;
+                    }
+                };
+                if config::get_config().common.print_key_event {
+                    log::info!("[SLED] watch event: {:?}", event);
+                }
+                let key = match &event {
+                    Event::Put(e) | Event::Delete(e) => e.key.clone(),
+                    Event::Empty => continue,
+                };
+                for (prefix, tx) in WATCHERS.read().await.iter() {
+                    if key.starts_with(prefix) {
+                        let tx = tx.clone();
+                        let event = event.clone();
+                        tokio::task::spawn(async move {
+                            if let Err(e) = tx.send(event).await {
+                                log::error!("[SLED] send event error: {}", e);
+                            }
+                        });
+                    }
+                }
+            }
+            log::info!("[SLED] watch event loop exit");
+        });
+        Arc::new(tx)
+    }
+}
+
+impl Default for SledDbChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Embedded key value meta store for single node deployments.
+///
+/// The records are stored as `/{module}/{key1}/{key2}\0{start_dt}`, the
+/// start_dt is zero padded so the versions of a key are sorted by start_dt.
+pub struct SledDb {
+    tree: sled::Db,
+}
+
+impl SledDb {
+    pub fn new() -> Self {
+        Self {
+            tree: CLIENT.clone(),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_tree(tree: sled::Db) -> Self {
+        Self { tree }
+    }
+
+    /// Returns all the records under the prefix, with the same matching as
+    /// the sql backends: module and key1 are matched exactly, key2 is matched
+    /// exactly or as a parent path.
+    fn scan(&self, prefix: &str) -> Result<Vec<Record>> {
+        let (module, key1, key2) = super::parse_key(prefix);
+        let scan_prefix = if module.is_empty() {
+            "/".to_string()
+        } else if key1.is_empty() {
+            format!("/{module}/")
+        } else if key2.is_empty() {
+            format!("/{module}/{key1}/")
+        } else {
+            format!("/{module}/{key1}/{key2}")
+        };
+        let mut records = Vec::new();
+        for item in self.tree.scan_prefix(scan_prefix.as_bytes()) {
+            let (k, v) = item?;
+            let Some(record) = Record::decode(&k, v) else {
+                continue;
+            };
+            if !key2.is_empty()
+                && record.key2 != key2
+                && !record.key2.starts_with(&format!("{key2}/"))
+            {
+                continue;
+            }
+            records.push(record);
+        }
+        records.sort_by_key(|r| r.start_dt);
+        Ok(records)
+    }
+
+    /// Returns the versions of the exact key, sorted by start_dt.
+    fn versions(&self, key: &str) -> Result<Vec<Record>> {
+        let (module, key1, key2) = super::parse_key(key);
+        let prefix = format!("/{module}/{key1}/{key2}\0");
+        self.tree
+            .scan_prefix(prefix.as_bytes())
+            .filter_map(|item| match item {
+                Ok((k, v)) => Record::decode(&k, v).map(Ok),
+                Err(e) => Some(Err(e.into())),
+            })
+            .collect()
+    }
+
+    async fn send_event(&self, event: Event) {
+        if let Err(e) = CHANNEL.watch_tx.clone().send(event).await {
+            log::error!("[SLED] send event error: {}", e);
+        }
+    }
+}
+
+impl Default for SledDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Record {
+    module: String,
+    key1: String,
+    key2: String,
+    start_dt: i64,
+    value: Bytes,
+}
+
+impl Record {
+    fn encode_key(key: &str, start_dt: i64) -> String {
+        let (module, key1, key2) = super::parse_key(key);
+        format!("/{module}/{key1}/{key2}\0{start_dt:020}")
+    }
+
+    fn decode(key: &[u8], value: sled::IVec) -> Option<Self> {
+        let key = std::str::from_utf8(key).ok()?;
+        let (key, start_dt) = key.split_once('\0')?;
+        let mut columns = key.splitn(4, '/').skip(1);
+        Some(Self {
+            module: columns.next()?.to_string(),
+            key1: columns.next()?.to_string(),
+            key2: columns.next().unwrap_or_default().to_string(),
+            start_dt: start_dt.parse().ok()?,
+            value: Bytes::from(value.to_vec()),
+        })
+    }
+
+    fn storage_key(&self) -> String {
+        format!(
+            "/{}/{}/{}\0{:020}",
+            self.module, self.key1, self.key2, self.start_dt
+        )
+    }
+}
+
+#[async_trait]
+impl super::Db for SledDb {
+    async fn create_table(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<super::Stats> {
+        Ok(super::Stats {
+            bytes_len: self.tree.size_on_disk().unwrap_or_default() as i64,
+            keys_count: self.tree.len() as i64,
+        })
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        match self.versions(key)?.pop() {
+            Some(r) => Ok(r.value),
+            None => Err(Error::from(DbError::KeyNotExists(key.to_string()))),
+        }
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        value: Bytes,
+        need_watch: bool,
+        start_dt: Option<i64>,
+    ) -> Result<()> {
+        let lock = WRITE_LOCK.lock().await;
+        self.tree.insert(
+            Record::encode_key(key, start_dt.unwrap_or_default()),
+            value.to_vec(),
+        )?;
+        self.tree.flush_async().await?;
+        drop(lock);
+
+        // event watch
+        if need_watch {
+            self.send_event(Event::Put(EventData {
+                key: key.to_string(),
+                value: Some(value),
+                start_dt,
+            }))
+            .await;
+        }
+
+        Ok(())
+    }
+
+    async fn get_for_update(
+        &self,
+        key: &str,
+        need_watch: bool,
+        start_dt: Option<i64>,
+        update_fn: Box<super::UpdateFn>,
+    ) -> Result<()> {
+        let lock = WRITE_LOCK.lock().await;
+        let mut versions = self.versions(key)?;
+        let row = match start_dt {
+            Some(start_dt) => versions.into_iter().find(|r| r.start_dt == start_dt),
+            None => versions.pop(),
+        };
+        let storage_key = match row.as_ref() {
+            Some(r) => r.storage_key(),
+            None => Record::encode_key(key, start_dt.unwrap_or_default()),
+        };
+        let (value, new_value) = match update_fn(row.map(|r| r.value))? {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        let mut batch = sled::Batch::default();
+        if let Some(value) = value.as_ref() {
+            batch.insert(storage_key.as_bytes(), value.to_vec());
+        }
+        let mut need_watch_dt = 0;
+        if let Some((new_key, new_value, new_start_dt)) = new_value.as_ref() {
+            need_watch_dt = new_start_dt.unwrap_or_default();
+            batch.insert(
+                Record::encode_key(new_key, need_watch_dt).as_bytes(),
+                new_value.to_vec(),
+            );
+        }
+        self.tree.apply_batch(batch)?;
+        self.tree.flush_async().await?;
+        drop(lock);
+
+        // event watch
+        if need_watch && (new_value.is_some() || value.is_some()) {
+            let start_dt = if need_watch_dt > 0 {
+                Some(need_watch_dt)
+            } else {
+                None
+            };
+            self.send_event(Event::Put(EventData {
+                key: key.to_string(),
+                value: Some(Bytes::from("")),
+                start_dt,
+            }))
+            .await;
+        }
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        key: &str,
+        with_prefix: bool,
+        need_watch: bool,
+        start_dt: Option<i64>,
+    ) -> Result<()> {
+        let lock = WRITE_LOCK.lock().await;
+        let mut records = if with_prefix {
+            self.scan(key)?
+        } else {
+            self.versions(key)?
+        };
+        if let Some(start_dt) = start_dt {
+            records.retain(|r| r.start_dt == start_dt);
+        }
+        let mut batch = sled::Batch::default();
+        for r in records.iter() {
+            batch.remove(r.storage_key().as_bytes());
+        }
+        self.tree.apply_batch(batch)?;
+        self.tree.flush_async().await?;
+        drop(lock);
+
+        // event watch
+        if need_watch {
+            let keys = if with_prefix && start_dt.is_none() {
+                records
+                    .iter()
+                    .map(|r| format!("/{}/{}/{}", r.module, r.key1, r.key2))
+                    .collect::<Vec<_>>()
+            } else if let Some(start_dt) = start_dt {
+                vec![format!("{}/{}", key, start_dt)]
+            } else {
+                vec![key.to_string()]
+            };
+            for key in keys {
+                self.send_event(Event::Delete(EventData {
+                    key,
+                    value: None,
+                    start_dt,
+                }))
+                .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<HashMap<String, Bytes>> {
+        Ok(self
+            .scan(prefix)?
+            .into_iter()
+            .map(|r| {
+                (
+                    super::build_key(&r.module, &r.key1, &r.key2, r.start_dt),
+                    r.value,
+                )
+            })
+            .collect())
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .scan(prefix)?
+            .into_iter()
+            .map(|r| format!("/{}/{}/{}", r.module, r.key1, r.key2))
+            .collect())
+    }
+
+    async fn list_values(&self, prefix: &str) -> Result<Vec<Bytes>> {
+        let mut items = self.list(prefix).await?;
+        let mut keys = items.keys().map(|k| k.to_string()).collect::<Vec<_>>();
+        keys.sort();
+        Ok(keys
+            .into_iter()
+            .map(|k| items.remove(&k).unwrap())
+            .collect())
+    }
+
+    async fn list_values_by_start_dt(
+        &self,
+        prefix: &str,
+        start_dt: Option<(i64, i64)>,
+    ) -> Result<Vec<(i64, Bytes)>> {
+        if start_dt.is_none() || start_dt == Some((0, 0)) {
+            let vals = self.list_values(prefix).await?;
+            return Ok(vals.into_iter().map(|v| (0, v)).collect());
+        }
+
+        let (min_dt, max_dt) = start_dt.unwrap();
+        Ok(self
+            .scan(prefix)?
+            .into_iter()
+            .filter(|r| r.start_dt >= min_dt && r.start_dt <= max_dt)
+            .map(|r| (r.start_dt, r.value))
+            .collect())
+    }
+
+    async fn count(&self, prefix: &str) -> Result<i64> {
+        Ok(self.scan(prefix)?.len() as i64)
+    }
+
+    async fn watch(&self, prefix: &str) -> Result<Arc<mpsc::Receiver<Event>>> {
+        let (tx, rx) = mpsc::channel(1024);
+        WATCHERS
+            .write()
+            .await
+            .insert(prefix.to_string(), Arc::new(tx));
+        Ok(Arc::new(rx))
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+
+    async fn add_start_dt_column(&self) -> Result<()> {
+        // the start_dt is part of the key from the beginning
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Db, *};
+
+    fn new_db() -> SledDb {
+        let tree = sled::Config::new().temporary(true).open().unwrap();
+        SledDb::with_tree(tree)
+    }
+
+    #[tokio::test]
+    async fn test_sled_get_put_delete() {
+        let db = new_db();
+        db.put("/schema/default/logs/app", Bytes::from("v1"), false, None)
+            .await
+            .unwrap();
+        db.put(
+            "/schema/default/logs/app",
+            Bytes::from("v2"),
+            false,
+            Some(10),
+        )
+        .await
+        .unwrap();
+        db.put("/schema/default/logs/app2", Bytes::from("v3"), false, None)
+            .await
+            .unwrap();
+        db.put("/schema/default/logs/app/x", Bytes::from("v4"), false, None)
+            .await
+            .unwrap();
+
+        // the latest version
+        assert_eq!(
+            db.get("/schema/default/logs/app").await.unwrap(),
+            Bytes::from("v2")
+        );
+        assert!(db.get("/schema/default/logs/none").await.is_err());
+
+        // app2 is not under the app prefix
+        assert_eq!(db.count("/schema/default/logs/app").await.unwrap(), 3);
+        assert_eq!(db.count("/schema/default/").await.unwrap(), 4);
+        assert_eq!(db.count("/schema/").await.unwrap(), 4);
+        assert_eq!(db.count("/user/").await.unwrap(), 0);
+        let items = db.list("/schema/default/logs/app").await.unwrap();
+        assert!(items.contains_key("/schema/default/logs/app"));
+        assert!(items.contains_key("/schema/default/logs/app/10"));
+        assert!(items.contains_key("/schema/default/logs/app/x"));
+        let values = db
+            .list_values_by_start_dt("/schema/default/logs/app", Some((5, 20)))
+            .await
+            .unwrap();
+        assert_eq!(values, vec![(10, Bytes::from("v2"))]);
+
+        db.delete("/schema/default/logs/app", false, false, Some(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get("/schema/default/logs/app").await.unwrap(),
+            Bytes::from("v1")
+        );
+        db.delete("/schema/default/logs/app", true, false, None)
+            .await
+            .unwrap();
+        assert_eq!(db.count("/schema/").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sled_get_for_update() {
+        let db = new_db();
+        for _ in 0..3 {
+            db.get_for_update(
+                "/kv/default/counter",
+                false,
+                None,
+                Box::new(|v| {
+                    let n = v
+                        .map(|v| {
+                            String::from_utf8(v.to_vec())
+                                .unwrap()
+                                .parse::<i64>()
+                                .unwrap()
+                        })
+                        .unwrap_or_default();
+                    Ok(Some((Some(Bytes::from((n + 1).to_string())), None)))
+                }),
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(
+            db.get("/kv/default/counter").await.unwrap(),
+            Bytes::from("3")
+        );
+    }
+}
@@ -256,37 +256,18 @@ impl super::Db for SqliteDb {
         let local_start_dt = start_dt.unwrap_or_default();
         let client = CLIENT_RW.clone();
         let client = client.lock().await;
+        // upsert in one statement, so the key is never visible with an empty
+        // value and a failed write leaves the previous value untouched
         let mut tx = client.begin().await?;
         if let Err(e) = sqlx::query(
-            r#"INSERT OR IGNORE INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, $3, $4, '');"#
+            r#"INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (module, key1, key2, start_dt) DO UPDATE SET value = excluded.value;"#,
         )
         .bind(&module)
         .bind(&key1)
         .bind(&key2)
         .bind(local_start_dt)
-        .execute(&mut *tx)
-        .await
-        {
-            if let Err(e) = tx.rollback().await {
-                log::error!("[SQLITE] rollback put meta error: {}", e);
-            }
-            return Err(e.into());
-        }
-        // need commit it first to avoid the deadlock of insert and update
-        if let Err(e) = tx.commit().await {
-            log::error!("[SQLITE] commit put meta error: {}", e);
-            return Err(e.into());
-        }
-
-        let mut tx = client.begin().await?;
-        if let Err(e) = sqlx::query(
-            r#"UPDATE meta SET value = $1 WHERE module = $2 AND key1 = $3 AND key2 = $4 AND start_dt = $5;"#
-        )
         .bind(String::from_utf8(value.to_vec()).unwrap_or_default())
-        .bind(&module)
-        .bind(&key1)
-        .bind(&key2)
-        .bind(local_start_dt)
         .execute(&mut *tx)
         .await
         {
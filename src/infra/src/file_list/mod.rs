@@ -36,6 +36,7 @@ pub static LOCAL_CACHE: Lazy<Box<dyn FileList>> = Lazy::new(connect_local_cache)
 
 pub fn connect_default() -> Box<dyn FileList> {
     match config::get_config().common.meta_store.as_str().into() {
+        MetaStore::Sled => Box::<sqlite::SqliteFileList>::default(),
         MetaStore::Sqlite => Box::<sqlite::SqliteFileList>::default(),
         MetaStore::Etcd => Box::<sqlite::SqliteFileList>::default(),
         MetaStore::Nats => Box::<sqlite::SqliteFileList>::default(),
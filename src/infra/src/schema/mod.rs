@@ -786,6 +786,63 @@ mod tests {
     #[test]
     fn test_is_widening_conversion() {
         assert!(is_widening_conversion(&DataType::Int8, &DataType::Int32));
+        assert!(is_widening_conversion(&DataType::Int32, &DataType::Int64));
+        assert!(is_widening_conversion(
+            &DataType::Float32,
+            &DataType::Float64
+        ));
+        assert!(!is_widening_conversion(&DataType::Int64, &DataType::Int32));
+        assert!(!is_widening_conversion(
+            &DataType::Float64,
+            &DataType::Int64
+        ));
+    }
+
+    #[test]
+    fn test_get_merge_schema_changes() {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Float32, true),
+            Field::new("c", DataType::Float64, true),
+        ]);
+
+        // widening conversions promote the field types
+        let inferred_schema = Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Float64, true),
+        ]);
+        let (is_schema_changed, delta, merged_fields) =
+            get_merge_schema_changes(&schema, &inferred_schema);
+        assert!(is_schema_changed);
+        assert_eq!(delta.len(), 2);
+        assert_eq!(
+            merged_fields,
+            vec![
+                Field::new("a", DataType::Int64, true),
+                Field::new("b", DataType::Float64, true),
+                Field::new("c", DataType::Float64, true),
+            ]
+        );
+
+        // narrower types keep the schema and are cast to the existing type
+        let inferred_schema = Schema::new(vec![Field::new("c", DataType::Int64, true)]);
+        let (is_schema_changed, delta, merged_fields) =
+            get_merge_schema_changes(&schema, &inferred_schema);
+        assert!(!is_schema_changed);
+        assert!(merged_fields.is_empty());
+        assert_eq!(delta[0].data_type(), &DataType::Float64);
+        assert_eq!(
+            delta[0].metadata().get("zo_cast").map(|v| v.as_str()),
+            Some("true")
+        );
+
+        // new fields are appended
+        let inferred_schema = Schema::new(vec![Field::new("d", DataType::Utf8, true)]);
+        let (is_schema_changed, delta, merged_fields) =
+            get_merge_schema_changes(&schema, &inferred_schema);
+        assert!(is_schema_changed);
+        assert!(delta.is_empty());
+        assert_eq!(merged_fields.len(), 4);
     }
 
     #[test]
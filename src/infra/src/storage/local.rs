@@ -97,6 +97,7 @@ impl ObjectStore for Local {
             }
             Err(err) => {
                 log::error!("disk File upload error: {:?}", err);
+                super::inc_errors(&err, "put", "local");
                 Err(err)
             }
         }
@@ -127,7 +128,8 @@ impl ObjectStore for Local {
         let result = self
             .client
             .get(&(format_key(&file, self.with_prefix).into()))
-            .await?;
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "local"))?;
 
         // metrics
         let data_len = result.meta.size;
@@ -154,7 +156,8 @@ impl ObjectStore for Local {
         let result = self
             .client
             .get_opts(&(format_key(&file, self.with_prefix).into()), options)
-            .await?;
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "local"))?;
 
         // metrics
         let data_len = result.meta.size;
@@ -181,7 +184,8 @@ impl ObjectStore for Local {
         let data = self
             .client
             .get_range(&(format_key(&file, self.with_prefix).into()), range)
-            .await?;
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "local"))?;
 
         // metrics
         let data_len = data.len();
@@ -223,6 +227,9 @@ impl ObjectStore for Local {
             }
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
+        if let Err(e) = &result {
+            super::inc_errors(e, "del", "local");
+        }
         result
     }
 
@@ -236,8 +243,21 @@ impl ObjectStore for Local {
         Err(Error::NotImplemented)
     }
 
-    async fn copy(&self, _from: &Path, _to: &Path) -> Result<()> {
-        Err(Error::NotImplemented)
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        // the local disk has no server side copy, read and write it again
+        let data = self
+            .client
+            .get(&(format_key(from.as_ref(), self.with_prefix).into()))
+            .await?
+            .bytes()
+            .await?;
+        self.client
+            .put(
+                &(format_key(to.as_ref(), self.with_prefix).into()),
+                data.into(),
+            )
+            .await?;
+        Ok(())
     }
 
     async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> Result<()> {
@@ -251,3 +271,27 @@ fn init_client(root_dir: &str) -> Box<dyn object_store::ObjectStore> {
             .expect("Error creating local file system"),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_copy() {
+        let root_dir = std::env::temp_dir().join("openobserve-test-local-copy");
+        std::fs::create_dir_all(&root_dir).unwrap();
+        let local = Local::new(root_dir.to_str().unwrap(), false);
+
+        let src = Path::from("files/default/logs/test/a.parquet");
+        let dst = Path::from("files/default/logs/test/b.parquet");
+        let data = Bytes::from_static(b"hello local");
+        local.put(&src, data.clone().into()).await.unwrap();
+        local.copy(&src, &dst).await.unwrap();
+        let got = local.get(&dst).await.unwrap().bytes().await.unwrap();
+        assert_eq!(got, data);
+        // the source file is kept
+        assert!(local.get(&src).await.is_ok());
+
+        std::fs::remove_dir_all(&root_dir).unwrap();
+    }
+}
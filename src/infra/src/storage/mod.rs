@@ -15,10 +15,16 @@
 
 use std::ops::Range;
 
+use bytes::BytesMut;
 use config::{get_config, is_local_disk_storage, metrics};
 use datafusion::parquet::data_type::AsBytes;
-use futures::{StreamExt, TryStreamExt};
-use object_store::{path::Path, GetRange, ObjectMeta, ObjectStore, WriteMultipart};
+use futures::{
+    stream::{BoxStream, Stream},
+    StreamExt, TryStreamExt,
+};
+use object_store::{
+    path::Path, GetRange, ObjectMeta, ObjectStore, PutMode, PutResult, WriteMultipart,
+};
 use once_cell::sync::Lazy;
 
 pub mod local;
@@ -26,6 +32,8 @@ pub mod remote;
 
 pub const CONCURRENT_REQUESTS: usize = 1000;
 pub const MULTI_PART_UPLOAD_DATA_SIZE: f64 = 100.0;
+pub const MULTI_PART_UPLOAD_PART_SIZE: usize = 64 * 1024 * 1024;
+const MULTI_PART_UPLOAD_CONCURRENCY: usize = 4;
 
 pub static DEFAULT: Lazy<Box<dyn ObjectStore>> = Lazy::new(default);
 pub static LOCAL_WAL: Lazy<Box<dyn ObjectStore>> = Lazy::new(local_wal);
@@ -57,14 +65,17 @@ fn local_wal() -> Box<dyn ObjectStore> {
     Box::new(local::Local::new(&cfg.common.data_wal_dir, false))
 }
 
+/// Lists the objects under the given prefix, only the objects whose key
+/// starts with the prefix are fetched from the backend, eg: `file_list/`.
+pub fn list_prefix(prefix: &str) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+    DEFAULT.list(Some(&prefix.into()))
+}
+
 pub async fn list(prefix: &str) -> object_store::Result<Vec<String>> {
-    let files = DEFAULT
-        .list(Some(&prefix.into()))
+    list_prefix(prefix)
         .map_ok(|meta| meta.location.to_string())
         .try_collect::<Vec<String>>()
         .await
-        .expect("Error listing files");
-    Ok(files)
 }
 
 pub async fn get(file: &str) -> object_store::Result<bytes::Bytes> {
@@ -81,23 +92,136 @@ pub async fn head(file: &str) -> object_store::Result<ObjectMeta> {
 }
 
 pub async fn put(file: &str, data: bytes::Bytes) -> object_store::Result<()> {
-    if bytes_size_in_mb(&data) >= MULTI_PART_UPLOAD_DATA_SIZE {
-        put_multipart(file, data).await?;
+    put_object(file, data).await?;
+    Ok(())
+}
+
+async fn put_object(file: &str, data: bytes::Bytes) -> object_store::Result<PutResult> {
+    if bytes_size_in_mb(&data) >= MULTI_PART_UPLOAD_DATA_SIZE && !is_local_disk_storage() {
+        multipart_upload_to(
+            &**DEFAULT,
+            &file.into(),
+            futures::stream::iter([data]),
+            MULTI_PART_UPLOAD_PART_SIZE,
+        )
+        .await
     } else {
-        DEFAULT.put(&file.into(), data.into()).await?;
+        DEFAULT.put(&file.into(), data.into()).await
     }
+}
+
+/// Uploads the file only if it doesn't exist, fails with `AlreadyExists`
+/// otherwise. Backends without conditional writes fail with `NotImplemented`.
+pub async fn put_if_absent(file: &str, data: bytes::Bytes) -> object_store::Result<()> {
+    DEFAULT
+        .put_opts(&file.into(), data.into(), PutMode::Create.into())
+        .await?;
     Ok(())
 }
 
+/// Uploads the file and, with `ZO_S3_FEATURE_CHECKSUM`, verifies the etag
+/// returned by the backend against the md5 of the data. The object is
+/// deleted if it doesn't match, so the caller keeps its copy and retries.
+pub async fn put_verified(file: &str, data: bytes::Bytes) -> Result<(), anyhow::Error> {
+    if !get_config().s3.feature_checksum || is_local_disk_storage() {
+        put(file, data).await?;
+        return Ok(());
+    }
+    let result = put_object(file, data.clone()).await?;
+    let Some(e_tag) = result.e_tag else {
+        return Ok(());
+    };
+    let e_tag = e_tag.trim_matches('"');
+    match expected_e_tag(&data, e_tag, MULTI_PART_UPLOAD_PART_SIZE) {
+        Some(expected) if expected != e_tag => {
+            del(&[file]).await?;
+            Err(anyhow::anyhow!(
+                "checksum mismatch after upload: {file}, expected etag {expected}, got {e_tag}"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the etag s3 computes for the data: the md5 of the object, or for
+/// multipart uploads the md5 of the md5s of the parts followed by the number
+/// of parts. Returns `None` if the etag is not a digest, eg: azure.
+fn expected_e_tag(data: &[u8], e_tag: &str, part_size: usize) -> Option<String> {
+    let is_md5 = |s: &str| s.len() == 32 && s.bytes().all(|c| c.is_ascii_hexdigit());
+    match e_tag.split_once('-') {
+        None if is_md5(e_tag) => Some(format!("{:x}", md5::compute(data))),
+        Some((digest, parts)) if is_md5(digest) => {
+            let parts = parts.parse::<usize>().ok()?;
+            if data.len().div_ceil(part_size) != parts {
+                return None;
+            }
+            let digests = data
+                .chunks(part_size)
+                .flat_map(|part| md5::compute(part).0)
+                .collect::<Vec<_>>();
+            Some(format!("{:x}-{parts}", md5::compute(digests)))
+        }
+        _ => None,
+    }
+}
+
 pub async fn put_multipart(file: &str, data: bytes::Bytes) -> object_store::Result<()> {
-    let path = Path::from(file);
-    let upload = DEFAULT.put_multipart(&path).await?;
-    let mut write = WriteMultipart::new(upload);
-    write.write(data.as_bytes());
-    write.finish().await?;
+    multipart_upload(file, futures::stream::iter([data])).await
+}
+
+/// Uploads a stream of bytes with the multipart upload api in parts of
+/// [`MULTI_PART_UPLOAD_PART_SIZE`], so large files don't hit the timeout of a
+/// single request. The local disk storage writes the file in one put.
+pub async fn multipart_upload(
+    file: &str,
+    stream: impl Stream<Item = bytes::Bytes> + Send,
+) -> object_store::Result<()> {
+    if is_local_disk_storage() {
+        let data = stream
+            .fold(BytesMut::new(), |mut buf, data| async move {
+                buf.extend_from_slice(&data);
+                buf
+            })
+            .await;
+        DEFAULT.put(&file.into(), data.freeze().into()).await?;
+        return Ok(());
+    }
+    multipart_upload_to(
+        &**DEFAULT,
+        &file.into(),
+        stream,
+        MULTI_PART_UPLOAD_PART_SIZE,
+    )
+    .await?;
     Ok(())
 }
 
+async fn multipart_upload_to(
+    store: &dyn ObjectStore,
+    path: &Path,
+    stream: impl Stream<Item = bytes::Bytes> + Send,
+    part_size: usize,
+) -> object_store::Result<PutResult> {
+    let upload = store.put_multipart(path).await?;
+    let mut write = WriteMultipart::new_with_chunk_size(upload, part_size);
+    futures::pin_mut!(stream);
+    while let Some(data) = stream.next().await {
+        // bound the parts in memory by waiting for the uploads in flight
+        if let Err(e) = write.wait_for_capacity(MULTI_PART_UPLOAD_CONCURRENCY).await {
+            write.abort().await?;
+            return Err(e);
+        }
+        write.write(data.as_bytes());
+    }
+    write.finish().await
+}
+
+/// Copies the file to a new key without downloading it, the source file is
+/// kept.
+pub async fn copy(src: &str, dst: &str) -> object_store::Result<()> {
+    DEFAULT.copy(&src.into(), &dst.into()).await
+}
+
 pub async fn del(files: &[&str]) -> object_store::Result<()> {
     if files.is_empty() {
         return Ok(());
@@ -139,6 +263,19 @@ pub async fn del(files: &[&str]) -> object_store::Result<()> {
     Ok(())
 }
 
+/// Counts a failed storage request, a missing object is not an error.
+fn inc_errors(err: &object_store::Error, method_type: &str, storage_type: &str) {
+    // expected outcomes of reads and conditional writes
+    if !matches!(
+        err,
+        object_store::Error::NotFound { .. } | object_store::Error::AlreadyExists { .. }
+    ) {
+        metrics::STORAGE_ERRORS
+            .with_label_values(&[method_type, storage_type])
+            .inc();
+    }
+}
+
 pub fn format_key(key: &str, with_prefix: bool) -> String {
     let cfg = get_config();
     if !is_local_disk_storage()
@@ -260,3 +397,63 @@ impl GetRangeExt for GetRange {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    #[test]
+    fn test_inc_errors() {
+        let counter = metrics::STORAGE_ERRORS.with_label_values(&["get", "test_inc_errors"]);
+        inc_errors(
+            &object_store::Error::NotFound {
+                path: "a".to_string(),
+                source: "missing".into(),
+            },
+            "get",
+            "test_inc_errors",
+        );
+        assert_eq!(counter.get(), 0);
+        inc_errors(
+            &object_store::Error::NotImplemented,
+            "get",
+            "test_inc_errors",
+        );
+        assert_eq!(counter.get(), 1);
+    }
+
+    #[test]
+    fn test_expected_e_tag() {
+        let data = b"hello world";
+        assert_eq!(
+            expected_e_tag(data, "5eb63bbbe01eeed093cb22bb8f5acdc3", 4).unwrap(),
+            "5eb63bbbe01eeed093cb22bb8f5acdc3"
+        );
+        let e_tag = expected_e_tag(data, "00000000000000000000000000000000-3", 4).unwrap();
+        assert!(e_tag.ends_with("-3"));
+        assert_ne!(e_tag, expected_e_tag(b"hello worlD", &e_tag, 4).unwrap());
+        // not the number of parts of the data
+        assert!(expected_e_tag(data, "00000000000000000000000000000000-2", 4).is_none());
+        // not a digest
+        assert!(expected_e_tag(data, "0x8DC1A2B3C4D5E6F", 4).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload() {
+        let store = InMemory::new();
+        let path = Path::from("files/default/logs/test/large.parquet");
+        // 200 MB in chunks of 1 MB, uploaded in 4 parts
+        let chunk = bytes::Bytes::from(vec![1u8; 1024 * 1024]);
+        let stream = futures::stream::iter(std::iter::repeat(chunk).take(200));
+        multipart_upload_to(&store, &path, stream, MULTI_PART_UPLOAD_PART_SIZE)
+            .await
+            .unwrap();
+
+        let meta = store.head(&path).await.unwrap();
+        assert_eq!(meta.size, 200 * 1024 * 1024);
+        let data = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert!(data.iter().all(|b| *b == 1));
+    }
+}
@@ -66,7 +66,7 @@ impl ObjectStore for Remote {
             .put_opts(&(format_key(&file, true).into()), payload, opts)
             .await
         {
-            Ok(_) => {
+            Ok(result) => {
                 // metrics
                 let columns = file.split('/').collect::<Vec<&str>>();
                 if columns[0] == "files" {
@@ -81,13 +81,12 @@ impl ObjectStore for Remote {
                         .with_label_values(&[columns[1], columns[2], "put", "remote"])
                         .inc_by(time);
                 }
-                Ok(PutResult {
-                    e_tag: None,
-                    version: None,
-                })
+                // the etag is used to verify the upload
+                Ok(result)
             }
             Err(err) => {
                 log::error!("s3 File upload error: {:?}", err);
+                super::inc_errors(&err, "put", "remote");
                 Err(err)
             }
         }
@@ -107,6 +106,7 @@ impl ObjectStore for Remote {
             Ok(r) => Ok(r),
             Err(err) => {
                 log::error!("s3 multipart File upload error: {:?}", err);
+                super::inc_errors(&err, "put", "remote");
                 Err(err)
             }
         }
@@ -115,7 +115,11 @@ impl ObjectStore for Remote {
     async fn get(&self, location: &Path) -> Result<GetResult> {
         let start = std::time::Instant::now();
         let file = location.to_string();
-        let result = self.client.get(&(format_key(&file, true).into())).await?;
+        let result = self
+            .client
+            .get(&(format_key(&file, true).into()))
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "remote"))?;
 
         // metrics
         let data_len = result.meta.size;
@@ -143,7 +147,8 @@ impl ObjectStore for Remote {
         let result = self
             .client
             .get_opts(&(format_key(&file, true).into()), options)
-            .await?;
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "remote"))?;
 
         // metrics
         let data_len = result.meta.size;
@@ -170,7 +175,8 @@ impl ObjectStore for Remote {
         let data = self
             .client
             .get_range(&(format_key(&file, true).into()), range)
-            .await?;
+            .await
+            .inspect_err(|e| super::inc_errors(e, "get", "remote"))?;
 
         // metrics
         let data_len = data.len();
@@ -212,6 +218,9 @@ impl ObjectStore for Remote {
             }
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
+        if let Err(e) = &result {
+            super::inc_errors(e, "del", "remote");
+        }
         result
     }
 
@@ -225,8 +234,15 @@ impl ObjectStore for Remote {
         Err(Error::NotImplemented)
     }
 
-    async fn copy(&self, _from: &Path, _to: &Path) -> Result<()> {
-        Err(Error::NotImplemented)
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        // server side copy, CopyObject on s3, rewrite on gcs and copy blob on azure
+        self.client
+            .copy(
+                &(format_key(from.as_ref(), true).into()),
+                &(format_key(to.as_ref(), true).into()),
+            )
+            .await
+            .inspect_err(|e| super::inc_errors(e, "copy", "remote"))
     }
 
     async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> Result<()> {
@@ -275,6 +291,12 @@ fn init_aws_config() -> object_store::Result<object_store::aws::AmazonS3> {
     if !cfg.s3.secret_key.is_empty() {
         builder = builder.with_secret_access_key(&cfg.s3.secret_key);
     }
+    if cfg.s3.feature_checksum {
+        builder = builder.with_checksum_algorithm(object_store::aws::Checksum::SHA256);
+    }
+    if cfg.s3.feature_conditional_put {
+        builder = builder.with_conditional_put(object_store::aws::S3ConditionalPut::ETagMatch);
+    }
     builder.build()
 }
 
@@ -288,6 +310,10 @@ fn init_azure_config() -> object_store::Result<object_store::azure::MicrosoftAzu
                 .with_allow_invalid_certificates(cfg.s3.allow_invalid_certificates),
         )
         .with_container_name(&cfg.s3.bucket_name);
+    if !cfg.s3.azure_connection_string.is_empty() {
+        builder = with_azure_connection_string(builder, &cfg.s3.azure_connection_string)?;
+    }
+    // fallback to the managed identity if no key is given
     if !cfg.s3.access_key.is_empty() {
         builder = builder.with_account(&cfg.s3.access_key);
     }
@@ -297,6 +323,47 @@ fn init_azure_config() -> object_store::Result<object_store::azure::MicrosoftAzu
     builder.build()
 }
 
+/// Applies an Azure storage connection string, eg:
+/// `DefaultEndpointsProtocol=https;AccountName=xxx;AccountKey=xxx;EndpointSuffix=core.windows.net`
+/// or `UseDevelopmentStorage=true` for Azurite.
+fn with_azure_connection_string(
+    mut builder: object_store::azure::MicrosoftAzureBuilder,
+    conn_str: &str,
+) -> object_store::Result<object_store::azure::MicrosoftAzureBuilder> {
+    let mut protocol = "https";
+    let mut account = None;
+    let mut suffix = None;
+    let mut has_endpoint = false;
+    for part in conn_str.split(';').filter(|p| !p.trim().is_empty()) {
+        let Some((key, value)) = part.split_once('=') else {
+            return Err(Error::Generic {
+                store: "azure",
+                source: format!("invalid connection string segment: {}", part).into(),
+            });
+        };
+        match key.trim() {
+            "DefaultEndpointsProtocol" => protocol = value,
+            "AccountName" => {
+                builder = builder.with_account(value);
+                account = Some(value);
+            }
+            // the key is base64 encoded and may end with '='
+            "AccountKey" => builder = builder.with_access_key(value),
+            "BlobEndpoint" => {
+                builder = builder.with_endpoint(value.to_string());
+                has_endpoint = true;
+            }
+            "EndpointSuffix" => suffix = Some(value),
+            "UseDevelopmentStorage" => builder = builder.with_use_emulator(value == "true"),
+            _ => {}
+        }
+    }
+    if let (false, Some(account), Some(suffix)) = (has_endpoint, account, suffix) {
+        builder = builder.with_endpoint(format!("{protocol}://{account}.blob.{suffix}"));
+    }
+    Ok(builder)
+}
+
 fn init_gcp_config() -> object_store::Result<object_store::gcp::GoogleCloudStorage> {
     let cfg = get_config();
     let mut builder = object_store::gcp::GoogleCloudStorageBuilder::from_env()
@@ -307,8 +374,11 @@ fn init_gcp_config() -> object_store::Result<object_store::gcp::GoogleCloudStora
                 .with_allow_invalid_certificates(cfg.s3.allow_invalid_certificates),
         )
         .with_bucket_name(&cfg.s3.bucket_name);
+    // fallback to the application default credentials if no key is given
     if !cfg.s3.access_key.is_empty() {
         builder = builder.with_service_account_path(&cfg.s3.access_key);
+    } else if !cfg.s3.gcp_service_account_key.is_empty() {
+        builder = builder.with_service_account_key(&cfg.s3.gcp_service_account_key);
     }
     builder.build()
 }
@@ -346,3 +416,115 @@ fn init_client() -> Box<dyn object_store::ObjectStore> {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use object_store::azure::AzureConfigKey;
+
+    use super::*;
+
+    /// Runs against a GCS emulator through the same config as the server, eg:
+    /// `docker run -p 4443:4443 fsouza/fake-gcs-server -scheme https
+    /// -public-host localhost:4443`, then create the bucket `openobserve-test`
+    /// and run with `ZO_S3_PROVIDER=gcs ZO_S3_BUCKET_NAME=openobserve-test
+    /// ZO_S3_ALLOW_INVALID_CERTIFICATES=true ZO_S3_GCP_SERVICE_ACCOUNT_KEY=
+    /// '{"private_key":"","private_key_id":"","client_email":"",
+    /// "gcs_base_url":"https://localhost:4443","disable_oauth":true}'`.
+    /// The unit tests workflow runs it with `--ignored`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_gcs_emulator() {
+        let cfg = get_config();
+        assert!(
+            matches!(cfg.s3.provider.as_str(), "gcs" | "gcp")
+                && !cfg.s3.gcp_service_account_key.is_empty(),
+            "ZO_S3_PROVIDER=gcs and ZO_S3_GCP_SERVICE_ACCOUNT_KEY are required"
+        );
+        let client = init_gcp_config().unwrap();
+
+        let path = Path::from("files/default/logs/test/a.parquet");
+        let data = Bytes::from_static(b"hello gcs");
+        client.put(&path, data.clone().into()).await.unwrap();
+        let got = client.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(got, data);
+
+        let files = client
+            .list(Some(&Path::from("files/default/")))
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(files.contains(&path.to_string()));
+
+        client.delete(&path).await.unwrap();
+        assert!(client.head(&path).await.is_err());
+    }
+
+    #[test]
+    fn test_azure_connection_string() {
+        let builder = object_store::azure::MicrosoftAzureBuilder::new();
+        let builder = with_azure_connection_string(
+            builder,
+            "DefaultEndpointsProtocol=https;AccountName=test;AccountKey=a2V5;EndpointSuffix=core.windows.net",
+        )
+        .unwrap();
+        assert_eq!(
+            builder.get_config_value(&AzureConfigKey::AccountName),
+            Some("test".to_string())
+        );
+        assert_eq!(
+            builder.get_config_value(&AzureConfigKey::AccessKey),
+            Some("a2V5".to_string())
+        );
+        assert_eq!(
+            builder.get_config_value(&AzureConfigKey::Endpoint),
+            Some("https://test.blob.core.windows.net".to_string())
+        );
+
+        let builder = object_store::azure::MicrosoftAzureBuilder::new();
+        let builder = with_azure_connection_string(builder, "UseDevelopmentStorage=true").unwrap();
+        assert_eq!(
+            builder.get_config_value(&AzureConfigKey::UseEmulator),
+            Some("true".to_string())
+        );
+
+        let builder = object_store::azure::MicrosoftAzureBuilder::new();
+        assert!(with_azure_connection_string(builder, "AccountName").is_err());
+    }
+
+    /// Runs against Azurite through the same config as the server, eg:
+    /// `docker run -p 10000:10000 mcr.microsoft.com/azure-storage/azurite
+    /// azurite-blob --blobHost 0.0.0.0`, then create the container
+    /// `openobserve-test` and run with `ZO_S3_PROVIDER=azure
+    /// ZO_S3_BUCKET_NAME=openobserve-test
+    /// ZO_S3_AZURE_CONNECTION_STRING=UseDevelopmentStorage=true`.
+    /// The unit tests workflow runs it with `--ignored`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_azurite() {
+        let cfg = get_config();
+        assert!(
+            cfg.s3.provider == "azure" && !cfg.s3.azure_connection_string.is_empty(),
+            "ZO_S3_PROVIDER=azure and ZO_S3_AZURE_CONNECTION_STRING are required"
+        );
+        let client = init_azure_config().unwrap();
+
+        let path = Path::from("files/default/logs/test/a.parquet");
+        let data = Bytes::from_static(b"hello azure");
+        client.put(&path, data.clone().into()).await.unwrap();
+        let got = client.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(got, data);
+
+        let files = client
+            .list(Some(&Path::from("files/default/")))
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(files.contains(&path.to_string()));
+
+        client.delete(&path).await.unwrap();
+        assert!(client.head(&path).await.is_err());
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use config::{metrics, utils::time::now_micros};
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
+
+/// Width of the rolling window of the compression ratio gauge.
+const WINDOW_MICROS: i64 = 60 * 1_000_000;
+
+/// A ratio below this suggests the ingested data is already compressed or
+/// binary.
+const LOW_COMPRESSION_RATIO: f64 = 1.5;
+
+static WINDOWS: Lazy<Mutex<HashMap<String, CompressionWindow>>> = Lazy::new(Default::default);
+
+/// The (time, uncompressed bytes, compressed bytes) of the files flushed in
+/// the last minute.
+#[derive(Default)]
+struct CompressionWindow {
+    samples: VecDeque<(i64, u64, u64)>,
+    uncompressed: u64,
+    compressed: u64,
+}
+
+impl CompressionWindow {
+    /// Adds a flushed file and returns the ratio of the window.
+    fn add(&mut self, now: i64, uncompressed: u64, compressed: u64) -> f64 {
+        self.samples.push_back((now, uncompressed, compressed));
+        self.uncompressed += uncompressed;
+        self.compressed += compressed;
+        while let Some((ts, u, c)) = self.samples.front().copied() {
+            if now - ts <= WINDOW_MICROS {
+                break;
+            }
+            self.samples.pop_front();
+            self.uncompressed -= u;
+            self.compressed -= c;
+        }
+        if self.compressed == 0 {
+            return 0.0;
+        }
+        self.uncompressed as f64 / self.compressed as f64
+    }
+}
+
+/// Records the raw json size and the compressed parquet size of a file
+/// flushed from WAL, and updates the rolling compression ratio of the stream.
+pub(crate) fn observe(
+    org_id: &str,
+    stream_type: &str,
+    stream_name: &str,
+    uncompressed: i64,
+    compressed: i64,
+) {
+    if uncompressed <= 0 || compressed <= 0 {
+        return;
+    }
+    let (uncompressed, compressed) = (uncompressed as u64, compressed as u64);
+    let labels = [org_id, stream_name, stream_type];
+    metrics::INGEST_UNCOMPRESSED_BYTES
+        .with_label_values(&labels)
+        .inc_by(uncompressed);
+    metrics::INGEST_COMPRESSED_BYTES
+        .with_label_values(&labels)
+        .inc_by(compressed);
+
+    let key = format!("{org_id}/{stream_type}/{stream_name}");
+    let mut windows = WINDOWS.lock().unwrap();
+    let ratio = windows
+        .entry(key)
+        .or_default()
+        .add(now_micros(), uncompressed, compressed);
+    drop(windows);
+    metrics::COMPRESSION_RATIO
+        .with_label_values(&labels)
+        .set(ratio);
+    if ratio < LOW_COMPRESSION_RATIO {
+        log::warn!(
+            "[INGESTER:PERSIST] low compression ratio {ratio:.2} for stream {org_id}/{stream_type}/{stream_name}, the data may be already compressed or binary"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_window() {
+        let mut window = CompressionWindow::default();
+        assert_eq!(window.add(0, 1000, 100), 10.0);
+        assert_eq!(window.add(30_000_000, 200, 100), 6.0);
+        // the first sample is out of the window
+        assert_eq!(window.add(61_000_000, 100, 100), 1.5);
+        assert_eq!(window.samples.len(), 2);
+        assert_eq!(window.add(200_000_000, 100, 0), 0.0);
+        assert_eq!(window.samples.len(), 1);
+    }
+}
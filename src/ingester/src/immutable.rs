@@ -182,9 +182,58 @@ pub(crate) async fn persist_table(idx: usize, path: PathBuf) -> Result<()> {
         .sub(stat.arrow_size as i64);
     metrics::INGEST_MEMTABLE_FILES.with_label_values(&[]).dec();
 
+    // the persisted files reach the upload threshold, wake up the file push job
+    let cfg = config::get_config();
+    let max_file_size = std::cmp::min(cfg.limit.max_file_size_on_disk, cfg.compact.max_file_size);
+    if stat.json_size >= max_file_size as i64 {
+        crate::WAL_PARQUET_FLUSH_NOTIFY.notify_one();
+    }
+
     Ok(())
 }
 
+/// Persists all the immutables at once, waiting for the ones which are being
+/// persisted by the background job. A table which fails to persist is logged
+/// and skipped so the others are still persisted, the first error is returned.
+pub(crate) async fn persist_all() -> Result<()> {
+    let mut failed = HashSet::new();
+    let mut first_err = None;
+    loop {
+        let paths = IMMUTABLES
+            .read()
+            .await
+            .keys()
+            .filter(|path| !failed.contains(*path))
+            .cloned()
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return match first_err {
+                Some(e) => Err(e),
+                None => Ok(()),
+            };
+        }
+        let mut has_processing = false;
+        for path in paths {
+            if !PROCESSING_TABLES.write().await.insert(path.clone()) {
+                has_processing = true;
+                continue;
+            }
+            if let Err(e) = persist_table(0, path.clone()).await {
+                log::error!(
+                    "[INGESTER:MEM] persist file: {} failed: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+                failed.insert(path);
+                first_err.get_or_insert(e);
+            }
+        }
+        if has_processing {
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+}
+
 pub(crate) async fn len() -> usize {
     IMMUTABLES.read().await.len()
 }
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+mod compression;
 mod entry;
 pub mod errors;
 mod immutable;
@@ -30,7 +31,7 @@ use config::RwAHashMap;
 pub use entry::Entry;
 pub use immutable::read_from_immutable;
 use once_cell::sync::Lazy;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex, Notify};
 pub use writer::{check_memtable_size, flush_all, get_writer, read_from_memtable, Writer};
 
 pub(crate) type ReadRecordBatchEntry = (Arc<Schema>, Vec<Arc<entry::RecordBatchEntry>>);
@@ -38,6 +39,10 @@ pub(crate) type ReadRecordBatchEntry = (Arc<Schema>, Vec<Arc<entry::RecordBatchE
 pub static WAL_PARQUET_METADATA: Lazy<RwAHashMap<String, config::meta::stream::FileMeta>> =
     Lazy::new(Default::default);
 
+/// Notified when persisted WAL parquet files are big enough to be moved to storage
+/// without waiting for the next file push interval
+pub static WAL_PARQUET_FLUSH_NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
 pub async fn init() -> errors::Result<()> {
     // check uncompleted parquet files, need delete those files
     wal::check_uncompleted_parquet_files().await?;
@@ -72,6 +77,17 @@ pub async fn init() -> errors::Result<()> {
     Ok(())
 }
 
+/// Closes all the memtables and persists them to wal parquet files right away,
+/// used to drain the ingester before it stops.
+pub async fn flush_all_to_parquet() -> errors::Result<()> {
+    // still persist the memtables which were closed before the error
+    let ret = flush_all().await;
+    if let Err(e) = &ret {
+        log::error!("[INGESTER] flush all memtables failed: {}", e);
+    }
+    immutable::persist_all().await.and(ret)
+}
+
 async fn run() -> errors::Result<()> {
     // start persidt worker
     let cfg = config::get_config();
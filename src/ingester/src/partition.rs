@@ -190,6 +190,13 @@ impl Partition {
                 .context(WriteParquetRecordBatchSnafu)?;
             writer.close().await.context(WriteParquetRecordBatchSnafu)?;
             file_meta.compressed_size = buf_parquet.len() as i64;
+            crate::compression::observe(
+                org_id,
+                stream_type,
+                stream_name,
+                file_meta.original_size,
+                file_meta.compressed_size,
+            );
 
             // write into local file
             let file_name = generate_filename_with_time_range(file_meta.min_ts, file_meta.max_ts);
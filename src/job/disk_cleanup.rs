@@ -0,0 +1,133 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use config::{cluster::LOCAL_NODE, get_config, utils::file::scan_files};
+use hashbrown::HashSet;
+use tokio::time;
+
+/// Deletes the orphaned `.par` files in the WAL directory. The memtable is
+/// written to `.par` files first and they are listed in a `.lock` file before
+/// they are renamed to `.parquet`, if the persist fails in between the files
+/// are never moved to storage. The startup of the ingester cleans them up as
+/// well, this job covers the nodes which keep running.
+pub async fn run() -> Result<(), anyhow::Error> {
+    let cfg = get_config();
+    if !LOCAL_NODE.is_ingester() || cfg.limit.file_orphan_age_hours == 0 {
+        return Ok(());
+    }
+
+    let mut interval = time::interval(time::Duration::from_secs(
+        cfg.limit.file_orphan_check_interval.max(60),
+    ));
+    interval.tick().await; // the first tick completes immediately
+    loop {
+        interval.tick().await;
+        let max_age = Duration::from_secs(cfg.limit.file_orphan_age_hours * 3600);
+        let wal_dir = PathBuf::from(&cfg.common.data_wal_dir);
+        let ret = tokio::task::spawn_blocking(move || {
+            remove_orphan_files(&wal_dir.join("files"), &wal_dir.join("logs"), max_age)
+        })
+        .await;
+        if let Err(e) = ret {
+            log::error!("[DISK_CLEANUP] remove orphan files error: {}", e);
+        }
+    }
+}
+
+// returns the number of deleted files
+fn remove_orphan_files(files_dir: &Path, logs_dir: &Path, max_age: Duration) -> usize {
+    // the .par files listed in a lock file are finished by the ingester
+    let locked = scan_files(logs_dir, "lock", None)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|lock_file| std::fs::read_to_string(lock_file).ok())
+        .flat_map(|data| {
+            data.lines()
+                .filter_map(|line| std::fs::canonicalize(line).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect::<HashSet<_>>();
+
+    let mut deleted = 0;
+    for file in scan_files(files_dir, "par", None).unwrap_or_default() {
+        let Ok(meta) = std::fs::metadata(&file) else {
+            continue;
+        };
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .unwrap_or_default();
+        if age < max_age {
+            continue;
+        }
+        if std::fs::canonicalize(&file).is_ok_and(|path| locked.contains(&path)) {
+            continue;
+        }
+        match std::fs::remove_file(&file) {
+            Ok(_) => {
+                log::warn!(
+                    "[DISK_CLEANUP] deleted orphan file: {}, size: {}",
+                    file,
+                    meta.len()
+                );
+                deleted += 1;
+            }
+            Err(e) => log::error!("[DISK_CLEANUP] delete orphan file {} error: {}", file, e),
+        }
+    }
+    deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_orphan_files() {
+        let root = std::env::temp_dir().join("openobserve-test-disk-cleanup");
+        let files_dir = root.join("files/default/logs/app");
+        let logs_dir = root.join("logs/0");
+        std::fs::create_dir_all(&files_dir).unwrap();
+        std::fs::create_dir_all(&logs_dir).unwrap();
+        let orphan = files_dir.join("a.par");
+        let locked = files_dir.join("b.par");
+        let parquet = files_dir.join("c.parquet");
+        for file in [&orphan, &locked, &parquet] {
+            std::fs::write(file, b"data").unwrap();
+        }
+        std::fs::write(logs_dir.join("1.lock"), locked.to_str().unwrap()).unwrap();
+
+        // not old enough
+        let max_age = Duration::from_secs(3600);
+        assert_eq!(
+            remove_orphan_files(&root.join("files"), &root.join("logs"), max_age),
+            0
+        );
+        assert_eq!(
+            remove_orphan_files(&root.join("files"), &root.join("logs"), Duration::ZERO),
+            1
+        );
+        assert!(!orphan.exists());
+        assert!(locked.exists());
+        assert!(parquet.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -58,22 +58,116 @@ pub fn generate_storage_file_name(
     stream_type: StreamType,
     stream_name: &str,
     wal_file_name: &str,
-) -> String {
+) -> Result<String, anyhow::Error> {
     // eg: 0/2023/08/21/08/8b8a5451bbe1c44b/ip=1234/7099303408192061440f3XQ2p.json
     let file_columns = wal_file_name.splitn(7, '/').collect::<Vec<&str>>();
+    if file_columns.len() < 7 {
+        return Err(anyhow::anyhow!(
+            "invalid wal file name, missing partition segments: {}",
+            wal_file_name
+        ));
+    }
     let stream_key = format!("{}/{}/{}", org_id, stream_type, stream_name);
     let file_date = format!(
         "{}/{}/{}/{}",
         file_columns[1], file_columns[2], file_columns[3], file_columns[4]
     );
     // let hash_id = file_columns[5].to_string();
-    let file_name = file_columns.last().unwrap().to_string();
-    let file_name_pos = file_name.rfind('/').unwrap_or_default();
+    let partition_key = get_partition_key_from_filename(file_columns.last().unwrap())?;
     let id = ider::generate();
-    let file_name = if file_name_pos == 0 {
+    let file_name = if partition_key.is_empty() {
         id
     } else {
-        format!("{}/{}", &file_name[..file_name_pos], id)
+        format!("{partition_key}/{id}")
+    };
+    Ok(format!(
+        "files/{stream_key}/{file_date}/{file_name}{FILE_EXT_PARQUET}"
+    ))
+}
+
+/// Returns the partition key part (`key=value/...`) of a wal file name tail,
+/// eg: `ip=1234/7099303408192061440f3XQ2p.json` -> `ip=1234`.
+///
+/// Every partition segment must be a `key=value` pair whose value contains
+/// none of `/`, `\` or `.`, so the key can't escape the stream prefix once
+/// it is joined into a storage path.
+pub fn get_partition_key_from_filename(file_name: &str) -> Result<String, anyhow::Error> {
+    let Some(pos) = file_name.rfind('/') else {
+        return Ok(String::new());
     };
-    format!("files/{stream_key}/{file_date}/{file_name}{FILE_EXT_PARQUET}")
+    let partition_key = &file_name[..pos];
+    for segment in partition_key.split('/') {
+        let Some((key, value)) = segment.split_once('=') else {
+            return Err(anyhow::anyhow!(
+                "invalid partition segment [{}] in file name: {}",
+                segment,
+                file_name
+            ));
+        };
+        if key.is_empty() || value.contains(['/', '\\', '.']) {
+            return Err(anyhow::anyhow!(
+                "invalid partition segment [{}] in file name: {}",
+                segment,
+                file_name
+            ));
+        }
+    }
+    Ok(partition_key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_storage_file_name() {
+        let key = generate_storage_file_name(
+            "default",
+            StreamType::Logs,
+            "olympics",
+            "0/2023/08/21/08/8b8a5451bbe1c44b/ip=1234/7099303408192061440f3XQ2p.parquet",
+        )
+        .unwrap();
+        assert!(key.starts_with("files/default/logs/olympics/2023/08/21/08/ip=1234/"));
+        assert!(key.ends_with(FILE_EXT_PARQUET));
+
+        let ret = generate_storage_file_name(
+            "default",
+            StreamType::Logs,
+            "olympics",
+            "stream_2023.parquet",
+        );
+        assert!(ret.is_err());
+    }
+
+    #[test]
+    fn test_get_partition_key_from_filename() {
+        assert_eq!(
+            get_partition_key_from_filename("7099303408192061440f3XQ2p.parquet").unwrap(),
+            ""
+        );
+        assert_eq!(
+            get_partition_key_from_filename("ip=1234/host=abc/7099303408192061440f3XQ2p.parquet")
+                .unwrap(),
+            "ip=1234/host=abc"
+        );
+
+        for file_name in [
+            "ip=../../../etc/7099303408192061440f3XQ2p.parquet",
+            "ip=..\\..\\etc/7099303408192061440f3XQ2p.parquet",
+            "ip=./7099303408192061440f3XQ2p.parquet",
+            "../7099303408192061440f3XQ2p.parquet",
+            "=1234/7099303408192061440f3XQ2p.parquet",
+        ] {
+            assert!(get_partition_key_from_filename(file_name).is_err());
+        }
+
+        let ret = generate_storage_file_name(
+            "default",
+            StreamType::Logs,
+            "olympics",
+            "0/2023/08/21/08/8b8a5451bbe1c44b/ip=../../../../7099303408192061440f3XQ2p.parquet",
+        );
+        assert!(ret.is_err());
+    }
 }
@@ -64,7 +64,7 @@ use infra::{
     },
     storage,
 };
-use ingester::WAL_PARQUET_METADATA;
+use ingester::{WAL_PARQUET_FLUSH_NOTIFY, WAL_PARQUET_METADATA};
 use once_cell::sync::Lazy;
 use parquet::arrow::async_reader::ParquetRecordBatchStream;
 use tokio::sync::{Mutex, RwLock};
@@ -72,11 +72,11 @@ use tokio::sync::{Mutex, RwLock};
 use crate::{
     common::{
         infra::wal,
-        meta::{authz::Authz, stream::SchemaRecords},
+        meta::{authz::Authz, storage_key::StorageKey, stream::SchemaRecords},
     },
     job::files::idx::write_parquet_index_to_disk,
     service::{
-        db,
+        db, iceberg,
         schema::generate_schema_for_defined_schema_fields,
         search::{datafusion::exec, tantivy::puffin_directory::writer::PuffinDirWriter},
     },
@@ -103,7 +103,7 @@ pub async fn run() -> Result<(), anyhow::Error> {
                         break;
                     }
                     Some((prefix, files)) => {
-                        if let Err(e) = move_files(thread_id, &prefix, files).await {
+                        if let Err(e) = move_files(thread_id, &prefix, files, false).await {
                             log::error!("[INGESTER:JOB] Error moving parquet files to remote: {e}");
                         }
                     }
@@ -117,10 +117,14 @@ pub async fn run() -> Result<(), anyhow::Error> {
         if cluster::is_offline() {
             break;
         }
-        tokio::time::sleep(tokio::time::Duration::from_secs(
-            cfg.limit.file_push_interval,
-        ))
-        .await;
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(
+                cfg.limit.file_push_interval,
+            )) => {}
+            _ = WAL_PARQUET_FLUSH_NOTIFY.notified() => {
+                log::debug!("[INGESTER:JOB] wal files reach the size threshold, scan files early");
+            }
+        }
         if let Err(e) = scan_wal_files(tx.clone()).await {
             log::error!("[INGESTER:JOB] Error prepare parquet files: {}", e);
         }
@@ -129,6 +133,23 @@ pub async fn run() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Move all the WAL parquet files to storage once, without waiting for the file
+/// push interval and ignoring the size thresholds used by the background job.
+///
+/// It is idempotent and safe to call while the background job is running: files
+/// which are being processed by the job are skipped, and files which are still
+/// locked by the ingester are kept on disk until they are released.
+pub async fn move_all_files_to_storage() -> Result<(), anyhow::Error> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(String, Vec<FileKey>)>(1);
+    let scanner = tokio::spawn(async move { scan_wal_files(tx).await });
+    while let Some((prefix, files)) = rx.recv().await {
+        if let Err(e) = move_files(0, &prefix, files, true).await {
+            log::error!("[INGESTER:JOB] Error moving parquet files to remote: {e}");
+        }
+    }
+    scanner.await?
+}
+
 async fn scan_wal_files(
     worker_tx: tokio::sync::mpsc::Sender<(String, Vec<FileKey>)>,
 ) -> Result<(), anyhow::Error> {
@@ -288,24 +309,39 @@ async fn prepare_files(
     Ok(partition_files_with_size)
 }
 
+#[tracing::instrument(
+    name = "job:files:move_files",
+    skip_all,
+    fields(thread_id = thread_id, prefix = prefix, files = files.len())
+)]
 async fn move_files(
     thread_id: usize,
     prefix: &str,
     files: Vec<FileKey>,
+    force: bool,
 ) -> Result<(), anyhow::Error> {
     if files.is_empty() {
         return Ok(());
     }
 
-    let columns = prefix.split('/').collect::<Vec<&str>>();
     // removed thread_id from prefix, so there is no thread_id in the path
     // eg: files/default/logs/olympics/2023/08/21/08/8b8a5451bbe1c44b/
     // eg: files/default/traces/default/2023/09/04/05/default/service_name=ingester/
-    // let _ = columns[0].to_string(); // files/
-    let org_id = columns[1].to_string();
-    let stream_type = StreamType::from(columns[2]);
-    let stream_name = columns[3].to_string();
-    let prefix_date = format!("{}-{}-{}", columns[4], columns[5], columns[6]);
+    let prefix_key = match StorageKey::parse(&format!("{prefix}/")) {
+        Ok(v) => v,
+        Err(e) => {
+            // release the files, they were marked as processing by scan_wal_files
+            let mut processing = PROCESSING_FILES.write().await;
+            for file in files.iter() {
+                processing.remove(&file.key);
+            }
+            return Err(e);
+        }
+    };
+    let org_id = prefix_key.org().to_string();
+    let stream_type = prefix_key.stream_type();
+    let stream_name = prefix_key.stream_name().to_string();
+    let prefix_date = prefix_key.date().format("%Y-%m-%d").to_string();
 
     // log::debug!("[INGESTER:JOB:{thread_id}] check deletion for partition: {}", prefix);
 
@@ -427,11 +463,12 @@ async fn move_files(
         .iter()
         .map(|f| f.meta.original_size)
         .sum::<i64>();
-    if total_original_size
-        < std::cmp::min(
-            cfg.limit.max_file_size_on_disk as i64,
-            cfg.compact.max_file_size as i64,
-        )
+    if !force
+        && total_original_size
+            < std::cmp::min(
+                cfg.limit.max_file_size_on_disk as i64,
+                cfg.compact.max_file_size as i64,
+            )
         && (cfg.limit.file_move_fields_limit == 0
             || stream_fields_num < cfg.limit.file_move_fields_limit)
     {
@@ -497,7 +534,8 @@ async fn move_files(
         }
 
         // write file list to storage
-        let ret = db::file_list::local::set(&new_file_name, Some(new_file_meta), false).await;
+        let ret =
+            db::file_list::local::set(&new_file_name, Some(new_file_meta.clone()), false).await;
         if let Err(e) = ret {
             log::error!(
                 "[INGESTER:JOB] Failed write parquet file meta: {}, error: {}",
@@ -511,6 +549,26 @@ async fn move_files(
             return Ok(());
         }
 
+        // add the file to the iceberg table of the stream at the next commit
+        if cfg.common.iceberg_catalog_enabled {
+            let file = FileKey::new(&new_file_name, new_file_meta, false);
+            if let Err(e) = iceberg::enqueue(
+                &org_id,
+                stream_type,
+                &stream_name,
+                &[file],
+                iceberg::Change::Append,
+            )
+            .await
+            {
+                log::error!(
+                    "[INGESTER:JOB] Failed queue iceberg commit: {}, error: {}",
+                    new_file_name,
+                    e
+                );
+            }
+        }
+
         // check if allowed to delete the file
         for file in new_file_list.iter() {
             let mut need_skip = true;
@@ -578,6 +636,15 @@ async fn move_files(
 
 /// merge some small files into one big file, upload to storage, returns the big
 /// file key and merged files
+#[tracing::instrument(
+    name = "job:files:merge_files",
+    skip_all,
+    fields(
+        thread_id = thread_id,
+        file.key = tracing::field::Empty,
+        file.size = tracing::field::Empty
+    )
+)]
 async fn merge_files(
     thread_id: usize,
     latest_schema: Arc<Schema>,
@@ -740,7 +807,7 @@ async fn merge_files(
         ));
     }
     let new_file_key =
-        super::generate_storage_file_name(&org_id, stream_type, &stream_name, &file_name);
+        super::generate_storage_file_name(&org_id, stream_type, &stream_name, &file_name)?;
     log::info!(
         "[INGESTER:JOB:{thread_id}] merge file successfully, {} files into a new file: {}, original_size: {}, compressed_size: {}, took: {} ms",
         retain_file_list.len(),
@@ -750,9 +817,29 @@ async fn merge_files(
         start.elapsed().as_millis(),
     );
 
+    let span = tracing::Span::current();
+    span.record("file.key", new_file_key.as_str());
+    span.record("file.size", new_file_meta.compressed_size);
+
     // upload file
     let buf = Bytes::from(buf);
-    storage::put(&new_file_key, buf.clone()).await?;
+    if let Err(e) = retry_with_backoff(
+        cfg.limit.file_push_max_retries,
+        tokio::time::Duration::from_millis(500),
+        |attempt| upload_file(&new_file_key, buf.clone(), attempt > 0),
+    )
+    .await
+    {
+        metrics::INGEST_WAL_UPLOAD_FAILURES
+            .with_label_values(&[&org_id, stream_type.to_string().as_str()])
+            .inc();
+        tracing::error!(file.key = new_file_key.as_str(), error = %e, "upload file failed");
+        return Err(anyhow::anyhow!(
+            "[INGESTER:JOB:{thread_id}] upload file {} failed: {}",
+            new_file_key,
+            e
+        ));
+    }
 
     if !cfg.common.inverted_index_enabled || !stream_type.is_basic_type() {
         return Ok((new_file_key, new_file_meta, retain_file_list));
@@ -801,6 +888,60 @@ async fn merge_files(
     Ok((new_file_key, new_file_meta, retain_file_list))
 }
 
+/// Upload the file to storage. On a retry, check whether the object already exists
+/// with the same size first, a previous attempt may have stored it but still failed,
+/// e.g. timed out while waiting for the response.
+async fn upload_file(file_key: &str, data: Bytes, is_retry: bool) -> Result<(), anyhow::Error> {
+    if is_retry {
+        if let Ok(meta) = storage::head(file_key).await {
+            if meta.size == data.len() {
+                log::info!(
+                    "[INGESTER:JOB] file already exists in storage, skip upload: {}",
+                    file_key
+                );
+                return Ok(());
+            }
+        }
+    }
+    // a corrupt upload is deleted and returns an error, the local file is kept
+    storage::put_verified(file_key, data).await
+}
+
+/// Run the given operation, retrying up to `max_retries` times with an exponential
+/// back-off which starts at `base_delay` and doubles after every failed attempt.
+/// The operation receives the current attempt number, starting from 0.
+async fn retry_with_backoff<T, E, F, Fut>(
+    max_retries: usize,
+    base_delay: tokio::time::Duration,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let mut delay = base_delay;
+    let mut attempt = 0;
+    loop {
+        match op(attempt).await {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < max_retries => {
+                attempt += 1;
+                log::warn!(
+                    "[INGESTER:JOB] operation failed, retrying in {} ms ({}/{}): {}",
+                    delay.as_millis(),
+                    attempt,
+                    max_retries,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 /// Create an inverted index file for the given file
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn generate_index_on_ingester(
@@ -1540,3 +1681,52 @@ pub(crate) async fn generate_tantivy_index<D: tantivy::Directory>(
 
     Ok(Some(index))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_move_files_invalid_prefix() {
+        let key = "files/default/logs/invalid/0/a.parquet";
+        PROCESSING_FILES.write().await.insert(key.to_string());
+        let files = vec![FileKey::new(key, Default::default(), false)];
+        assert!(move_files(0, "files/default/logs/invalid", files, false)
+            .await
+            .is_err());
+        assert!(!PROCESSING_FILES.read().await.contains(key));
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let delay = tokio::time::Duration::from_millis(1);
+
+        // fails twice, then succeeds on the third attempt
+        let calls = AtomicUsize::new(0);
+        let ret = retry_with_backoff(3, delay, |_| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n < 2 {
+                    Err("storage error")
+                } else {
+                    Ok(n)
+                }
+            }
+        })
+        .await;
+        assert_eq!(ret, Ok(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // gives up once the retries are exhausted
+        let calls = AtomicUsize::new(0);
+        let ret: Result<(), _> = retry_with_backoff(1, delay, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err("storage error") }
+        })
+        .await;
+        assert!(ret.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::{cluster::LOCAL_NODE, get_config};
+use tokio::time;
+
+use crate::service::iceberg;
+
+/// Commits the files queued by the WAL flush, compaction and data retention
+/// to the Iceberg tables of the streams, each stream is committed by the
+/// compactor owning it
+pub async fn run() -> Result<(), anyhow::Error> {
+    let cfg = get_config();
+    if !cfg.common.iceberg_catalog_enabled || !LOCAL_NODE.is_compactor() {
+        return Ok(());
+    }
+
+    log::info!("[ICEBERG] start iceberg commit job");
+
+    let mut interval = time::interval(time::Duration::from_secs(
+        cfg.common.iceberg_commit_interval,
+    ));
+    interval.tick().await; // the first tick completes immediately
+    loop {
+        interval.tick().await;
+        iceberg::flush().await;
+    }
+}
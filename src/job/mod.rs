@@ -13,6 +13,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::atomic::Ordering;
+
 use config::cluster::LOCAL_NODE;
 use infra::file_list as infra_file_list;
 #[cfg(feature = "enterprise")]
@@ -24,20 +26,27 @@ use crate::{
         infra::config::SYSLOG_ENABLED,
         meta::{organization::DEFAULT_ORG, user::UserRequest},
     },
-    service::{db, self_reporting, users},
+    handler::tcp_udp::fluent_forward,
+    service::{db, search::cache::query_cache::QUERY_CACHE, self_reporting, users},
 };
 
 mod alert_manager;
 mod compactor;
+mod disk_cleanup;
 pub(crate) mod files;
 mod flatten_compactor;
+mod iceberg;
+mod logs_tail;
 pub mod metrics;
 mod mmdb_downloader;
 mod promql;
 mod promql_self_consume;
+mod recording_rules;
+mod rollup;
 mod stats;
 pub(crate) mod syslog_server;
 mod telemetry;
+mod wal_metrics;
 
 pub use mmdb_downloader::MMDB_INIT_NOTIFIER;
 
@@ -87,6 +96,9 @@ pub async fn init() -> Result<(), anyhow::Error> {
     // check version
     db::version::set().await.expect("db version set failed");
 
+    // drop the query cache of the last run
+    QUERY_CACHE.init().await;
+
     // Auth auditing should be done by router also
     #[cfg(feature = "enterprise")]
     tokio::task::spawn(async move { self_reporting::run_audit_publish().await });
@@ -122,6 +134,9 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(async move { db::dashboards::reports::watch().await });
     tokio::task::spawn(async move { db::organization::watch().await });
     tokio::task::spawn(async move { db::pipeline::watch().await });
+    tokio::task::spawn(async move { db::stream_alias::watch().await });
+    tokio::task::spawn(async move { db::recording_rules::watch().await });
+    tokio::task::spawn(async move { db::silences::watch().await });
     #[cfg(feature = "enterprise")]
     tokio::task::spawn(async move { db::ofga::watch().await });
 
@@ -167,13 +182,27 @@ pub async fn init() -> Result<(), anyhow::Error> {
     db::syslog::cache_syslog_settings()
         .await
         .expect("syslog settings cache failed");
+    db::stream_alias::cache()
+        .await
+        .expect("stream alias cache failed");
+    db::recording_rules::cache()
+        .await
+        .expect("recording rules cache failed");
+    db::silences::cache().await.expect("silences cache failed");
 
     // cache pipeline
     db::pipeline::cache().await.expect("Pipeline cache failed");
 
     infra_file_list::create_table_index().await?;
     infra_file_list::LOCAL_CACHE.create_table_index().await?;
-    tokio::task::spawn(async move { db::file_list::cache_stats().await });
+    tokio::task::spawn(async move {
+        // the node is not ready until the stats are loaded, so retry on error
+        while let Err(e) = db::file_list::cache_stats().await {
+            log::error!("Load stream stats from file list error: {}", e);
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+        }
+        db::file_list::FILE_LIST_LOADED.store(true, Ordering::Release);
+    });
 
     #[cfg(feature = "enterprise")]
     db::ofga::cache().await.expect("ofga model cache failed");
@@ -197,9 +226,15 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(async move { stats::run().await });
     tokio::task::spawn(async move { compactor::run().await });
     tokio::task::spawn(async move { flatten_compactor::run().await });
+    tokio::task::spawn(async move { rollup::run().await });
+    tokio::task::spawn(async move { iceberg::run().await });
     tokio::task::spawn(async move { metrics::run().await });
+    tokio::task::spawn(async move { wal_metrics::run().await });
+    tokio::task::spawn(async move { logs_tail::run().await });
+    tokio::task::spawn(async move { disk_cleanup::run().await });
     tokio::task::spawn(async move { promql::run().await });
     tokio::task::spawn(async move { alert_manager::run().await });
+    tokio::task::spawn(async move { recording_rules::run().await });
 
     // load metrics disk cache
     tokio::task::spawn(async move { crate::service::promql::search::init().await });
@@ -222,6 +257,20 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(async move { db::syslog::watch().await });
     tokio::task::spawn(async move { db::syslog::watch_syslog_settings().await });
 
+    // Fluentd forward protocol server start
+    if cfg.tcp.fluent_forward_enabled && LOCAL_NODE.is_ingester() {
+        let addr = format!("0.0.0.0:{}", cfg.tcp.fluent_forward_port);
+        match tokio::net::TcpListener::bind(&addr).await {
+            Ok(listener) => {
+                log::info!("Starting forward protocol server on {}", addr);
+                tokio::task::spawn(async move { fluent_forward::tcp_server(listener).await });
+            }
+            Err(e) => {
+                log::error!("Failed to bind forward protocol server on {}: {}", addr, e);
+            }
+        }
+    }
+
     let start_syslog = *SYSLOG_ENABLED.read();
     if start_syslog {
         syslog_server::run(start_syslog, true)
@@ -0,0 +1,153 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use config::{
+    cluster::LOCAL_NODE,
+    utils::{
+        hash::{gxhash, Sum64},
+        time::now_micros,
+    },
+};
+use parking_lot::Mutex;
+use tokio::time;
+
+use crate::{
+    common::infra::{cluster::get_cached_online_alert_manager_nodes, config::RECORDING_RULES},
+    service::{recording_rules, silences},
+};
+
+const SILENCES_CLEANUP_INTERVAL: u64 = 600;
+
+/// Evaluates the recording rule groups at their intervals, every group is
+/// evaluated by one of the alert managers. The groups are read from the
+/// cache at every tick, so saved or deleted groups are picked up right away.
+pub async fn run() -> Result<(), anyhow::Error> {
+    if !LOCAL_NODE.is_alert_manager() {
+        return Ok(());
+    }
+
+    log::info!("[RECORDING RULES] start recording rules job");
+
+    tokio::task::spawn(async move { run_silences_cleanup().await });
+
+    // {org_id}/{group_name} -> (interval, next evaluation time)
+    let mut schedule: HashMap<String, (i64, i64)> = HashMap::new();
+    // the groups being evaluated, a group isn't evaluated again until the
+    // last evaluation finished
+    let in_flight: Arc<Mutex<HashSet<String>>> = Default::default();
+    let mut interval = time::interval(time::Duration::from_secs(1));
+    interval.tick().await; // trigger the first run
+    loop {
+        interval.tick().await;
+        let mut nodes = get_cached_online_alert_manager_nodes()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|node| node.name)
+            .collect::<Vec<_>>();
+        nodes.sort();
+        let groups = RECORDING_RULES
+            .iter()
+            .map(|v| (v.key().clone(), v.value().clone()))
+            .collect::<Vec<_>>();
+        // forget the deleted groups and the groups moved to other nodes
+        schedule.retain(|key, _| RECORDING_RULES.contains_key(key) && is_local_group(&nodes, key));
+        recording_rules::prune_alerts(&groups, |key| is_local_group(&nodes, key));
+
+        let now = now_micros();
+        for (key, group) in groups {
+            if !is_local_group(&nodes, &key) {
+                continue;
+            }
+            let group_interval = recording_rules::interval_micros(&group);
+            let (prev_interval, next) = schedule
+                .entry(key.clone())
+                .or_insert((group_interval, align(now, group_interval)));
+            if *prev_interval != group_interval {
+                *prev_interval = group_interval;
+                *next = align(now, group_interval);
+            }
+            if *next > now {
+                continue;
+            }
+            let eval_ts = *next;
+            // the evaluations missed while busy are skipped
+            *next = align(now, group_interval) + group_interval;
+            if !in_flight.lock().insert(key.clone()) {
+                log::warn!("[RECORDING RULES] skip group: {key}, the last evaluation is running");
+                continue;
+            }
+
+            let org_id = key.split('/').next().unwrap_or_default().to_string();
+            let guard = InFlightGuard {
+                in_flight: in_flight.clone(),
+                key,
+            };
+            tokio::task::spawn(async move {
+                let _guard = guard;
+                if let Err(e) = recording_rules::evaluate(&org_id, &group, eval_ts).await {
+                    log::error!(
+                        "[RECORDING RULES] evaluate group: {org_id}/{}, err: {e}",
+                        group.name
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// Removes the group from the groups being evaluated when the evaluation
+/// ends, even if it panicked
+struct InFlightGuard {
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    key: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(&self.key);
+    }
+}
+
+/// Deletes the ended silences
+async fn run_silences_cleanup() {
+    let mut interval = time::interval(time::Duration::from_secs(SILENCES_CLEANUP_INTERVAL));
+    interval.tick().await; // trigger the first run
+    loop {
+        interval.tick().await;
+        if let Err(e) = silences::cleanup().await {
+            log::error!("[RECORDING RULES] silences cleanup error: {e}");
+        }
+    }
+}
+
+fn align(ts: i64, interval: i64) -> i64 {
+    ts - ts % interval
+}
+
+/// The groups are spread over the online alert managers by the hash of their
+/// key, without a known alert manager this node evaluates all of them.
+fn is_local_group(nodes: &[String], key: &str) -> bool {
+    if nodes.is_empty() {
+        return true;
+    }
+    let idx = gxhash::new().sum64(key) % nodes.len() as u64;
+    nodes[idx as usize] == LOCAL_NODE.name
+}
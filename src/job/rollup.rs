@@ -0,0 +1,43 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::{cluster::LOCAL_NODE, get_config};
+
+use crate::service::compact;
+
+/// Downsamples the metrics streams into rollup streams
+pub async fn run() -> Result<(), anyhow::Error> {
+    if !LOCAL_NODE.is_compactor() {
+        return Ok(());
+    }
+
+    let cfg = get_config();
+    if !cfg.compact.enabled || !cfg.compact.metrics_rollup_enabled {
+        return Ok(());
+    }
+
+    log::info!("[COMPACTOR] start metrics rollup job");
+
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(
+            get_config().compact.metrics_rollup_interval,
+        ))
+        .await;
+        log::debug!("[COMPACTOR] Running metrics rollup");
+        if let Err(e) = compact::rollup::run().await {
+            log::error!("[COMPACTOR] run metrics rollup error: {e}");
+        }
+    }
+}
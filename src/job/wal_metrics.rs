@@ -0,0 +1,66 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+
+use config::{cluster::LOCAL_NODE, get_config, metrics, utils::file::scan_files};
+use tokio::time;
+
+pub async fn run() -> Result<(), anyhow::Error> {
+    if !LOCAL_NODE.is_ingester() {
+        return Ok(());
+    }
+
+    let mut interval = time::interval(time::Duration::from_secs(30));
+    loop {
+        interval.tick().await;
+        match tokio::task::spawn_blocking(collect_wal_stats).await {
+            Ok(stats) => {
+                for (file_type, files, bytes) in stats {
+                    metrics::WAL_FILES_TOTAL
+                        .with_label_values(&[file_type])
+                        .set(files as i64);
+                    metrics::WAL_BYTES_TOTAL
+                        .with_label_values(&[file_type])
+                        .set(bytes as i64);
+                }
+            }
+            Err(e) => {
+                log::error!("[WAL_METRICS] collect wal stats error: {}", e);
+            }
+        }
+    }
+}
+
+// returns the number and total size of the memtable wal files and of the
+// parquet files which are not uploaded to storage yet
+fn collect_wal_stats() -> [(&'static str, u64, u64); 2] {
+    let wal_dir = PathBuf::from(&get_config().common.data_wal_dir);
+    let (wal_files, wal_bytes) = dir_stats(&wal_dir.join("logs"), "wal");
+    let (parquet_files, parquet_bytes) = dir_stats(&wal_dir.join("files"), "parquet");
+    [
+        ("wal", wal_files, wal_bytes),
+        ("parquet", parquet_files, parquet_bytes),
+    ]
+}
+
+fn dir_stats(dir: &Path, ext: &str) -> (u64, u64) {
+    let files = scan_files(dir, ext, None).unwrap_or_default();
+    let bytes = files
+        .iter()
+        .map(|file| std::fs::metadata(file).map(|m| m.len()).unwrap_or_default())
+        .sum();
+    (files.len() as u64, bytes)
+}
@@ -41,19 +41,22 @@ use openobserve::{
         grpc::{
             auth::check_auth,
             flight::FlightServiceImpl,
+            flight_sql::FlightSqlServiceImpl,
             request::{
                 event::Eventer,
                 ingest::Ingester,
                 logs::LogsServer,
+                logs_ingestion::LogsIngester,
                 metrics::{ingester::MetricsIngester, querier::MetricsQuerier},
                 query_cache::QueryCacheServerImpl,
                 traces::TraceServer,
+                vector::VectorIngester,
             },
         },
         http::router::*,
     },
     job, router,
-    service::{db, metadata, search::SEARCH_SERVER, self_reporting},
+    service::{db, metadata, search::SEARCH_SERVER, shutdown},
 };
 use opentelemetry::{global, trace::TracerProvider, KeyValue};
 use opentelemetry_otlp::WithExportConfig;
@@ -63,19 +66,23 @@ use opentelemetry_proto::tonic::collector::{
     trace::v1::trace_service_server::TraceServiceServer,
 };
 use opentelemetry_sdk::{propagation::TraceContextPropagator, Resource};
-use proto::cluster_rpc::{
-    event_server::EventServer, ingest_server::IngestServer, metrics_server::MetricsServer,
-    query_cache_server::QueryCacheServer, search_server::SearchServer,
+use proto::{
+    cluster_rpc::{
+        event_server::EventServer, ingest_server::IngestServer,
+        logs_ingestion_service_server::LogsIngestionServiceServer, metrics_server::MetricsServer,
+        query_cache_server::QueryCacheServer, search_server::SearchServer,
+    },
+    vector_rpc::vector_server::VectorServer,
 };
 #[cfg(feature = "profiling")]
 use pyroscope::PyroscopeAgent;
 #[cfg(feature = "profiling")]
 use pyroscope_pprofrs::{pprof_backend, PprofConfig};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, watch};
 use tonic::{
     codec::CompressionEncoding,
     metadata::{MetadataKey, MetadataMap, MetadataValue},
-    transport::{Identity, ServerTlsConfig},
+    transport::{Certificate, Identity, ServerTlsConfig},
 };
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_opentelemetry::OpenTelemetryLayer;
@@ -355,13 +362,6 @@ async fn main() -> Result<(), anyhow::Error> {
     }
     log::info!("HTTP server stopped");
 
-    // flush usage report
-    self_reporting::flush().await;
-
-    // leave the cluster
-    _ = cluster::leave().await;
-    log::info!("Node left cluster");
-
     // stop gRPC server
     grpc_shutudown_tx.send(()).ok();
     grpc_stopped_rx.await.ok();
@@ -434,10 +434,46 @@ async fn init_common_grpc_server(
     let ingest_svc = IngestServer::new(Ingester)
         .send_compressed(CompressionEncoding::Gzip)
         .accept_compressed(CompressionEncoding::Gzip);
+    let logs_ingestion_svc = LogsIngestionServiceServer::new(LogsIngester)
+        .send_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .max_decoding_message_size(cfg.grpc.max_message_size * 1024 * 1024);
     let flight_svc = FlightServiceServer::new(FlightServiceImpl)
         .send_compressed(CompressionEncoding::Gzip)
         .accept_compressed(CompressionEncoding::Gzip);
 
+    // the gRPC server stops on the drain of the node, the flight sql and
+    // vector servers stop with it, the drain waits until all of them stopped
+    let listener = shutdown::listener();
+    let (servers_shutdown_tx, servers_shutdown_rx) = watch::channel(false);
+
+    // the flight sql service can't share the port with the internal flight service
+    if cfg.grpc.flight_sql_enabled && config::cluster::LOCAL_NODE.is_querier() {
+        let addr: SocketAddr = format!("{}:{}", ip, cfg.grpc.flight_sql_port).parse()?;
+        let shutdown_rx = servers_shutdown_rx.clone();
+        let listener = shutdown::listener();
+        tokio::task::spawn(async move {
+            if let Err(e) = init_flight_sql_server(addr, shutdown_rx).await {
+                log::error!("Flight SQL server runs failed: {}", e);
+            }
+            drop(listener);
+        });
+    }
+
+    // the vector sink can't send credentials, it is served on its own port
+    // with mTLS
+    if cfg.grpc.vector_enabled && config::cluster::LOCAL_NODE.is_ingester() {
+        let addr: SocketAddr = format!("{}:{}", ip, cfg.grpc.vector_port).parse()?;
+        let shutdown_rx = servers_shutdown_rx.clone();
+        let listener = shutdown::listener();
+        tokio::task::spawn(async move {
+            if let Err(e) = init_vector_server(addr, shutdown_rx).await {
+                log::error!("Vector server runs failed: {}", e);
+            }
+            drop(listener);
+        });
+    }
+
     log::info!(
         "starting gRPC server {} at {}",
         if cfg.grpc.tls_enabled { "with TLS" } else { "" },
@@ -462,17 +498,85 @@ async fn init_common_grpc_server(
         .add_service(logs_svc)
         .add_service(query_cache_svc)
         .add_service(ingest_svc)
+        .add_service(logs_ingestion_svc)
         .add_service(flight_svc)
         .serve_with_shutdown(gaddr, async {
-            shutdown_rx.await.ok();
+            tokio::select! {
+                _ = shutdown_rx => {}
+                _ = shutdown::listener_stopped(listener.clone()) => {}
+            }
+            servers_shutdown_tx.send(true).ok();
             log::info!("gRPC server starts shutting down");
         })
         .await
         .expect("gRPC server init failed");
+    drop(listener);
     stopped_tx.send(()).ok();
     Ok(())
 }
 
+async fn init_flight_sql_server(
+    addr: SocketAddr,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<(), anyhow::Error> {
+    let cfg = get_config();
+    let flight_sql_svc = FlightServiceServer::new(FlightSqlServiceImpl)
+        .send_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .max_decoding_message_size(cfg.grpc.max_message_size * 1024 * 1024)
+        .max_encoding_message_size(cfg.grpc.max_message_size * 1024 * 1024);
+
+    log::info!(
+        "starting Flight SQL server {} at {}",
+        if cfg.grpc.tls_enabled { "with TLS" } else { "" },
+        addr
+    );
+    let builder = if cfg.grpc.tls_enabled {
+        let cert = std::fs::read_to_string(&cfg.grpc.tls_cert_path)?;
+        let key = std::fs::read_to_string(&cfg.grpc.tls_key_path)?;
+        let identity = Identity::from_pem(cert, key);
+        tonic::transport::Server::builder().tls_config(ServerTlsConfig::new().identity(identity))?
+    } else {
+        tonic::transport::Server::builder()
+    };
+    builder
+        .layer(tonic::service::interceptor(check_auth))
+        .add_service(flight_sql_svc)
+        .serve_with_shutdown(addr, async move {
+            shutdown_rx.wait_for(|stop| *stop).await.ok();
+            log::info!("Flight SQL server starts shutting down");
+        })
+        .await?;
+    Ok(())
+}
+
+async fn init_vector_server(
+    addr: SocketAddr,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<(), anyhow::Error> {
+    let cfg = get_config();
+    let vector_svc = VectorServer::new(VectorIngester)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .max_decoding_message_size(cfg.grpc.max_message_size * 1024 * 1024);
+
+    log::info!("starting Vector server with mTLS at {}", addr);
+    let cert = std::fs::read_to_string(&cfg.grpc.vector_tls_cert_path)?;
+    let key = std::fs::read_to_string(&cfg.grpc.vector_tls_key_path)?;
+    let client_ca = std::fs::read_to_string(&cfg.grpc.vector_tls_client_ca_path)?;
+    let tls_config = ServerTlsConfig::new()
+        .identity(Identity::from_pem(cert, key))
+        .client_ca_root(Certificate::from_pem(client_ca));
+    tonic::transport::Server::builder()
+        .tls_config(tls_config)?
+        .add_service(vector_svc)
+        .serve_with_shutdown(addr, async move {
+            shutdown_rx.wait_for(|stop| *stop).await.ok();
+            log::info!("Vector server starts shutting down");
+        })
+        .await?;
+    Ok(())
+}
+
 async fn init_router_grpc_server(
     init_tx: oneshot::Sender<()>,
     shutdown_rx: oneshot::Receiver<()>,
@@ -480,6 +584,8 @@ async fn init_router_grpc_server(
 ) -> Result<(), anyhow::Error> {
     let cfg = get_config();
     let gaddr: SocketAddr = format!("0.0.0.0:{}", cfg.grpc.port).parse()?;
+    // the gRPC server stops on the drain of the node
+    let listener = shutdown::listener();
     let logs_svc = LogsServiceServer::new(router::grpc::ingest::logs::LogsServer)
         .send_compressed(CompressionEncoding::Gzip)
         .accept_compressed(CompressionEncoding::Gzip)
@@ -516,11 +622,15 @@ async fn init_router_grpc_server(
         .add_service(metrics_svc)
         .add_service(traces_svc)
         .serve_with_shutdown(gaddr, async {
-            shutdown_rx.await.ok();
+            tokio::select! {
+                _ = shutdown_rx => {}
+                _ = shutdown::listener_stopped(listener.clone()) => {}
+            }
             log::info!("gRPC server starts shutting down");
         })
         .await
         .expect("gRPC server init failed");
+    drop(listener);
     stopped_tx.send(()).ok();
     Ok(())
 }
@@ -616,10 +726,11 @@ async fn init_http_server() -> Result<(), anyhow::Error> {
         .disable_signals()
         .run();
     let handle = server.handle();
-    tokio::task::spawn(async move {
+    let drain = tokio::task::spawn(async move {
         graceful_shutdown(handle).await;
     });
     server.await?;
+    drain.await?;
     Ok(())
 }
 
@@ -715,10 +826,11 @@ async fn init_http_server_without_tracing() -> Result<(), anyhow::Error> {
         .disable_signals()
         .run();
     let handle = server.handle();
-    tokio::task::spawn(async move {
+    let drain = tokio::task::spawn(async move {
         graceful_shutdown(handle).await;
     });
     server.await?;
+    drain.await?;
     Ok(())
 }
 
@@ -757,13 +869,7 @@ async fn graceful_shutdown(handle: ServerHandle) {
     // tokio::signal::ctrl_c().await.unwrap();
     // println!("ctrl-c received!");
 
-    // offline the node
-    if let Err(e) = cluster::set_offline(true).await {
-        log::error!("set offline failed: {}", e);
-    }
-    log::info!("Node is offline");
-
-    handle.stop(true).await;
+    shutdown::drain(handle).await;
 }
 
 /// Setup the tracing related components
@@ -144,5 +144,22 @@ fn main() -> Result<()> {
         .unwrap();
     file.write_all(code.as_str().as_ref()).unwrap();
 
+    tonic_build::configure()
+        .compile(&["proto/vector/vector.proto"], &["proto"])
+        .unwrap();
+
+    for name in ["event", "vector"] {
+        let path = format!("src/generated/{name}.rs");
+        let generated_source_path = out.join(format!("{name}.rs"));
+        let code = std::fs::read_to_string(generated_source_path).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .create(true)
+            .open(path)
+            .unwrap();
+        file.write_all(code.as_str().as_ref()).unwrap();
+    }
+
     Ok(())
 }
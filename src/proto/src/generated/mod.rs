@@ -14,4 +14,6 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod cluster;
+pub mod event;
 pub mod prometheus;
+pub mod vector;
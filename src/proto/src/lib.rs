@@ -15,7 +15,10 @@
 
 mod generated;
 
-pub use generated::{cluster as cluster_rpc, prometheus as prometheus_rpc};
+pub use generated::{
+    cluster as cluster_rpc, event as vector_event, prometheus as prometheus_rpc,
+    vector as vector_rpc,
+};
 
 impl From<Vec<serde_json::Value>> for cluster_rpc::IngestionData {
     fn from(usages: Vec<serde_json::Value>) -> Self {
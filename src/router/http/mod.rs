@@ -18,7 +18,7 @@ use std::collections::HashMap;
 use ::config::{
     get_config,
     meta::{
-        cluster::{Role, RoleGroup},
+        cluster::{Node, Role, RoleGroup},
         promql::RequestRangeQuery,
     },
     utils::rand::get_rand_element,
@@ -59,6 +59,8 @@ const QUERIER_ROUTES_BY_BODY: [&str; 2] = [
     "/prometheus/api/v1/query_exemplars",
 ];
 const FIXED_QUERIER_ROUTES: [&str; 3] = ["/summary", "/schema", "/streams"];
+// ingestion endpoints with the stream in the path, eg: /api/{org}/{stream}/_json
+const STREAM_INGESTION_ROUTES: [&str; 4] = ["_json", "_multi", "_kinesis_firehose", "_sub"];
 
 struct URLDetails {
     is_error: bool,
@@ -66,6 +68,7 @@ struct URLDetails {
     path: String,
     full_url: String,
     node_addr: String,
+    ingester_node: Option<String>,
 }
 
 #[inline]
@@ -83,6 +86,30 @@ fn is_fixed_querier_route(path: &str) -> bool {
     FIXED_QUERIER_ROUTES.iter().any(|x| path.contains(x))
 }
 
+/// Returns the `{org}/{stream}` of an ingestion request with the stream in
+/// the path.
+fn get_ingestion_stream_key(path: &str) -> Option<String> {
+    let path = &path[..path.find('?').unwrap_or(path.len())];
+    let path = &path[path.find("/api/")? + 5..];
+    let columns = path.split('/').collect::<Vec<_>>();
+    if columns.len() != 3 || !STREAM_INGESTION_ROUTES.contains(&columns[2]) {
+        return None;
+    }
+    Some(format!("{}/{}", columns[0], columns[1]))
+}
+
+/// Picks the ingester owning the stream of the request, so the data of a
+/// stream lands in the wal of one node instead of small files on all of them.
+/// Returns `None` if the request has no stream or the owner is not online.
+async fn get_ingester_by_stream<'a>(path: &str, nodes: &'a [Node]) -> Option<&'a Node> {
+    if !get_config().route.ingest_by_stream {
+        return None;
+    }
+    let key = get_ingestion_stream_key(path)?;
+    let name = cluster::get_node_from_consistent_hash(&key, &Role::Ingester, None).await?;
+    nodes.iter().find(|node| node.name == name)
+}
+
 #[route(
     "/config",
     method = "GET",
@@ -240,11 +267,17 @@ async fn get_url(path: &str) -> URLDetails {
             path: path.to_string(),
             full_url: "".to_string(),
             node_addr: "".to_string(),
+            ingester_node: None,
         };
     }
 
     let nodes = nodes.unwrap();
-    let node = get_rand_element(&nodes);
+    let node = if node_type == Role::Ingester {
+        get_ingester_by_stream(path, &nodes).await
+    } else {
+        None
+    };
+    let node = node.unwrap_or_else(|| get_rand_element(&nodes));
     URLDetails {
         is_error: false,
         error: None,
@@ -254,6 +287,7 @@ async fn get_url(path: &str) -> URLDetails {
             .http_addr
             .replace("http://", "")
             .replace("https://", ""),
+        ingester_node: (node_type == Role::Ingester).then(|| node.name.clone()),
     }
 }
 
@@ -296,6 +330,9 @@ async fn default_proxy(
             new_resp.insert_header((key.clone(), value.clone()));
         }
     }
+    if let Some(node_name) = new_url.ingester_node.as_ref() {
+        new_resp.insert_header(("X-Ingester-Node", node_name.as_str()));
+    }
 
     // set body
     let body = match resp
@@ -487,6 +524,20 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_router_get_ingestion_stream_key() {
+        assert_eq!(
+            get_ingestion_stream_key("/api/default/olympics/_json"),
+            Some("default/olympics".to_string())
+        );
+        assert_eq!(
+            get_ingestion_stream_key("/base/api/default/olympics/_multi?a=b"),
+            Some("default/olympics".to_string())
+        );
+        assert_eq!(get_ingestion_stream_key("/api/default/_bulk"), None);
+        assert_eq!(get_ingestion_stream_key("/api/default/v1/logs"), None);
+    }
+
     #[test]
     fn test_router_is_querier_route_by_body() {
         assert!(is_querier_route_by_body("/prometheus/api/v1/query_range"));
@@ -153,6 +153,7 @@ impl QueryConditionExt for QueryCondition {
                     ),
                     query_exemplars: false,
                     no_cache: None,
+                    timezone: None,
                 };
                 let resp = match promql::search::search("", org_id, &req, "", 0).await {
                     Ok(v) => v,
@@ -369,6 +370,13 @@ impl QueryConditionExt for QueryCondition {
                 search_type,
                 search_event_context,
                 use_cache: None,
+                cursor: None,
+                use_cursor: false,
+                streams: vec![],
+                approximate_count: false,
+                use_fts: false,
+                params: Default::default(),
+                force: false,
             };
             SearchService::search(&trace_id, org_id, stream_type, None, &req).await
         };
@@ -0,0 +1,590 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Delta Lake transaction log of the compacted files of a stream, written to
+//! `files/{org}/{stream_type}/{stream}/_delta_log/` so the stream can be read
+//! by Delta Lake consumers. A commit takes the next version of the log with
+//! a conditional write under the cluster lock of the table, so two writers
+//! never overwrite each other's commit. Every `CHECKPOINT_INTERVAL` versions
+//! the state of the table is written to a parquet checkpoint.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow::json::ReaderBuilder;
+use arrow_schema::{DataType, Field, Fields, Schema};
+use config::{
+    meta::stream::{FileKey, StreamType},
+    utils::{
+        arrow::record_batches_to_json_rows, json, parquet::read_recordbatch_from_bytes,
+        time::now_micros,
+    },
+    FILE_EXT_PARQUET,
+};
+use dashmap::DashMap;
+use infra::{dist_lock, storage};
+use once_cell::sync::Lazy;
+use parquet::arrow::ArrowWriter;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const DELTA_LOG_DIR: &str = "_delta_log";
+const LAST_CHECKPOINT: &str = "_last_checkpoint";
+const MIN_READER_VERSION: u32 = 1;
+const MIN_WRITER_VERSION: u32 = 2;
+
+/// How many times a commit is retried when another writer took its version
+const COMMIT_ATTEMPTS: usize = 5;
+
+/// A checkpoint is written every `CHECKPOINT_INTERVAL` versions
+const CHECKPOINT_INTERVAL: i64 = 10;
+
+/// table root -> the last version and schema written by this node, a lock
+/// per table so the commits of different streams don't wait on each other
+static TABLES: Lazy<DashMap<String, Arc<Mutex<Option<(i64, String)>>>>> =
+    Lazy::new(Default::default);
+
+/// Content of `_delta_log/_last_checkpoint`
+#[derive(Debug, Serialize, Deserialize)]
+struct LastCheckpoint {
+    version: i64,
+    size: usize,
+}
+
+/// Appends a commit with the added and removed files of a compaction, the
+/// data of the table doesn't change.
+pub async fn commit(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    events: &[FileKey],
+) -> Result<(), anyhow::Error> {
+    write_commit(org_id, stream_type, stream_name, events, "OPTIMIZE", false).await
+}
+
+/// Appends a commit removing the files deleted by the data retention.
+pub async fn commit_retention(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    events: &[FileKey],
+) -> Result<(), anyhow::Error> {
+    write_commit(org_id, stream_type, stream_name, events, "DELETE", true).await
+}
+
+async fn write_commit(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    events: &[FileKey],
+    operation: &str,
+    data_change: bool,
+) -> Result<(), anyhow::Error> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let root = format!("files/{org_id}/{stream_type}/{stream_name}/");
+    let schema = infra::schema::get(org_id, stream_name, stream_type).await?;
+    let schema_string = json::to_string(&schema_to_delta(&schema))?;
+
+    // the compaction of a stream runs partitions in parallel, serialize the
+    // commits of the table on this node, and across the nodes with the
+    // cluster lock for the backends without conditional writes
+    let table = TABLES.entry(root.clone()).or_default().clone();
+    let mut table = table.lock().await;
+    let lock_key = format!("/delta_log/{org_id}/{stream_type}/{stream_name}");
+    let locker = dist_lock::lock(&lock_key, 0).await?;
+    let ret = write_commit_locked(
+        &root,
+        &mut table,
+        schema_string,
+        events,
+        operation,
+        data_change,
+    )
+    .await;
+    dist_lock::unlock(&locker).await?;
+    ret
+}
+
+async fn write_commit_locked(
+    root: &str,
+    table: &mut Option<(i64, String)>,
+    schema_string: String,
+    events: &[FileKey],
+    operation: &str,
+    data_change: bool,
+) -> Result<(), anyhow::Error> {
+    let mut last_version = table.as_ref().map(|(v, _)| *v);
+    for _ in 0..COMMIT_ATTEMPTS {
+        // another node may have written the log since the last commit, e.g.
+        // the stream moved to another compactor
+        let version = next_version(root, last_version).await?;
+        // the schema is only known if this node wrote the previous commit
+        let last_schema = table
+            .as_ref()
+            .filter(|(v, _)| *v + 1 == version)
+            .map(|(_, schema)| schema.as_str());
+        let data = commit_data(
+            root,
+            version,
+            last_schema,
+            &schema_string,
+            events,
+            operation,
+            data_change,
+        )?;
+        let file = commit_file(root, version);
+        match storage::put_if_absent(&file, data.clone().into()).await {
+            Ok(()) => {}
+            // lost the race for the version, probe the log again
+            Err(object_store::Error::AlreadyExists { .. }) => {
+                last_version = Some(version);
+                continue;
+            }
+            // the backend has no conditional writes, the cluster lock keeps
+            // the writers apart
+            Err(object_store::Error::NotImplemented) => storage::put(&file, data.into()).await?,
+            Err(e) => return Err(e.into()),
+        }
+        *table = Some((version, schema_string));
+        if version > 0 && version % CHECKPOINT_INTERVAL == 0 {
+            if let Err(e) = write_checkpoint(root, version).await {
+                log::error!("[DELTA_LOG] write checkpoint {version} of {root} error: {e}");
+            }
+        }
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "delta log of {root} is being written concurrently, gave up after {COMMIT_ATTEMPTS} attempts"
+    ))
+}
+
+fn commit_file(root: &str, version: i64) -> String {
+    format!("{root}{DELTA_LOG_DIR}/{version:020}.json")
+}
+
+fn checkpoint_file(root: &str, version: i64) -> String {
+    format!("{root}{DELTA_LOG_DIR}/{version:020}.checkpoint.parquet")
+}
+
+/// Returns the first version which isn't in the log. The log is probed from
+/// the last version known by this node, or from the last checkpoint, instead
+/// of listing the whole log.
+async fn next_version(root: &str, last_version: Option<i64>) -> Result<i64, anyhow::Error> {
+    let mut version = match last_version {
+        Some(v) => v + 1,
+        None => last_checkpoint(root).await?.map_or(0, |c| c.version + 1),
+    };
+    loop {
+        match storage::get(&commit_file(root, version)).await {
+            Ok(_) => version += 1,
+            Err(object_store::Error::NotFound { .. }) => return Ok(version),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+async fn last_checkpoint(root: &str) -> Result<Option<LastCheckpoint>, anyhow::Error> {
+    match storage::get(&format!("{root}{DELTA_LOG_DIR}/{LAST_CHECKPOINT}")).await {
+        Ok(data) => Ok(Some(json::from_slice(&data)?)),
+        Err(object_store::Error::NotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes the checkpoint of `version`: the state of the last checkpoint with
+/// the commits after it replayed, then points `_last_checkpoint` to it.
+async fn write_checkpoint(root: &str, version: i64) -> Result<(), anyhow::Error> {
+    let mut state = TableState::default();
+    let first = match last_checkpoint(root).await? {
+        Some(c) if c.version < version => {
+            let data = storage::get(&checkpoint_file(root, c.version)).await?;
+            let (_, batches) = read_recordbatch_from_bytes(&data).await?;
+            for row in record_batches_to_json_rows(&batches.iter().collect::<Vec<_>>())? {
+                state.apply(json::Value::Object(row));
+            }
+            c.version + 1
+        }
+        _ => 0,
+    };
+    for v in first..=version {
+        let data = storage::get(&commit_file(root, v)).await?;
+        for line in data.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+            state.apply(json::from_slice(line)?);
+        }
+    }
+
+    let actions = state.into_actions();
+    let data = checkpoint_data(&actions)?;
+    storage::put(&checkpoint_file(root, version), data.into()).await?;
+    let last = json::to_vec(&LastCheckpoint {
+        version,
+        size: actions.len(),
+    })?;
+    storage::put(
+        &format!("{root}{DELTA_LOG_DIR}/{LAST_CHECKPOINT}"),
+        last.into(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Encodes the actions as the rows of a parquet checkpoint.
+fn checkpoint_data(actions: &[json::Value]) -> Result<Vec<u8>, anyhow::Error> {
+    let schema = Arc::new(checkpoint_schema());
+    let mut decoder = ReaderBuilder::new(schema.clone()).build_decoder()?;
+    decoder.serialize(actions)?;
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, schema, None)?;
+    if let Some(batch) = decoder.flush()? {
+        writer.write(&batch)?;
+    }
+    writer.close()?;
+    Ok(buf)
+}
+
+/// The protocol, the metadata and the live files of the table, the removed
+/// files are dropped from the checkpoint.
+#[derive(Default)]
+struct TableState {
+    protocol: Option<json::Value>,
+    metadata: Option<json::Value>,
+    files: BTreeMap<String, json::Value>,
+}
+
+impl TableState {
+    fn apply(&mut self, mut action: json::Value) {
+        if let Some(protocol) = action.get_mut("protocol") {
+            self.protocol = Some(protocol.take());
+        } else if let Some(metadata) = action.get_mut("metaData") {
+            self.metadata = Some(metadata.take());
+        } else if let Some(add) = action.get_mut("add") {
+            if let Some(path) = add["path"].as_str() {
+                self.files.insert(path.to_string(), add.take());
+            }
+        } else if let Some(path) = action.get("remove").and_then(|r| r["path"].as_str()) {
+            self.files.remove(path);
+        }
+    }
+
+    fn into_actions(self) -> Vec<json::Value> {
+        let mut actions = Vec::with_capacity(self.files.len() + 2);
+        if let Some(protocol) = self.protocol {
+            actions.push(json::json!({ "protocol": protocol }));
+        }
+        if let Some(metadata) = self.metadata {
+            actions.push(json::json!({ "metaData": metadata }));
+        }
+        actions.extend(
+            self.files
+                .into_values()
+                .map(|add| json::json!({ "add": add })),
+        );
+        actions
+    }
+}
+
+/// The columns of the checkpoint for the actions written by this module.
+fn checkpoint_schema() -> Schema {
+    let string_map = || {
+        DataType::Map(
+            Arc::new(Field::new(
+                "key_value",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("key", DataType::Utf8, false),
+                    Field::new("value", DataType::Utf8, true),
+                ])),
+                false,
+            )),
+            false,
+        )
+    };
+    let protocol = Fields::from(vec![
+        Field::new("minReaderVersion", DataType::Int32, false),
+        Field::new("minWriterVersion", DataType::Int32, false),
+    ]);
+    let format = Fields::from(vec![
+        Field::new("provider", DataType::Utf8, false),
+        Field::new("options", string_map(), true),
+    ]);
+    let metadata = Fields::from(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("format", DataType::Struct(format), false),
+        Field::new("schemaString", DataType::Utf8, false),
+        Field::new(
+            "partitionColumns",
+            DataType::List(Arc::new(Field::new("element", DataType::Utf8, false))),
+            false,
+        ),
+        Field::new("configuration", string_map(), true),
+    ]);
+    let add = Fields::from(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("partitionValues", string_map(), false),
+        Field::new("size", DataType::Int64, false),
+        Field::new("modificationTime", DataType::Int64, false),
+        Field::new("dataChange", DataType::Boolean, false),
+        Field::new("stats", DataType::Utf8, true),
+    ]);
+    Schema::new(vec![
+        Field::new("protocol", DataType::Struct(protocol), true),
+        Field::new("metaData", DataType::Struct(metadata), true),
+        Field::new("add", DataType::Struct(add), true),
+    ])
+}
+
+/// The newline delimited actions of the commit of `version`.
+fn commit_data(
+    root: &str,
+    version: i64,
+    last_schema: Option<&str>,
+    schema_string: &str,
+    events: &[FileKey],
+    operation: &str,
+    data_change: bool,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut actions = Vec::with_capacity(events.len() + 3);
+    if version == 0 {
+        actions.push(json::json!({
+            "protocol": {
+                "minReaderVersion": MIN_READER_VERSION,
+                "minWriterVersion": MIN_WRITER_VERSION,
+            }
+        }));
+    }
+    if last_schema != Some(schema_string) {
+        actions.push(json::json!({
+            "metaData": {
+                "id": root.trim_end_matches('/'),
+                "format": {"provider": "parquet", "options": {}},
+                "schemaString": schema_string,
+                "partitionColumns": [],
+                "configuration": {},
+            }
+        }));
+    }
+    let now = now_micros() / 1000;
+    actions.extend(file_actions(root, events, now, data_change));
+    actions.push(json::json!({
+        "commitInfo": {"timestamp": now, "operation": operation, "operationParameters": {}}
+    }));
+
+    let mut data = Vec::new();
+    for action in actions {
+        data.extend(json::to_vec(&action)?);
+        data.push(b'\n');
+    }
+    Ok(data)
+}
+
+/// Generates the `add` action of the new files and the `remove` action of the
+/// deleted files, `data_change` is false when compaction only rewrote the
+/// data.
+fn file_actions(root: &str, events: &[FileKey], now: i64, data_change: bool) -> Vec<json::Value> {
+    events
+        .iter()
+        .filter(|file| file.key.ends_with(FILE_EXT_PARQUET))
+        .filter_map(|file| {
+            let path = file.key.strip_prefix(root)?;
+            if file.deleted {
+                return Some(json::json!({
+                    "remove": {"path": path, "deletionTimestamp": now, "dataChange": data_change}
+                }));
+            }
+            let stats = json::json!({
+                "numRecords": file.meta.records,
+                "minValues": {"_timestamp": file.meta.min_ts},
+                "maxValues": {"_timestamp": file.meta.max_ts},
+            });
+            Some(json::json!({
+                "add": {
+                    "path": path,
+                    "partitionValues": {},
+                    "size": file.meta.compressed_size,
+                    "modificationTime": now,
+                    "dataChange": data_change,
+                    "stats": stats.to_string(),
+                }
+            }))
+        })
+        .collect()
+}
+
+/// Converts the stream schema to the Delta Lake schema, Delta Lake doesn't
+/// have unsigned types so they are stored as signed.
+fn schema_to_delta(schema: &Schema) -> json::Value {
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let data_type = match field.data_type() {
+                DataType::Boolean => "boolean",
+                DataType::Int8 => "byte",
+                DataType::Int16 => "short",
+                DataType::Int32 => "integer",
+                DataType::Int64
+                | DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64 => "long",
+                DataType::Float32 => "float",
+                DataType::Float64 => "double",
+                DataType::Binary | DataType::LargeBinary => "binary",
+                _ => "string",
+            };
+            json::json!({
+                "name": field.name(),
+                "type": data_type,
+                "nullable": field.is_nullable(),
+                "metadata": {},
+            })
+        })
+        .collect::<Vec<_>>();
+    json::json!({"type": "struct", "fields": fields})
+}
+
+#[cfg(test)]
+mod tests {
+    use config::meta::stream::FileMeta;
+
+    use super::*;
+
+    #[test]
+    fn test_schema_to_delta() {
+        let schema = Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("log", DataType::Utf8, true),
+            Field::new("took", DataType::Float64, true),
+        ]);
+        let delta = schema_to_delta(&schema);
+        assert_eq!(delta["type"], "struct");
+        assert_eq!(delta["fields"][0]["type"], "long");
+        assert_eq!(delta["fields"][0]["nullable"], false);
+        assert_eq!(delta["fields"][1]["type"], "string");
+        assert_eq!(delta["fields"][2]["type"], "double");
+    }
+
+    #[test]
+    fn test_file_actions() {
+        let root = "files/default/logs/app/";
+        let new_file = |key: &str, deleted: bool| FileKey {
+            key: key.to_string(),
+            meta: FileMeta {
+                min_ts: 1,
+                max_ts: 2,
+                records: 3,
+                compressed_size: 4,
+                ..Default::default()
+            },
+            deleted,
+            segment_ids: None,
+        };
+        let events = vec![
+            new_file("files/default/logs/app/2024/01/01/00/a.parquet", false),
+            new_file("files/default/logs/app/2024/01/01/00/b.parquet", true),
+        ];
+        let actions = file_actions(root, &events, 100, false);
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0]["add"]["path"], "2024/01/01/00/a.parquet");
+        assert_eq!(actions[0]["add"]["size"], 4);
+        let stats: json::Value =
+            json::from_str(actions[0]["add"]["stats"].as_str().unwrap()).unwrap();
+        assert_eq!(stats["numRecords"], 3);
+        assert_eq!(stats["maxValues"]["_timestamp"], 2);
+        assert_eq!(actions[1]["remove"]["path"], "2024/01/01/00/b.parquet");
+        assert_eq!(actions[1]["remove"]["dataChange"], false);
+    }
+
+    #[test]
+    fn test_commit_data() {
+        let root = "files/default/logs/app/";
+        let events = vec![FileKey {
+            key: "files/default/logs/app/2024/01/01/00/a.parquet".to_string(),
+            meta: Default::default(),
+            deleted: true,
+            segment_ids: None,
+        }];
+        let data = commit_data(root, 0, None, "{}", &events, "DELETE", true).unwrap();
+        let actions = String::from_utf8(data).unwrap();
+        let actions = actions
+            .lines()
+            .map(|line| json::from_str::<json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 4);
+        assert!(actions[0].get("protocol").is_some());
+        assert!(actions[1].get("metaData").is_some());
+        assert_eq!(actions[2]["remove"]["dataChange"], true);
+        assert_eq!(actions[3]["commitInfo"]["operation"], "DELETE");
+
+        // the schema didn't change since the last commit
+        let data = commit_data(root, 1, Some("{}"), "{}", &events, "DELETE", true).unwrap();
+        assert_eq!(String::from_utf8(data).unwrap().lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint() {
+        let root = "files/default/logs/app/";
+        let new_file = |name: &str, deleted: bool| FileKey {
+            key: format!("{root}2024/01/01/00/{name}"),
+            meta: Default::default(),
+            deleted,
+            segment_ids: None,
+        };
+        let mut state = TableState::default();
+        let commits = [
+            commit_data(
+                root,
+                0,
+                None,
+                "{}",
+                &[new_file("a.parquet", false), new_file("b.parquet", false)],
+                "OPTIMIZE",
+                false,
+            ),
+            commit_data(
+                root,
+                1,
+                Some("{}"),
+                "{}",
+                &[new_file("a.parquet", true)],
+                "DELETE",
+                true,
+            ),
+        ];
+        for data in commits {
+            for line in String::from_utf8(data.unwrap()).unwrap().lines() {
+                state.apply(json::from_str(line).unwrap());
+            }
+        }
+        let actions = state.into_actions();
+        assert_eq!(actions.len(), 3);
+
+        let data = checkpoint_data(&actions).unwrap();
+        let (_, batches) = read_recordbatch_from_bytes(&data.into()).await.unwrap();
+        let rows = record_batches_to_json_rows(&batches.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["protocol"]["minWriterVersion"], MIN_WRITER_VERSION);
+        assert_eq!(rows[1]["metaData"]["schemaString"], "{}");
+        assert_eq!(rows[2]["add"]["path"], "2024/01/01/00/b.parquet");
+
+        // the checkpoint is the starting state of the next one
+        let mut state = TableState::default();
+        for row in rows {
+            state.apply(json::Value::Object(row));
+        }
+        assert_eq!(state.files.len(), 1);
+        assert!(state.protocol.is_some() && state.metadata.is_some());
+    }
+}
@@ -60,7 +60,8 @@ use crate::{
     common::infra::cluster::get_node_by_uuid,
     job::files::parquet::{create_tantivy_index, generate_index_on_compactor},
     service::{
-        db, file_list,
+        compact::delta_log,
+        db, file_list, iceberg,
         schema::generate_schema_for_defined_schema_fields,
         search::{datafusion::exec, DATAFUSION_RUNTIME},
         stream,
@@ -444,48 +445,28 @@ pub async fn merge_by_stream(
             // delete duplicated files
             files_with_size.dedup_by(|a, b| a.key == b.key);
             // partition files by size
-            if files_with_size.len() <= 1 {
+            if files_with_size.len() < cfg.compact.min_files {
                 return Ok(());
             }
 
             // group files need to merge
-            let mut batch_groups = Vec::new();
-            let mut new_file_list = Vec::new();
-            let mut new_file_size = 0;
-            for file in files_with_size.iter() {
-                if new_file_size + file.meta.original_size > cfg.compact.max_file_size as i64 {
-                    if new_file_list.len() <= 1 {
-                        if job_strategy == MergeStrategy::FileSize {
-                            break;
-                        }
-                        new_file_size = 0;
-                        new_file_list.clear();
-                        continue; // this batch don't need to merge, skip
-                    }
-                    batch_groups.push(MergeBatch {
-                        batch_id: batch_groups.len(),
-                        org_id: org_id.clone(),
-                        stream_type,
-                        stream_name: stream_name.clone(),
-                        prefix: prefix.clone(),
-                        files: new_file_list.clone(),
-                    });
-                    new_file_size = 0;
-                    new_file_list.clear();
-                }
-                new_file_size += file.meta.original_size;
-                new_file_list.push(file.clone());
-            }
-            if new_file_list.len() > 1 {
-                batch_groups.push(MergeBatch {
-                    batch_id: batch_groups.len(),
-                    org_id: org_id.clone(),
-                    stream_type,
-                    stream_name: stream_name.clone(),
-                    prefix: prefix.clone(),
-                    files: new_file_list.clone(),
-                });
-            }
+            let batch_groups = generate_merge_groups(
+                &files_with_size,
+                &job_strategy,
+                cfg.compact.max_file_size as i64,
+                cfg.compact.min_files,
+            )
+            .into_iter()
+            .enumerate()
+            .map(|(batch_id, files)| MergeBatch {
+                batch_id,
+                org_id: org_id.clone(),
+                stream_type,
+                stream_name: stream_name.clone(),
+                prefix: prefix.clone(),
+                files,
+            })
+            .collect::<Vec<_>>();
 
             if batch_groups.is_empty() {
                 return Ok(()); // no files need to merge
@@ -548,6 +529,38 @@ pub async fn merge_by_stream(
                 // write file list to storage
                 match write_file_list(&org_id, &events).await {
                     Ok(_) => {
+                        if cfg.compact.delta_log_enabled {
+                            if let Err(e) =
+                                delta_log::commit(&org_id, stream_type, &stream_name, &events).await
+                            {
+                                log::error!(
+                                    "[COMPACT] write delta log failed: {}/{}/{}, err: {}",
+                                    org_id,
+                                    stream_type,
+                                    stream_name,
+                                    e
+                                );
+                            }
+                        }
+                        if cfg.common.iceberg_catalog_enabled {
+                            if let Err(e) = iceberg::enqueue(
+                                &org_id,
+                                stream_type,
+                                &stream_name,
+                                &events,
+                                iceberg::Change::Rewrite,
+                            )
+                            .await
+                            {
+                                log::error!(
+                                    "[COMPACT] queue iceberg commit failed: {}/{}/{}, err: {}",
+                                    org_id,
+                                    stream_type,
+                                    stream_name,
+                                    e
+                                );
+                            }
+                        }
                         // update stream stats
                         if stream_stats.doc_num != 0 {
                             let stream_key = format!("{org_id}/{stream_type}/{stream_name}");
@@ -602,6 +615,39 @@ pub async fn merge_by_stream(
     Ok(())
 }
 
+/// Groups the sorted files of a partition into batches to merge, every batch
+/// has at least `min_files` files and at most `max_file_size` bytes.
+fn generate_merge_groups(
+    files: &[FileKey],
+    strategy: &MergeStrategy,
+    max_file_size: i64,
+    min_files: usize,
+) -> Vec<Vec<FileKey>> {
+    let mut groups = Vec::new();
+    let mut new_file_list = Vec::new();
+    let mut new_file_size = 0;
+    for file in files.iter() {
+        if new_file_size + file.meta.original_size > max_file_size {
+            if new_file_list.len() < min_files {
+                if *strategy == MergeStrategy::FileSize {
+                    break;
+                }
+                new_file_size = 0;
+                new_file_list.clear();
+                continue; // this batch don't need to merge, skip
+            }
+            groups.push(std::mem::take(&mut new_file_list));
+            new_file_size = 0;
+        }
+        new_file_size += file.meta.original_size;
+        new_file_list.push(file.clone());
+    }
+    if new_file_list.len() >= min_files {
+        groups.push(new_file_list);
+    }
+    groups
+}
+
 /// merge small files into big file, upload to storage, returns the big file key and merged files
 pub async fn merge_files(
     thread_id: usize,
@@ -1147,3 +1193,50 @@ fn generate_schema_diff(
 
     Ok(diff_fields)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_files(sizes: &[i64]) -> Vec<FileKey> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| FileKey {
+                key: format!("files/default/logs/test/2024/01/01/00/{i}.parquet"),
+                meta: FileMeta {
+                    original_size: *size,
+                    ..Default::default()
+                },
+                deleted: false,
+                segment_ids: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_merge_groups() {
+        // 10 small files are merged into 3 files
+        let files = new_files(&[30; 10]);
+        let groups = generate_merge_groups(&files, &MergeStrategy::FileTime, 100, 2);
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            vec![3, 3, 3]
+        );
+        let groups = generate_merge_groups(&files, &MergeStrategy::FileTime, 1000, 2);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), 10);
+
+        // not enough small files
+        let groups = generate_merge_groups(&files[..3], &MergeStrategy::FileTime, 1000, 4);
+        assert!(groups.is_empty());
+
+        // big files are skipped
+        let files = new_files(&[10, 200, 10, 10]);
+        let groups = generate_merge_groups(&files, &MergeStrategy::FileTime, 100, 2);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), 2);
+        let groups = generate_merge_groups(&files, &MergeStrategy::FileSize, 100, 2);
+        assert!(groups.is_empty());
+    }
+}
@@ -31,9 +31,11 @@ use tokio::sync::{mpsc, Semaphore};
 use crate::{common::infra::cluster::get_node_from_consistent_hash, service::db};
 
 pub mod deleted;
+pub mod delta_log;
 pub mod flatten;
 pub mod merge;
 pub mod retention;
+pub mod rollup;
 pub mod stats;
 
 /// compactor retention run steps:
@@ -22,6 +22,7 @@ use config::{
     meta::stream::{
         FileKey, FileListDeleted, FileMeta, PartitionTimeLevel, StreamStats, StreamType, TimeRange,
     },
+    metrics,
     utils::time::{hour_micros, BASE_TIME},
 };
 use infra::{cache, dist_lock, file_list as infra_file_list};
@@ -29,7 +30,7 @@ use itertools::Itertools;
 
 use crate::{
     common::infra::cluster::get_node_by_uuid,
-    service::{db, file_list},
+    service::{compact::delta_log, db, file_list, iceberg},
 };
 
 /// This function will split the original time range based on the exclude range
@@ -474,6 +475,8 @@ async fn delete_from_file_list(
         return Ok(());
     }
 
+    let files_num = files.len() as u64;
+
     // collect stream stats
     let mut stream_stats = StreamStats::default();
 
@@ -499,8 +502,50 @@ async fn delete_from_file_list(
         });
     }
 
+    let cfg = get_config();
+    let deleted_files = if cfg.compact.delta_log_enabled || cfg.common.iceberg_catalog_enabled {
+        hours_files.values().flatten().cloned().collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+
     // write file list to storage
     write_file_list(org_id, hours_files).await?;
+    if cfg.compact.delta_log_enabled {
+        if let Err(e) =
+            delta_log::commit_retention(org_id, stream_type, stream_name, &deleted_files).await
+        {
+            log::error!(
+                "[COMPACT] write delta log failed: {}/{}/{}, err: {}",
+                org_id,
+                stream_type,
+                stream_name,
+                e
+            );
+        }
+    }
+    if cfg.common.iceberg_catalog_enabled {
+        if let Err(e) = iceberg::enqueue(
+            org_id,
+            stream_type,
+            stream_name,
+            &deleted_files,
+            iceberg::Change::Delete,
+        )
+        .await
+        {
+            log::error!(
+                "[COMPACT] queue iceberg commit failed: {}/{}/{}, err: {}",
+                org_id,
+                stream_type,
+                stream_name,
+                e
+            );
+        }
+    }
+    metrics::FILES_DELETED_TOTAL
+        .with_label_values(&[org_id, stream_type.to_string().as_str()])
+        .inc_by(files_num);
 
     // update stream stats
     if stream_stats.doc_num != 0 {
@@ -0,0 +1,437 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use arrow_schema::Schema;
+use config::{
+    cluster::LOCAL_NODE,
+    get_config, ider,
+    meta::{
+        cluster::Role,
+        promql::{EXEMPLARS_LABEL, HASH_LABEL, NAME_LABEL, TYPE_LABEL, VALUE_LABEL},
+        search,
+        stream::StreamType,
+    },
+    utils::{json, time::now_micros},
+};
+
+use crate::{
+    common::infra::cluster::get_node_from_consistent_hash,
+    service::{db, metrics, search as SearchService},
+};
+
+/// The label of the rollup streams telling which aggregate a series holds,
+/// one of `min`, `max`, `sum`, `count`, `avg` and `last`.
+pub const AGGREGATE_LABEL: &str = "aggregate";
+
+/// The rows of the rollup query are read in pages of this size.
+const PAGE_SIZE: usize = 10_000;
+
+const AGGREGATES: [&str; 6] = ["min", "max", "sum", "count", "avg", "last"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+/// From the finest to the coarsest.
+pub const GRANULARITIES: [Granularity; 3] = [
+    Granularity::FiveMinutes,
+    Granularity::OneHour,
+    Granularity::OneDay,
+];
+
+impl Granularity {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Granularity::FiveMinutes => "5m",
+            Granularity::OneHour => "1h",
+            Granularity::OneDay => "1d",
+        }
+    }
+
+    /// Bucket width in microseconds
+    pub fn micros(&self) -> i64 {
+        match self {
+            Granularity::FiveMinutes => 300 * 1_000_000,
+            Granularity::OneHour => 3600 * 1_000_000,
+            Granularity::OneDay => 86_400 * 1_000_000,
+        }
+    }
+
+    /// The rollup stream of the metrics stream, eg: `cpu_usage_5m`
+    pub fn stream_name(&self, stream_name: &str) -> String {
+        format!("{stream_name}_{}", self.suffix())
+    }
+
+    /// Time range covered by one rollup query, keeps the number of buckets
+    /// per series of a query small
+    pub fn window(&self) -> i64 {
+        match self {
+            Granularity::FiveMinutes => 3600 * 1_000_000,
+            Granularity::OneHour | Granularity::OneDay => 86_400 * 1_000_000,
+        }
+    }
+
+    /// Start of the bucket containing the time
+    pub fn align(&self, time: i64) -> i64 {
+        time - time.rem_euclid(self.micros())
+    }
+}
+
+/// Rollup streams are not rolled up again, they are marked in their stream
+/// settings when the rollup job creates them.
+pub async fn is_rollup_stream(org_id: &str, stream_name: &str) -> bool {
+    infra::schema::get_settings(org_id, stream_name, StreamType::Metrics)
+        .await
+        .is_some_and(|settings| settings.rollup_stream)
+}
+
+/// Marks the rollup stream in its stream settings, the stream is created if
+/// it doesn't exist yet.
+async fn mark_rollup_stream(org_id: &str, stream_name: &str) -> Result<(), anyhow::Error> {
+    let mut settings = infra::schema::get_settings(org_id, stream_name, StreamType::Metrics)
+        .await
+        .unwrap_or_default();
+    if settings.rollup_stream {
+        return Ok(());
+    }
+    settings.rollup_stream = true;
+    let metadata = [("settings".to_string(), json::to_string(&settings)?)].into();
+    db::schema::update_setting(org_id, stream_name, StreamType::Metrics, metadata).await
+}
+
+/// Rolls up the metrics streams of all the organizations, every stream is
+/// handled by one compactor node.
+pub async fn run() -> Result<(), anyhow::Error> {
+    let orgs = db::schema::list_organizations_from_cache().await;
+    for org_id in orgs {
+        let streams = db::schema::list_streams_from_cache(&org_id, StreamType::Metrics).await;
+        for stream_name in streams {
+            if is_rollup_stream(&org_id, &stream_name).await {
+                continue;
+            }
+            let Some(node_name) =
+                get_node_from_consistent_hash(&stream_name, &Role::Compactor, None).await
+            else {
+                continue; // no compactor node
+            };
+            if LOCAL_NODE.name.ne(&node_name) {
+                continue; // not this node
+            }
+            if let Err(e) = rollup_stream(&org_id, &stream_name).await {
+                log::error!("[COMPACTOR] metrics rollup stream: {org_id}/{stream_name}, err: {e}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rolls up the complete buckets since the last run at every granularity,
+/// the offset is saved after each window so a failed run resumes from there.
+async fn rollup_stream(org_id: &str, stream_name: &str) -> Result<(), anyhow::Error> {
+    let stats = infra::cache::stats::get_stream_stats(org_id, stream_name, StreamType::Metrics);
+    if stats.doc_time_min == 0 {
+        return Ok(()); // no data
+    }
+    let schema = infra::schema::get(org_id, stream_name, StreamType::Metrics).await?;
+    let labels = label_columns(&schema);
+    let now = now_micros() - get_config().compact.metrics_rollup_delay * 1_000_000;
+
+    for granularity in GRANULARITIES {
+        let suffix = granularity.suffix();
+        let mut offset = db::compact::rollup::get_offset(org_id, stream_name, suffix).await?;
+        if offset == 0 {
+            offset = granularity.align(stats.doc_time_min);
+        }
+        let end = granularity.align(now);
+        if offset < end {
+            mark_rollup_stream(org_id, &granularity.stream_name(stream_name)).await?;
+        }
+        while offset < end {
+            let window_end = (offset + granularity.window()).min(end);
+            let sql = rollup_sql(stream_name, &labels, granularity, offset, window_end);
+            let mut from = 0;
+            loop {
+                let hits = query(org_id, &sql, offset, window_end, from).await?;
+                let records = to_records(stream_name, &labels, granularity, &hits);
+                metrics::ingest_records(org_id, records).await?;
+                if hits.len() < PAGE_SIZE {
+                    break;
+                }
+                from += PAGE_SIZE;
+            }
+            db::compact::rollup::set_offset(org_id, stream_name, suffix, window_end).await?;
+            offset = window_end;
+        }
+    }
+    Ok(())
+}
+
+/// The label columns of a metrics stream, the series is identified by them
+fn label_columns(schema: &Schema) -> Vec<String> {
+    let timestamp = &get_config().common.column_timestamp;
+    schema
+        .fields()
+        .iter()
+        .map(|f| f.name())
+        .filter(|name| {
+            *name != timestamp
+                && *name != VALUE_LABEL
+                && *name != HASH_LABEL
+                && *name != NAME_LABEL
+                && *name != EXEMPLARS_LABEL
+        })
+        .cloned()
+        .collect()
+}
+
+/// Aggregates the samples of every series into buckets of the granularity
+fn rollup_sql(
+    stream_name: &str,
+    labels: &[String],
+    granularity: Granularity,
+    start: i64,
+    end: i64,
+) -> String {
+    let timestamp = &get_config().common.column_timestamp;
+    let bucket = granularity.micros();
+    let labels = labels
+        .iter()
+        .map(|l| format!("\"{l}\""))
+        .collect::<Vec<_>>();
+    let mut group_by = vec!["zo_rollup_ts".to_string()];
+    group_by.extend(labels.iter().cloned());
+    let mut select = vec![format!(
+        "{timestamp} - {timestamp} % {bucket} AS zo_rollup_ts"
+    )];
+    select.extend(labels);
+    for agg in AGGREGATES {
+        let expr = match agg {
+            "last" => format!("last_value({VALUE_LABEL} ORDER BY {timestamp})"),
+            _ => format!("{agg}({VALUE_LABEL})"),
+        };
+        select.push(format!("{expr} AS zo_rollup_{agg}"));
+    }
+    // the rows are paged, the order must be stable
+    let group_by = group_by.join(", ");
+    format!(
+        "SELECT {} FROM \"{stream_name}\" WHERE {timestamp} >= {start} AND {timestamp} < {end} GROUP BY {group_by} ORDER BY {group_by}",
+        select.join(", "),
+    )
+}
+
+async fn query(
+    org_id: &str,
+    sql: &str,
+    start: i64,
+    end: i64,
+    from: usize,
+) -> Result<Vec<json::Value>, anyhow::Error> {
+    let req = search::Request {
+        query: search::Query {
+            sql: sql.to_string(),
+            from: from as i64,
+            size: PAGE_SIZE as i64,
+            start_time: start,
+            end_time: end,
+            ..Default::default()
+        },
+        encoding: search::RequestEncoding::Empty,
+        regions: vec![],
+        clusters: vec![],
+        timeout: 0,
+        search_type: None,
+        search_event_context: None,
+        use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: true,
+    };
+    let res = SearchService::search(&ider::uuid(), org_id, StreamType::Metrics, None, &req).await?;
+    Ok(res.hits)
+}
+
+/// Every row of the rollup query becomes one sample per aggregate, as a
+/// gauge of the rollup stream labeled with the aggregate, so the rollup
+/// streams are queried like any other metrics stream.
+fn to_records(
+    stream_name: &str,
+    labels: &[String],
+    granularity: Granularity,
+    hits: &[json::Value],
+) -> Vec<json::Value> {
+    let timestamp = &get_config().common.column_timestamp;
+    let rollup_name = granularity.stream_name(stream_name);
+    let mut records = Vec::with_capacity(hits.len() * AGGREGATES.len());
+    for hit in hits {
+        let Some(ts) = hit.get("zo_rollup_ts").and_then(|v| v.as_i64()) else {
+            continue;
+        };
+        let mut series = json::Map::new();
+        for label in labels {
+            if let Some(value) = hit.get(label).filter(|v| !v.is_null()) {
+                series.insert(label.to_string(), value.clone());
+            }
+        }
+        series.insert(NAME_LABEL.to_string(), rollup_name.clone().into());
+        series.insert(TYPE_LABEL.to_string(), "gauge".into());
+        series.insert(timestamp.to_string(), ts.into());
+        for agg in AGGREGATES {
+            let Some(value) = hit.get(format!("zo_rollup_{agg}")).and_then(|v| v.as_f64()) else {
+                continue;
+            };
+            let mut record = series.clone();
+            record.insert(AGGREGATE_LABEL.to_string(), agg.into());
+            record.insert(VALUE_LABEL.to_string(), value.into());
+            records.push(json::Value::Object(record));
+        }
+    }
+    records
+}
+
+/// Picks the rollup of the metrics stream for a range query, the coarsest
+/// granularity which still gives a sample per step. Returns the granularity
+/// and the time up to which the stream is rolled up, the data after it must
+/// be read from the raw stream.
+pub async fn select_rollup(
+    org_id: &str,
+    stream_name: &str,
+    start: i64,
+    step: i64,
+) -> Option<(Granularity, i64)> {
+    if !get_config().compact.metrics_rollup_enabled || is_rollup_stream(org_id, stream_name).await {
+        return None;
+    }
+    let granularity = select_granularity(step)?;
+    let offset = db::compact::rollup::get_offset(org_id, stream_name, granularity.suffix())
+        .await
+        .ok()?;
+    // the rollup doesn't cover any of the range
+    (offset > start).then_some((granularity, offset))
+}
+
+fn select_granularity(step: i64) -> Option<Granularity> {
+    GRANULARITIES
+        .iter()
+        .rev()
+        .find(|g| g.micros() <= step)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::{DataType, Field};
+
+    use super::*;
+
+    #[test]
+    fn test_granularity() {
+        let g = Granularity::OneHour;
+        assert_eq!(g.stream_name("cpu"), "cpu_1h");
+        assert_eq!(g.align(7_200_000_000 + 5), 7_200_000_000);
+        assert_eq!(g.align(7_200_000_000), 7_200_000_000);
+        for g in GRANULARITIES {
+            assert_eq!(g.window() % g.micros(), 0);
+        }
+    }
+
+    #[test]
+    fn test_select_granularity() {
+        let minute = 60 * 1_000_000;
+        assert_eq!(select_granularity(minute), None);
+        assert_eq!(
+            select_granularity(5 * minute),
+            Some(Granularity::FiveMinutes)
+        );
+        assert_eq!(
+            select_granularity(30 * minute),
+            Some(Granularity::FiveMinutes)
+        );
+        assert_eq!(select_granularity(120 * minute), Some(Granularity::OneHour));
+        assert_eq!(
+            select_granularity(7 * 1440 * minute),
+            Some(Granularity::OneDay)
+        );
+    }
+
+    #[test]
+    fn test_label_columns() {
+        let schema = Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new(NAME_LABEL, DataType::Utf8, false),
+            Field::new(HASH_LABEL, DataType::Utf8, false),
+            Field::new(VALUE_LABEL, DataType::Float64, false),
+            Field::new("host", DataType::Utf8, true),
+        ]);
+        assert_eq!(label_columns(&schema), vec!["host".to_string()]);
+    }
+
+    #[test]
+    fn test_rollup_sql() {
+        let sql = rollup_sql(
+            "cpu",
+            &["host".to_string()],
+            Granularity::FiveMinutes,
+            0,
+            10,
+        );
+        assert_eq!(
+            sql,
+            "SELECT _timestamp - _timestamp % 300000000 AS zo_rollup_ts, \"host\", \
+             min(value) AS zo_rollup_min, max(value) AS zo_rollup_max, \
+             sum(value) AS zo_rollup_sum, count(value) AS zo_rollup_count, \
+             avg(value) AS zo_rollup_avg, \
+             last_value(value ORDER BY _timestamp) AS zo_rollup_last FROM \"cpu\" \
+             WHERE _timestamp >= 0 AND _timestamp < 10 GROUP BY zo_rollup_ts, \"host\" \
+             ORDER BY zo_rollup_ts, \"host\""
+        );
+    }
+
+    #[test]
+    fn test_to_records() {
+        let hits = vec![json::json!({
+            "zo_rollup_ts": 300000000,
+            "host": "a",
+            "zo_rollup_min": 1.0,
+            "zo_rollup_max": 3.0,
+            "zo_rollup_sum": 4.0,
+            "zo_rollup_count": 2,
+            "zo_rollup_avg": 2.0,
+            "zo_rollup_last": 3.0,
+        })];
+        let records = to_records(
+            "cpu",
+            &["host".to_string(), "region".to_string()],
+            Granularity::FiveMinutes,
+            &hits,
+        );
+        assert_eq!(records.len(), 6);
+        let count = records
+            .iter()
+            .find(|r| r[AGGREGATE_LABEL] == "count")
+            .unwrap();
+        assert_eq!(count[NAME_LABEL], "cpu_5m");
+        assert_eq!(count["host"], "a");
+        assert_eq!(count[VALUE_LABEL], 2.0);
+        assert_eq!(count["_timestamp"], 300000000);
+        assert!(count.get("region").is_none());
+    }
+}
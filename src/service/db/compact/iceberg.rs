@@ -0,0 +1,74 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::{
+    ider,
+    meta::stream::{FileMeta, StreamType},
+    utils::json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::service::{db, iceberg::Change};
+
+const PREFIX: &str = "/compact/iceberg/";
+
+/// The files of a stream changed together, queued for the iceberg commit
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Batch {
+    pub change: Change,
+    pub files: Vec<BatchFile>,
+    /// When the batch was queued, in microseconds
+    pub queued_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BatchFile {
+    pub key: String,
+    pub meta: FileMeta,
+    pub deleted: bool,
+}
+
+pub async fn put(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    batch: &Batch,
+) -> Result<(), anyhow::Error> {
+    let key = format!(
+        "{PREFIX}{org_id}/{stream_type}/{stream_name}/{}",
+        ider::uuid()
+    );
+    Ok(db::put(&key, json::to_vec(batch)?.into(), db::NO_NEED_WATCH, None).await?)
+}
+
+/// Lists the queued batches, keyed by `{org_id}/{stream_type}/{stream_name}/{id}`
+pub async fn list() -> Result<Vec<(String, Batch)>, anyhow::Error> {
+    let mut items = Vec::new();
+    for (key, value) in db::list(PREFIX).await? {
+        let key = key.strip_prefix(PREFIX).unwrap().to_string();
+        match json::from_slice(&value) {
+            Ok(batch) => items.push((key, batch)),
+            Err(e) => log::error!("[ICEBERG] invalid queued batch {key}: {e}"),
+        }
+    }
+    Ok(items)
+}
+
+pub async fn delete(keys: &[String]) -> Result<(), anyhow::Error> {
+    for key in keys {
+        db::delete_if_exists(&format!("{PREFIX}{key}"), false, db::NO_NEED_WATCH).await?;
+    }
+    Ok(())
+}
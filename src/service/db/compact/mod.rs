@@ -15,6 +15,8 @@
 
 pub mod file_list;
 pub mod files;
+pub mod iceberg;
 pub mod organization;
 pub mod retention;
+pub mod rollup;
 pub mod stats;
@@ -0,0 +1,57 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::service::db;
+
+/// Returns the time up to which the stream is rolled up at the granularity,
+/// 0 if it was never rolled up.
+pub async fn get_offset(
+    org_id: &str,
+    stream_name: &str,
+    granularity: &str,
+) -> Result<i64, anyhow::Error> {
+    let key = format!("/compact/rollup/{org_id}/{stream_name}/{granularity}");
+    let value = match db::get(&key).await {
+        Ok(ret) => String::from_utf8_lossy(&ret).to_string(),
+        Err(_) => String::from("0"),
+    };
+    let offset: i64 = value.parse()?;
+    Ok(offset)
+}
+
+pub async fn set_offset(
+    org_id: &str,
+    stream_name: &str,
+    granularity: &str,
+    offset: i64,
+) -> Result<(), anyhow::Error> {
+    let key = format!("/compact/rollup/{org_id}/{stream_name}/{granularity}");
+    db::put(&key, offset.to_string().into(), db::NO_NEED_WATCH, None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rollup_offset() {
+        const OFFSET: i64 = 100;
+
+        set_offset("default", "cpu", "5m", OFFSET).await.unwrap();
+        assert_eq!(get_offset("default", "cpu", "5m").await.unwrap(), OFFSET);
+        assert_eq!(get_offset("default", "cpu", "1h").await.unwrap(), 0);
+    }
+}
@@ -53,6 +53,13 @@ pub async fn get(org_id: &str, name: &str) -> Result<Vec<vrl::value::Value>, any
         search_type: None,
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     // do search
     match SearchService::search("", org_id, StreamType::EnrichmentTables, None, &req).await {
@@ -13,6 +13,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::atomic::AtomicBool;
+
 use config::{meta::stream::FileMeta, RwHashMap, RwHashSet};
 use dashmap::{DashMap, DashSet};
 use infra::{cache, cache::stats, file_list, file_list as infra_file_list};
@@ -20,7 +22,11 @@ use once_cell::sync::Lazy;
 
 pub mod broadcast;
 pub mod local;
-use crate::service::db;
+use crate::service::{db, search::cache::query_cache::QUERY_CACHE};
+
+/// Whether the stream stats have been loaded from the file list after
+/// startup, the node reports `/readyz` only after that.
+pub static FILE_LIST_LOADED: AtomicBool = AtomicBool::new(false);
 
 pub static DEPULICATE_FILES: Lazy<RwHashSet<String>> =
     Lazy::new(|| DashSet::with_capacity_and_hasher(1024, Default::default()));
@@ -58,6 +64,7 @@ pub async fn progress(
                 e
             );
         }
+        QUERY_CACHE.invalidate_file(key, data.unwrap()).await;
         // update stream stats realtime
         if config::get_config().common.local_mode {
             if let Err(e) = cache::stats::incr_stream_stats(key, data.unwrap()) {
@@ -75,10 +82,12 @@ pub async fn progress(
 
 pub async fn cache_stats() -> Result<(), anyhow::Error> {
     let orgs = db::schema::list_organizations_from_cache().await;
+    let mut failed = 0;
     for org_id in orgs {
         let ret = infra_file_list::get_stream_stats(&org_id, None, None).await;
         if ret.is_err() {
             log::error!("Load stream stats error: {}", ret.err().unwrap());
+            failed += 1;
             continue;
         }
         for (stream, stats) in ret.unwrap() {
@@ -90,5 +99,10 @@ pub async fn cache_stats() -> Result<(), anyhow::Error> {
         }
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
     }
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "load stream stats failed for {failed} organizations"
+        ));
+    }
     Ok(())
 }
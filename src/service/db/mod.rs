@@ -36,12 +36,15 @@ pub mod metrics;
 pub mod ofga;
 pub mod organization;
 pub mod pipeline;
+pub mod recording_rules;
 pub mod saved_view;
 pub mod scheduler;
 pub mod schema;
 pub mod search_job;
 pub mod session;
 pub mod short_url;
+pub mod silences;
+pub mod stream_alias;
 pub mod syslog;
 pub mod user;
 pub mod version;
@@ -0,0 +1,118 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use config::utils::json;
+
+use crate::{
+    common::{infra::config::RECORDING_RULES, meta::recording_rule::RuleGroup},
+    service::db,
+};
+
+const RULES_PREFIX: &str = "/recording_rules/";
+
+/// Rule groups are stored as `/recording_rules/{org_id}/{group_name}`
+#[tracing::instrument(name = "service:db:recording_rules:set", skip(group))]
+pub async fn set(org_id: &str, group: &RuleGroup) -> Result<(), anyhow::Error> {
+    let key = format!("{RULES_PREFIX}{org_id}/{}", group.name);
+    db::put(
+        &key,
+        json::to_vec(group).unwrap().into(),
+        db::NEED_WATCH,
+        None,
+    )
+    .await?;
+    RECORDING_RULES.insert(
+        key.strip_prefix(RULES_PREFIX).unwrap().to_string(),
+        group.clone(),
+    );
+    Ok(())
+}
+
+#[tracing::instrument(name = "service:db:recording_rules:delete")]
+pub async fn delete(org_id: &str, name: &str) -> Result<(), anyhow::Error> {
+    let key = format!("{RULES_PREFIX}{org_id}/{name}");
+    db::delete(&key, false, db::NEED_WATCH, None).await?;
+    RECORDING_RULES.remove(key.strip_prefix(RULES_PREFIX).unwrap());
+    Ok(())
+}
+
+pub fn list(org_id: &str) -> Vec<RuleGroup> {
+    let prefix = format!("{org_id}/");
+    let mut groups = RECORDING_RULES
+        .iter()
+        .filter(|v| v.key().starts_with(&prefix))
+        .map(|v| v.value().clone())
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    groups
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let cluster_coordinator = db::get_coordinator().await;
+    let mut events = cluster_coordinator.watch(RULES_PREFIX).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("Start watching recording rules");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_recording_rules: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            db::Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(RULES_PREFIX).unwrap();
+                let item_value: RuleGroup = if config::get_config().common.meta_store_external {
+                    match db::get(&ev.key).await {
+                        Ok(val) => match json::from_slice(&val) {
+                            Ok(val) => val,
+                            Err(e) => {
+                                log::error!("Error getting value: {}", e);
+                                continue;
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("Error getting value: {}", e);
+                            continue;
+                        }
+                    }
+                } else {
+                    json::from_slice(&ev.value.unwrap()).unwrap()
+                };
+                RECORDING_RULES.insert(item_key.to_owned(), item_value);
+            }
+            db::Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(RULES_PREFIX).unwrap();
+                RECORDING_RULES.remove(item_key);
+            }
+            db::Event::Empty => {}
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let ret = db::list(RULES_PREFIX).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(RULES_PREFIX).unwrap();
+        let json_val: RuleGroup = json::from_slice(&item_value).unwrap();
+        RECORDING_RULES.insert(item_key.to_owned(), json_val);
+    }
+    log::info!("Recording rules Cached");
+    Ok(())
+}
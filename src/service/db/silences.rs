@@ -0,0 +1,118 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use config::utils::json;
+
+use crate::{
+    common::{infra::config::SILENCES, meta::silence::Silence},
+    service::db,
+};
+
+const SILENCES_PREFIX: &str = "/silences/";
+
+/// Silences are stored as `/silences/{org_id}/{silence_id}`
+#[tracing::instrument(name = "service:db:silences:set", skip(silence))]
+pub async fn set(org_id: &str, silence: &Silence) -> Result<(), anyhow::Error> {
+    let key = format!("{SILENCES_PREFIX}{org_id}/{}", silence.id);
+    db::put(
+        &key,
+        json::to_vec(silence).unwrap().into(),
+        db::NEED_WATCH,
+        None,
+    )
+    .await?;
+    SILENCES.insert(
+        key.strip_prefix(SILENCES_PREFIX).unwrap().to_string(),
+        silence.clone(),
+    );
+    Ok(())
+}
+
+#[tracing::instrument(name = "service:db:silences:delete")]
+pub async fn delete(org_id: &str, id: &str) -> Result<(), anyhow::Error> {
+    let key = format!("{SILENCES_PREFIX}{org_id}/{id}");
+    db::delete(&key, false, db::NEED_WATCH, None).await?;
+    SILENCES.remove(key.strip_prefix(SILENCES_PREFIX).unwrap());
+    Ok(())
+}
+
+pub fn list(org_id: &str) -> Vec<Silence> {
+    let prefix = format!("{org_id}/");
+    let mut silences = SILENCES
+        .iter()
+        .filter(|v| v.key().starts_with(&prefix))
+        .map(|v| v.value().clone())
+        .collect::<Vec<_>>();
+    silences.sort_by(|a, b| a.starts_at.cmp(&b.starts_at).then(a.id.cmp(&b.id)));
+    silences
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let cluster_coordinator = db::get_coordinator().await;
+    let mut events = cluster_coordinator.watch(SILENCES_PREFIX).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("Start watching silences");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_silences: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            db::Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(SILENCES_PREFIX).unwrap();
+                let item_value: Silence = if config::get_config().common.meta_store_external {
+                    match db::get(&ev.key).await {
+                        Ok(val) => match json::from_slice(&val) {
+                            Ok(val) => val,
+                            Err(e) => {
+                                log::error!("Error getting value: {}", e);
+                                continue;
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("Error getting value: {}", e);
+                            continue;
+                        }
+                    }
+                } else {
+                    json::from_slice(&ev.value.unwrap()).unwrap()
+                };
+                SILENCES.insert(item_key.to_owned(), item_value);
+            }
+            db::Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(SILENCES_PREFIX).unwrap();
+                SILENCES.remove(item_key);
+            }
+            db::Event::Empty => {}
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let ret = db::list(SILENCES_PREFIX).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(SILENCES_PREFIX).unwrap();
+        let json_val: Silence = json::from_slice(&item_value).unwrap();
+        SILENCES.insert(item_key.to_owned(), json_val);
+    }
+    log::info!("Silences Cached");
+    Ok(())
+}
@@ -0,0 +1,115 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use config::{meta::stream::StreamType, utils::json};
+
+use crate::{common::infra::config::STREAM_ALIASES, service::db};
+
+const ALIAS_PREFIX: &str = "/stream_alias/";
+
+/// Aliases are stored as `/stream_alias/{org_id}/{stream_type}/{alias}` with
+/// the name of the stream they point to as value.
+#[tracing::instrument(name = "service:db:stream_alias:set")]
+pub async fn set(
+    org_id: &str,
+    stream_type: StreamType,
+    alias: &str,
+    stream_name: &str,
+) -> Result<(), anyhow::Error> {
+    let key = format!("{ALIAS_PREFIX}{org_id}/{stream_type}/{alias}");
+    db::put(
+        &key,
+        json::to_vec(stream_name).unwrap().into(),
+        db::NEED_WATCH,
+        None,
+    )
+    .await?;
+    // make the alias usable on this node right away
+    STREAM_ALIASES.insert(
+        key.strip_prefix(ALIAS_PREFIX).unwrap().to_string(),
+        stream_name.to_string(),
+    );
+    Ok(())
+}
+
+#[tracing::instrument(name = "service:db:stream_alias:delete")]
+pub async fn delete(
+    org_id: &str,
+    stream_type: StreamType,
+    alias: &str,
+) -> Result<(), anyhow::Error> {
+    let key = format!("{ALIAS_PREFIX}{org_id}/{stream_type}/{alias}");
+    db::delete(&key, false, db::NEED_WATCH, None).await?;
+    STREAM_ALIASES.remove(key.strip_prefix(ALIAS_PREFIX).unwrap());
+    Ok(())
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let cluster_coordinator = db::get_coordinator().await;
+    let mut events = cluster_coordinator.watch(ALIAS_PREFIX).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("Start watching stream aliases");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_stream_aliases: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            db::Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(ALIAS_PREFIX).unwrap();
+                let item_value: String = if config::get_config().common.meta_store_external {
+                    match db::get(&ev.key).await {
+                        Ok(val) => match json::from_slice(&val) {
+                            Ok(val) => val,
+                            Err(e) => {
+                                log::error!("Error getting value: {}", e);
+                                continue;
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("Error getting value: {}", e);
+                            continue;
+                        }
+                    }
+                } else {
+                    json::from_slice(&ev.value.unwrap()).unwrap()
+                };
+                STREAM_ALIASES.insert(item_key.to_owned(), item_value);
+            }
+            db::Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(ALIAS_PREFIX).unwrap();
+                STREAM_ALIASES.remove(item_key);
+            }
+            db::Event::Empty => {}
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let ret = db::list(ALIAS_PREFIX).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(ALIAS_PREFIX).unwrap();
+        let json_val: String = json::from_slice(&item_value).unwrap();
+        STREAM_ALIASES.insert(item_key.to_owned(), json_val);
+    }
+    log::info!("Stream aliases Cached");
+    Ok(())
+}
@@ -17,7 +17,7 @@
 
 use std::{collections::BTreeMap, fs, net::IpAddr, sync::Arc, time::SystemTime};
 
-use config::{get_config, MMDB_CITY_FILE_NAME};
+use config::{get_config, utils::json, MMDB_CITY_FILE_NAME};
 #[cfg(feature = "enterprise")]
 use maxminddb::geoip2::Enterprise;
 use maxminddb::{
@@ -28,6 +28,11 @@ use serde::{Deserialize, Serialize};
 use vector_enrichment::{Case, Condition, IndexHandle, Table};
 use vrl::value::{ObjectMap, Value};
 
+/// Fields added to the records by the geoip enrichment at ingestion.
+pub const GEO_COUNTRY_FIELD: &str = "_geo_country";
+pub const GEO_CITY_FIELD: &str = "_geo_city";
+pub const GEO_ASN_FIELD: &str = "_geo_asn";
+
 // MaxMind GeoIP database files have a type field we can use to recognize
 // specific products. If we encounter one of these two types, we look for
 // ASN/ISP information; otherwise we expect to be working with a City database.
@@ -400,6 +405,46 @@ impl Table for Geoip {
     }
 }
 
+/// Adds the country and city from the city database and the autonomous system
+/// number from the ASN database of the ip address in `field` to the record.
+/// Records without a valid ip address in the field are left untouched.
+pub fn enrich_record(
+    city: Option<&Geoip>,
+    asn: Option<&Geoip>,
+    field: &str,
+    record: &mut json::Map<String, json::Value>,
+) {
+    let Some(ip) = record
+        .get(field)
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.trim().parse::<IpAddr>().ok())
+    else {
+        return;
+    };
+    let select = |fields: &[&str]| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+    if let Some(info) =
+        city.and_then(|t| t.lookup(ip, Some(&select(&["country_name", "city_name"]))))
+    {
+        for (key, name) in [
+            ("country_name", GEO_COUNTRY_FIELD),
+            ("city_name", GEO_CITY_FIELD),
+        ] {
+            if let Some(val) = info.get(key).and_then(|v| v.as_str()) {
+                record.insert(name.to_string(), json::Value::String(val.to_string()));
+            }
+        }
+    }
+    if let Some(info) = asn.and_then(|t| t.lookup(ip, Some(&select(&["autonomous_system_number"]))))
+    {
+        if let Some(val) = info
+            .get("autonomous_system_number")
+            .and_then(|v| v.as_integer())
+        {
+            record.insert(GEO_ASN_FIELD.to_string(), val.into());
+        }
+    }
+}
+
 impl std::fmt::Debug for Geoip {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -409,3 +454,57 @@ impl std::fmt::Debug for Geoip {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use config::utils::json::json;
+
+    use super::*;
+
+    fn test_table(name: &str) -> Geoip {
+        Geoip::new(GeoipConfig {
+            path: format!(
+                "{}/tests/test-data/geoip/{name}",
+                env!("CARGO_MANIFEST_DIR")
+            ),
+            locale: default_locale(),
+        })
+        .unwrap()
+    }
+
+    fn enrich(ip: &str) -> json::Value {
+        let city = test_table("GeoLite2-City-Test.mmdb");
+        let asn = test_table("GeoLite2-ASN-Test.mmdb");
+        let mut record = json!({ "client_ip": ip });
+        enrich_record(
+            Some(&city),
+            Some(&asn),
+            "client_ip",
+            record.as_object_mut().unwrap(),
+        );
+        record
+    }
+
+    #[test]
+    fn test_enrich_record() {
+        assert_eq!(
+            enrich("81.2.69.142"),
+            json!({
+                "client_ip": "81.2.69.142",
+                "_geo_country": "United Kingdom",
+                "_geo_city": "London",
+            })
+        );
+        // no city in the database
+        assert_eq!(
+            enrich("2001:218::1"),
+            json!({ "client_ip": "2001:218::1", "_geo_country": "Japan" })
+        );
+        assert_eq!(
+            enrich("1.128.0.1"),
+            json!({ "client_ip": "1.128.0.1", "_geo_asn": 1221 })
+        );
+        assert_eq!(enrich("8.8.8.8"), json!({ "client_ip": "8.8.8.8" }));
+        assert_eq!(enrich("not an ip"), json!({ "client_ip": "not an ip" }));
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use config::{
+    cluster::{is_online, LOCAL_NODE},
+    get_config,
+};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::common::infra::cluster;
+
+static CHECKERS: Lazy<HealthCheckers> = Lazy::new(|| {
+    HealthCheckers::new(vec![
+        Arc::new(StorageChecker),
+        Arc::new(WalChecker),
+        Arc::new(ClusterChecker),
+    ])
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Unhealthy,
+}
+
+pub type HealthReport = (bool, BTreeMap<&'static str, HealthStatus>);
+
+/// A subsystem reported by `/healthz` and `/readyz`, the node is unhealthy if
+/// any critical subsystem is unhealthy.
+#[async_trait]
+pub trait HealthChecker: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn is_critical(&self) -> bool {
+        true
+    }
+
+    async fn check(&self) -> HealthStatus;
+}
+
+struct HealthCheckers {
+    checkers: RwLock<Vec<Arc<dyn HealthChecker>>>,
+    // the last report and when it was taken, the lock is held while checking so
+    // concurrent probes wait for the running check instead of starting another
+    last: tokio::sync::Mutex<Option<(Instant, HealthReport)>>,
+}
+
+impl HealthCheckers {
+    fn new(checkers: Vec<Arc<dyn HealthChecker>>) -> Self {
+        Self {
+            checkers: RwLock::new(checkers),
+            last: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn register(&self, checker: Arc<dyn HealthChecker>) {
+        let mut checkers = self.checkers.write();
+        checkers.retain(|c| c.name() != checker.name());
+        checkers.push(checker);
+    }
+
+    async fn check_all(&self, timeout: Duration, cache_ttl: Duration) -> HealthReport {
+        let mut last = self.last.lock().await;
+        if let Some((at, report)) = last.as_ref() {
+            if at.elapsed() < cache_ttl {
+                return report.clone();
+            }
+        }
+        let checkers = self.checkers.read().clone();
+        let tasks = checkers.iter().map(|checker| async move {
+            let status = tokio::time::timeout(timeout, checker.check())
+                .await
+                .unwrap_or(HealthStatus::Unhealthy);
+            (checker.as_ref(), status)
+        });
+        let results = futures::future::join_all(tasks).await;
+        let healthy = results
+            .iter()
+            .all(|(checker, status)| !checker.is_critical() || *status != HealthStatus::Unhealthy);
+        let statuses = results
+            .into_iter()
+            .map(|(checker, status)| (checker.name(), status))
+            .collect();
+        let report = (healthy, statuses);
+        *last = Some((Instant::now(), report.clone()));
+        report
+    }
+}
+
+pub fn register(checker: Arc<dyn HealthChecker>) {
+    CHECKERS.register(checker);
+}
+
+/// Runs all the checks concurrently, a check which doesn't finish in
+/// `ZO_HEALTH_CHECK_TIMEOUT` is unhealthy. The report is reused for
+/// `ZO_HEALTH_CHECK_CACHE_TTL` seconds, so frequent probes don't hit the
+/// storage on every request.
+pub async fn check_all() -> HealthReport {
+    let cfg = get_config();
+    CHECKERS
+        .check_all(
+            Duration::from_secs(cfg.health_check.timeout),
+            Duration::from_secs(cfg.health_check.cache_ttl),
+        )
+        .await
+}
+
+/// The object storage answers requests, a missing object is fine.
+struct StorageChecker;
+
+#[async_trait]
+impl HealthChecker for StorageChecker {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    async fn check(&self) -> HealthStatus {
+        match infra::storage::head("healthz").await {
+            Ok(_) | Err(object_store::Error::NotFound { .. }) => HealthStatus::Ok,
+            Err(e) => {
+                log::error!("[HEALTH] storage check error: {e}");
+                HealthStatus::Unhealthy
+            }
+        }
+    }
+}
+
+/// The wal directory of an ingester is writable.
+struct WalChecker;
+
+#[async_trait]
+impl HealthChecker for WalChecker {
+    fn name(&self) -> &'static str {
+        "wal"
+    }
+
+    async fn check(&self) -> HealthStatus {
+        if !LOCAL_NODE.is_ingester() {
+            return HealthStatus::Ok;
+        }
+        let dir = std::path::PathBuf::from(&get_config().common.data_wal_dir);
+        let file = dir.join(".healthz");
+        let ret = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            tokio::fs::write(&file, b"ok").await?;
+            tokio::fs::remove_file(&file).await
+        }
+        .await;
+        match ret {
+            Ok(()) => HealthStatus::Ok,
+            Err(e) => {
+                log::error!("[HEALTH] wal check {:?} error: {e}", dir);
+                HealthStatus::Unhealthy
+            }
+        }
+    }
+}
+
+/// The node is online in the cluster and there is an ingester to write to,
+/// the node can still serve requests otherwise.
+struct ClusterChecker;
+
+#[async_trait]
+impl HealthChecker for ClusterChecker {
+    fn name(&self) -> &'static str {
+        "cluster"
+    }
+
+    fn is_critical(&self) -> bool {
+        false
+    }
+
+    async fn check(&self) -> HealthStatus {
+        if get_config().common.local_mode {
+            return HealthStatus::Ok;
+        }
+        let online = is_online();
+        let has_ingester = cluster::get_cached_online_ingester_nodes()
+            .await
+            .is_some_and(|nodes| !nodes.is_empty());
+        if online && has_ingester {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Degraded
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedChecker(&'static str, bool, HealthStatus);
+
+    #[async_trait]
+    impl HealthChecker for FixedChecker {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn is_critical(&self) -> bool {
+            self.1
+        }
+
+        async fn check(&self) -> HealthStatus {
+            self.2
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_all() {
+        let timeout = Duration::from_secs(1);
+        let checkers = HealthCheckers::new(vec![]);
+        checkers.register(Arc::new(FixedChecker("a", true, HealthStatus::Ok)));
+        checkers.register(Arc::new(FixedChecker("b", false, HealthStatus::Unhealthy)));
+        let (healthy, statuses) = checkers.check_all(timeout, Duration::ZERO).await;
+        assert!(healthy);
+        assert_eq!(statuses.get("b"), Some(&HealthStatus::Unhealthy));
+
+        // replaces the checker with the same name
+        checkers.register(Arc::new(FixedChecker("a", true, HealthStatus::Unhealthy)));
+        let (healthy, statuses) = checkers.check_all(timeout, Duration::ZERO).await;
+        assert!(!healthy);
+        assert_eq!(statuses.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_check_all_cached() {
+        let timeout = Duration::from_secs(1);
+        let cache_ttl = Duration::from_secs(3600);
+        let checkers =
+            HealthCheckers::new(vec![Arc::new(FixedChecker("a", true, HealthStatus::Ok))]);
+        assert!(checkers.check_all(timeout, cache_ttl).await.0);
+        // the cached report is returned until it expires
+        checkers.register(Arc::new(FixedChecker("a", true, HealthStatus::Unhealthy)));
+        assert!(checkers.check_all(timeout, cache_ttl).await.0);
+        assert!(!checkers.check_all(timeout, Duration::ZERO).await.0);
+    }
+}
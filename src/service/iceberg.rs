@@ -0,0 +1,1106 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Apache Iceberg metadata of the parquet files of a stream, written to
+//! `files/{org}/{stream_type}/{stream}/metadata/` in the layout of the Hadoop
+//! catalog (`v{n}.metadata.json` and `version-hint.text`), so a catalog server
+//! can register the table by its metadata location.
+//!
+//! The files uploaded by the WAL flush, merged by compaction and deleted by
+//! the data retention are queued in the meta store and committed by the
+//! iceberg job of the compactor owning the stream every
+//! `ZO_ICEBERG_COMMIT_INTERVAL`, one snapshot per stream. A snapshot adds a
+//! manifest of the new files and rewrites the manifests that reference the
+//! removed files, the manifests are merged into one once there are
+//! `ZO_ICEBERG_MANIFEST_MERGE_COUNT` of them, and the snapshots older than
+//! `ZO_ICEBERG_SNAPSHOT_MAX_AGE` are expired.
+
+use apache_avro::{types::Value as AvroValue, Reader, Schema as AvroSchema, Writer};
+use arrow_schema::{DataType, Schema};
+use config::{
+    cluster::LOCAL_NODE,
+    get_config, ider, is_local_disk_storage,
+    meta::{
+        cluster::Role,
+        stream::{FileKey, StreamType},
+    },
+    utils::{json, time::now_micros},
+    FILE_EXT_PARQUET,
+};
+use hashbrown::{HashMap, HashSet};
+use infra::{dist_lock, storage};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    common::infra::cluster::get_node_from_consistent_hash,
+    service::db::{
+        self,
+        compact::iceberg::{Batch, BatchFile},
+    },
+};
+
+const FORMAT_VERSION: i64 = 2;
+const METADATA_DIR: &str = "metadata";
+const VERSION_HINT: &str = "version-hint.text";
+const NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+
+/// Iceberg's `write.metadata.previous-versions-max` default
+const PREVIOUS_VERSIONS_MAX: usize = 100;
+
+const STATUS_EXISTING: i32 = 0;
+const STATUS_ADDED: i32 = 1;
+const STATUS_DELETED: i32 = 2;
+
+static MANIFEST_LIST_SCHEMA: Lazy<AvroSchema> = Lazy::new(|| {
+    AvroSchema::parse_str(
+        r#"{"type": "record", "name": "manifest_file", "fields": [
+            {"name": "manifest_path", "type": "string", "field-id": 500},
+            {"name": "manifest_length", "type": "long", "field-id": 501},
+            {"name": "partition_spec_id", "type": "int", "field-id": 502},
+            {"name": "content", "type": "int", "field-id": 517},
+            {"name": "sequence_number", "type": "long", "field-id": 515},
+            {"name": "min_sequence_number", "type": "long", "field-id": 516},
+            {"name": "added_snapshot_id", "type": "long", "field-id": 503},
+            {"name": "added_files_count", "type": "int", "field-id": 504},
+            {"name": "existing_files_count", "type": "int", "field-id": 505},
+            {"name": "deleted_files_count", "type": "int", "field-id": 506},
+            {"name": "added_rows_count", "type": "long", "field-id": 512},
+            {"name": "existing_rows_count", "type": "long", "field-id": 513},
+            {"name": "deleted_rows_count", "type": "long", "field-id": 514}
+        ]}"#,
+    )
+    .unwrap()
+});
+
+static MANIFEST_ENTRY_SCHEMA: Lazy<AvroSchema> = Lazy::new(|| {
+    AvroSchema::parse_str(
+        r#"{"type": "record", "name": "manifest_entry", "fields": [
+            {"name": "status", "type": "int", "field-id": 0},
+            {"name": "snapshot_id", "type": ["null", "long"], "default": null, "field-id": 1},
+            {"name": "sequence_number", "type": ["null", "long"], "default": null, "field-id": 3},
+            {"name": "file_sequence_number", "type": ["null", "long"], "default": null, "field-id": 4},
+            {"name": "data_file", "field-id": 2, "type": {"type": "record", "name": "r2", "fields": [
+                {"name": "content", "type": "int", "field-id": 134},
+                {"name": "file_path", "type": "string", "field-id": 100},
+                {"name": "file_format", "type": "string", "field-id": 101},
+                {"name": "partition", "field-id": 102, "type": {"type": "record", "name": "r102", "fields": []}},
+                {"name": "record_count", "type": "long", "field-id": 103},
+                {"name": "file_size_in_bytes", "type": "long", "field-id": 104}
+            ]}}
+        ]}"#,
+    )
+    .unwrap()
+});
+
+/// The deletes of the files which aren't in the table yet are queued again
+/// for their add, up to this age in microseconds
+const UNRESOLVED_DELETE_MAX_AGE: i64 = 24 * 3600 * 1_000_000;
+
+/// The snapshot summary property of the ids of the committed batches
+const BATCHES_PROPERTY: &str = "openobserve.batches";
+
+// the commits of this node are serialized, the dist lock covers the cluster
+static COMMIT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Why the files of a stream changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    /// The WAL flush uploaded new files
+    Append,
+    /// Compaction merged files, the data of the table doesn't change
+    Rewrite,
+    /// The data retention deleted files
+    Delete,
+}
+
+/// The queued batches of a stream folded into one snapshot
+#[derive(Default)]
+struct PendingCommit {
+    files: Vec<FileKey>,
+    changes: Vec<Change>,
+    /// The ids of the batches in the commit
+    batches: Vec<String>,
+    /// When the deleted files were queued
+    deleted_at: HashMap<String, i64>,
+}
+
+impl PendingCommit {
+    fn add_batch(&mut self, id: &str, batch: &Batch) {
+        for file in batch.files.iter() {
+            if file.deleted {
+                self.deleted_at.insert(file.key.clone(), batch.queued_at);
+            }
+            self.push(FileKey::new(&file.key, file.meta.clone(), file.deleted));
+        }
+        self.add_change(batch.change);
+        self.batches.push(id.to_string());
+    }
+
+    fn push(&mut self, file: FileKey) {
+        // a file added and removed before the commit is never in the table.
+        // The delete may come first: the nodes queue their batches
+        // independently, so the compactor may merge a file before the add of
+        // the ingester is committed
+        if let Some(pos) = self
+            .files
+            .iter()
+            .position(|f| f.deleted != file.deleted && f.key == file.key)
+        {
+            self.files.remove(pos);
+            return;
+        }
+        self.files.push(file);
+    }
+
+    fn add_change(&mut self, change: Change) {
+        if !self.changes.contains(&change) {
+            self.changes.push(change);
+        }
+    }
+
+    /// The snapshot operation of the changes
+    fn operation(&self) -> &'static str {
+        match self.changes.as_slice() {
+            [Change::Append] => "append",
+            [Change::Rewrite] => "replace",
+            [Change::Delete] => "delete",
+            _ => "overwrite",
+        }
+    }
+}
+
+/// Queues the new and deleted files of the stream for the next commit. The
+/// queue is kept in the meta store, so a restart of the node loses nothing.
+pub async fn enqueue(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    files: &[FileKey],
+    change: Change,
+) -> Result<(), anyhow::Error> {
+    let files = files
+        .iter()
+        .filter(|f| f.key.ends_with(FILE_EXT_PARQUET))
+        .map(|f| BatchFile {
+            key: f.key.clone(),
+            meta: f.meta.clone(),
+            deleted: f.deleted,
+        })
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        return Ok(());
+    }
+    let batch = Batch {
+        change,
+        files,
+        queued_at: now_micros(),
+    };
+    db::compact::iceberg::put(org_id, stream_type, stream_name, &batch).await
+}
+
+/// Commits the queued batches of the streams this node owns, one snapshot per
+/// stream. The batches of a failed commit stay in the queue for the next one.
+pub async fn flush() {
+    let batches = match db::compact::iceberg::list().await {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("[ICEBERG] list queued batches failed: {e}");
+            return;
+        }
+    };
+    let mut streams: HashMap<String, Vec<(String, Batch)>> = HashMap::new();
+    for (key, batch) in batches {
+        let Some((stream, id)) = key.rsplit_once('/') else {
+            continue;
+        };
+        streams
+            .entry(stream.to_string())
+            .or_default()
+            .push((id.to_string(), batch));
+    }
+    for (stream, mut batches) in streams {
+        let columns = stream.split('/').collect::<Vec<_>>();
+        let [org_id, stream_type, stream_name] = columns.as_slice() else {
+            continue;
+        };
+        // the queue is shared by the cluster, one compactor commits a stream
+        match get_node_from_consistent_hash(stream_name, &Role::Compactor, None).await {
+            Some(node) if node == LOCAL_NODE.name => {}
+            _ => continue,
+        }
+        batches.sort_by(|a, b| (a.1.queued_at, &a.0).cmp(&(b.1.queued_at, &b.0)));
+        let stream_type = StreamType::from(*stream_type);
+        if let Err(e) = commit_batches(org_id, stream_type, stream_name, &batches).await {
+            log::error!("[ICEBERG] commit snapshot failed: {stream}, err: {e}");
+        }
+    }
+}
+
+/// Commits the batches of the stream and removes them from the queue. The
+/// deletes of the files which aren't in the table yet are queued again until
+/// the add of the file cancels them, or dropped after
+/// `UNRESOLVED_DELETE_MAX_AGE`.
+async fn commit_batches(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+    batches: &[(String, Batch)],
+) -> Result<(), anyhow::Error> {
+    let schema = infra::schema::get(org_id, stream_name, stream_type).await?;
+    let root = format!("files/{org_id}/{stream_type}/{stream_name}/");
+
+    let _guard = COMMIT_LOCK.lock().await;
+    let lock_key = format!("/iceberg/{org_id}/{stream_type}/{stream_name}");
+    let locker = dist_lock::lock(&lock_key, 0).await?;
+    let ret = commit_batches_locked(&root, &schema, batches).await;
+    dist_lock::unlock(&locker).await?;
+    let unresolved = ret?;
+
+    let now = now_micros();
+    let mut requeued = Vec::new();
+    let mut queued_at = now;
+    for (file, deleted_at) in unresolved {
+        if now - deleted_at > UNRESOLVED_DELETE_MAX_AGE {
+            log::warn!(
+                "[ICEBERG] drop the delete of {}, the file was never added to the table",
+                file.key
+            );
+            continue;
+        }
+        queued_at = queued_at.min(deleted_at);
+        requeued.push(BatchFile {
+            key: file.key,
+            meta: file.meta,
+            deleted: true,
+        });
+    }
+    if !requeued.is_empty() {
+        let batch = Batch {
+            change: Change::Delete,
+            files: requeued,
+            queued_at,
+        };
+        db::compact::iceberg::put(org_id, stream_type, stream_name, &batch).await?;
+    }
+
+    let keys = batches
+        .iter()
+        .map(|(id, _)| format!("{org_id}/{stream_type}/{stream_name}/{id}"))
+        .collect::<Vec<_>>();
+    db::compact::iceberg::delete(&keys).await
+}
+
+/// Returns the deleted files which aren't in the table, with the time they
+/// were queued.
+async fn commit_batches_locked(
+    root: &str,
+    schema: &Schema,
+    batches: &[(String, Batch)],
+) -> Result<Vec<(FileKey, i64)>, anyhow::Error> {
+    let meta_dir = format!("{root}{METADATA_DIR}/");
+    let (version, metadata) = match load_metadata(&meta_dir).await? {
+        Some(v) => v,
+        None => (0, new_metadata(&storage_uri(root))),
+    };
+    // the batches of the last commit are still queued if the node stopped
+    // before removing them, they are not committed twice
+    let committed = committed_batches(&metadata);
+    let mut commit = PendingCommit::default();
+    for (id, batch) in batches {
+        if !committed.contains(id) {
+            commit.add_batch(id, batch);
+        }
+    }
+    if commit.files.is_empty() {
+        return Ok(vec![]);
+    }
+    let unresolved = commit_snapshot(root, schema, version, metadata, &commit).await?;
+    Ok(unresolved
+        .into_iter()
+        .map(|file| {
+            let deleted_at = commit.deleted_at.get(&file.key).copied().unwrap_or(0);
+            (file, deleted_at)
+        })
+        .collect())
+}
+
+/// The ids of the batches of the current snapshot
+fn committed_batches(metadata: &json::Value) -> HashSet<String> {
+    let current_id = metadata["current-snapshot-id"].as_i64();
+    metadata["snapshots"]
+        .as_array()
+        .and_then(|snapshots| {
+            snapshots
+                .iter()
+                .find(|s| s["snapshot-id"].as_i64() == current_id)
+        })
+        .and_then(|s| s["summary"][BATCHES_PROPERTY].as_str())
+        .map(|v| {
+            v.split(',')
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ManifestFile {
+    manifest_path: String,
+    manifest_length: i64,
+    partition_spec_id: i32,
+    content: i32,
+    sequence_number: i64,
+    min_sequence_number: i64,
+    added_snapshot_id: i64,
+    added_files_count: i32,
+    existing_files_count: i32,
+    deleted_files_count: i32,
+    added_rows_count: i64,
+    existing_rows_count: i64,
+    deleted_rows_count: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ManifestEntry {
+    status: i32,
+    snapshot_id: Option<i64>,
+    sequence_number: Option<i64>,
+    file_sequence_number: Option<i64>,
+    data_file: DataFile,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct DataFile {
+    content: i32,
+    file_path: String,
+    file_format: String,
+    partition: Partition,
+    record_count: i64,
+    file_size_in_bytes: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Partition {}
+
+/// Commits the files of the pending commit as a new snapshot of the table,
+/// returns the deleted files which the table doesn't have.
+async fn commit_snapshot(
+    root: &str,
+    schema: &Schema,
+    version: i64,
+    mut metadata: json::Value,
+    commit: &PendingCommit,
+) -> Result<Vec<FileKey>, anyhow::Error> {
+    let cfg = get_config();
+    let meta_dir = format!("{root}{METADATA_DIR}/");
+    let files = &commit.files;
+    let schema_id = update_schema(&mut metadata, schema);
+    let now = now_micros();
+    let snapshot_id = now;
+    let sequence_number = metadata["last-sequence-number"].as_i64().unwrap_or(0) + 1;
+    let parent_id = metadata["current-snapshot-id"]
+        .as_i64()
+        .filter(|id| *id >= 0);
+
+    let mut manifests = match current_manifest_list(&metadata) {
+        Some(path) => read_manifest_list(&path).await?,
+        None => Vec::new(),
+    };
+
+    // the new manifest has the added files and the deleted files
+    let mut entries = Vec::new();
+    let removed = files
+        .iter()
+        .filter(|f| f.deleted)
+        .map(|f| storage_uri(&f.key))
+        .collect::<HashSet<_>>();
+    // the manifests carried over are merged into one once there are enough of
+    // them, a table would otherwise have about one manifest per compacted file
+    let merge_count = cfg.common.iceberg_manifest_merge_count;
+    let merge = merge_count > 0 && manifests.len() >= merge_count;
+    let mut found = HashSet::new();
+    if merge || !removed.is_empty() {
+        let mut kept = Vec::with_capacity(manifests.len());
+        let mut merged = Vec::new();
+        for manifest in manifests {
+            let (existing, deleted) = split_manifest(&manifest, &removed).await?;
+            let changed = !deleted.is_empty();
+            found.extend(deleted.iter().map(|e| e.data_file.file_path.clone()));
+            entries.extend(deleted.into_iter().map(|mut e| {
+                e.status = STATUS_DELETED;
+                e.snapshot_id = Some(snapshot_id);
+                e
+            }));
+            if merge {
+                merged.extend(existing);
+            } else if !changed {
+                kept.push(manifest);
+            } else if !existing.is_empty() {
+                let path = format!("{meta_dir}{}-m1.avro", ider::uuid());
+                kept.push(
+                    write_manifest(&path, &metadata, snapshot_id, sequence_number, &existing)
+                        .await?,
+                );
+            }
+        }
+        if !merged.is_empty() {
+            let path = format!("{meta_dir}{}-m2.avro", ider::uuid());
+            kept.push(
+                write_manifest(&path, &metadata, snapshot_id, sequence_number, &merged).await?,
+            );
+        }
+        manifests = kept;
+    }
+    entries.extend(files.iter().filter(|f| !f.deleted).map(|f| ManifestEntry {
+        status: STATUS_ADDED,
+        snapshot_id: Some(snapshot_id),
+        sequence_number: None,
+        file_sequence_number: None,
+        data_file: DataFile {
+            content: 0,
+            file_path: storage_uri(&f.key),
+            file_format: "PARQUET".to_string(),
+            partition: Partition::default(),
+            record_count: f.meta.records,
+            file_size_in_bytes: f.meta.compressed_size,
+        },
+    }));
+    // the deletes of the files the table doesn't have are handed back
+    let unresolved = files
+        .iter()
+        .filter(|f| f.deleted && !found.contains(&storage_uri(&f.key)))
+        .cloned()
+        .collect::<Vec<_>>();
+    if entries.is_empty() && !merge {
+        return Ok(unresolved);
+    }
+    if !entries.is_empty() {
+        let path = format!("{meta_dir}{}-m0.avro", ider::uuid());
+        manifests
+            .push(write_manifest(&path, &metadata, snapshot_id, sequence_number, &entries).await?);
+    }
+
+    let manifest_list = format!("{meta_dir}snap-{snapshot_id}-{}.avro", ider::uuid());
+    write_manifest_list(
+        &manifest_list,
+        snapshot_id,
+        parent_id,
+        sequence_number,
+        &manifests,
+    )
+    .await?;
+
+    let mut snapshot = json::json!({
+        "snapshot-id": snapshot_id,
+        "sequence-number": sequence_number,
+        "timestamp-ms": now / 1000,
+        "manifest-list": storage_uri(&manifest_list),
+        "summary": {"operation": commit.operation()},
+        "schema-id": schema_id,
+    });
+    snapshot["summary"][BATCHES_PROPERTY] = commit.batches.join(",").into();
+    if let Some(parent_id) = parent_id {
+        snapshot["parent-snapshot-id"] = parent_id.into();
+    }
+    metadata["snapshots"].as_array_mut().unwrap().push(snapshot);
+    metadata["snapshot-log"]
+        .as_array_mut()
+        .unwrap()
+        .push(json::json!({"snapshot-id": snapshot_id, "timestamp-ms": now / 1000}));
+    if version > 0 {
+        metadata["metadata-log"]
+            .as_array_mut()
+            .unwrap()
+            .push(json::json!({
+                "metadata-file": storage_uri(&format!("{meta_dir}v{version}.metadata.json")),
+                "timestamp-ms": metadata["last-updated-ms"],
+            }));
+    }
+    metadata["current-snapshot-id"] = snapshot_id.into();
+    metadata["refs"] = json::json!({"main": {"snapshot-id": snapshot_id, "type": "branch"}});
+    metadata["last-sequence-number"] = sequence_number.into();
+    metadata["last-updated-ms"] = (now / 1000).into();
+    let expired = expire_snapshots(
+        &mut metadata,
+        (now - cfg.common.iceberg_snapshot_max_age * 1_000_000) / 1000,
+    );
+    let old_metadata_files = trim_metadata_log(&mut metadata);
+
+    // the version hint is updated last, readers never see a partial commit
+    let version = version + 1;
+    let file = format!("{meta_dir}v{version}.metadata.json");
+    storage::put(&file, json::to_vec(&metadata)?.into()).await?;
+    storage::put(
+        &format!("{meta_dir}{VERSION_HINT}"),
+        version.to_string().into(),
+    )
+    .await?;
+
+    // the files of the expired snapshots are no longer referenced by the table
+    if let Err(e) = delete_expired_files(&metadata, &manifests, &expired, &old_metadata_files).await
+    {
+        log::error!("[ICEBERG] delete expired files of {root} failed: {e}");
+    }
+    Ok(unresolved)
+}
+
+/// Removes the snapshots older than `cutoff_ms` from the metadata, the current
+/// snapshot is always kept. Returns the removed snapshots.
+fn expire_snapshots(metadata: &mut json::Value, cutoff_ms: i64) -> Vec<json::Value> {
+    let current_id = metadata["current-snapshot-id"].as_i64();
+    let snapshots = std::mem::take(metadata["snapshots"].as_array_mut().unwrap());
+    let (kept, expired): (Vec<_>, Vec<_>) = snapshots.into_iter().partition(|s| {
+        s["snapshot-id"].as_i64() == current_id
+            || s["timestamp-ms"].as_i64().unwrap_or_default() >= cutoff_ms
+    });
+    let kept_ids = kept
+        .iter()
+        .filter_map(|s| s["snapshot-id"].as_i64())
+        .collect::<HashSet<_>>();
+    metadata["snapshots"] = json::Value::Array(kept);
+    metadata["snapshot-log"]
+        .as_array_mut()
+        .unwrap()
+        .retain(|s| {
+            s["snapshot-id"]
+                .as_i64()
+                .is_some_and(|id| kept_ids.contains(&id))
+        });
+    expired
+}
+
+/// Keeps the last `PREVIOUS_VERSIONS_MAX` entries of the metadata log,
+/// returns the storage keys of the metadata files dropped from it.
+fn trim_metadata_log(metadata: &mut json::Value) -> Vec<String> {
+    let log = metadata["metadata-log"].as_array_mut().unwrap();
+    let n = log.len().saturating_sub(PREVIOUS_VERSIONS_MAX);
+    log.drain(..n)
+        .filter_map(|entry| entry["metadata-file"].as_str().map(storage_key))
+        .collect()
+}
+
+/// Deletes the manifest lists of the expired snapshots, their manifests which
+/// no snapshot kept references and the metadata files dropped from the log.
+/// A manifest is only ever carried over to the next snapshot, so a manifest of
+/// an expired snapshot is still used only if the oldest kept snapshot has it.
+async fn delete_expired_files(
+    metadata: &json::Value,
+    current_manifests: &[ManifestFile],
+    expired: &[json::Value],
+    old_metadata_files: &[String],
+) -> Result<(), anyhow::Error> {
+    let mut files = old_metadata_files.to_vec();
+    if !expired.is_empty() {
+        let oldest = metadata["snapshots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .min_by_key(|s| s["sequence-number"].as_i64().unwrap_or_default());
+        let current_id = metadata["current-snapshot-id"].as_i64();
+        let live = match oldest {
+            Some(s) if s["snapshot-id"].as_i64() != current_id => {
+                let path = storage_key(s["manifest-list"].as_str().unwrap_or_default());
+                read_manifest_list(&path).await?
+            }
+            _ => current_manifests.to_vec(),
+        };
+        let live = live
+            .into_iter()
+            .map(|m| m.manifest_path)
+            .collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        for snapshot in expired {
+            let Some(list) = snapshot["manifest-list"].as_str().map(storage_key) else {
+                continue;
+            };
+            let manifests = match read_manifest_list(&list).await {
+                Ok(v) => v,
+                Err(e) => {
+                    log::warn!("[ICEBERG] read expired manifest list {list} failed: {e}");
+                    vec![]
+                }
+            };
+            for manifest in manifests {
+                if !live.contains(&manifest.manifest_path)
+                    && seen.insert(manifest.manifest_path.clone())
+                {
+                    files.push(storage_key(&manifest.manifest_path));
+                }
+            }
+            files.push(list);
+        }
+    }
+    if files.is_empty() {
+        return Ok(());
+    }
+    let files = files.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+    storage::del(&files).await?;
+    Ok(())
+}
+
+/// Returns the current version and table metadata, or `None` for a new table.
+async fn load_metadata(meta_dir: &str) -> Result<Option<(i64, json::Value)>, anyhow::Error> {
+    let version = match storage::get(&format!("{meta_dir}{VERSION_HINT}")).await {
+        Ok(data) => String::from_utf8_lossy(&data).trim().parse::<i64>()?,
+        Err(object_store::Error::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let data = storage::get(&format!("{meta_dir}v{version}.metadata.json")).await?;
+    Ok(Some((version, json::from_slice(&data)?)))
+}
+
+fn new_metadata(location: &str) -> json::Value {
+    json::json!({
+        "format-version": FORMAT_VERSION,
+        "table-uuid": ider::uuid(),
+        "location": location.trim_end_matches('/'),
+        "last-sequence-number": 0,
+        "last-updated-ms": now_micros() / 1000,
+        "last-column-id": 0,
+        "schemas": [],
+        "current-schema-id": -1,
+        "partition-specs": [{"spec-id": 0, "fields": []}],
+        "default-spec-id": 0,
+        "last-partition-id": 999,
+        "sort-orders": [{"order-id": 0, "fields": []}],
+        "default-sort-order-id": 0,
+        "properties": {},
+        "current-snapshot-id": -1,
+        "refs": {},
+        "snapshots": [],
+        "snapshot-log": [],
+        "metadata-log": [],
+    })
+}
+
+/// Adds a new schema to the metadata if the stream schema changed, returns
+/// the current schema id. Columns keep their field id by name, the parquet
+/// files don't have field ids so readers resolve them by the name mapping.
+fn update_schema(metadata: &mut json::Value, schema: &Schema) -> i64 {
+    let current_id = metadata["current-schema-id"].as_i64().unwrap_or(-1);
+    let mut field_ids = HashMap::new();
+    for schema in metadata["schemas"].as_array().unwrap() {
+        for field in schema["fields"].as_array().unwrap() {
+            field_ids.insert(
+                field["name"].as_str().unwrap_or_default().to_string(),
+                field["id"].as_i64().unwrap_or_default(),
+            );
+        }
+    }
+    let mut last_column_id = metadata["last-column-id"].as_i64().unwrap_or(0);
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let id = *field_ids
+                .entry(field.name().to_string())
+                .or_insert_with(|| {
+                    last_column_id += 1;
+                    last_column_id
+                });
+            json::json!({
+                "id": id,
+                "name": field.name(),
+                "required": false,
+                "type": iceberg_type(field.data_type()),
+            })
+        })
+        .collect::<Vec<_>>();
+    let current = metadata["schemas"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["schema-id"].as_i64() == Some(current_id));
+    if current.is_some_and(|s| s["fields"].as_array() == Some(&fields)) {
+        return current_id;
+    }
+
+    let schema_id = current_id + 1;
+    metadata["schemas"]
+        .as_array_mut()
+        .unwrap()
+        .push(json::json!({
+            "type": "struct",
+            "schema-id": schema_id,
+            "fields": fields,
+        }));
+    metadata["current-schema-id"] = schema_id.into();
+    metadata["last-column-id"] = last_column_id.into();
+    let name_mapping = field_ids
+        .iter()
+        .map(|(name, id)| json::json!({"field-id": id, "names": [name]}))
+        .collect::<Vec<_>>();
+    metadata["properties"][NAME_MAPPING_PROPERTY] =
+        json::Value::String(json::to_string(&name_mapping).unwrap());
+    schema_id
+}
+
+/// Iceberg doesn't have unsigned types, they are widened to the signed type.
+fn iceberg_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Boolean => "boolean",
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 => {
+            "int"
+        }
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => "long",
+        DataType::Float32 => "float",
+        DataType::Float64 => "double",
+        DataType::Binary | DataType::LargeBinary => "binary",
+        _ => "string",
+    }
+}
+
+fn current_manifest_list(metadata: &json::Value) -> Option<String> {
+    let snapshot_id = metadata["current-snapshot-id"].as_i64()?;
+    let snapshot = metadata["snapshots"]
+        .as_array()?
+        .iter()
+        .find(|s| s["snapshot-id"].as_i64() == Some(snapshot_id))?;
+    let uri = snapshot["manifest-list"].as_str()?;
+    Some(storage_key(uri))
+}
+
+/// Splits the live entries of a manifest into the ones to keep and the ones
+/// which are removed, the inherited snapshot id and sequence numbers are
+/// written explicitly as the entries move to a new manifest.
+async fn split_manifest(
+    manifest: &ManifestFile,
+    removed: &HashSet<String>,
+) -> Result<(Vec<ManifestEntry>, Vec<ManifestEntry>), anyhow::Error> {
+    let data = storage::get(&storage_key(&manifest.manifest_path)).await?;
+    let mut existing = Vec::new();
+    let mut deleted = Vec::new();
+    for value in Reader::new(&data[..])? {
+        let mut entry: ManifestEntry = apache_avro::from_value(&value?)?;
+        if entry.status == STATUS_DELETED {
+            continue;
+        }
+        entry.snapshot_id = entry.snapshot_id.or(Some(manifest.added_snapshot_id));
+        entry.sequence_number = entry.sequence_number.or(Some(manifest.sequence_number));
+        entry.file_sequence_number = entry
+            .file_sequence_number
+            .or(Some(manifest.sequence_number));
+        if removed.contains(&entry.data_file.file_path) {
+            deleted.push(entry);
+        } else {
+            entry.status = STATUS_EXISTING;
+            existing.push(entry);
+        }
+    }
+    Ok((existing, deleted))
+}
+
+async fn write_manifest(
+    path: &str,
+    metadata: &json::Value,
+    snapshot_id: i64,
+    sequence_number: i64,
+    entries: &[ManifestEntry],
+) -> Result<ManifestFile, anyhow::Error> {
+    let current_id = metadata["current-schema-id"].clone();
+    let schema = metadata["schemas"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["schema-id"] == current_id)
+        .cloned()
+        .unwrap_or_default();
+    let mut writer = Writer::new(&MANIFEST_ENTRY_SCHEMA, Vec::new());
+    writer.add_user_metadata("schema".to_string(), json::to_string(&schema)?)?;
+    writer.add_user_metadata("schema-id".to_string(), current_id.to_string())?;
+    writer.add_user_metadata("partition-spec".to_string(), "[]")?;
+    writer.add_user_metadata("partition-spec-id".to_string(), "0")?;
+    writer.add_user_metadata("format-version".to_string(), FORMAT_VERSION.to_string())?;
+    writer.add_user_metadata("content".to_string(), "data")?;
+    for entry in entries {
+        writer.append(to_avro(entry, &MANIFEST_ENTRY_SCHEMA)?)?;
+    }
+    let data = writer.into_inner()?;
+
+    let mut manifest = ManifestFile {
+        manifest_path: storage_uri(path),
+        manifest_length: data.len() as i64,
+        partition_spec_id: 0,
+        content: 0,
+        sequence_number,
+        min_sequence_number: sequence_number,
+        added_snapshot_id: snapshot_id,
+        added_files_count: 0,
+        existing_files_count: 0,
+        deleted_files_count: 0,
+        added_rows_count: 0,
+        existing_rows_count: 0,
+        deleted_rows_count: 0,
+    };
+    for entry in entries {
+        let rows = entry.data_file.record_count;
+        match entry.status {
+            STATUS_ADDED => {
+                manifest.added_files_count += 1;
+                manifest.added_rows_count += rows;
+            }
+            STATUS_EXISTING => {
+                manifest.existing_files_count += 1;
+                manifest.existing_rows_count += rows;
+            }
+            _ => {
+                manifest.deleted_files_count += 1;
+                manifest.deleted_rows_count += rows;
+            }
+        }
+        if let Some(seq) = entry.sequence_number {
+            manifest.min_sequence_number = manifest.min_sequence_number.min(seq);
+        }
+    }
+    storage::put(path, data.into()).await?;
+    Ok(manifest)
+}
+
+async fn read_manifest_list(path: &str) -> Result<Vec<ManifestFile>, anyhow::Error> {
+    let data = storage::get(path).await?;
+    let mut manifests = Vec::new();
+    for value in Reader::new(&data[..])? {
+        manifests.push(apache_avro::from_value(&value?)?);
+    }
+    Ok(manifests)
+}
+
+async fn write_manifest_list(
+    path: &str,
+    snapshot_id: i64,
+    parent_id: Option<i64>,
+    sequence_number: i64,
+    manifests: &[ManifestFile],
+) -> Result<(), anyhow::Error> {
+    let mut writer = Writer::new(&MANIFEST_LIST_SCHEMA, Vec::new());
+    writer.add_user_metadata("snapshot-id".to_string(), snapshot_id.to_string())?;
+    if let Some(parent_id) = parent_id {
+        writer.add_user_metadata("parent-snapshot-id".to_string(), parent_id.to_string())?;
+    }
+    writer.add_user_metadata("sequence-number".to_string(), sequence_number.to_string())?;
+    writer.add_user_metadata("format-version".to_string(), FORMAT_VERSION.to_string())?;
+    for manifest in manifests {
+        writer.append(to_avro(manifest, &MANIFEST_LIST_SCHEMA)?)?;
+    }
+    storage::put(path, writer.into_inner()?.into()).await?;
+    Ok(())
+}
+
+fn to_avro<T: Serialize>(value: &T, schema: &AvroSchema) -> Result<AvroValue, anyhow::Error> {
+    Ok(apache_avro::to_value(value)?.resolve(schema)?)
+}
+
+/// Returns the absolute uri of a storage key, the table metadata only has
+/// absolute paths.
+fn storage_uri(key: &str) -> String {
+    let cfg = get_config();
+    if is_local_disk_storage() {
+        let dir = std::fs::canonicalize(&cfg.common.data_stream_dir)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| cfg.common.data_stream_dir.clone());
+        return format!("file://{}/{key}", dir.trim_end_matches('/'));
+    }
+    let scheme = match cfg.s3.provider.as_str() {
+        "gcs" => "gs",
+        "azure" => "az",
+        _ => "s3",
+    };
+    format!(
+        "{scheme}://{}/{}",
+        cfg.s3.bucket_name,
+        storage::format_key(key, true)
+    )
+}
+
+/// Returns the storage key of an absolute uri written by [`storage_uri`].
+fn storage_key(uri: &str) -> String {
+    let base = storage_uri("");
+    uri.strip_prefix(&base).unwrap_or(uri).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::Field;
+
+    use super::*;
+
+    #[test]
+    fn test_update_schema() {
+        let mut metadata = new_metadata("s3://bucket/files/default/logs/app");
+        let schema = Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("log", DataType::Utf8, true),
+        ]);
+        assert_eq!(update_schema(&mut metadata, &schema), 0);
+        assert_eq!(update_schema(&mut metadata, &schema), 0);
+
+        // new columns get new ids, existing columns keep theirs
+        let schema = Schema::new(vec![
+            Field::new("took", DataType::Float64, true),
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("log", DataType::Utf8, true),
+        ]);
+        assert_eq!(update_schema(&mut metadata, &schema), 1);
+        let fields = &metadata["schemas"][1]["fields"];
+        assert_eq!(fields[0]["id"], 3);
+        assert_eq!(fields[0]["type"], "double");
+        assert_eq!(fields[1]["id"], 1);
+        assert_eq!(fields[1]["type"], "long");
+        assert_eq!(metadata["last-column-id"], 3);
+        let mapping: json::Value = json::from_str(
+            metadata["properties"][NAME_MAPPING_PROPERTY]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(mapping.as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_manifest_entry_avro() {
+        let entry = ManifestEntry {
+            status: STATUS_ADDED,
+            snapshot_id: Some(1),
+            sequence_number: None,
+            file_sequence_number: None,
+            data_file: DataFile {
+                content: 0,
+                file_path: "s3://bucket/files/default/logs/app/a.parquet".to_string(),
+                file_format: "PARQUET".to_string(),
+                partition: Partition::default(),
+                record_count: 10,
+                file_size_in_bytes: 100,
+            },
+        };
+        let mut writer = Writer::new(&MANIFEST_ENTRY_SCHEMA, Vec::new());
+        writer
+            .append(to_avro(&entry, &MANIFEST_ENTRY_SCHEMA).unwrap())
+            .unwrap();
+        let data = writer.into_inner().unwrap();
+        let values = Reader::new(&data[..])
+            .unwrap()
+            .map(|v| apache_avro::from_value::<ManifestEntry>(&v.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![entry]);
+    }
+
+    #[test]
+    fn test_pending_commit() {
+        let file = |key: &str, deleted: bool| BatchFile {
+            key: key.to_string(),
+            meta: Default::default(),
+            deleted,
+        };
+        let batch = |change, files| Batch {
+            change,
+            files,
+            queued_at: 1,
+        };
+        let mut commit = PendingCommit::default();
+        commit.add_batch(
+            "1",
+            &batch(
+                Change::Append,
+                vec![file("a.parquet", false), file("b.parquet", false)],
+            ),
+        );
+        assert_eq!(commit.operation(), "append");
+        // merged before the commit, only the merged file gets in the table
+        commit.add_batch(
+            "2",
+            &batch(
+                Change::Rewrite,
+                vec![
+                    file("a.parquet", true),
+                    file("b.parquet", true),
+                    file("c.parquet", false),
+                    file("d.parquet", true),
+                    file("e.parquet", true),
+                ],
+            ),
+        );
+        assert_eq!(commit.operation(), "overwrite");
+        // the add of a file whose delete was queued first cancels the delete
+        commit.add_batch("3", &batch(Change::Append, vec![file("e.parquet", false)]));
+        let keys = commit
+            .files
+            .iter()
+            .map(|f| (f.key.as_str(), f.deleted))
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![("c.parquet", false), ("d.parquet", true)]);
+        assert_eq!(commit.batches, vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_committed_batches() {
+        let mut metadata = new_metadata("s3://bucket/files/default/logs/app");
+        assert!(committed_batches(&metadata).is_empty());
+        metadata["snapshots"] = json::json!([
+            {"snapshot-id": 1, "summary": {"operation": "append", BATCHES_PROPERTY: "a,b"}},
+            {"snapshot-id": 2, "summary": {"operation": "append", BATCHES_PROPERTY: "c"}},
+        ]);
+        metadata["current-snapshot-id"] = 2.into();
+        assert_eq!(
+            committed_batches(&metadata),
+            HashSet::from(["c".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_expire_snapshots() {
+        let mut metadata = new_metadata("s3://bucket/files/default/logs/app");
+        for (id, ts) in [(1, 100), (2, 200), (3, 300)] {
+            metadata["snapshots"]
+                .as_array_mut()
+                .unwrap()
+                .push(json::json!({"snapshot-id": id, "timestamp-ms": ts}));
+            metadata["snapshot-log"]
+                .as_array_mut()
+                .unwrap()
+                .push(json::json!({"snapshot-id": id, "timestamp-ms": ts}));
+        }
+        metadata["current-snapshot-id"] = 3.into();
+        let expired = expire_snapshots(&mut metadata, 200);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0]["snapshot-id"], 1);
+        assert_eq!(metadata["snapshots"].as_array().unwrap().len(), 2);
+        assert_eq!(metadata["snapshot-log"].as_array().unwrap().len(), 2);
+        // the current snapshot is never expired
+        let expired = expire_snapshots(&mut metadata, 1000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(metadata["snapshots"][0]["snapshot-id"], 3);
+    }
+
+    #[test]
+    fn test_trim_metadata_log() {
+        let mut metadata = new_metadata("s3://bucket/files/default/logs/app");
+        for version in 1..=PREVIOUS_VERSIONS_MAX + 2 {
+            metadata["metadata-log"]
+                .as_array_mut()
+                .unwrap()
+                .push(json::json!({"metadata-file": format!("v{version}.metadata.json")}));
+        }
+        let removed = trim_metadata_log(&mut metadata);
+        assert_eq!(removed, vec!["v1.metadata.json", "v2.metadata.json"]);
+        assert_eq!(
+            metadata["metadata-log"].as_array().unwrap().len(),
+            PREVIOUS_VERSIONS_MAX
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, Result};
+use config::{get_config, utils::json};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Value};
+
+/// Global functions of the base library which can load code from files.
+const UNSAFE_GLOBALS: [&str; 4] = ["dofile", "loadfile", "load", "require"];
+
+/// A Lua script which transforms the records of a stream at ingestion.
+///
+/// The script runs in a sandbox with only the `table`, `string`, `math` and
+/// `utf8` libraries, there is no `io`, `os` or `package`. It receives the
+/// record as a table in `...` and returns the new record, or `nil` to drop it:
+///
+/// ```lua
+/// local record = ...
+/// if record.level == "debug" then return nil end
+/// record.message = string.gsub(record.message, "%d%d%d%d%-%d%d%d%d", "[REDACTED]")
+/// return record
+/// ```
+pub struct LuaTransform {
+    lua: Lua,
+    func: Function,
+    max_instructions: u32,
+}
+
+impl LuaTransform {
+    pub fn new(script: &str) -> Result<Self> {
+        let cfg = get_config();
+        Self::with_limits(
+            script,
+            cfg.limit.ingest_lua_max_instructions,
+            cfg.limit.ingest_lua_memory_limit * 1024 * 1024,
+        )
+    }
+
+    pub fn with_limits(script: &str, max_instructions: u32, memory_limit: usize) -> Result<Self> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+            LuaOptions::default(),
+        )?;
+        lua.set_memory_limit(memory_limit)?;
+        let globals = lua.globals();
+        for name in UNSAFE_GLOBALS {
+            globals.raw_remove(name)?;
+        }
+        let func = lua
+            .load(script)
+            .set_name("ingest_transform")
+            .into_function()?;
+        Ok(Self {
+            lua,
+            func,
+            max_instructions,
+        })
+    }
+
+    /// Runs the script on a record, returns `None` if the script drops it.
+    pub fn apply(&self, record: &json::Value) -> Result<Option<json::Value>> {
+        // the instruction count restarts when the hook is set
+        let max_instructions = self.max_instructions;
+        self.lua.set_hook(
+            HookTriggers::new().every_nth_instruction(max_instructions),
+            move |_, _| {
+                Err(mlua::Error::runtime(format!(
+                    "exceeded the limit of {max_instructions} instructions"
+                )))
+            },
+        );
+        let ret = self.func.call::<Value>(self.lua.to_value(record)?);
+        self.lua.remove_hook();
+        match ret? {
+            Value::Nil => Ok(None),
+            ret @ Value::Table(_) => match self.lua.from_value::<json::Value>(ret)? {
+                json::Value::Object(record) => Ok(Some(json::Value::Object(record))),
+                _ => Err(anyhow!("the script must return a table of fields or nil")),
+            },
+            ret => Err(anyhow!(
+                "the script must return a table or nil, got {}",
+                ret.type_name()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::utils::json::json;
+
+    use super::*;
+
+    fn transform(script: &str) -> LuaTransform {
+        LuaTransform::with_limits(script, 100_000, 16 * 1024 * 1024).unwrap()
+    }
+
+    #[test]
+    fn test_lua_transform() {
+        let t = transform(
+            r#"local record = ...
+if record.level == "debug" then return nil end
+record.message = string.gsub(record.message, "%d%d%d%d%-%d%d%d%d", "[REDACTED]")
+record.msg_len = #record.message
+return record"#,
+        );
+        assert_eq!(
+            t.apply(&json!({"level": "info", "message": "card 1234-5678"}))
+                .unwrap(),
+            Some(json!({"level": "info", "message": "card [REDACTED]", "msg_len": 15}))
+        );
+        assert_eq!(t.apply(&json!({"level": "debug"})).unwrap(), None);
+        assert!(transform("return 1").apply(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_lua_transform_sandbox() {
+        for script in [
+            r#"return io.open("/etc/passwd"):read("a")"#,
+            r#"os.execute("ls")"#,
+            r#"return dofile("/etc/passwd")"#,
+            r#"return loadfile("/etc/passwd")()"#,
+            r#"return require("os")"#,
+            r#"return package.loadlib("libc.so", "open")"#,
+        ] {
+            assert!(transform(script).apply(&json!({})).is_err(), "{script}");
+        }
+    }
+
+    #[test]
+    fn test_lua_transform_limits() {
+        let t = transform("while true do end");
+        assert!(t.apply(&json!({})).is_err());
+        // the limit applies to each record
+        let t = transform("for i = 1, 1000 do end return ...");
+        for _ in 0..1000 {
+            assert!(t.apply(&json!({})).is_ok());
+        }
+        let t = transform(r#"return { s = string.rep("x", 64 * 1024 * 1024) }"#);
+        assert!(t.apply(&json!({})).is_err());
+    }
+}
@@ -39,7 +39,10 @@ use config::{
 use infra::schema::STREAM_RECORD_ID_GENERATOR;
 use proto::cluster_rpc::IngestionType;
 use vrl::{
-    compiler::{runtime::Runtime, CompilationResult, TargetValueRef},
+    compiler::{
+        runtime::{Runtime, Terminate},
+        CompilationResult, Program, TargetValueRef,
+    },
     prelude::state,
 };
 
@@ -53,11 +56,15 @@ use crate::{
         meta::{ingestion::IngestionRequest, stream::SchemaRecords},
         utils::functions::get_vrl_compiler_config,
     },
-    service::{alerts::alert::AlertExt, db, logs::bulk::TRANSFORM_FAILED},
+    service::{
+        alerts::alert::AlertExt, db, logs::bulk::TRANSFORM_FAILED,
+        schema::check_schema_compatibility,
+    },
 };
 
 pub mod grpc;
 pub mod ingestion_service;
+pub mod lua;
 
 pub type TriggerAlertData = Vec<(Alert, Vec<Map<String, Value>>)>;
 
@@ -151,6 +158,44 @@ pub fn apply_vrl_fn(
     }
 }
 
+/// Applies the ingest transform of a stream to a record, returns `None` if
+/// the program aborts, which drops the record. The original record is kept if
+/// the program fails or doesn't return an object.
+pub fn apply_ingest_transform(
+    runtime: &mut Runtime,
+    program: &Program,
+    row: Value,
+    org_id: &str,
+    stream_name: &str,
+) -> Option<Value> {
+    let mut metadata = vrl::value::Value::from(BTreeMap::new());
+    let mut target = TargetValueRef {
+        value: &mut vrl::value::Value::from(&row),
+        metadata: &mut metadata,
+        secrets: &mut vrl::value::Secrets::new(),
+    };
+    let timezone = vrl::compiler::TimeZone::Local;
+    let err = match runtime.resolve(&mut target, program, &timezone) {
+        Ok(res) => match Value::try_from(res) {
+            Ok(val) if val.is_object() => return Some(val),
+            Ok(val) => format!("result is not an object: {val}"),
+            Err(err) => format!("{err:?}"),
+        },
+        Err(Terminate::Abort(_)) => return None,
+        Err(Terminate::Error(err)) => err.to_string(),
+    };
+    metrics::INGEST_ERRORS
+        .with_label_values(&[
+            org_id,
+            StreamType::Logs.to_string().as_str(),
+            stream_name,
+            TRANSFORM_FAILED,
+        ])
+        .inc();
+    log::warn!("{org_id}/{stream_name} ingest transform failed: {err}. Returning original row.");
+    Some(row)
+}
+
 pub async fn get_stream_partition_keys(
     org_id: &str,
     stream_type: &StreamType,
@@ -412,6 +457,33 @@ pub fn check_ingestion_allowed(org_id: &str, stream_name: Option<&str>) -> Resul
     Ok(())
 }
 
+/// Drops the records of the streams whose schema conflicts with them, see
+/// [`check_schema_compatibility`]. `records` returns the records of a stream
+/// and `on_reject` reports the records of a rejected stream.
+pub async fn reject_incompatible_records<T, R, F>(
+    org_id: &str,
+    stream_type: StreamType,
+    data_by_stream: &mut HashMap<String, T>,
+    records: R,
+    mut on_reject: F,
+) where
+    R: for<'a> Fn(&'a T) -> Vec<&'a Map<String, Value>>,
+    F: FnMut(&str, &T, anyhow::Error),
+{
+    let mut incompatible_streams = Vec::new();
+    for (stream_name, data) in data_by_stream.iter() {
+        if let Err(e) =
+            check_schema_compatibility(org_id, stream_name, stream_type, records(data)).await
+        {
+            on_reject(stream_name, data, e);
+            incompatible_streams.push(stream_name.to_string());
+        }
+    }
+    for stream_name in incompatible_streams {
+        data_by_stream.remove(&stream_name);
+    }
+}
+
 pub fn get_val_for_attr(attr_val: &Value) -> Value {
     let local_val = attr_val.as_object().unwrap();
     if let Some((key, value)) = local_val.into_iter().next() {
@@ -546,6 +618,42 @@ mod tests {
 
     use super::*;
 
+    fn transform(func: &str, row: Value) -> Option<Value> {
+        let program = compile_vrl_function(func, "default").unwrap().program;
+        let mut runtime = init_functions_runtime();
+        apply_ingest_transform(&mut runtime, &program, row, "default", "test")
+    }
+
+    #[test]
+    fn test_ingest_transform_rename() {
+        let row = json!({"msg": "hello", "level": "info"});
+        let res = transform(".message = del(.msg)", row).unwrap();
+        assert_eq!(res, json!({"message": "hello", "level": "info"}));
+    }
+
+    #[test]
+    fn test_ingest_transform_redact() {
+        let row = json!({"message": "user a@b.com paid with 4111-1111-1111-1111"});
+        let func = r#".message = replace(string!(.message), r'\d{4}-\d{4}-\d{4}-\d{4}', "[REDACTED]")
+.message = replace(.message, r'[\w.]+@[\w.]+', "[EMAIL]")"#;
+        let res = transform(func, row).unwrap();
+        assert_eq!(res, json!({"message": "user [EMAIL] paid with [REDACTED]"}));
+    }
+
+    #[test]
+    fn test_ingest_transform_drop() {
+        let func = r#"if .level == "debug" { abort }
+.kept = true"#;
+        assert_eq!(transform(func, json!({"level": "debug"})), None);
+        assert_eq!(
+            transform(func, json!({"level": "info"})),
+            Some(json!({"level": "info", "kept": true}))
+        );
+        // a failing program keeps the original record
+        let row = json!({"a": "x"});
+        assert_eq!(transform(".b = to_int!(.a)", row.clone()), Some(row));
+    }
+
     #[test]
     fn test_format_partition_key() {
         assert_eq!(format_partition_key("default/olympics"), "defaultolympics");
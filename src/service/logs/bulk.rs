@@ -37,9 +37,10 @@ use crate::{
     common::meta::ingestion::{BulkResponse, BulkResponseError, BulkResponseItem, IngestionStatus},
     service::{
         format_stream_name,
-        ingestion::check_ingestion_allowed,
+        ingestion::{self, check_ingestion_allowed},
         pipeline::batch_execution::{ExecutablePipeline, ExecutablePipelineBulkInputs},
         schema::get_upto_discard_error,
+        stream,
     },
 };
 
@@ -132,6 +133,7 @@ pub async fn ingest(
             if !cfg.common.skip_formatting_stream_name {
                 stream_name = format_stream_name(&stream_name);
             }
+            stream_name = stream::resolve_alias(org_id, StreamType::Logs, &stream_name);
 
             // skip blocked streams
             let key = format!("{org_id}/{}/{stream_name}", StreamType::Logs);
@@ -482,6 +484,33 @@ pub async fn ingest(
     drop(streams_need_original_set);
     drop(user_defined_schema_map);
 
+    // reject the records of the streams whose schema conflicts with them
+    ingestion::reject_incompatible_records(
+        org_id,
+        StreamType::Logs,
+        &mut json_data_by_stream,
+        |(json_data, _)| json_data.iter().map(|(_, v)| v).collect(),
+        |stream_name, (json_data, _), e| {
+            bulk_res.errors = true;
+            for (_, record) in json_data.iter() {
+                let doc_id = record
+                    .get("_id")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string());
+                add_record_status(
+                    stream_name.to_string(),
+                    &doc_id,
+                    "".to_string(),
+                    None,
+                    &mut bulk_res,
+                    Some(SCHEMA_CONFORMANCE_FAILED.to_string()),
+                    Some(e.to_string()),
+                );
+            }
+        },
+    )
+    .await;
+
     let (metric_rpt_status_code, response_body) = {
         let mut status = IngestionStatus::Bulk(bulk_res);
         let write_result = super::write_logs_by_stream(
@@ -0,0 +1,449 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of the Fluentd forward protocol used by the `forward` output of
+//! Fluent Bit, see <https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1>
+
+use std::io::{Cursor, Read};
+
+use actix_web::web;
+use anyhow::{anyhow, Result};
+use config::{get_config, utils::json};
+use flate2::read::MultiGzDecoder;
+use rmpv::Value;
+
+use crate::common::meta::ingestion::{IngestionRequest, IngestionResponse};
+
+/// Records of one forward protocol message, all with the same tag.
+#[derive(Debug)]
+pub struct ForwardMessage {
+    pub tag: String,
+    pub records: Vec<json::Value>,
+    /// Set when the client asks for an acknowledgement.
+    pub chunk: Option<String>,
+}
+
+/// Finds where the msgpack messages of a connection end, keeping the offset
+/// scanned so far so a message received over many reads is scanned once.
+#[derive(Debug, Default)]
+pub struct FrameScanner {
+    pos: usize,
+    // the number of values left in each array or map being scanned
+    pending: Vec<u64>,
+}
+
+impl FrameScanner {
+    /// Returns the size of the message at the beginning of `buf`, or `None` if
+    /// `buf` doesn't hold a whole message yet. `buf` must only grow between
+    /// calls until a size is returned, then the message must be removed from
+    /// it.
+    pub fn next_frame(&mut self, buf: &[u8], max_size: usize) -> Result<Option<usize>> {
+        loop {
+            let Some((header, len, items)) = value_header(&buf[self.pos..])? else {
+                return Ok(None);
+            };
+            if self.pos + header + len > max_size {
+                return Err(anyhow!("message is larger than {max_size} bytes"));
+            }
+            if buf.len() < self.pos + header + len {
+                return Ok(None);
+            }
+            self.pos += header + len;
+            if items > 0 {
+                self.pending.push(items);
+                continue;
+            }
+            // a value is complete, close the arrays and maps it completes
+            loop {
+                match self.pending.last_mut() {
+                    None => {
+                        let size = self.pos;
+                        self.pos = 0;
+                        return Ok(Some(size));
+                    }
+                    Some(n) => {
+                        *n -= 1;
+                        if *n > 0 {
+                            break;
+                        }
+                        self.pending.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the size of the header, the size of the data and the number of
+/// nested values of the msgpack value at the beginning of `buf`, or `None` if
+/// `buf` doesn't hold the whole header yet.
+fn value_header(buf: &[u8]) -> Result<Option<(usize, usize, u64)>> {
+    let Some(&marker) = buf.first() else {
+        return Ok(None);
+    };
+    // the size of the length field following the marker
+    let uint = |n: usize| -> Option<u64> {
+        let data = buf.get(1..1 + n)?;
+        Some(data.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+    };
+    let header = match marker {
+        0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => Some((1, 0, 0)),
+        0x80..=0x8f => Some((1, 0, (marker & 0x0f) as u64 * 2)),
+        0x90..=0x9f => Some((1, 0, (marker & 0x0f) as u64)),
+        0xa0..=0xbf => Some((1, (marker & 0x1f) as usize, 0)),
+        0xc4 | 0xd9 => uint(1).map(|n| (2, n as usize, 0)),
+        0xc5 | 0xda => uint(2).map(|n| (3, n as usize, 0)),
+        0xc6 | 0xdb => uint(4).map(|n| (5, n as usize, 0)),
+        0xc7 => uint(1).map(|n| (3, n as usize, 0)),
+        0xc8 => uint(2).map(|n| (4, n as usize, 0)),
+        0xc9 => uint(4).map(|n| (6, n as usize, 0)),
+        0xca => Some((1, 4, 0)),
+        0xcb => Some((1, 8, 0)),
+        0xcc | 0xd0 => Some((1, 1, 0)),
+        0xcd | 0xd1 => Some((1, 2, 0)),
+        0xce | 0xd2 => Some((1, 4, 0)),
+        0xcf | 0xd3 => Some((1, 8, 0)),
+        0xd4 => Some((2, 1, 0)),
+        0xd5 => Some((2, 2, 0)),
+        0xd6 => Some((2, 4, 0)),
+        0xd7 => Some((2, 8, 0)),
+        0xd8 => Some((2, 16, 0)),
+        0xdc => uint(2).map(|n| (3, 0, n)),
+        0xdd => uint(4).map(|n| (5, 0, n)),
+        0xde => uint(2).map(|n| (3, 0, n * 2)),
+        0xdf => uint(4).map(|n| (5, 0, n * 2)),
+        0xc1 => return Err(anyhow!("invalid msgpack marker: 0xc1")),
+    };
+    Ok(header)
+}
+
+/// Decodes a whole message found by [`FrameScanner`], the decompressed
+/// entries of a message are limited to `max_size` bytes.
+pub fn decode(buf: &[u8], max_size: usize) -> Result<ForwardMessage> {
+    let value = rmpv::decode::read_value(&mut Cursor::new(buf))
+        .map_err(|e| anyhow!("invalid msgpack: {e}"))?;
+    decode_message(value, max_size)
+}
+
+fn decode_message(value: Value, max_size: usize) -> Result<ForwardMessage> {
+    let Value::Array(mut items) = value else {
+        return Err(anyhow!("message is not an array"));
+    };
+    if items.len() < 2 {
+        return Err(anyhow!("message has {} items", items.len()));
+    }
+    let tag = items[0]
+        .as_str()
+        .ok_or_else(|| anyhow!("tag is not a string"))?
+        .to_string();
+    let option = match items.len() {
+        // [tag, time, record, option]
+        4 => items.pop(),
+        // [tag, entries, option] or [tag, time, record]
+        3 if items[2].is_map() && !items[1].is_ext() && !items[1].is_number() => items.pop(),
+        _ => None,
+    };
+    let option = option.unwrap_or(Value::Nil);
+    let chunk = get_option(&option, "chunk").map(|v| v.to_string());
+
+    let records = match items.len() {
+        // Message mode: [tag, time, record]
+        3 => {
+            let record = items.pop().unwrap();
+            vec![decode_entry(&items[1], record)?]
+        }
+        2 => match items.pop().unwrap() {
+            // Forward mode: [tag, [[time, record], ...]]
+            Value::Array(entries) => entries
+                .into_iter()
+                .map(decode_entry_array)
+                .collect::<Result<Vec<_>>>()?,
+            // PackedForward and CompressedPackedForward mode: [tag, msgpack stream]
+            Value::Binary(data) => decode_packed_entries(&data, &option, max_size)?,
+            Value::String(data) => decode_packed_entries(data.as_bytes(), &option, max_size)?,
+            _ => return Err(anyhow!("invalid entries of tag {tag}")),
+        },
+        n => return Err(anyhow!("message has {n} items")),
+    };
+    Ok(ForwardMessage {
+        tag,
+        records,
+        chunk,
+    })
+}
+
+fn get_option<'a>(option: &'a Value, key: &str) -> Option<&'a str> {
+    option
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .and_then(|(_, v)| v.as_str())
+}
+
+fn decode_packed_entries(data: &[u8], option: &Value, max_size: usize) -> Result<Vec<json::Value>> {
+    let mut decompressed = Vec::new();
+    let data = match get_option(option, "compressed") {
+        Some("gzip") => {
+            MultiGzDecoder::new(data)
+                .take(max_size as u64 + 1)
+                .read_to_end(&mut decompressed)?;
+            if decompressed.len() > max_size {
+                return Err(anyhow!(
+                    "decompressed entries are larger than {max_size} bytes"
+                ));
+            }
+            &decompressed[..]
+        }
+        Some(v) => return Err(anyhow!("unsupported compression: {v}")),
+        None => data,
+    };
+    let mut cursor = Cursor::new(data);
+    let mut records = Vec::new();
+    while (cursor.position() as usize) < data.len() {
+        let entry = rmpv::decode::read_value(&mut cursor)
+            .map_err(|e| anyhow!("invalid packed entry: {e}"))?;
+        records.push(decode_entry_array(entry)?);
+    }
+    Ok(records)
+}
+
+fn decode_entry_array(entry: Value) -> Result<json::Value> {
+    let Value::Array(mut entry) = entry else {
+        return Err(anyhow!("entry is not an array"));
+    };
+    if entry.len() != 2 {
+        return Err(anyhow!("entry has {} items", entry.len()));
+    }
+    let record = entry.pop().unwrap();
+    decode_entry(&entry[0], record)
+}
+
+/// Converts an entry to a json record with the time as `_timestamp`.
+fn decode_entry(time: &Value, record: Value) -> Result<json::Value> {
+    let timestamp = decode_time(time)?;
+    let json::Value::Object(mut record) = to_json(record) else {
+        return Err(anyhow!("record is not a map"));
+    };
+    record.insert(
+        get_config().common.column_timestamp.clone(),
+        json::Value::Number(timestamp.into()),
+    );
+    Ok(json::Value::Object(record))
+}
+
+/// Returns the time in microseconds, the time is either an integer of
+/// seconds or an EventTime extension with nanoseconds.
+fn decode_time(time: &Value) -> Result<i64> {
+    match time {
+        Value::Ext(0, data) if data.len() == 8 => {
+            let secs = u32::from_be_bytes(data[..4].try_into().unwrap()) as i64;
+            let nanos = u32::from_be_bytes(data[4..].try_into().unwrap()) as i64;
+            Ok(secs * 1_000_000 + nanos / 1_000)
+        }
+        Value::Integer(v) => v
+            .as_i64()
+            .map(|v| v * 1_000_000)
+            .ok_or_else(|| anyhow!("invalid time: {v}")),
+        Value::F32(v) => Ok((*v as f64 * 1_000_000.0) as i64),
+        Value::F64(v) => Ok((v * 1_000_000.0) as i64),
+        v => Err(anyhow!("invalid time: {v}")),
+    }
+}
+
+fn to_json(value: Value) -> json::Value {
+    match value {
+        Value::Nil => json::Value::Null,
+        Value::Boolean(v) => json::Value::Bool(v),
+        Value::Integer(v) => match (v.as_i64(), v.as_u64()) {
+            (Some(v), _) => v.into(),
+            (_, Some(v)) => v.into(),
+            _ => json::Value::Null,
+        },
+        Value::F32(v) => json::Number::from_f64(v as f64).map_or(json::Value::Null, Into::into),
+        Value::F64(v) => json::Number::from_f64(v).map_or(json::Value::Null, Into::into),
+        Value::String(v) => String::from_utf8_lossy(v.as_bytes()).into_owned().into(),
+        Value::Binary(v) => String::from_utf8_lossy(&v).into_owned().into(),
+        Value::Array(v) => json::Value::Array(v.into_iter().map(to_json).collect()),
+        Value::Map(v) => json::Value::Object(
+            v.into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Value::String(k) => String::from_utf8_lossy(k.as_bytes()).into_owned(),
+                        k => k.to_string(),
+                    };
+                    (key, to_json(v))
+                })
+                .collect(),
+        ),
+        Value::Ext(..) => json::Value::Null,
+    }
+}
+
+/// The response to a message with the `chunk` option.
+pub fn encode_ack(chunk: &str) -> Vec<u8> {
+    let ack = Value::Map(vec![(Value::from("ack"), Value::from(chunk))]);
+    let mut buf = Vec::new();
+    rmpv::encode::write_value(&mut buf, &ack).unwrap();
+    buf
+}
+
+/// Ingests the records of a message into the stream named by its tag.
+pub async fn ingest(org_id: &str, message: ForwardMessage) -> Result<IngestionResponse> {
+    let data = web::Bytes::from(json::to_vec(&message.records)?);
+    super::ingest::ingest(
+        0,
+        org_id,
+        &message.tag,
+        IngestionRequest::JSON(&data),
+        "",
+        None,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmpv::encode::write_value(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn decode_one(buf: &[u8]) -> (ForwardMessage, usize) {
+        let n = FrameScanner::default()
+            .next_frame(buf, 1024 * 1024)
+            .unwrap()
+            .unwrap();
+        (decode(&buf[..n], 1024 * 1024).unwrap(), n)
+    }
+
+    fn record(n: i64) -> Value {
+        Value::Map(vec![(Value::from("log"), Value::from(format!("line {n}")))])
+    }
+
+    fn event_time(secs: u32, nanos: u32) -> Value {
+        let mut data = secs.to_be_bytes().to_vec();
+        data.extend_from_slice(&nanos.to_be_bytes());
+        Value::Ext(0, data)
+    }
+
+    #[test]
+    fn test_decode_message_mode() {
+        let buf = encode(&Value::Array(vec![
+            Value::from("app.logs"),
+            Value::from(1_700_000_000),
+            record(1),
+            Value::Map(vec![(Value::from("chunk"), Value::from("abc"))]),
+        ]));
+        let (message, n) = decode_one(&buf);
+        assert_eq!(n, buf.len());
+        assert_eq!(message.tag, "app.logs");
+        assert_eq!(message.chunk.as_deref(), Some("abc"));
+        assert_eq!(message.records.len(), 1);
+        assert_eq!(message.records[0]["log"], "line 1");
+        assert_eq!(message.records[0]["_timestamp"], 1_700_000_000_000_000i64);
+    }
+
+    #[test]
+    fn test_decode_forward_mode() {
+        let entries = (0..3)
+            .map(|i| Value::Array(vec![event_time(1_700_000_000, 5_000), record(i)]))
+            .collect();
+        let buf = encode(&Value::Array(vec![
+            Value::from("app"),
+            Value::Array(entries),
+        ]));
+        let (message, _) = decode_one(&buf);
+        assert_eq!(message.records.len(), 3);
+        assert_eq!(message.chunk, None);
+        assert_eq!(message.records[2]["_timestamp"], 1_700_000_000_000_005i64);
+    }
+
+    #[test]
+    fn test_decode_packed_forward_mode() {
+        let mut entries = Vec::new();
+        for i in 0..5 {
+            entries.extend(encode(&Value::Array(vec![Value::from(1), record(i)])));
+        }
+        let buf = encode(&Value::Array(vec![
+            Value::from("app"),
+            Value::Binary(entries.clone()),
+        ]));
+        let (message, _) = decode_one(&buf);
+        assert_eq!(message.records.len(), 5);
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&entries).unwrap();
+        let buf = encode(&Value::Array(vec![
+            Value::from("app"),
+            Value::Binary(gz.finish().unwrap()),
+            Value::Map(vec![(Value::from("compressed"), Value::from("gzip"))]),
+        ]));
+        let (message, _) = decode_one(&buf);
+        assert_eq!(message.records.len(), 5);
+        assert_eq!(message.records[4]["log"], "line 4");
+    }
+
+    #[test]
+    fn test_decode_gzip_limit() {
+        let mut entries = Vec::new();
+        for i in 0..100 {
+            entries.extend(encode(&Value::Array(vec![Value::from(1), record(i)])));
+        }
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&entries).unwrap();
+        let buf = encode(&Value::Array(vec![
+            Value::from("app"),
+            Value::Binary(gz.finish().unwrap()),
+            Value::Map(vec![(Value::from("compressed"), Value::from("gzip"))]),
+        ]));
+        assert!(decode(&buf, buf.len()).is_err());
+        assert_eq!(decode(&buf, entries.len()).unwrap().records.len(), 100);
+    }
+
+    #[test]
+    fn test_frame_scanner() {
+        let mut buf = encode(&Value::Array(vec![
+            Value::from("app"),
+            Value::Array(vec![
+                Value::Array(vec![event_time(1_700_000_000, 0), record(1)]),
+                Value::Array(vec![Value::from(1), Value::Map(vec![])]),
+            ]),
+            Value::Map(vec![(Value::from("chunk"), Value::from("abc"))]),
+        ]));
+        let len = buf.len();
+        // the next message follows
+        buf.extend_from_slice(&buf.clone());
+
+        // the message arrives one byte at a time
+        let mut scanner = FrameScanner::default();
+        for i in 1..len {
+            assert!(scanner.next_frame(&buf[..i], 1024).unwrap().is_none());
+        }
+        assert_eq!(scanner.next_frame(&buf, 1024).unwrap(), Some(len));
+        assert_eq!(decode(&buf[..len], 1024).unwrap().records.len(), 2);
+        assert_eq!(scanner.next_frame(&buf[len..], 1024).unwrap(), Some(len));
+
+        assert!(FrameScanner::default().next_frame(&buf, len - 1).is_err());
+        assert!(FrameScanner::default().next_frame(&[0xc1], 1024).is_err());
+        assert!(decode(&encode(&Value::from(1)), 1024).is_err());
+    }
+}
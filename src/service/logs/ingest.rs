@@ -48,8 +48,11 @@ use crate::{
         StreamStatus,
     },
     service::{
-        format_stream_name, get_formatted_stream_name, ingestion::check_ingestion_allowed,
-        logs::bulk::TRANSFORM_FAILED, schema::get_upto_discard_error,
+        format_stream_name, get_formatted_stream_name,
+        ingestion::check_ingestion_allowed,
+        logs::bulk::TRANSFORM_FAILED,
+        schema::{check_schema_compatibility, get_upto_discard_error},
+        stream,
     },
 };
 
@@ -74,6 +77,7 @@ pub async fn ingest(
     } else {
         format_stream_name(in_stream_name)
     };
+    let stream_name = stream::resolve_alias(org_id, StreamType::Logs, &stream_name);
     check_ingestion_allowed(org_id, Some(&stream_name))?;
 
     let min_ts = (Utc::now() - Duration::try_hours(cfg.limit.ingest_allowed_upto).unwrap())
@@ -110,17 +114,22 @@ pub async fn ingest(
 
     let json_req: Vec<json::Value>; // to hold json request because of borrow checker
     let (endpoint, usage_type, data) = match in_req {
-        IngestionRequest::JSON(req) => {
-            json_req = json::from_slice(req).unwrap_or({
-                let val: json::Value = json::from_slice(req)?;
-                vec![val]
-            });
-            (
+        IngestionRequest::JSON(req) => match parse_json_body(req)? {
+            Some(v) => {
+                json_req = v;
+                (
+                    "/api/org/ingest/logs/_json",
+                    UsageType::Json,
+                    IngestionData::JSON(&json_req),
+                )
+            }
+            // newline delimited json is read line by line like _multi
+            None => (
                 "/api/org/ingest/logs/_json",
                 UsageType::Json,
-                IngestionData::JSON(&json_req),
-            )
-        }
+                IngestionData::Multi(req),
+            ),
+        },
         IngestionRequest::Multi(req) => (
             "/api/org/ingest/logs/_multi",
             UsageType::Multi,
@@ -352,6 +361,17 @@ pub async fn ingest(
         ));
     }
 
+    // reject the records which conflict with the stream schema
+    for (stream_name, (json_data, _)) in json_data_by_stream.iter() {
+        check_schema_compatibility(
+            org_id,
+            stream_name,
+            StreamType::Logs,
+            json_data.iter().map(|(_, v)| v).collect(),
+        )
+        .await?;
+    }
+
     // drop memory-intensive variables
     drop(streams_need_original_set);
     drop(executable_pipeline);
@@ -434,6 +454,19 @@ pub fn handle_timestamp(
     Ok(timestamp)
 }
 
+/// Parses the body of `_json`, a json array or a single object. Returns `None`
+/// if the body is newline delimited json, as sent by many log shippers.
+fn parse_json_body(body: &[u8]) -> Result<Option<Vec<json::Value>>, json::Error> {
+    if let Ok(v) = json::from_slice::<Vec<json::Value>>(body) {
+        return Ok(Some(v));
+    }
+    match json::from_slice::<json::Value>(body) {
+        Ok(v) => Ok(Some(vec![v])),
+        Err(_) if body.trim_ascii_start().starts_with(b"{") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 impl Iterator for IngestionDataIter<'_> {
     type Item = Result<json::Value, IngestionError>;
 
@@ -825,11 +858,39 @@ fn construct_values_from_open_telemetry_v1_metric(
 
 #[cfg(test)]
 mod tests {
+    use std::io::BufRead;
+
+    use config::utils::json;
+
     use super::{
         decode_and_decompress_to_string, decode_and_decompress_to_vec,
         deserialize_aws_record_from_vec, extract_resource_id_from_amazon_resource_number,
-        get_size_of_var_int_header,
+        get_size_of_var_int_header, parse_json_body,
     };
+    use crate::common::meta::ingestion::IngestionDataIter;
+
+    #[test]
+    fn test_parse_json_body() {
+        let body = br#"[{"a":1},{"a":2}]"#;
+        assert_eq!(parse_json_body(body).unwrap().unwrap().len(), 2);
+        let body = b"{\n  \"a\": 1\n}\n";
+        assert_eq!(parse_json_body(body).unwrap().unwrap().len(), 1);
+        assert!(parse_json_body(b"[{\"a\":1}").is_err());
+
+        // newline delimited json with escaped newlines in the values
+        let mut body = Vec::new();
+        for i in 0..10000 {
+            let line = json::json!({"i": i, "log": format!("line {i}\nstack\n\tat main")});
+            body.extend(json::to_vec(&line).unwrap());
+            body.push(b'\n');
+        }
+        assert!(parse_json_body(&body).unwrap().is_none());
+        let records = IngestionDataIter::MultiIter(std::io::BufReader::new(&body[..]).lines())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 10000);
+        assert_eq!(records[42]["log"], "line 42\nstack\n\tat main");
+    }
 
     #[test]
     fn test_decode_and_decompress_success_string() {
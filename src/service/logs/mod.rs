@@ -28,20 +28,25 @@ use config::{
     meta::{
         alerts::alert::Alert,
         self_reporting::usage::{RequestStats, UsageType},
-        stream::{PartitionTimeLevel, StreamParams, StreamPartition, StreamType},
+        stream::{PartitionTimeLevel, StreamParams, StreamPartition, StreamSettings, StreamType},
     },
     metrics,
     utils::{
+        flatten,
         json::{estimate_json_bytes, get_string_value, pickup_string_value, Map, Value},
         schema_ext::SchemaExt,
     },
-    DISTINCT_FIELDS,
+    DISTINCT_FIELDS, ID_COL_NAME, ORIGINAL_DATA_COL_NAME,
 };
 use infra::schema::{unwrap_partition_time_level, SchemaCache};
 
 use super::{
     db::organization::get_org_setting,
-    ingestion::{evaluate_trigger, write_file, TriggerAlertData},
+    enrichment_table::geoip,
+    ingestion::{
+        self, compile_vrl_function, evaluate_trigger, init_functions_runtime, lua::LuaTransform,
+        write_file, TriggerAlertData,
+    },
     metadata::{
         distinct_values::{DvItem, DISTINCT_STREAM_PREFIX},
         write, MetadataItem, MetadataType,
@@ -49,7 +54,13 @@ use super::{
     schema::stream_schema_exists,
 };
 use crate::{
-    common::meta::{ingestion::IngestionStatus, stream::SchemaRecords},
+    common::{
+        infra::config::{GEOIP_ASN_TABLE, GEOIP_CITY_TABLE},
+        meta::{
+            ingestion::{IngestionStatus, RecordStatus},
+            stream::SchemaRecords,
+        },
+    },
     service::{
         alerts::alert::AlertExt, db, ingestion::get_write_partition_key, schema::check_for_schema,
         self_reporting::report_request_usage_stats,
@@ -57,10 +68,13 @@ use crate::{
 };
 
 pub mod bulk;
+pub mod fluent_forward;
 pub mod ingest;
 pub mod otlp_grpc;
 pub mod otlp_http;
 pub mod syslog;
+pub mod tail;
+pub mod vector;
 
 static BULK_OPERATORS: [&str; 3] = ["create", "index", "update"];
 
@@ -195,6 +209,26 @@ fn set_parsing_error(parse_error: &mut String, field: &Field) {
     ));
 }
 
+/// Drops the records of the streams whose schema conflicts with them, see
+/// [`ingestion::reject_incompatible_records`], and counts them as failed.
+async fn reject_incompatible_records(
+    org_id: &str,
+    status: &mut RecordStatus,
+    json_data_by_stream: &mut HashMap<String, O2IngestJsonData>,
+) {
+    ingestion::reject_incompatible_records(
+        org_id,
+        StreamType::Logs,
+        json_data_by_stream,
+        |(json_data, _)| json_data.iter().map(|(_, v)| v).collect(),
+        |_, (json_data, _), e| {
+            status.failed += json_data.len() as u32;
+            status.error = e.to_string();
+        },
+    )
+    .await;
+}
+
 async fn write_logs_by_stream(
     thread_id: usize,
     org_id: &str,
@@ -262,7 +296,7 @@ async fn write_logs(
     org_id: &str,
     stream_name: &str,
     status: &mut IngestionStatus,
-    json_data: Vec<(i64, Map<String, Value>)>,
+    mut json_data: Vec<(i64, Map<String, Value>)>,
 ) -> Result<RequestStats> {
     let cfg = get_config();
     let log_ingest_errors = ingestion_log_enabled().await;
@@ -280,6 +314,40 @@ async fn write_logs(
         .await
         .unwrap_or_default();
 
+    // apply the ingest transforms, the dropped records are accepted but not written
+    if stream_settings.ingest_transform.is_some() || stream_settings.ingest_lua_transform.is_some()
+    {
+        let (records, dropped) =
+            apply_ingest_transform(org_id, stream_name, &stream_settings, json_data);
+        json_data = records;
+        for doc_id in dropped {
+            match status {
+                IngestionStatus::Record(status) => status.successful += 1,
+                IngestionStatus::Bulk(bulk_res) => bulk::add_record_status(
+                    stream_name.to_string(),
+                    &doc_id,
+                    "".to_string(),
+                    None,
+                    bulk_res,
+                    None,
+                    None,
+                ),
+            }
+        }
+        if json_data.is_empty() {
+            return Ok(RequestStats::default());
+        }
+    }
+
+    // enrich the records with the geo info of the ip address
+    if let Some(field) = stream_settings.geoip_field.as_deref() {
+        let city = GEOIP_CITY_TABLE.read();
+        let asn = GEOIP_ASN_TABLE.read();
+        for (_, record) in json_data.iter_mut() {
+            geoip::enrich_record(city.as_ref(), asn.as_ref(), field, record);
+        }
+    }
+
     let mut partition_keys: Vec<StreamPartition> = vec![];
     let mut partition_time_level = PartitionTimeLevel::from(cfg.limit.logs_file_retention.as_str());
     if stream_schema.has_partition_keys {
@@ -502,6 +570,9 @@ async fn write_logs(
         }
     }
 
+    // send the records to the live tails of the stream
+    tail::publish(org_id, stream_name, &write_buf);
+
     // write data to wal
     let writer = ingester::get_writer(
         thread_id,
@@ -531,6 +602,113 @@ async fn write_logs(
     Ok(req_stats)
 }
 
+/// Applies the ingest transforms of the stream to the records, the VRL
+/// program first and then the Lua script. Returns the kept records and the
+/// `_id` of the dropped records. The records are flattened again and keep
+/// their original timestamp. The transforms don't see `_original`, it is
+/// rebuilt from the transformed record so redacted values don't leak into it.
+fn apply_ingest_transform(
+    org_id: &str,
+    stream_name: &str,
+    settings: &StreamSettings,
+    json_data: Vec<(i64, Map<String, Value>)>,
+) -> (Vec<(i64, Map<String, Value>)>, Vec<Option<String>>) {
+    let program = match settings
+        .ingest_transform
+        .as_deref()
+        .map(|func| compile_vrl_function(func, org_id))
+    {
+        Some(Ok(vrl)) => Some(vrl.program),
+        Some(Err(e)) => {
+            log::error!("{org_id}/{stream_name} compile ingest transform error: {e}");
+            None
+        }
+        None => None,
+    };
+    let lua = match settings
+        .ingest_lua_transform
+        .as_deref()
+        .map(LuaTransform::new)
+    {
+        Some(Ok(lua)) => Some(lua),
+        Some(Err(e)) => {
+            log::error!("{org_id}/{stream_name} load ingest lua transform error: {e}");
+            None
+        }
+        None => None,
+    };
+    if program.is_none() && lua.is_none() {
+        return (json_data, vec![]);
+    }
+
+    let cfg = get_config();
+    let mut runtime = init_functions_runtime();
+    let mut records = Vec::with_capacity(json_data.len());
+    let mut dropped = Vec::new();
+    for (timestamp, mut record) in json_data {
+        let doc_id = record
+            .get("_id")
+            .and_then(|v| v.as_str().map(|v| v.to_string()));
+        let original = record.remove(ORIGINAL_DATA_COL_NAME);
+        let record_id = record.remove(ID_COL_NAME);
+        let mut value = Value::Object(record);
+        if let Some(program) = program.as_ref() {
+            match crate::service::ingestion::apply_ingest_transform(
+                &mut runtime,
+                program,
+                value,
+                org_id,
+                stream_name,
+            ) {
+                Some(v) => value = v,
+                None => {
+                    dropped.push(doc_id);
+                    continue;
+                }
+            }
+        }
+        if let Some(lua) = lua.as_ref() {
+            match lua.apply(&value) {
+                Ok(Some(v)) => value = v,
+                Ok(None) => {
+                    dropped.push(doc_id);
+                    continue;
+                }
+                Err(e) => {
+                    metrics::INGEST_ERRORS
+                        .with_label_values(&[
+                            org_id,
+                            StreamType::Logs.to_string().as_str(),
+                            stream_name,
+                            bulk::TRANSFORM_FAILED,
+                        ])
+                        .inc();
+                    log::warn!(
+                        "{org_id}/{stream_name} ingest lua transform failed: {e}. Returning original row."
+                    );
+                }
+            }
+        }
+        let original = original.map(|_| Value::String(value.to_string()));
+        let mut record = match flatten::flatten_with_level(value, cfg.limit.ingest_flatten_level) {
+            Ok(Value::Object(record)) => record,
+            _ => {
+                dropped.push(doc_id);
+                continue;
+            }
+        };
+        record.insert(cfg.common.column_timestamp.clone(), timestamp.into());
+        if let Some(original) = original {
+            record.insert(ORIGINAL_DATA_COL_NAME.to_string(), original);
+        }
+        if let Some(record_id) = record_id {
+            record.insert(ID_COL_NAME.to_string(), record_id);
+        }
+        records.push((timestamp, record));
+    }
+    (records, dropped)
+}
+
 pub fn refactor_map(
     original_map: Map<String, Value>,
     defined_schema_keys: &HashSet<String>,
@@ -608,4 +786,31 @@ mod tests {
         let ret_val = cast_to_type(&mut local_val, delta);
         assert!(ret_val.is_ok());
     }
+
+    #[test]
+    fn test_apply_ingest_transform_original() {
+        let settings = StreamSettings {
+            ingest_transform: Some(
+                r#".message = replace(string!(.message), r'\d{4}-\d{4}', "[REDACTED]")"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let mut record = Map::new();
+        record.insert("message".to_string(), Value::from("card 1234-5678"));
+        record.insert(
+            ORIGINAL_DATA_COL_NAME.to_string(),
+            Value::from(r#"{"message":"card 1234-5678"}"#),
+        );
+        record.insert(ID_COL_NAME.to_string(), Value::from("7"));
+        let (records, dropped) =
+            apply_ingest_transform("default", "logs", &settings, vec![(1, record)]);
+        assert!(dropped.is_empty());
+        let record = &records[0].1;
+        assert_eq!(record["message"], "card [REDACTED]");
+        assert_eq!(record[ID_COL_NAME], "7");
+        let original = record[ORIGINAL_DATA_COL_NAME].as_str().unwrap();
+        assert!(original.contains("card [REDACTED]"));
+        assert!(!original.contains("1234-5678"));
+    }
 }
@@ -47,6 +47,7 @@ use crate::{
         },
         logs::bulk::TRANSFORM_FAILED,
         schema::get_upto_discard_error,
+        stream,
     },
 };
 
@@ -66,6 +67,7 @@ pub async fn handle_grpc_request(
         Some(name) => format_stream_name(name),
         None => "default".to_owned(),
     };
+    let stream_name = stream::resolve_alias(org_id, StreamType::Logs, &stream_name);
     check_ingestion_allowed(org_id, Some(&stream_name))?;
 
     let cfg = get_config();
@@ -342,6 +344,10 @@ pub async fn handle_grpc_request(
     drop(timestamps);
     drop(user_defined_schema_map);
 
+    // reject the records which conflict with the stream schema
+    super::reject_incompatible_records(org_id, &mut stream_status.status, &mut json_data_by_stream)
+        .await;
+
     // Update partial success
     if stream_status.status.failed > 0 {
         res.partial_success = Some(ExportLogsPartialSuccess {
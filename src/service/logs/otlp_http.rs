@@ -47,6 +47,7 @@ use crate::{
         ingestion::{check_ingestion_allowed, get_val_for_attr},
         logs::bulk::TRANSFORM_FAILED,
         schema::get_upto_discard_error,
+        stream,
     },
 };
 
@@ -60,7 +61,8 @@ pub async fn logs_proto_handler(
     in_stream_name: Option<&str>,
     user_email: &str,
 ) -> Result<HttpResponse> {
-    let request = ExportLogsServiceRequest::decode(body).expect("Invalid protobuf");
+    let request = ExportLogsServiceRequest::decode(body)
+        .map_err(|e| anyhow::anyhow!("Invalid protobuf: {}", e))?;
     match super::otlp_grpc::handle_grpc_request(
         thread_id,
         org_id,
@@ -103,6 +105,7 @@ pub async fn logs_json_handler(
         Some(name) => format_stream_name(name),
         None => "default".to_owned(),
     };
+    let stream_name = stream::resolve_alias(org_id, StreamType::Logs, &stream_name);
     check_ingestion_allowed(org_id, Some(&stream_name))?;
 
     let min_ts = (Utc::now() - Duration::try_hours(cfg.limit.ingest_allowed_upto).unwrap())
@@ -474,6 +477,10 @@ pub async fn logs_json_handler(
     drop(timestamps);
     drop(user_defined_schema_map);
 
+    // reject the records which conflict with the stream schema
+    super::reject_incompatible_records(org_id, &mut stream_status.status, &mut json_data_by_stream)
+        .await;
+
     // Update partial success
     if stream_status.status.failed > 0 {
         res.partial_success = Some(ExportLogsPartialSuccess {
@@ -0,0 +1,255 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use config::utils::{json, time::now_micros};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::broadcast;
+
+use crate::common::meta::stream::SchemaRecords;
+
+/// Records buffered for a slow subscriber before it starts skipping records,
+/// and records kept to replay to a reconnecting subscriber.
+const TAIL_CHANNEL_SIZE: usize = 1024;
+
+/// How long the channel of a stream is kept after its last subscription is
+/// dropped, so a reconnecting subscriber can catch up on the missed records.
+const TAIL_RECONNECT_WINDOW: i64 = 60 * 1_000_000;
+
+/// A record of the live tail and its id, the ids of a stream are increasing.
+pub type TailRecord = (u64, Arc<json::Value>);
+
+/// org_id -> stream_name -> the live tail channel of the log stream, only the
+/// streams with subscribers, or recently with subscribers, have a channel.
+static TAILS: Lazy<RwLock<HashMap<String, HashMap<String, Arc<Tail>>>>> =
+    Lazy::new(Default::default);
+
+struct Tail {
+    tx: broadcast::Sender<TailRecord>,
+    // the recent records and the next id, locked while sending
+    recent: Mutex<(VecDeque<TailRecord>, u64)>,
+    // when the last subscription was dropped, in microseconds
+    idle_since: Mutex<Option<i64>>,
+}
+
+impl Tail {
+    fn new() -> Self {
+        Self {
+            tx: broadcast::channel(TAIL_CHANNEL_SIZE).0,
+            // start from the current time so the ids keep increasing after the
+            // channel is recreated
+            recent: Mutex::new((VecDeque::new(), now_micros() as u64)),
+            idle_since: Mutex::new(None),
+        }
+    }
+
+    // the last subscription was dropped longer than the reconnect window ago
+    fn is_expired(&self, now: i64) -> bool {
+        self.tx.receiver_count() == 0
+            && self
+                .idle_since
+                .lock()
+                .is_some_and(|t| now - t > TAIL_RECONNECT_WINDOW)
+    }
+}
+
+/// A live tail of the records of a log stream ingested by this node.
+pub struct Subscription {
+    tail: Arc<Tail>,
+    replay: VecDeque<TailRecord>,
+    rx: broadcast::Receiver<TailRecord>,
+}
+
+impl Subscription {
+    /// Returns the records missed since the last event id first, and then
+    /// the records as they are ingested.
+    pub async fn recv(&mut self) -> Result<TailRecord, broadcast::error::RecvError> {
+        match self.replay.pop_front() {
+            Some(record) => Ok(record),
+            None => self.rx.recv().await,
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // this subscription is the last receiver
+        if self.tail.tx.receiver_count() <= 1 {
+            *self.tail.idle_since.lock() = Some(now_micros());
+        }
+    }
+}
+
+/// Subscribes to the live tail of a log stream, the buffered records after
+/// `last_event_id` are replayed first.
+pub fn subscribe(org_id: &str, stream_name: &str, last_event_id: Option<u64>) -> Subscription {
+    let tail = {
+        let mut tails = TAILS.write();
+        let tail = tails
+            .entry(org_id.to_string())
+            .or_default()
+            .entry(stream_name.to_string())
+            .or_insert_with(|| Arc::new(Tail::new()))
+            .clone();
+        // reset under the lock, so the sweep doesn't evict the tail before
+        // the subscription is created
+        *tail.idle_since.lock() = None;
+        tail
+    };
+    let recent = tail.recent.lock();
+    let replay = match last_event_id {
+        Some(last_id) => recent
+            .0
+            .iter()
+            .filter(|(id, _)| *id > last_id)
+            .cloned()
+            .collect(),
+        None => VecDeque::new(),
+    };
+    let rx = tail.tx.subscribe();
+    drop(recent);
+    Subscription { tail, replay, rx }
+}
+
+/// Sends the records about to be written to the wal to the subscribers of
+/// the stream.
+pub fn publish(org_id: &str, stream_name: &str, buf: &HashMap<String, SchemaRecords>) {
+    let Some(tail) = TAILS
+        .read()
+        .get(org_id)
+        .and_then(|streams| streams.get(stream_name))
+        .cloned()
+    else {
+        return;
+    };
+    let mut recent = tail.recent.lock();
+    for record in buf.values().flat_map(|entry| entry.records.iter()) {
+        let id = recent.1;
+        recent.1 += 1;
+        if recent.0.len() >= TAIL_CHANNEL_SIZE {
+            recent.0.pop_front();
+        }
+        recent.0.push_back((id, record.clone()));
+        // no subscriber at the moment, the record is kept for reconnecting
+        let _ = tail.tx.send((id, record.clone()));
+    }
+}
+
+/// Drops the channels of the streams without subscribers for longer than the
+/// reconnect window, called periodically by the tail job.
+pub fn evict_idle() {
+    let now = now_micros();
+    let mut tails = TAILS.write();
+    tails.retain(|_, streams| {
+        streams.retain(|_, tail| !tail.is_expired(now));
+        !streams.is_empty()
+    });
+}
+
+/// Checks the `_log` field of the record contains the filter, an empty filter
+/// matches all the records.
+pub fn matches(record: &json::Value, filter: &str) -> bool {
+    filter.is_empty()
+        || record
+            .get("_log")
+            .and_then(|v| v.as_str())
+            .is_some_and(|v| v.contains(filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::Schema;
+
+    use super::*;
+
+    fn new_buf(records: Vec<json::Value>) -> HashMap<String, SchemaRecords> {
+        HashMap::from([(
+            "2024_01_01_00".to_string(),
+            SchemaRecords {
+                schema_key: "".to_string(),
+                schema: Arc::new(Schema::empty()),
+                records: records.into_iter().map(Arc::new).collect(),
+                records_size: 0,
+            },
+        )])
+    }
+
+    #[tokio::test]
+    async fn test_tail_subscription() {
+        let mut sub = subscribe("org_tail", "default", None);
+        let start = std::time::Instant::now();
+        publish(
+            "org_tail",
+            "default",
+            &new_buf(vec![json::json!({"_log": "hello"})]),
+        );
+        let (id, record) = sub.recv().await.unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+        assert_eq!(record.get("_log").unwrap(), "hello");
+
+        // other streams are not sent to the subscription
+        publish(
+            "org_tail",
+            "other",
+            &new_buf(vec![json::json!({"_log": "x"})]),
+        );
+        assert!(sub.rx.try_recv().is_err());
+
+        // the records published while disconnected are replayed
+        drop(sub);
+        publish(
+            "org_tail",
+            "default",
+            &new_buf(vec![json::json!({"_log": "missed"})]),
+        );
+        let mut sub = subscribe("org_tail", "default", Some(id));
+        let (next_id, record) = sub.recv().await.unwrap();
+        assert!(next_id > id);
+        assert_eq!(record.get("_log").unwrap(), "missed");
+        assert!(sub.rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tail_evict_idle() {
+        let org_id = "org_tail_evict";
+        let sub = subscribe(org_id, "default", None);
+        let tail = sub.tail.clone();
+        evict_idle();
+        assert!(TAILS.read().contains_key(org_id));
+
+        // kept for the reconnect window after the last subscription is dropped
+        drop(sub);
+        evict_idle();
+        assert!(TAILS.read().contains_key(org_id));
+
+        *tail.idle_since.lock() = Some(now_micros() - TAIL_RECONNECT_WINDOW - 1);
+        evict_idle();
+        assert!(!TAILS.read().contains_key(org_id));
+    }
+
+    #[test]
+    fn test_tail_matches() {
+        let record = json::json!({"_log": "GET /api 500"});
+        assert!(matches(&record, ""));
+        assert!(matches(&record, "500"));
+        assert!(!matches(&record, "404"));
+        assert!(!matches(&json::json!({"log": "500"}), "500"));
+    }
+}
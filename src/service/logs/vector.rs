@@ -0,0 +1,159 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Mapping of the log events pushed by the Vector `vector` sink over its gRPC
+//! (v2) protocol.
+
+use actix_web::web;
+use anyhow::Result;
+use config::{get_config, utils::json};
+use opentelemetry_proto::tonic::common::v1::{
+    any_value, AnyValue, ArrayValue, KeyValue, KeyValueList,
+};
+use proto::vector_event::{event_wrapper::Event, value::Kind, EventWrapper, Value};
+
+use crate::{
+    common::meta::ingestion::{IngestionRequest, IngestionResponse},
+    service::ingestion::grpc::get_val_with_type_retained,
+};
+
+/// Converts a log event to a json record, the fields are mapped like the
+/// attributes of an OTLP log record and the `timestamp` field set by the
+/// Vector sources is used as `_timestamp`. Metric events are skipped.
+pub fn to_record(event: EventWrapper) -> Option<json::Value> {
+    let Some(Event::Log(log)) = event.event else {
+        return None;
+    };
+    let cfg = get_config();
+    let mut record = json::Map::new();
+    // the fields of the event are either in `value` or in `fields`
+    let fields = match log.value.and_then(|v| v.kind) {
+        Some(Kind::Map(map)) => map.fields.into_iter().chain(log.fields).collect::<Vec<_>>(),
+        _ => log.fields.into_iter().collect(),
+    };
+    for (key, value) in fields {
+        if key == "timestamp" && !record.contains_key(&cfg.common.column_timestamp) {
+            if let Some(Kind::Timestamp(ts)) = &value.kind {
+                record.insert(
+                    cfg.common.column_timestamp.clone(),
+                    (ts.seconds * 1_000_000 + ts.nanos as i64 / 1_000).into(),
+                );
+                continue;
+            }
+        }
+        record.insert(key, get_val_with_type_retained(&Some(&to_any_value(value))));
+    }
+    Some(json::Value::Object(record))
+}
+
+/// Maps the value to the OTLP `AnyValue`, timestamps become microseconds.
+fn to_any_value(value: Value) -> AnyValue {
+    let value = value.kind.and_then(|kind| match kind {
+        Kind::RawBytes(v) => Some(any_value::Value::StringValue(
+            String::from_utf8_lossy(&v).into_owned(),
+        )),
+        Kind::Timestamp(v) => Some(any_value::Value::IntValue(
+            v.seconds * 1_000_000 + v.nanos as i64 / 1_000,
+        )),
+        Kind::Integer(v) => Some(any_value::Value::IntValue(v)),
+        Kind::Float(v) => Some(any_value::Value::DoubleValue(v)),
+        Kind::Boolean(v) => Some(any_value::Value::BoolValue(v)),
+        Kind::Map(v) => Some(any_value::Value::KvlistValue(KeyValueList {
+            values: v
+                .fields
+                .into_iter()
+                .map(|(key, v)| KeyValue {
+                    key,
+                    value: Some(to_any_value(v)),
+                })
+                .collect(),
+        })),
+        Kind::Array(v) => Some(any_value::Value::ArrayValue(ArrayValue {
+            values: v.items.into_iter().map(to_any_value).collect(),
+        })),
+        Kind::Null(_) => None,
+    });
+    AnyValue { value }
+}
+
+/// Ingests a batch of records into the stream.
+pub async fn ingest(
+    org_id: &str,
+    stream_name: &str,
+    records: &[json::Value],
+) -> Result<IngestionResponse> {
+    let data = web::Bytes::from(json::to_vec(records)?);
+    super::ingest::ingest(
+        0,
+        org_id,
+        stream_name,
+        IngestionRequest::JSON(&data),
+        "",
+        None,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use proto::vector_event::{Log, Timestamp, ValueMap};
+
+    use super::*;
+
+    fn value(kind: Kind) -> Value {
+        Value { kind: Some(kind) }
+    }
+
+    fn log_event(message: &str) -> EventWrapper {
+        let fields = HashMap::from([
+            (
+                "message".to_string(),
+                value(Kind::RawBytes(message.as_bytes().to_vec())),
+            ),
+            (
+                "timestamp".to_string(),
+                value(Kind::Timestamp(Timestamp {
+                    seconds: 1_700_000_000,
+                    nanos: 5_000,
+                })),
+            ),
+            (
+                "kubernetes".to_string(),
+                value(Kind::Map(ValueMap {
+                    fields: HashMap::from([("pod".to_string(), value(Kind::Integer(1)))]),
+                })),
+            ),
+        ]);
+        EventWrapper {
+            event: Some(Event::Log(Log {
+                fields,
+                value: None,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_to_record() {
+        let record = to_record(log_event("a")).unwrap();
+        assert_eq!(record["message"], "a");
+        assert_eq!(record["_timestamp"], 1_700_000_000_000_005i64);
+        // nested values are mapped like the OTLP attributes
+        assert_eq!(record["kubernetes"]["pod"], "1");
+        assert!(record.get("timestamp").is_none());
+        assert!(to_record(EventWrapper { event: None }).is_none());
+    }
+}
@@ -218,6 +218,12 @@ impl TraceListIndex {
                 distinct_value_fields: vec![],
                 index_updated_at: 0,
                 extended_retention_days: vec![],
+                derived_fields: vec![],
+                ingest_transform: None,
+                ingest_lua_transform: None,
+                geoip_field: None,
+                custom_partition_key: vec![],
+                rollup_stream: false,
             };
 
             stream::save_stream_settings(org_id, STREAM_NAME, StreamType::Metadata, settings)
@@ -41,7 +41,9 @@ use crate::{
     service::{
         alerts::alert::AlertExt,
         db, format_stream_name,
-        ingestion::{evaluate_trigger, get_write_partition_key, write_file, TriggerAlertData},
+        ingestion::{
+            self, evaluate_trigger, get_write_partition_key, write_file, TriggerAlertData,
+        },
         pipeline::batch_execution::ExecutablePipeline,
         schema::check_for_schema,
         self_reporting::report_request_usage_stats,
@@ -238,6 +240,27 @@ pub async fn ingest(org_id: &str, body: web::Bytes) -> Result<IngestionResponse>
         }
     }
 
+    // reject the records which conflict with the stream schema
+    ingestion::reject_incompatible_records(
+        org_id,
+        StreamType::Metrics,
+        &mut json_data_by_stream,
+        |json_data| {
+            json_data
+                .iter()
+                .filter_map(|(v, _)| v.as_object())
+                .collect()
+        },
+        |stream_name, json_data, e| {
+            let stream_status = stream_status_map
+                .entry(stream_name.to_string())
+                .or_insert_with(|| StreamStatus::new(stream_name));
+            stream_status.status.failed += json_data.len() as u32;
+            stream_status.status.error = e.to_string();
+        },
+    )
+    .await;
+
     let cfg = config::get_config();
     for (stream_name, json_data) in json_data_by_stream {
         if !stream_partitioning_map.contains_key(&stream_name) {
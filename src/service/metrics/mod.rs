@@ -14,13 +14,20 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use config::{
-    meta::promql::{Metadata, EXEMPLARS_LABEL, HASH_LABEL, METADATA_LABEL, VALUE_LABEL},
+    cluster::LOCAL_NODE,
+    meta::{
+        promql::{Metadata, EXEMPLARS_LABEL, HASH_LABEL, METADATA_LABEL, VALUE_LABEL},
+        stream::StreamType,
+    },
     utils::hash::{gxhash, Sum64},
 };
 use datafusion::arrow::datatypes::Schema;
 use once_cell::sync::Lazy;
+use proto::cluster_rpc;
 use regex::Regex;
 
+use crate::service::ingestion::ingestion_service;
+
 pub mod json;
 pub mod otlp;
 pub mod prom;
@@ -37,6 +44,34 @@ const EXCLUDE_LABELS: [&str; 7] = [
 
 static RE_CORRECT_LABEL_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^a-zA-Z0-9_]+").unwrap());
 
+/// Ingests the json records of metrics samples, on this node when it is an
+/// ingester, otherwise through the ingestion service of the cluster.
+pub async fn ingest_records(
+    org_id: &str,
+    records: Vec<config::utils::json::Value>,
+) -> Result<(), anyhow::Error> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    if LOCAL_NODE.is_ingester() {
+        let body = config::utils::json::to_vec(&records)?;
+        json::ingest(org_id, body.into()).await?;
+        return Ok(());
+    }
+    let req = cluster_rpc::IngestionRequest {
+        org_id: org_id.to_string(),
+        stream_name: "".to_string(),
+        stream_type: StreamType::Metrics.to_string(),
+        data: Some(cluster_rpc::IngestionData::from(records)),
+        ingestion_type: Some(cluster_rpc::IngestionType::Json.into()),
+    };
+    match ingestion_service::ingest(req).await {
+        Ok(resp) if resp.status_code == 200 => Ok(()),
+        Ok(resp) => Err(anyhow::anyhow!(resp.message)),
+        Err(e) => Err(e),
+    }
+}
+
 pub fn get_prom_metadata_from_schema(schema: &Schema) -> Option<Metadata> {
     let metadata = schema.metadata.get(METADATA_LABEL)?;
     let metadata: Metadata = config::utils::json::from_str(metadata).unwrap();
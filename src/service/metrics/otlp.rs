@@ -52,7 +52,7 @@ use crate::{
         alerts::alert::AlertExt,
         db, format_stream_name,
         ingestion::{
-            evaluate_trigger,
+            self, evaluate_trigger,
             grpc::{get_exemplar_val, get_metric_val, get_val},
             write_file, TriggerAlertData,
         },
@@ -414,6 +414,19 @@ pub async fn handle_otlp_request(
         }
     }
 
+    // reject the data points which conflict with the stream schema
+    ingestion::reject_incompatible_records(
+        org_id,
+        StreamType::Metrics,
+        &mut json_data_by_stream,
+        |json_data| json_data.iter().filter_map(|v| v.as_object()).collect(),
+        |_, json_data, e| {
+            partial_success.rejected_data_points += json_data.len() as i64;
+            partial_success.error_message = e.to_string();
+        },
+    )
+    .await;
+
     for (local_metric_name, json_data) in json_data_by_stream {
         // get partition keys
         let partition_det = stream_partitioning_map.get(&local_metric_name).unwrap();
@@ -52,7 +52,7 @@ use crate::{
         ingestion::{evaluate_trigger, write_file, TriggerAlertData},
         metrics::format_label_name,
         pipeline::batch_execution::ExecutablePipeline,
-        schema::{check_for_schema, stream_schema_exists},
+        schema::{check_for_schema, check_schema_compatibility, stream_schema_exists},
         search as search_service,
         self_reporting::report_request_usage_stats,
     },
@@ -401,6 +401,20 @@ pub async fn remote_write(
         }
     }
 
+    // reject the request if the samples conflict with the stream schema
+    for (stream_name, json_data) in json_data_by_stream.iter() {
+        check_schema_compatibility(
+            org_id,
+            stream_name,
+            StreamType::Metrics,
+            json_data
+                .iter()
+                .filter_map(|(v, _)| v.as_object())
+                .collect(),
+        )
+        .await?;
+    }
+
     for (stream_name, json_data) in json_data_by_stream {
         // get partition keys
         let partition_det = stream_partitioning_map.get(&stream_name).unwrap();
@@ -745,6 +759,13 @@ pub(crate) async fn get_series(
         search_type: None,
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     let series = match search_service::search("", org_id, StreamType::Metrics, None, &req).await {
         Err(err) => {
@@ -891,6 +912,13 @@ pub(crate) async fn get_label_values(
         search_type: None,
         search_event_context: None,
         use_cache: None,
+        cursor: None,
+        use_cursor: false,
+        streams: vec![],
+        approximate_count: false,
+        use_fts: false,
+        params: Default::default(),
+        force: false,
     };
     let mut label_values = match search_service::search("", org_id, stream_type, None, &req).await {
         Ok(resp) => resp
@@ -27,6 +27,8 @@ pub mod file_list;
 pub mod folders;
 pub mod functions;
 pub mod grpc;
+pub mod health;
+pub mod iceberg;
 pub mod ingestion;
 pub mod kv;
 pub mod logs;
@@ -35,13 +37,16 @@ pub mod metrics;
 pub mod organization;
 pub mod pipeline;
 pub mod promql;
+pub mod recording_rules;
 pub mod schema;
 pub mod search;
 #[cfg(feature = "enterprise")]
 pub mod search_jobs;
 pub mod self_reporting;
 pub mod session;
+pub mod shutdown;
 pub mod short_url;
+pub mod silences;
 pub mod stream;
 pub mod syslogs_route;
 pub mod tls;
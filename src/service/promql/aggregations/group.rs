@@ -29,3 +29,63 @@ pub fn group(timestamp: i64, param: &Option<LabelModifier>, data: &Value) -> Res
         score_values,
     )))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use promql_parser::label::Labels as LabelNames;
+
+    use super::*;
+    use crate::service::promql::value::{InstantValue, Label, Sample};
+
+    fn instant(job: &str, instance: &str, value: f64) -> InstantValue {
+        InstantValue {
+            labels: vec![
+                Arc::new(Label::new("instance", instance)),
+                Arc::new(Label::new("job", job)),
+            ],
+            sample: Sample::new(1, value),
+        }
+    }
+
+    fn data() -> Value {
+        Value::Vector(vec![
+            instant("a", "1", 10.0),
+            instant("a", "2", -3.5),
+            instant("b", "1", f64::NAN),
+        ])
+    }
+
+    #[test]
+    fn test_group() {
+        let Value::Vector(v) = group(2, &None, &data()).unwrap() else {
+            panic!("group should return a vector");
+        };
+        assert_eq!(v.len(), 1);
+        assert!(v[0].labels.is_empty());
+        assert_eq!(v[0].sample.value, 1.0);
+        assert_eq!(v[0].sample.timestamp, 2);
+        assert!(matches!(
+            group(2, &None, &Value::None).unwrap(),
+            Value::None
+        ));
+    }
+
+    #[test]
+    fn test_group_by_without() {
+        for modifier in [
+            LabelModifier::Include(LabelNames::new(vec!["job"])),
+            LabelModifier::Exclude(LabelNames::new(vec!["instance"])),
+        ] {
+            let Value::Vector(mut v) = group(2, &Some(modifier), &data()).unwrap() else {
+                panic!("group should return a vector");
+            };
+            v.sort_by(|a, b| a.labels[0].value.cmp(&b.labels[0].value));
+            assert_eq!(v.len(), 2);
+            assert_eq!(v[0].labels, vec![Arc::new(Label::new("job", "a"))]);
+            assert_eq!(v[1].labels, vec![Arc::new(Label::new("job", "b"))]);
+            assert!(v.iter().all(|v| v.sample.value == 1.0));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use datafusion::error::{DataFusionError, Result};
+use promql_parser::parser::Expr as PromExpr;
+
+use crate::service::promql::{
+    aggregations::sample_key,
+    value::{InstantValue, Value},
+    Engine,
+};
+
+/// Experimental, returns every series with a probability of `ratio`, a
+/// negative ratio returns the series which `1 + ratio` wouldn't, so
+/// `limit_ratio(r, v)` and `limit_ratio(r - 1, v)` split the vector.
+///
+/// https://prometheus.io/docs/prometheus/latest/querying/operators/#aggregation-operators
+pub async fn limit_ratio(ctx: &mut Engine, param: Box<PromExpr>, data: &Value) -> Result<Value> {
+    let param = ctx.exec_expr(&param).await?;
+    let ratio = match param {
+        Value::Float(v) if !v.is_nan() => v.clamp(-1.0, 1.0),
+        _ => {
+            return Err(DataFusionError::Plan(
+                "[limit_ratio] param must be a number between -1 and 1".to_string(),
+            ));
+        }
+    };
+
+    let data = match data {
+        Value::Vector(v) => v,
+        Value::None => return Ok(Value::None),
+        _ => {
+            return Err(DataFusionError::Plan(
+                "[limit_ratio] function only accept vector values".to_string(),
+            ));
+        }
+    };
+
+    Ok(Value::Vector(select_limit_ratio(data, ratio)))
+}
+
+/// A series is kept by its labels, so the same series are kept at every
+/// evaluation.
+fn select_limit_ratio(data: &[InstantValue], ratio: f64) -> Vec<InstantValue> {
+    data.iter()
+        .filter(|item| {
+            let key = sample_key(&item.labels);
+            if ratio >= 0.0 {
+                key < ratio
+            } else {
+                key >= 1.0 + ratio
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::{Label, Sample};
+
+    fn data() -> Vec<InstantValue> {
+        (0..1000)
+            .map(|i| InstantValue {
+                labels: vec![Arc::new(Label::new("instance", i.to_string().as_str()))],
+                sample: Sample::new(1, i as f64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_limit_ratio() {
+        let data = data();
+        assert!(select_limit_ratio(&data, 0.0).is_empty());
+        assert_eq!(select_limit_ratio(&data, 1.0).len(), 1000);
+        assert_eq!(select_limit_ratio(&data, -1.0).len(), 1000);
+
+        let kept = select_limit_ratio(&data, 0.3);
+        assert!((200..400).contains(&kept.len()), "kept {}", kept.len());
+        // deterministic
+        assert_eq!(select_limit_ratio(&data, 0.3).len(), kept.len());
+        // the negative ratio returns the complement
+        let rest = select_limit_ratio(&data, 0.3 - 1.0);
+        assert_eq!(kept.len() + rest.len(), 1000);
+        assert!(kept
+            .iter()
+            .all(|k| rest.iter().all(|r| r.sample.value != k.sample.value)));
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::FxIndexMap;
+use datafusion::error::{DataFusionError, Result};
+use promql_parser::parser::{Expr as PromExpr, LabelModifier};
+
+use crate::service::promql::{
+    aggregations::{group_labels, sample_key},
+    value::{InstantValue, LabelsExt, Signature, Value},
+    Engine,
+};
+
+/// Experimental, returns `k` pseudo randomly picked series of every group.
+///
+/// https://prometheus.io/docs/prometheus/latest/querying/operators/#aggregation-operators
+pub async fn limitk(
+    ctx: &mut Engine,
+    param: Box<PromExpr>,
+    modifier: &Option<LabelModifier>,
+    data: &Value,
+) -> Result<Value> {
+    let param = ctx.exec_expr(&param).await?;
+    let k = match param {
+        Value::Float(v) => v as usize,
+        _ => {
+            return Err(DataFusionError::Plan(
+                "[limitk] param must be NumberLiteral".to_string(),
+            ));
+        }
+    };
+
+    let data = match data {
+        Value::Vector(v) => v,
+        Value::None => return Ok(Value::None),
+        _ => {
+            return Err(DataFusionError::Plan(
+                "[limitk] function only accept vector values".to_string(),
+            ));
+        }
+    };
+
+    Ok(Value::Vector(select_limitk(data, k, modifier)))
+}
+
+/// The same series are picked at every evaluation as long as they exist, the
+/// order of the series is random but seeded by their labels.
+fn select_limitk(
+    data: &[InstantValue],
+    k: usize,
+    modifier: &Option<LabelModifier>,
+) -> Vec<InstantValue> {
+    let mut groups: FxIndexMap<Signature, Vec<(f64, &InstantValue)>> = Default::default();
+    for item in data.iter() {
+        groups
+            .entry(group_labels(modifier, &item.labels).signature())
+            .or_default()
+            .push((sample_key(&item.labels), item));
+    }
+
+    groups
+        .into_values()
+        .flat_map(|mut items| {
+            items.sort_by(|a, b| a.0.total_cmp(&b.0));
+            items.into_iter().take(k).map(|(_, item)| item.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use promql_parser::label::Labels as LabelNames;
+
+    use super::*;
+    use crate::service::promql::value::{Label, Sample};
+
+    fn data() -> Vec<InstantValue> {
+        (0..20)
+            .map(|i| InstantValue {
+                labels: vec![
+                    Arc::new(Label::new("instance", i.to_string().as_str())),
+                    Arc::new(Label::new("job", if i % 2 == 0 { "a" } else { "b" })),
+                ],
+                sample: Sample::new(1, i as f64),
+            })
+            .collect()
+    }
+
+    fn values(data: &[InstantValue]) -> Vec<f64> {
+        let mut values = data.iter().map(|v| v.sample.value).collect::<Vec<_>>();
+        values.sort_by(f64::total_cmp);
+        values
+    }
+
+    #[test]
+    fn test_limitk() {
+        let data = data();
+        let picked = select_limitk(&data, 5, &None);
+        assert_eq!(picked.len(), 5);
+        // the same series are picked whatever the order of the input
+        let mut reversed = data.clone();
+        reversed.reverse();
+        assert_eq!(values(&picked), values(&select_limitk(&reversed, 5, &None)));
+        assert_eq!(select_limitk(&data, 30, &None).len(), 20);
+        assert!(select_limitk(&data, 0, &None).is_empty());
+    }
+
+    #[test]
+    fn test_limitk_by() {
+        let by_job = Some(LabelModifier::Include(LabelNames::new(vec!["job"])));
+        let picked = select_limitk(&data(), 2, &by_job);
+        assert_eq!(picked.len(), 4);
+        let even = picked.iter().filter(|v| v.sample.value as i64 % 2 == 0);
+        assert_eq!(even.count(), 2);
+    }
+}
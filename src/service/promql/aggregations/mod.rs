@@ -13,12 +13,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
 use config::{meta::promql::NAME_LABEL, FxIndexMap};
 use datafusion::error::{DataFusionError, Result};
 use itertools::Itertools;
 use promql_parser::parser::{Expr as PromExpr, LabelModifier};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use rayon::prelude::*;
 
 use crate::service::promql::{
@@ -31,6 +37,8 @@ mod bottomk;
 mod count;
 mod count_values;
 mod group;
+mod limit_ratio;
+mod limitk;
 mod max;
 mod min;
 mod quantile;
@@ -44,6 +52,8 @@ pub(crate) use bottomk::bottomk;
 pub(crate) use count::count;
 pub(crate) use count_values::count_values;
 pub(crate) use group::group;
+pub(crate) use limit_ratio::limit_ratio;
+pub(crate) use limitk::limitk;
 pub(crate) use max::max;
 pub(crate) use min::min;
 pub(crate) use quantile::quantile;
@@ -78,6 +88,7 @@ pub(crate) struct StatisticItems {
 pub(crate) struct TopItem {
     pub(crate) index: usize,
     pub(crate) value: f64,
+    pub(crate) signature: Signature,
 }
 
 pub fn labels_to_include(
@@ -112,6 +123,23 @@ pub fn labels_to_exclude(
         .collect()
 }
 
+/// The labels of the group of a series, as set by `by` or `without`
+fn group_labels(modifier: &Option<LabelModifier>, labels: &Labels) -> Labels {
+    match modifier {
+        Some(LabelModifier::Include(include)) => labels_to_include(&include.labels, labels),
+        Some(LabelModifier::Exclude(exclude)) => labels_to_exclude(&exclude.labels, labels),
+        None => Labels::default(),
+    }
+}
+
+/// A pseudo random number in `[0, 1)` seeded by the labels of a series, so a
+/// series is sampled the same way at every evaluation of a range query.
+fn sample_key(labels: &Labels) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    labels.signature().hash(&mut hasher);
+    StdRng::seed_from_u64(hasher.finish()).gen::<f64>()
+}
+
 fn eval_arithmetic_processor(
     score_values: &mut HashMap<Signature, ArithmeticItem>,
     f_handler: fn(total: f64, val: f64) -> f64,
@@ -247,61 +275,42 @@ pub async fn eval_top(
         }
     };
 
+    Ok(Value::Vector(select_top(data, n, modifier, is_bottom)))
+}
+
+/// Picks the `n` largest (smallest for bottomk) elements of every group, all
+/// of them when a group has fewer. NaN is ranked after any number, and equal
+/// values are ordered by the label signature so the result is stable.
+fn select_top(
+    data: &[InstantValue],
+    n: usize,
+    modifier: &Option<LabelModifier>,
+    is_bottom: bool,
+) -> Vec<InstantValue> {
     let mut score_values: FxIndexMap<Signature, Vec<TopItem>> = Default::default();
-    match modifier {
-        Some(v) => match v {
-            LabelModifier::Include(labels) => {
-                for (i, item) in data.iter().enumerate() {
-                    let sum_labels = labels_to_include(&labels.labels, &item.labels);
-                    if item.sample.value.is_nan() {
-                        continue;
-                    }
-                    let signature = sum_labels.signature();
-                    let value = score_values.entry(signature).or_default();
-                    value.push(TopItem {
-                        index: i,
-                        value: item.sample.value,
-                    });
-                }
-            }
-            LabelModifier::Exclude(labels) => {
-                for (i, item) in data.iter().enumerate() {
-                    let sum_labels = labels_to_exclude(&labels.labels, &item.labels);
-                    if item.sample.value.is_nan() {
-                        continue;
-                    }
-                    let signature = sum_labels.signature();
-                    let value = score_values.entry(signature).or_default();
-                    value.push(TopItem {
-                        index: i,
-                        value: item.sample.value,
-                    });
-                }
-            }
-        },
-        None => {
-            for (i, item) in data.iter().enumerate() {
-                let sum_labels = Labels::default();
-                if item.sample.value.is_nan() {
-                    continue;
-                }
-                let signature = sum_labels.signature();
-                let value = score_values.entry(signature).or_default();
-                value.push(TopItem {
-                    index: i,
-                    value: item.sample.value,
-                });
-            }
-        }
+    for (i, item) in data.iter().enumerate() {
+        score_values
+            .entry(group_labels(modifier, &item.labels).signature())
+            .or_default()
+            .push(TopItem {
+                index: i,
+                value: item.sample.value,
+                signature: item.labels.signature(),
+            });
     }
 
-    let comparator = if is_bottom {
-        |a: &TopItem, b: &TopItem| a.value.partial_cmp(&b.value).unwrap()
-    } else {
-        |a: &TopItem, b: &TopItem| b.value.partial_cmp(&a.value).unwrap()
+    let comparator = |a: &TopItem, b: &TopItem| {
+        let order = match (a.value.is_nan(), b.value.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if is_bottom => a.value.total_cmp(&b.value),
+            (false, false) => b.value.total_cmp(&a.value),
+        };
+        order.then_with(|| a.signature.cmp(&b.signature))
     };
 
-    let values = score_values
+    score_values
         .values()
         .flat_map(|items| {
             items
@@ -311,8 +320,7 @@ pub async fn eval_top(
                 .collect::<Vec<_>>()
         })
         .map(|item| data[item.index].clone())
-        .collect();
-    Ok(Value::Vector(values))
+        .collect()
 }
 
 pub(crate) fn eval_std_dev_var(
@@ -356,6 +364,17 @@ pub(crate) fn eval_std_dev_var(
     Ok(Some(score_values))
 }
 
+/// Formats a sample value like Prometheus does for `count_values`
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
 pub(crate) fn eval_count_values(
     param: &Option<LabelModifier>,
     data: &Value,
@@ -373,32 +392,17 @@ pub(crate) fn eval_count_values(
     };
 
     let mut score_values = HashMap::default();
-    match param {
-        Some(v) => match v {
-            LabelModifier::Include(labels) => {
-                let mut labels = labels.labels.clone();
-                labels.push(label_name.to_string());
-                for item in data.iter() {
-                    let sum_labels = labels_to_include(&labels, &item.labels);
-                    eval_count_values_processor(&mut score_values, &sum_labels);
-                }
-            }
-            LabelModifier::Exclude(labels) => {
-                let mut labels = labels.labels.clone();
-                labels.push(label_name.to_string());
-                for item in data.iter() {
-                    let sum_labels = labels_to_exclude(&labels, &item.labels);
-                    eval_count_values_processor(&mut score_values, &sum_labels);
-                }
-            }
-        },
-        None => {
-            for item in data.iter() {
-                let mut sum_labels = Labels::default();
-                sum_labels.set(label_name, item.sample.value.to_string().as_str());
-                eval_count_values_processor(&mut score_values, &sum_labels);
-            }
-        }
+    for item in data.iter() {
+        let mut sum_labels = match param {
+            Some(LabelModifier::Include(labels)) => labels_to_include(&labels.labels, &item.labels),
+            Some(LabelModifier::Exclude(labels)) => labels_to_exclude(&labels.labels, &item.labels),
+            None => Labels::default(),
+        };
+        // the value label replaces a grouping label of the same name
+        sum_labels = sum_labels.without_label(label_name);
+        sum_labels.set(label_name, &format_value(item.sample.value));
+        sum_labels.sort_by(|a, b| a.name.cmp(&b.name));
+        eval_count_values_processor(&mut score_values, &sum_labels);
     }
     Ok(Some(score_values))
 }
@@ -425,3 +429,134 @@ pub(crate) fn score_to_instant_value(
         .collect();
     values
 }
+
+#[cfg(test)]
+mod tests {
+    use promql_parser::label::Labels as LabelNames;
+
+    use super::*;
+
+    fn instant(labels: &[(&str, &str)], value: f64) -> InstantValue {
+        InstantValue {
+            labels: labels
+                .iter()
+                .map(|(name, value)| Arc::new(Label::new(*name, *value)))
+                .collect(),
+            sample: Sample::new(1, value),
+        }
+    }
+
+    fn values(data: &[InstantValue]) -> Vec<f64> {
+        data.iter().map(|v| v.sample.value).collect()
+    }
+
+    fn data() -> Vec<InstantValue> {
+        vec![
+            instant(&[("job", "a"), ("instance", "1")], 1.0),
+            instant(&[("job", "a"), ("instance", "2")], 3.0),
+            instant(&[("job", "a"), ("instance", "3")], 2.0),
+            instant(&[("job", "b"), ("instance", "1")], 5.0),
+            instant(&[("job", "b"), ("instance", "2")], f64::NAN),
+        ]
+    }
+
+    #[test]
+    fn test_topk() {
+        let data = data();
+        assert_eq!(values(&select_top(&data, 2, &None, false)), vec![5.0, 3.0]);
+        assert_eq!(values(&select_top(&data, 2, &None, true)), vec![1.0, 2.0]);
+        // NaN is ranked last, k larger than the vector returns everything
+        let all = select_top(&data, 10, &None, false);
+        assert_eq!(values(&all[..4]), vec![5.0, 3.0, 2.0, 1.0]);
+        assert!(all[4].sample.value.is_nan());
+        assert!(select_top(&data, 0, &None, false).is_empty());
+    }
+
+    #[test]
+    fn test_topk_by_without() {
+        let data = data();
+        let by_job = Some(LabelModifier::Include(LabelNames::new(vec!["job"])));
+        assert_eq!(
+            values(&select_top(&data, 1, &by_job, false)),
+            vec![3.0, 5.0]
+        );
+        assert_eq!(values(&select_top(&data, 1, &by_job, true)), vec![1.0, 5.0]);
+        let without_instance = Some(LabelModifier::Exclude(LabelNames::new(vec!["instance"])));
+        let top = select_top(&data, 2, &without_instance, false);
+        assert_eq!(values(&top[..3]), vec![3.0, 2.0, 5.0]);
+        assert!(top[3].sample.value.is_nan());
+    }
+
+    #[test]
+    fn test_topk_ties() {
+        let data = (1..=4)
+            .map(|i| instant(&[("instance", i.to_string().as_str())], 1.0))
+            .collect::<Vec<_>>();
+        let mut reversed = data.clone();
+        reversed.reverse();
+        let top = select_top(&data, 2, &None, false);
+        let top_reversed = select_top(&reversed, 2, &None, false);
+        assert_eq!(top.len(), 2);
+        for (a, b) in top.iter().zip(top_reversed.iter()) {
+            assert_eq!(a.labels.signature(), b.labels.signature());
+        }
+        assert!(top[0].labels.signature() < top[1].labels.signature());
+    }
+
+    fn count_values(modifier: &Option<LabelModifier>, data: &[InstantValue]) -> Vec<(Labels, u64)> {
+        let mut counts = eval_count_values(
+            modifier,
+            &Value::Vector(data.to_vec()),
+            "count_values",
+            "value",
+        )
+        .unwrap()
+        .unwrap()
+        .into_values()
+        .map(|item| (item.labels, item.count))
+        .collect::<Vec<_>>();
+        counts
+            .sort_by_key(|(labels, _)| labels.iter().map(|l| l.value.clone()).collect::<Vec<_>>());
+        counts
+    }
+
+    #[test]
+    fn test_count_values() {
+        let data = vec![
+            instant(&[("instance", "1"), ("job", "a")], 1.0),
+            instant(&[("instance", "2"), ("job", "a")], 1.0),
+            instant(&[("instance", "3"), ("job", "b")], 1.0),
+            instant(&[("instance", "4"), ("job", "b")], 2.5),
+        ];
+        assert_eq!(
+            count_values(&None, &data),
+            vec![
+                (instant(&[("value", "1")], 0.0).labels, 3),
+                (instant(&[("value", "2.5")], 0.0).labels, 1),
+            ]
+        );
+        let by_job = Some(LabelModifier::Include(LabelNames::new(vec!["job"])));
+        assert_eq!(
+            count_values(&by_job, &data),
+            vec![
+                (instant(&[("job", "a"), ("value", "1")], 0.0).labels, 2),
+                (instant(&[("job", "b"), ("value", "1")], 0.0).labels, 1),
+                (instant(&[("job", "b"), ("value", "2.5")], 0.0).labels, 1),
+            ]
+        );
+        let without_instance = Some(LabelModifier::Exclude(LabelNames::new(vec!["instance"])));
+        assert_eq!(
+            count_values(&without_instance, &data),
+            count_values(&by_job, &data)
+        );
+    }
+
+    #[test]
+    fn test_count_values_format_value() {
+        assert_eq!(format_value(1.0), "1");
+        assert_eq!(format_value(0.25), "0.25");
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(f64::NAN), "NaN");
+    }
+}
@@ -13,10 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::collections::{HashMap, HashSet};
 
 use config::meta::promql::NAME_LABEL;
 use datafusion::error::{DataFusionError, Result};
@@ -26,7 +23,7 @@ use rayon::prelude::*;
 
 use crate::service::promql::{
     binaries::scalar_binary_operations,
-    value::{signature, InstantValue, Label, LabelsExt, Sample, Signature, Value},
+    value::{signature, InstantValue, Labels, LabelsExt, Sample, Signature, Value},
 };
 
 // DROP_METRIC_VECTOR_BIN_OP if the operation is one of these, drop the metric
@@ -102,6 +99,40 @@ pub async fn vector_scalar_bin_op(
     Ok(Value::Vector(output))
 }
 
+/// The labels two vector elements are matched on, as set by the `on` and
+/// `ignoring` modifiers. Without `on` all the labels but the ignored ones and
+/// the metric name are matched.
+///
+/// https://prometheus.io/docs/prometheus/latest/querying/operators/#vector-matching
+struct Matching {
+    on: bool,
+    labels: Vec<String>,
+}
+
+impl Matching {
+    fn new(expr: &BinaryExpr) -> Self {
+        let modifier = expr.modifier.as_ref();
+        let on = modifier.is_some_and(|m| m.is_matching_on());
+        let labels = modifier
+            .and_then(|m| m.matching.as_ref())
+            .map(|m| m.labels().labels.clone())
+            .unwrap_or_default();
+        Self { on, labels }
+    }
+
+    fn labels(&self, labels: &Labels) -> Labels {
+        if self.on {
+            labels.keep(&self.labels)
+        } else {
+            labels.delete(&self.labels).without_metric_name()
+        }
+    }
+
+    fn signature(&self, labels: &Labels) -> Signature {
+        signature(&self.labels(labels))
+    }
+}
+
 /// vector1 or vector2 results in a vector that contains all original elements
 /// (label sets + values) of vector1 and additionally all elements of vector2
 /// which do not have matching label sets in vector1.
@@ -122,9 +153,10 @@ fn vector_or(expr: &BinaryExpr, left: &[InstantValue], right: &[InstantValue]) -
         return Ok(Value::Vector(left.to_vec()));
     }
 
+    let matching = Matching::new(expr);
     let lhs_sig: HashSet<Signature> = left
         .par_iter()
-        .map(|item| signature(&item.labels))
+        .map(|item| matching.signature(&item.labels))
         .collect();
 
     // Add all right-hand side elements which have not been added from the left-hand
@@ -132,7 +164,7 @@ fn vector_or(expr: &BinaryExpr, left: &[InstantValue], right: &[InstantValue]) -
     let right_instants: Vec<InstantValue> = right
         .par_iter()
         .filter(|item| {
-            let right_sig = signature(&item.labels);
+            let right_sig = matching.signature(&item.labels);
             !lhs_sig.contains(&right_sig)
         })
         .map(|item| item.clone())
@@ -164,16 +196,17 @@ fn vector_unless(
         return Ok(Value::Vector(left.to_vec()));
     }
     // Generate all the signatures from the right hand.
+    let matching = Matching::new(expr);
     let rhs_sig: HashSet<Signature> = right
         .par_iter()
-        .map(|item| signature(&item.labels))
+        .map(|item| matching.signature(&item.labels))
         .collect();
 
     // Now filter out all the matching labels from left.
     let output: Vec<InstantValue> = left
         .par_iter()
         .filter(|item| {
-            let left_sig = signature(&item.labels);
+            let left_sig = matching.signature(&item.labels);
             !rhs_sig.contains(&left_sig)
         })
         .map(|val| val.clone())
@@ -195,21 +228,20 @@ fn vector_and(expr: &BinaryExpr, left: &[InstantValue], right: &[InstantValue])
     }
 
     if left.is_empty() || right.is_empty() {
-        return Err(DataFusionError::NotImplemented(
-            "Either left or right operand is empty.".to_string(),
-        ));
+        return Ok(Value::Vector(vec![]));
     }
 
+    let matching = Matching::new(expr);
     let rhs_sig: HashSet<Signature> = right
         .par_iter()
-        .map(|item| signature(&item.labels))
+        .map(|item| matching.signature(&item.labels))
         .collect();
 
     // Now include all the matching ones from the right
     let output: Vec<InstantValue> = left
         .par_iter()
         .filter(|item| {
-            let left_sig = signature(&item.labels);
+            let left_sig = matching.signature(&item.labels);
             rhs_sig.contains(&left_sig)
         })
         .map(|instant| InstantValue {
@@ -221,6 +253,10 @@ fn vector_and(expr: &BinaryExpr, left: &[InstantValue], right: &[InstantValue])
     Ok(Value::Vector(output))
 }
 
+/// Arithmetic and comparison operators between two vectors. Each element of
+/// the "many" side is matched with the element of the "one" side with the
+/// same matching labels: both sides are "one" sides by default, `group_left`
+/// makes the left side the "many" side and `group_right` the right side.
 fn vector_arithmetic_operators(
     expr: &BinaryExpr,
     left: &[InstantValue],
@@ -230,52 +266,61 @@ fn vector_arithmetic_operators(
 
     let return_bool = expr.return_bool();
     let comparison_operator = expr.op.is_comparison_operator();
+    let card = expr
+        .modifier
+        .as_ref()
+        .map_or(VectorMatchCardinality::OneToOne, |m| m.card.clone());
+    let matching = Matching::new(expr);
+
+    let (many, one) = if card == VectorMatchCardinality::OneToMany {
+        (right, left)
+    } else {
+        (left, right)
+    };
 
-    // These are here so that we can generate signatures based on these labels.
-    let mut labels_to_include_set = vec![];
-    let mut labels_to_exclude_set = vec![NAME_LABEL.to_string()];
-
-    let is_matching_on = expr.is_matching_on();
-    if is_matching_on {
-        let modifier = expr.modifier.as_ref().unwrap();
-        if modifier.is_matching_on() {
-            labels_to_include_set = modifier.matching.as_ref().unwrap().labels().labels.clone();
-            labels_to_include_set.sort();
-        } else {
-            let excluded_labels = modifier.matching.as_ref().unwrap().labels().labels.clone();
-            labels_to_exclude_set.extend(excluded_labels);
-            labels_to_exclude_set.sort();
+    // Get the hash for the labels on the "one" side
+    let mut one_sig: HashMap<Signature, &InstantValue> = HashMap::with_capacity(one.len());
+    for instant in one {
+        if one_sig
+            .insert(matching.signature(&instant.labels), instant)
+            .is_some()
+        {
+            return Err(DataFusionError::Execution(
+                "many-to-many matching not allowed: matching labels must be unique on one side"
+                    .to_string(),
+            ));
         }
     }
 
-    // These labels should be used to compare values between lhs - rhs
-    let labels_to_compare = |labels: &Vec<Arc<Label>>| {
-        if is_matching_on {
-            labels.keep(&labels_to_include_set)
-        } else {
-            labels.delete(&labels_to_exclude_set)
-        }
-    };
-
-    // Get the hash for the labels on the right
-    let rhs_sig: HashMap<Signature, InstantValue> = right
+    // Iterate over the "many" side and pick up the corresponding instance
+    let matched: Vec<(Signature, &InstantValue, &InstantValue)> = many
         .par_iter()
-        .map(|instant| {
-            let signature = labels_to_compare(&instant.labels).signature();
-            (signature, instant.clone())
+        .flat_map(|instant| {
+            let sig = matching.signature(&instant.labels);
+            one_sig
+                .get(&sig)
+                .map(|one_instant| (sig, instant, *one_instant))
         })
         .collect();
+    if card == VectorMatchCardinality::OneToOne {
+        let mut seen = HashSet::with_capacity(matched.len());
+        if !matched.iter().all(|(sig, ..)| seen.insert(sig)) {
+            return Err(DataFusionError::Execution(
+                "multiple matches for labels: many-to-one matching must be explicit (group_left/group_right)"
+                    .to_string(),
+            ));
+        }
+    }
 
-    // Iterate over left and pick up the corresponding instance from rhs
-    let output: Vec<InstantValue> = left
+    let output: Vec<InstantValue> = matched
         .par_iter()
-        .flat_map(|instant| {
-            let left_sig = labels_to_compare(&instant.labels).signature();
-            rhs_sig
-                .get(&left_sig)
-                .map(|rhs_instant| (instant, rhs_instant))
-        })
-        .flat_map(|(lhs_instant, rhs_instant)| {
+        .flat_map(|(_, many_instant, one_instant)| {
+            // the operands keep their sides
+            let (lhs_instant, rhs_instant) = if card == VectorMatchCardinality::OneToMany {
+                (one_instant, many_instant)
+            } else {
+                (many_instant, one_instant)
+            };
             scalar_binary_operations(
                 operator,
                 lhs_instant.sample.value,
@@ -284,42 +329,75 @@ fn vector_arithmetic_operators(
                 comparison_operator,
             )
             .ok()
-            .map(|value| {
-                let mut labels = if return_bool || DROP_METRIC_VECTOR_BIN_OP.contains(&operator) {
-                    lhs_instant.labels.without_metric_name()
-                } else {
-                    lhs_instant.labels.clone()
-                };
-
-                if let Some(modifier) = expr.modifier.as_ref() {
-                    if modifier.card == VectorMatchCardinality::OneToOne {
-                        labels = labels_to_compare(&labels);
-                    }
-
-                    // group_labels from the `group_x` modifier are taken from the "one"-side.
-                    if let Some(group_labels) = modifier.card.labels() {
-                        for ln in group_labels.labels.iter() {
-                            let value = rhs_instant.labels.get_value(ln);
-                            if !value.is_empty() {
-                                labels.set(ln, &value);
-                            }
-                        }
-                    }
-                }
-                InstantValue {
-                    labels,
-                    sample: Sample {
-                        timestamp: lhs_instant.sample.timestamp,
-                        value,
-                    },
-                }
+            .map(|value| InstantValue {
+                labels: result_labels(
+                    &card,
+                    &matching,
+                    &many_instant.labels,
+                    &one_instant.labels,
+                    return_bool || DROP_METRIC_VECTOR_BIN_OP.contains(&operator),
+                ),
+                sample: Sample {
+                    timestamp: many_instant.sample.timestamp,
+                    value,
+                },
             })
         })
         .collect();
 
+    if card != VectorMatchCardinality::OneToOne {
+        let mut seen = HashSet::with_capacity(output.len());
+        if !output
+            .iter()
+            .all(|item| seen.insert(signature(&item.labels)))
+        {
+            return Err(DataFusionError::Execution(
+                "multiple matches for labels: grouping labels must ensure unique matches"
+                    .to_string(),
+            ));
+        }
+    }
+
     Ok(Value::Vector(output))
 }
 
+/// The labels of a result element are the labels of the "many" side, only the
+/// matching labels for one-to-one matching, plus the labels listed in the
+/// `group_x` modifier which are taken from the "one" side.
+fn result_labels(
+    card: &VectorMatchCardinality,
+    matching: &Matching,
+    many: &Labels,
+    one: &Labels,
+    drop_metric_name: bool,
+) -> Labels {
+    let mut labels = if drop_metric_name {
+        many.without_metric_name()
+    } else {
+        many.clone()
+    };
+
+    if *card == VectorMatchCardinality::OneToOne {
+        labels = if matching.on {
+            labels.keep(&matching.labels)
+        } else {
+            labels.delete(&matching.labels)
+        };
+    }
+
+    if let Some(group_labels) = card.labels() {
+        for ln in group_labels.labels.iter() {
+            labels = labels.without_label(ln);
+            let value = one.get_value(ln);
+            if !value.is_empty() {
+                labels.set(ln, &value);
+            }
+        }
+        labels.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    labels
+}
+
 /// Implement binary operations between two vectors
 ///
 /// https://prometheus.io/docs/prometheus/latest/querying/operators/#comparison-binary-operators
@@ -353,3 +431,177 @@ pub fn vector_bin_op(
         _ => vector_arithmetic_operators(expr, left, right),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use promql_parser::parser::{self, Expr};
+
+    use super::*;
+    use crate::service::promql::value::Label;
+
+    fn binary(query: &str) -> BinaryExpr {
+        match parser::parse(query).unwrap() {
+            Expr::Binary(expr) => expr,
+            expr => panic!("not a binary expression: {expr:?}"),
+        }
+    }
+
+    fn instant(labels: &[(&str, &str)], value: f64) -> InstantValue {
+        let mut labels: Labels = labels
+            .iter()
+            .map(|(name, value)| Arc::new(Label::new(*name, *value)))
+            .collect();
+        labels.sort_by(|a, b| a.name.cmp(&b.name));
+        InstantValue {
+            labels,
+            sample: Sample::new(1, value),
+        }
+    }
+
+    fn eval(query: &str, left: &[InstantValue], right: &[InstantValue]) -> Vec<(Labels, f64)> {
+        let Value::Vector(output) = vector_bin_op(&binary(query), left, right).unwrap() else {
+            panic!("binary operation should return a vector");
+        };
+        let mut output = output
+            .into_iter()
+            .map(|v| (v.labels, v.sample.value))
+            .collect::<Vec<_>>();
+        output.sort_by(|a, b| a.1.total_cmp(&b.1));
+        output
+    }
+
+    fn labels(labels: &[(&str, &str)]) -> Labels {
+        instant(labels, 0.0).labels
+    }
+
+    #[test]
+    fn test_one_to_one() {
+        let left = [instant(
+            &[("__name__", "a"), ("job", "x"), ("instance", "1")],
+            10.0,
+        )];
+        let right = [instant(
+            &[("__name__", "b"), ("job", "x"), ("instance", "1")],
+            2.0,
+        )];
+        assert_eq!(
+            eval("a / b", &left, &right),
+            vec![(labels(&[("job", "x"), ("instance", "1")]), 5.0)]
+        );
+        // comparisons without bool keep the metric name
+        assert_eq!(
+            eval("a > b", &left, &right),
+            vec![(left[0].labels.clone(), 10.0)]
+        );
+    }
+
+    #[test]
+    fn test_on() {
+        let left = [instant(
+            &[("__name__", "a"), ("job", "x"), ("instance", "1")],
+            10.0,
+        )];
+        let right = [instant(
+            &[("__name__", "b"), ("job", "x"), ("team", "t")],
+            2.0,
+        )];
+        assert!(eval("a / b", &left, &right).is_empty());
+        assert_eq!(
+            eval("a / on(job) b", &left, &right),
+            vec![(labels(&[("job", "x")]), 5.0)]
+        );
+    }
+
+    #[test]
+    fn test_ignoring() {
+        let left = [instant(
+            &[("__name__", "a"), ("job", "x"), ("instance", "1")],
+            10.0,
+        )];
+        let right = [instant(
+            &[("__name__", "b"), ("job", "x"), ("instance", "2")],
+            2.0,
+        )];
+        assert!(eval("a / b", &left, &right).is_empty());
+        assert_eq!(
+            eval("a / ignoring(instance) b", &left, &right),
+            vec![(labels(&[("job", "x")]), 5.0)]
+        );
+    }
+
+    #[test]
+    fn test_group_left() {
+        let left = [
+            instant(&[("__name__", "a"), ("job", "x"), ("instance", "1")], 10.0),
+            instant(&[("__name__", "a"), ("job", "x"), ("instance", "2")], 20.0),
+        ];
+        let right = [instant(
+            &[("__name__", "b"), ("job", "x"), ("team", "t")],
+            2.0,
+        )];
+        assert_eq!(
+            eval("a / on(job) group_left(team) b", &left, &right),
+            vec![
+                (
+                    labels(&[("job", "x"), ("instance", "1"), ("team", "t")]),
+                    5.0
+                ),
+                (
+                    labels(&[("job", "x"), ("instance", "2"), ("team", "t")]),
+                    10.0
+                ),
+            ]
+        );
+        // many-to-one matching must be explicit
+        assert!(vector_bin_op(&binary("a / on(job) b"), &left, &right).is_err());
+    }
+
+    #[test]
+    fn test_group_right() {
+        let left = [instant(&[("__name__", "a"), ("job", "x")], 100.0)];
+        let right = [
+            instant(&[("__name__", "b"), ("job", "x"), ("instance", "1")], 10.0),
+            instant(&[("__name__", "b"), ("job", "x"), ("instance", "2")], 20.0),
+        ];
+        assert_eq!(
+            eval("a - ignoring(instance) group_right b", &left, &right),
+            vec![
+                (labels(&[("job", "x"), ("instance", "2")]), 80.0),
+                (labels(&[("job", "x"), ("instance", "1")]), 90.0),
+            ]
+        );
+        // the "one" side must be unique
+        assert!(vector_bin_op(&binary("a - on(job) group_right b"), &right, &left).is_err());
+    }
+
+    #[test]
+    fn test_set_operations_matching() {
+        let left = [instant(
+            &[("__name__", "a"), ("job", "x"), ("instance", "1")],
+            1.0,
+        )];
+        let right = [instant(
+            &[("__name__", "b"), ("job", "x"), ("instance", "2")],
+            2.0,
+        )];
+        assert!(eval("a and b", &left, &right).is_empty());
+        assert_eq!(eval("a and on(job) b", &left, &right).len(), 1);
+        assert!(eval("a unless ignoring(instance) b", &left, &right).is_empty());
+    }
+
+    #[test]
+    fn test_unless() {
+        let left = [instant(&[("a", "1")], 1.0)];
+        assert!(eval("x unless y", &left, &[instant(&[("a", "1")], 2.0)]).is_empty());
+        assert_eq!(
+            eval("x unless y", &left, &[instant(&[("a", "2")], 2.0)]),
+            vec![(labels(&[("a", "1")]), 1.0)]
+        );
+        assert_eq!(
+            eval("x unless y", &left, &[]),
+            vec![(labels(&[("a", "1")]), 1.0)]
+        );
+    }
+}
@@ -33,11 +33,11 @@ use datafusion::{
 use futures::{future::try_join_all, TryStreamExt};
 use hashbrown::HashMap;
 use promql_parser::{
-    label::MatchOp,
+    label::{MatchOp, Matcher},
     parser::{
         token, AggregateExpr, BinModifier, BinaryExpr, Call, Expr as PromExpr, Function,
         FunctionArgs, LabelModifier, MatrixSelector, NumberLiteral, Offset, ParenExpr,
-        StringLiteral, UnaryExpr, VectorMatchCardinality, VectorSelector,
+        StringLiteral, SubqueryExpr, UnaryExpr, ValueType, VectorMatchCardinality, VectorSelector,
     },
 };
 use rayon::slice::ParallelSliceMut;
@@ -46,8 +46,9 @@ use super::{
     utils::{apply_label_selector, apply_matchers},
     PromqlContext,
 };
-use crate::service::promql::{
-    aggregations, binaries, functions, micros, value::*, DEFAULT_MAX_SERIES_PER_QUERY,
+use crate::service::{
+    compact::rollup,
+    promql::{aggregations, binaries, functions, micros, value::*, DEFAULT_MAX_SERIES_PER_QUERY},
 };
 
 pub struct Engine {
@@ -58,6 +59,8 @@ pub struct Engine {
     col_filters: Option<HashSet<String>>,
     result_type: Option<String>,
     trace_id: String,
+    /// Extra time to load before the start of the query for subqueries
+    subquery_lookback: i64,
 }
 
 impl Engine {
@@ -68,11 +71,13 @@ impl Engine {
             col_filters: Some(HashSet::new()),
             result_type: None,
             trace_id: trace_id.to_string(),
+            subquery_lookback: 0,
         }
     }
 
     pub async fn exec(&mut self, prom_expr: &PromExpr) -> Result<(Value, Option<String>)> {
         self.extract_columns_from_prom_expr(prom_expr)?;
+        self.subquery_lookback = subquery_lookback(prom_expr);
         let value = self.exec_expr(prom_expr).await?;
         Ok((value, self.result_type.clone()))
     }
@@ -153,8 +158,11 @@ impl Engine {
     ) {
         if let Some(label_modifier) = modifier {
             match op.id() {
-                // topk and bottomk query all columns when with modifiers
-                token::T_TOPK | token::T_BOTTOMK => self.col_filters = None,
+                // topk, bottomk and limitk return the input series, query all
+                // columns when with modifiers
+                token::T_TOPK | token::T_BOTTOMK | token::T_LIMITK | token::T_LIMIT_RATIO => {
+                    self.col_filters = None
+                }
                 _ => {
                     if let (Some(col_filters), LabelModifier::Include(labels)) =
                         (&mut self.col_filters, label_modifier)
@@ -219,9 +227,14 @@ impl Engine {
 
                 // This is a very special case, as we treat the float also a
                 // `Value::Vector(vec![element])` therefore, better convert it
-                // back to its representation.
+                // back to its representation. A vector with a single element
+                // is still a vector, e.g. `up unless {job="a"}`.
                 let rhs = match rhs {
-                    Value::Vector(v) if v.len() == 1 => Value::Float(v[0].sample.value),
+                    Value::Vector(v)
+                        if v.len() == 1 && matches!(expr.rhs.value_type(), ValueType::Scalar) =>
+                    {
+                        Value::Float(v[0].sample.value)
+                    }
                     _ => rhs,
                 };
                 match (lhs, rhs) {
@@ -238,6 +251,14 @@ impl Engine {
                     (Value::Vector(left), Value::Vector(right)) => {
                         binaries::vector_bin_op(expr, &left, &right)?
                     }
+                    // an operand without data is an empty vector, `up unless down` is `up`
+                    // when there is no `down`
+                    (Value::Vector(left), Value::None) => {
+                        binaries::vector_bin_op(expr, &left, &[])?
+                    }
+                    (Value::None, Value::Vector(right)) => {
+                        binaries::vector_bin_op(expr, &[], &right)?
+                    }
                     (Value::Vector(left), Value::Float(right)) => {
                         binaries::vector_scalar_bin_op(expr, &left, right, false).await?
                     }
@@ -255,51 +276,7 @@ impl Engine {
                 }
             }
             PromExpr::Paren(ParenExpr { expr }) => self.exec_expr(expr).await?,
-            PromExpr::Subquery(expr) => {
-                let val = self.exec_expr(&expr.expr).await?;
-                let time_window = Some(TimeWindow::new(self.time, expr.range));
-                let matrix = match val {
-                    Value::Vector(v) => v
-                        .iter()
-                        .map(|v| RangeValue {
-                            labels: v.labels.to_owned(),
-                            samples: vec![v.sample.clone()],
-                            exemplars: None,
-                            time_window: time_window.clone(),
-                        })
-                        .collect(),
-                    Value::Instant(v) => {
-                        vec![RangeValue {
-                            labels: v.labels.to_owned(),
-                            samples: vec![v.sample.clone()],
-                            exemplars: None,
-                            time_window,
-                        }]
-                    }
-                    Value::Range(v) => vec![v],
-                    Value::Matrix(vs) => vs,
-                    Value::Sample(s) => vec![RangeValue {
-                        labels: Labels::default(),
-                        samples: vec![s],
-                        exemplars: None,
-                        time_window,
-                    }],
-                    Value::Float(val) => vec![RangeValue {
-                        labels: Labels::default(),
-                        samples: vec![Sample::new(self.time, val)],
-                        exemplars: None,
-                        time_window,
-                    }],
-                    v => {
-                        return Err(DataFusionError::NotImplemented(format!(
-                            "Unsupported subquery, the return value should have been a matrix but got {:?}",
-                            v.get_type()
-                        )));
-                    }
-                };
-
-                Value::Matrix(matrix)
-            }
+            PromExpr::Subquery(expr) => self.eval_subquery(expr).await?,
             PromExpr::NumberLiteral(NumberLiteral { val }) => Value::Float(*val),
             PromExpr::StringLiteral(StringLiteral { val }) => Value::String(val.clone()),
             PromExpr::VectorSelector(v) => {
@@ -407,6 +384,66 @@ impl Engine {
         Ok(values)
     }
 
+    /// Subquery --- evaluate the inner expression at every step of the range
+    /// and collect the results into a range vector, e.g.
+    /// `rate(metric[5m])[1h:1m]`.
+    ///
+    /// See <https://prometheus.io/blog/2019/01/28/subquery-support/>
+    async fn eval_subquery(&mut self, expr: &SubqueryExpr) -> Result<Value> {
+        // the resolution defaults to the step of the query
+        let step = expr
+            .step
+            .map(micros)
+            .filter(|step| *step > 0)
+            .unwrap_or(self.ctx.interval);
+        let offset_modifier = match expr.offset {
+            Some(Offset::Pos(offset)) => micros(offset),
+            Some(Offset::Neg(offset)) => -micros(offset),
+            None => 0,
+        };
+
+        let eval_ts = self.time;
+        let mut series: HashMap<Signature, RangeValue> = HashMap::default();
+        let time_window = Some(TimeWindow::new(eval_ts, expr.range));
+        for ts in subquery_steps(eval_ts - offset_modifier, micros(expr.range), step) {
+            self.time = ts;
+            let val = self.exec_expr(&expr.expr).await;
+            self.time = eval_ts;
+            let samples = match val? {
+                Value::Vector(v) => v.into_iter().map(|v| (v.labels, v.sample.value)).collect(),
+                Value::Instant(v) => vec![(v.labels, v.sample.value)],
+                Value::Sample(s) => vec![(Labels::default(), s.value)],
+                Value::Float(val) => vec![(Labels::default(), val)],
+                Value::None => vec![],
+                v => {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "Unsupported subquery, the inner expression should return an instant vector but got {:?}",
+                        v.get_type()
+                    )));
+                }
+            };
+            for (labels, value) in samples {
+                // samples are moved by the offset like the samples of a range selector
+                let sample = Sample::new(ts + offset_modifier, value);
+                series
+                    .entry(labels.signature())
+                    .or_insert_with(|| RangeValue {
+                        labels,
+                        samples: vec![],
+                        exemplars: None,
+                        time_window: time_window.clone(),
+                    })
+                    .samples
+                    .push(sample);
+            }
+        }
+
+        if series.is_empty() {
+            return Ok(Value::None);
+        }
+        Ok(Value::Matrix(series.into_values().collect()))
+    }
+
     /// Range vector selector --- select a whole time range at each evaluation
     /// timestamp.
     ///
@@ -559,9 +596,9 @@ impl Engine {
         selector: &VectorSelector,
         range: Option<Duration>,
     ) -> Result<HashMap<HashLabelValue, RangeValue>> {
-        let start_time = std::time::Instant::now();
         // https://promlabs.com/blog/2020/07/02/selecting-data-in-promql/#lookback-delta
-        let mut start = self.ctx.start - range.map_or(self.ctx.lookback_delta, micros);
+        let mut start =
+            self.ctx.start - range.map_or(self.ctx.lookback_delta, micros) - self.subquery_lookback;
         let mut end = self.ctx.end; // 30 minutes + 5m = 35m
 
         if let Some(offset) = selector.offset.clone() {
@@ -577,6 +614,89 @@ impl Engine {
             }
         }
 
+        // read the part of a range query which is rolled up from the rollup stream
+        let table_name = selector.name.as_ref().unwrap();
+        if range.is_none() && self.ctx.start < self.ctx.end {
+            if let Some((granularity, rolled_up)) =
+                rollup::select_rollup(&self.ctx.org_id, table_name, start, self.ctx.interval).await
+            {
+                let rolled_up = rolled_up.min(end);
+                let mut metrics = self
+                    .load_rollup(selector, granularity, start, rolled_up)
+                    .await?;
+                if rolled_up < end {
+                    let raw = self.load_range(selector, rolled_up, end).await?;
+                    merge_series(&mut metrics, raw);
+                }
+                return Ok(metrics);
+            }
+        }
+
+        self.load_range(selector, start, end).await
+    }
+
+    /// Loads the `last` series of the rollup stream as the series of the raw
+    /// stream, a plain selector evaluates to the latest sample like on the raw
+    /// stream. Every bucket is repeated over its time span so that each
+    /// evaluation in the bucket finds it within the lookback delta.
+    async fn load_rollup(
+        &self,
+        selector: &VectorSelector,
+        granularity: rollup::Granularity,
+        start: i64,
+        end: i64,
+    ) -> Result<HashMap<HashLabelValue, RangeValue>> {
+        let table_name = selector.name.as_ref().unwrap();
+        let mut rollup_selector = selector.clone();
+        rollup_selector.name = Some(granularity.stream_name(table_name));
+        rollup_selector
+            .matchers
+            .matchers
+            .retain(|mat| mat.name != NAME_LABEL);
+        rollup_selector.matchers.matchers.push(Matcher::new(
+            MatchOp::Equal,
+            rollup::AGGREGATE_LABEL,
+            "last",
+        ));
+        // the bucket containing the start also covers the first evaluations
+        let mut metrics = self
+            .load_range(&rollup_selector, granularity.align(start) - 1, end)
+            .await?;
+        let lookback = self.ctx.lookback_delta.max(1) as usize;
+        for metric in metrics.values_mut() {
+            metric.labels = metric
+                .labels
+                .iter()
+                .filter(|label| label.name != rollup::AGGREGATE_LABEL)
+                .map(|label| {
+                    if label.name == NAME_LABEL {
+                        Arc::new(Label::new(NAME_LABEL, table_name.as_str()))
+                    } else {
+                        label.clone()
+                    }
+                })
+                .collect();
+            metric.samples = metric
+                .samples
+                .iter()
+                .flat_map(|sample| {
+                    (sample.timestamp..sample.timestamp + granularity.micros())
+                        .step_by(lookback)
+                        .map(move |timestamp| Sample::new(timestamp, sample.value))
+                })
+                .collect();
+        }
+        Ok(metrics)
+    }
+
+    async fn load_range(
+        &self,
+        selector: &VectorSelector,
+        start: i64,
+        end: i64,
+    ) -> Result<HashMap<HashLabelValue, RangeValue>> {
+        let start_time = std::time::Instant::now();
+
         // 1. Group by metrics (sets of label name-value pairs)
         let table_name = selector.name.as_ref().unwrap();
         log::info!(
@@ -706,6 +826,19 @@ impl Engine {
             token::T_QUANTILE => {
                 aggregations::quantile(self, sample_time, param.clone().unwrap(), &input).await?
             }
+            token::T_LIMITK | token::T_LIMIT_RATIO
+                if !config::get_config().common.promql_experimental_enabled =>
+            {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "{op} is experimental, set ZO_PROMQL_EXPERIMENTAL_ENABLED=true to enable it"
+                )));
+            }
+            token::T_LIMITK => {
+                aggregations::limitk(self, param.clone().unwrap(), modifier, &input).await?
+            }
+            token::T_LIMIT_RATIO => {
+                aggregations::limit_ratio(self, param.clone().unwrap(), &input).await?
+            }
             _ => {
                 return Err(DataFusionError::NotImplemented(format!(
                     "Unsupported Aggregate: {:?}",
@@ -790,6 +923,7 @@ impl Engine {
             "hour",
             "minute",
             "month",
+            "pi",
             "time",
             "year",
         ]);
@@ -823,10 +957,11 @@ impl Engine {
         Ok(match func_name {
             Func::Abs => functions::abs(&input)?,
             Func::Absent => functions::absent(&input, self.time)?,
-            Func::AbsentOverTime => functions::absent_over_time(&input)?,
+            Func::AbsentOverTime => functions::absent_over_time(&input, self.time)?,
             Func::AvgOverTime => functions::avg_over_time(&input)?,
             Func::Ceil => functions::ceil(&input)?,
             Func::Changes => functions::changes(&input)?,
+            Func::Cos => functions::cos(&input)?,
             Func::Clamp => {
                 let err =
                     "Invalid args, expected \"clamp(v instant-vector, min scalar, max scalar)\"";
@@ -837,12 +972,7 @@ impl Engine {
                 let max = self.call_expr_third_arg(args).await?;
 
                 let (min_f, max_f) = match (min, max) {
-                    (Value::Float(min), Value::Float(max)) => {
-                        if min > max {
-                            return Ok(Value::Vector(vec![]));
-                        }
-                        (min, max)
-                    }
+                    (Value::Float(min), Value::Float(max)) => (min, max),
                     _ => {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
@@ -861,7 +991,7 @@ impl Engine {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
                 };
-                functions::clamp(&input, f64::MIN, max_f)?
+                functions::clamp_max(&input, max_f)?
             }
             Func::ClampMin => {
                 let err = "Invalid args, expected \"clamp(v instant-vector, min scalar)\"";
@@ -875,16 +1005,17 @@ impl Engine {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
                 };
-                functions::clamp(&input, min_f, f64::MAX)?
+                functions::clamp_min(&input, min_f)?
             }
             Func::CountOverTime => functions::count_over_time(&input)?,
-            Func::DayOfMonth => functions::day_of_month(&input)?,
-            Func::DayOfWeek => functions::day_of_week(&input)?,
-            Func::DayOfYear => functions::day_of_year(&input)?,
-            Func::DaysInMonth => functions::days_in_month(&input)?,
+            Func::DayOfMonth => functions::day_of_month(&input, self.ctx.timezone.as_deref())?,
+            Func::DayOfWeek => functions::day_of_week(&input, self.ctx.timezone.as_deref())?,
+            Func::DayOfYear => functions::day_of_year(&input, self.ctx.timezone.as_deref())?,
+            Func::DaysInMonth => functions::days_in_month(&input, self.ctx.timezone.as_deref())?,
             Func::Delta => functions::delta(&input)?,
             Func::Deriv => functions::deriv(&input)?,
             Func::Exp => functions::exp(&input)?,
+            Func::FirstOverTime => functions::first_over_time(&input)?,
             Func::Floor => functions::floor(&input)?,
             Func::HistogramCount => {
                 return Err(DataFusionError::NotImplemented(format!(
@@ -941,7 +1072,7 @@ impl Engine {
 
                 functions::holt_winters(&input, scaling_factor, trend_factor)?
             }
-            Func::Hour => functions::hour(&input)?,
+            Func::Hour => functions::hour(&input, self.ctx.timezone.as_deref())?,
             Func::Idelta => functions::idelta(&input)?,
             Func::Increase => functions::increase(&input)?,
             Func::Irate => functions::irate(&input)?,
@@ -959,14 +1090,14 @@ impl Engine {
 
                 let mut source_labels = vec![];
                 for each_src in args.args[3..].iter() {
-                    if let Value::String(label) = self.exec_expr(each_src).await.unwrap() {
-                        source_labels.push(label);
-                    };
-                }
-                if source_labels.is_empty() {
-                    return Err(DataFusionError::NotImplemented(
-                        "source labels can not be empty or invalid".into(),
-                    ));
+                    match self.exec_expr(each_src).await? {
+                        Value::String(label) => source_labels.push(label),
+                        _ => {
+                            return Err(DataFusionError::NotImplemented(
+                                "Invalid source label found".into(),
+                            ));
+                        }
+                    }
                 }
                 functions::label_join(&input, &dst_label, &separator, source_labels)?
             }
@@ -997,10 +1128,12 @@ impl Engine {
             Func::Ln => functions::ln(&input)?,
             Func::Log10 => functions::log10(&input)?,
             Func::Log2 => functions::log2(&input)?,
+            Func::MadOverTime => functions::mad_over_time(&input)?,
             Func::MaxOverTime => functions::max_over_time(&input)?,
             Func::MinOverTime => functions::min_over_time(&input)?,
-            Func::Minute => functions::minute(&input)?,
-            Func::Month => functions::month(&input)?,
+            Func::Minute => functions::minute(&input, self.ctx.timezone.as_deref())?,
+            Func::Month => functions::month(&input, self.ctx.timezone.as_deref())?,
+            Func::Pi => functions::pi()?,
             Func::PredictLinear => {
                 let err = "Invalid args, expected \"predict_linear(v range-vector, t scalar)\"";
 
@@ -1014,6 +1147,7 @@ impl Engine {
                 )?;
                 functions::predict_linear(&input, prediction_steps)?
             }
+            Func::PresentOverTime => functions::present_over_time(&input)?,
             Func::QuantileOverTime => {
                 let err = "Invalid args, expected \"quantile_over_time(scalar, range-vector)\"";
 
@@ -1032,60 +1166,87 @@ impl Engine {
             Func::Rate => functions::rate(&input)?,
             Func::Resets => functions::resets(&input)?,
             Func::Round => functions::round(&input)?,
-            Func::Scalar => match input {
-                Value::Float(_) => input,
-                _ => {
-                    return Err(DataFusionError::NotImplemented(format!(
-                        "Invalid scalar value: {:?}",
-                        input
-                    )));
-                }
-            },
+            Func::Scalar => functions::scalar(&input)?,
             Func::Sgn => functions::sgn(&input)?,
-            Func::Sort => {
-                return Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported Function: {:?}",
-                    func_name
-                )));
-            }
-            Func::SortDesc => {
-                return Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported Function: {:?}",
-                    func_name
-                )));
-            }
+            Func::Sin => functions::sin(&input)?,
+            Func::Sort => functions::sort(&input)?,
+            Func::SortDesc => functions::sort_desc(&input)?,
             Func::Sqrt => functions::sqrt(&input)?,
             Func::StddevOverTime => functions::stddev_over_time(&input)?,
             Func::StdvarOverTime => functions::stdvar_over_time(&input)?,
             Func::SumOverTime => functions::sum_over_time(&input)?,
             Func::Time => Value::Float((self.time / 1_000_000) as f64),
-            Func::Timestamp => match &input {
-                Value::Vector(instant_value) => {
-                    let out: Vec<InstantValue> = instant_value
-                        .iter()
-                        .map(|instant| InstantValue {
-                            labels: instant.labels.without_metric_name(),
-                            sample: Sample {
-                                timestamp: instant.sample.timestamp,
-                                value: (instant.sample.timestamp / 1000 / 1000) as f64,
-                            },
-                        })
-                        .collect();
-                    Value::Vector(out)
-                }
-                _ => {
-                    return Err(DataFusionError::NotImplemented(format!(
-                        "Unexpected input to timestamp function: {:?}",
-                        &input
-                    )));
-                }
-            },
+            Func::Timestamp => functions::timestamp(&input)?,
             Func::Vector => functions::vector(&input, self.time)?,
-            Func::Year => functions::year(&input)?,
+            Func::Year => functions::year(&input, self.ctx.timezone.as_deref())?,
         })
     }
 }
 
+/// Appends the samples of the raw series to the rollup series with the same
+/// labels, the series are keyed by different hashes in the two streams.
+fn merge_series(
+    metrics: &mut HashMap<HashLabelValue, RangeValue>,
+    raw: HashMap<HashLabelValue, RangeValue>,
+) {
+    let keys: HashMap<Signature, HashLabelValue> = metrics
+        .iter()
+        .map(|(key, metric)| (metric.labels.signature(), key.clone()))
+        .collect();
+    for (key, value) in raw {
+        match keys
+            .get(&value.labels.signature())
+            .and_then(|key| metrics.get_mut(key))
+        {
+            Some(metric) => metric.extend(value),
+            None => {
+                metrics.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Evaluation timestamps of a subquery, the multiples of the step in the
+/// range `(eval_ts - range, eval_ts]`. Steps are aligned to the epoch rather
+/// than to the evaluation time, so consecutive evaluations of a range query
+/// see the same inner samples.
+fn subquery_steps(eval_ts: i64, range: i64, step: i64) -> impl Iterator<Item = i64> {
+    let step = step.max(1);
+    let start = eval_ts - range;
+    let first = start.div_euclid(step) * step + step;
+    (first..=eval_ts).step_by(step as usize)
+}
+
+/// Time before the start of the query the data needs to be loaded for,
+/// so the inner expressions of subqueries can be evaluated over their range.
+fn subquery_lookback(expr: &PromExpr) -> i64 {
+    match expr {
+        PromExpr::Subquery(expr) => {
+            let offset = match expr.offset {
+                Some(Offset::Pos(offset)) => micros(offset),
+                _ => 0,
+            };
+            micros(expr.range) + offset + subquery_lookback(&expr.expr)
+        }
+        PromExpr::Aggregate(AggregateExpr { expr, param, .. }) => {
+            let param = param.as_ref().map_or(0, |param| subquery_lookback(param));
+            subquery_lookback(expr).max(param)
+        }
+        PromExpr::Unary(UnaryExpr { expr }) => subquery_lookback(expr),
+        PromExpr::Binary(BinaryExpr { lhs, rhs, .. }) => {
+            subquery_lookback(lhs).max(subquery_lookback(rhs))
+        }
+        PromExpr::Paren(ParenExpr { expr }) => subquery_lookback(expr),
+        PromExpr::Call(Call { args, .. }) => args
+            .args
+            .iter()
+            .map(|arg| subquery_lookback(arg))
+            .max()
+            .unwrap_or_default(),
+        _ => 0,
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn selector_load_data_from_datafusion(
     trace_id: &str,
@@ -1535,3 +1696,131 @@ async fn load_exemplars_from_datafusion(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use config::meta::search::ScanStats;
+    use datafusion::arrow::{array::RecordBatch, datatypes::Field};
+
+    use super::*;
+    use crate::service::promql::TableProvider;
+
+    // not aligned to a minute
+    const END: i64 = 1_700_000_000_000_000;
+
+    /// A counter increasing by 1 every 15 seconds over the 3 hours before END
+    struct MockProvider;
+
+    #[async_trait]
+    impl TableProvider for MockProvider {
+        async fn create_context(
+            &self,
+            _org_id: &str,
+            stream_name: &str,
+            _time_range: (i64, i64),
+            _matchers: promql_parser::label::Matchers,
+            _label_selector: Option<HashSet<String>>,
+            _filters: &mut [(String, Vec<String>)],
+        ) -> Result<Vec<(SessionContext, Arc<Schema>, ScanStats)>> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("_timestamp", DataType::Int64, false),
+                Field::new(NAME_LABEL, DataType::Utf8, false),
+                Field::new(HASH_LABEL, DataType::Utf8, false),
+                Field::new(VALUE_LABEL, DataType::Float64, false),
+            ]));
+            let n = 720;
+            let timestamps = (0..n)
+                .map(|i| END - (n - i) * 15_000_000)
+                .collect::<Vec<_>>();
+            let values = (0..n).map(|i| i as f64).collect::<Vec<_>>();
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(timestamps)),
+                    Arc::new(StringArray::from(vec![stream_name; n as usize])),
+                    Arc::new(StringArray::from(vec!["1"; n as usize])),
+                    Arc::new(Float64Array::from(values)),
+                ],
+            )?;
+            let ctx = SessionContext::new();
+            ctx.register_batch(stream_name, batch)?;
+            Ok(vec![(ctx, schema, ScanStats::default())])
+        }
+    }
+
+    async fn exec(query: &str) -> Value {
+        exec_in_timezone(query, None).await
+    }
+
+    async fn exec_in_timezone(query: &str, timezone: Option<&str>) -> Value {
+        let mut ctx = PromqlContext::new("default", MockProvider, false, 60);
+        ctx.start = END;
+        ctx.end = END;
+        ctx.timezone = timezone.map(|tz| tz.to_string());
+        let mut engine = Engine::new("test", Arc::new(ctx), END);
+        let expr = promql_parser::parser::parse(query).unwrap();
+        engine.exec(&expr).await.unwrap().0
+    }
+
+    #[test]
+    fn test_subquery_steps() {
+        let minute = 60_000_000;
+        let steps = subquery_steps(END, 60 * minute, minute).collect::<Vec<_>>();
+        assert_eq!(steps.len(), 60);
+        assert!(steps.iter().all(|ts| ts % minute == 0));
+        assert!(steps[0] > END - 60 * minute);
+        assert!(*steps.last().unwrap() <= END);
+        // the end of the range is included when it is aligned
+        let steps = subquery_steps(60 * minute, 5 * minute, minute).collect::<Vec<_>>();
+        assert_eq!(steps, (56..=60).map(|m| m * minute).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_subquery_lookback() {
+        let minute = 60_000_000;
+        let lookback = |query| subquery_lookback(&promql_parser::parser::parse(query).unwrap());
+        assert_eq!(lookback("rate(metric[5m])"), 0);
+        assert_eq!(lookback("rate(metric[5m])[1h:1m]"), 60 * minute);
+        assert_eq!(
+            lookback("max_over_time(rate(metric[5m])[1h:1m] offset 10m) + metric"),
+            70 * minute
+        );
+        assert_eq!(
+            lookback("max_over_time(max_over_time(metric[5m:1m])[1h:])"),
+            65 * minute
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eval_subquery() {
+        let Value::Matrix(matrix) = exec("rate(metric[5m])[1h:1m]").await else {
+            panic!("subquery should return a matrix");
+        };
+        assert_eq!(matrix.len(), 1);
+        let samples = &matrix[0].samples;
+        assert_eq!(samples.len(), 60);
+        assert!(samples.iter().all(|s| s.timestamp % 60_000_000 == 0));
+        assert!(samples.iter().all(|s| (s.value - 1.0 / 15.0).abs() < 1e-3));
+    }
+
+    #[tokio::test]
+    async fn test_eval_function_over_subquery() {
+        let Value::Vector(vector) = exec("max_over_time(rate(metric[5m])[1h:1m])").await else {
+            panic!("function over subquery should return a vector");
+        };
+        assert_eq!(vector.len(), 1);
+        assert!((vector[0].sample.value - 1.0 / 15.0).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn test_eval_time_function_in_timezone() {
+        // the epoch is 19:00 of the previous day in UTC-5
+        for (timezone, hour) in [(None, 0.0), (Some("Etc/GMT+5"), 19.0)] {
+            let Value::Vector(vector) = exec_in_timezone("hour(vector(0))", timezone).await else {
+                panic!("hour should return a vector");
+            };
+            assert_eq!(vector[0].sample.value, hour);
+        }
+    }
+}
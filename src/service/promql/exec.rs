@@ -21,7 +21,7 @@ use std::{
 use config::meta::search::ScanStats;
 use datafusion::error::{DataFusionError, Result};
 use hashbrown::{HashMap, HashSet};
-use promql_parser::parser::EvalStmt;
+use promql_parser::parser::{EvalStmt, Expr};
 use tokio::sync::{Mutex, RwLock, Semaphore};
 
 use super::Engine;
@@ -43,6 +43,8 @@ pub struct PromqlContext {
     /// Default look back from sample search.
     pub lookback_delta: i64,
     pub query_exemplars: bool,
+    /// IANA timezone of the time functions, UTC if `None`.
+    pub timezone: Option<String>,
     /// key — metric name; value — time series data
     pub data_cache: Arc<RwLock<HashMap<String, Value>>>,
     pub scan_stats: Arc<RwLock<ScanStats>>,
@@ -64,6 +66,7 @@ impl PromqlContext {
             end: now,
             interval: five_min,
             query_exemplars,
+            timezone: None,
             lookback_delta: five_min,
             data_cache: Arc::new(RwLock::new(HashMap::default())),
             data_loading: Arc::new(Mutex::new(HashSet::default())),
@@ -102,7 +105,10 @@ impl PromqlContext {
             if let Value::Float(val) = value {
                 value = Value::Sample(Sample::new(self.end, val));
             }
-            value.sort();
+            // keep the order of sort() and sort_desc()
+            if !is_sort_expr(&expr) {
+                value.sort();
+            }
             if result_type_exec.is_some() {
                 result_type = result_type_exec;
             }
@@ -291,3 +297,11 @@ impl PromqlContext {
         Ok((value, result_type, *self.scan_stats.read().await))
     }
 }
+
+fn is_sort_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(call) => matches!(call.func.name, "sort" | "sort_desc"),
+        Expr::Paren(paren) => is_sort_expr(&paren.expr),
+        _ => false,
+    }
+}
@@ -13,18 +13,116 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use datafusion::error::Result;
+use datafusion::error::{DataFusionError, Result};
 
-use crate::service::promql::value::{RangeValue, Value};
+use crate::service::promql::value::{InstantValue, Labels, LabelsExt, Sample, Value};
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#absent_over_time
-pub(crate) fn absent_over_time(data: &Value) -> Result<Value> {
-    super::eval_idelta(data, "absent_over_time", exec, false)
+///
+/// Returns an empty vector if any of the series has samples in the range,
+/// otherwise a single element vector with the value 1. The labels of the
+/// element are the labels shared by all the series, without `__name__`.
+pub(crate) fn absent_over_time(data: &Value, eval_ts: i64) -> Result<Value> {
+    let data = match data {
+        Value::Matrix(v) => v,
+        Value::None => {
+            return Ok(Value::Vector(vec![InstantValue {
+                labels: Labels::default(),
+                sample: Sample::new(eval_ts, 1.0),
+            }]));
+        }
+        v => {
+            return Err(DataFusionError::Plan(format!(
+                "absent_over_time: matrix argument expected but got {}",
+                v.get_type()
+            )));
+        }
+    };
+
+    if data.iter().any(|metric| !metric.samples.is_empty()) {
+        return Ok(Value::Vector(vec![]));
+    }
+
+    let labels = match data.split_first() {
+        Some((first, rest)) => first
+            .labels
+            .without_metric_name()
+            .into_iter()
+            .filter(|label| {
+                rest.iter()
+                    .all(|metric| metric.labels.get_value(&label.name) == label.value)
+            })
+            .collect(),
+        None => Labels::default(),
+    };
+    let eval_ts = data
+        .first()
+        .and_then(|metric| metric.time_window.as_ref())
+        .map(|window| window.eval_ts)
+        .unwrap_or(eval_ts);
+    Ok(Value::Vector(vec![InstantValue {
+        labels,
+        sample: Sample::new(eval_ts, 1.0),
+    }]))
 }
 
-fn exec(data: &RangeValue) -> Option<f64> {
-    if data.samples.is_empty() {
-        return Some(1.0);
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use config::meta::promql::NAME_LABEL;
+
+    use super::*;
+    use crate::service::promql::value::{Label, RangeValue, TimeWindow};
+
+    fn range_value(labels: &[(&str, &str)], samples: Vec<Sample>) -> RangeValue {
+        RangeValue {
+            labels: labels
+                .iter()
+                .map(|(name, value)| Arc::new(Label::new(*name, *value)))
+                .collect(),
+            samples,
+            exemplars: None,
+            time_window: Some(TimeWindow::new(80_000_000, Duration::from_secs(30))),
+        }
+    }
+
+    #[test]
+    fn test_absent_over_time_with_samples() {
+        let data = Value::Matrix(vec![
+            range_value(&[(NAME_LABEL, "up"), ("job", "a")], vec![]),
+            range_value(
+                &[(NAME_LABEL, "up"), ("job", "b")],
+                vec![Sample::new(70_000_000, 1.0)],
+            ),
+        ]);
+        let result = absent_over_time(&data, 80_000_000).unwrap();
+        assert!(result.get_vector().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_absent_over_time_all_series_missing() {
+        let data = Value::Matrix(vec![
+            range_value(&[(NAME_LABEL, "up"), ("env", "prod"), ("job", "a")], vec![]),
+            range_value(&[(NAME_LABEL, "up"), ("env", "prod"), ("job", "b")], vec![]),
+        ]);
+        let result = absent_over_time(&data, 80_000_000).unwrap();
+        let result = result.get_vector().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].sample.value, 1.0);
+        assert_eq!(result[0].sample.timestamp, 80_000_000);
+        assert_eq!(result[0].labels.keys(), vec!["env".to_string()]);
+        assert_eq!(result[0].labels.get_value("env"), "prod");
+    }
+
+    #[test]
+    fn test_absent_over_time_no_series() {
+        for data in [Value::None, Value::Matrix(vec![])] {
+            let result = absent_over_time(&data, 90_000_000).unwrap();
+            let result = result.get_vector().unwrap();
+            assert_eq!(result.len(), 1);
+            assert!(result[0].labels.is_empty());
+            assert_eq!(result[0].sample.timestamp, 90_000_000);
+        }
     }
-    None
 }
@@ -18,26 +18,123 @@ use datafusion::error::{DataFusionError, Result};
 use crate::service::promql::value::{InstantValue, LabelsExt, Sample, Value};
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp
+///
+/// A NaN bound yields NaN samples like Prometheus, `f64::clamp` would panic.
 pub(crate) fn clamp(data: &Value, min: f64, max: f64) -> Result<Value> {
+    if min > max {
+        return Err(DataFusionError::Plan(format!(
+            "clamp: min {min} must not be greater than max {max}"
+        )));
+    }
+    exec(data, "clamp", |value| nan_max(min, nan_min(max, value)))
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp_min
+pub(crate) fn clamp_min(data: &Value, min: f64) -> Result<Value> {
+    exec(data, "clamp_min", |value| nan_max(value, min))
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp_max
+pub(crate) fn clamp_max(data: &Value, max: f64) -> Result<Value> {
+    exec(data, "clamp_max", |value| nan_min(value, max))
+}
+
+/// `math.Max` of Go: NaN if either side is NaN, unlike `f64::max`.
+fn nan_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        a.max(b)
+    }
+}
+
+/// `math.Min` of Go: NaN if either side is NaN, unlike `f64::min`.
+fn nan_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        a.min(b)
+    }
+}
+
+fn exec(data: &Value, fn_name: &str, op: impl Fn(f64) -> f64) -> Result<Value> {
     let vec = match data {
         Value::Vector(v) => v,
         Value::None => return Ok(Value::None),
         _ => {
-            return Err(DataFusionError::Plan(
-                "clamp: InstantValue argument expected".into(),
-            ));
+            return Err(DataFusionError::Plan(format!(
+                "{fn_name}: InstantValue argument expected"
+            )));
         }
     };
 
     let out = vec
         .iter()
-        .map(|instant| {
-            let value = instant.sample.value.clamp(min, max);
-            InstantValue {
-                sample: Sample::new(instant.sample.timestamp, value),
-                labels: instant.labels.without_metric_name(),
-            }
+        .map(|instant| InstantValue {
+            sample: Sample::new(instant.sample.timestamp, op(instant.sample.value)),
+            labels: instant.labels.without_metric_name(),
         })
         .collect();
     Ok(Value::Vector(out))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(data: Value) -> Vec<f64> {
+        data.get_vector()
+            .unwrap()
+            .iter()
+            .map(|v| v.sample.value)
+            .collect()
+    }
+
+    fn input() -> Value {
+        Value::Vector(
+            [1.0, 5.0, 10.0, 15.0]
+                .into_iter()
+                .map(|v| InstantValue {
+                    labels: vec![],
+                    sample: Sample::new(1_000_000, v),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_clamp() {
+        let out = clamp(&input(), 5.0, 10.0).unwrap();
+        assert_eq!(values(out), vec![5.0, 5.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_clamp_min() {
+        let out = clamp_min(&input(), 5.0).unwrap();
+        assert_eq!(values(out), vec![5.0, 5.0, 10.0, 15.0]);
+    }
+
+    #[test]
+    fn test_clamp_max() {
+        let out = clamp_max(&input(), 10.0).unwrap();
+        assert_eq!(values(out), vec![1.0, 5.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_clamp_inverted_min_max() {
+        let err = clamp(&input(), 10.0, 5.0).unwrap_err();
+        assert!(matches!(err, DataFusionError::Plan(_)));
+    }
+
+    #[test]
+    fn test_clamp_nan_bounds() {
+        let out = clamp(&input(), f64::NAN, 10.0).unwrap();
+        assert!(values(out).iter().all(|v| v.is_nan()));
+        let out = clamp(&input(), 5.0, f64::NAN).unwrap();
+        assert!(values(out).iter().all(|v| v.is_nan()));
+        let out = clamp_min(&input(), f64::NAN).unwrap();
+        assert!(values(out).iter().all(|v| v.is_nan()));
+        let out = clamp_max(&input(), f64::NAN).unwrap();
+        assert!(values(out).iter().all(|v| v.is_nan()));
+    }
+}
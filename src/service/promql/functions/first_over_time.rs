@@ -0,0 +1,50 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use datafusion::error::Result;
+
+use crate::service::promql::value::{RangeValue, Value};
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#aggregation_over_time
+pub(crate) fn first_over_time(data: &Value) -> Result<Value> {
+    // Same as last_over_time, the metric name is kept
+    super::eval_idelta(data, "first_over_time", exec, true)
+}
+
+fn exec(data: &RangeValue) -> Option<f64> {
+    data.samples.first().map(|s| s.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::promql::functions::test_utils::{matrix, samples};
+
+    #[test]
+    fn test_first_over_time() {
+        let five_points = samples(10, &[3.0, 1.0, 4.0, 1.0, 5.0]);
+        let cases = [
+            (five_points.clone(), Some(3.0)),
+            (five_points[2..3].to_vec(), Some(4.0)),
+            (vec![], None),
+        ];
+        for (points, expected) in cases {
+            let out = first_over_time(&matrix(points)).unwrap();
+            let out = out.get_vector().unwrap();
+            assert_eq!(out.first().map(|v| v.sample.value), expected);
+            assert!(out.len() <= 1);
+        }
+    }
+}
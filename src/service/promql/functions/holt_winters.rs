@@ -22,6 +22,17 @@ use crate::service::promql::{
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#holt_winters
 pub(crate) fn holt_winters(data: &Value, scaling_factor: f64, trend_factor: f64) -> Result<Value> {
+    if !(scaling_factor > 0.0 && scaling_factor < 1.0) {
+        return Err(DataFusionError::Plan(format!(
+            "holt_winters: invalid smoothing factor. Expected: 0 < sf < 1, got: {scaling_factor}"
+        )));
+    }
+    if !(trend_factor > 0.0 && trend_factor < 1.0) {
+        return Err(DataFusionError::Plan(format!(
+            "holt_winters: invalid trend factor. Expected: 0 < tf < 1, got: {trend_factor}"
+        )));
+    }
+
     let data = match data {
         Value::Matrix(v) => v,
         Value::None => return Ok(Value::None),
@@ -76,3 +87,66 @@ pub fn holt_winters_calculation(
 
     Some(current_smoothed)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::service::promql::value::TimeWindow;
+
+    fn range_value(values: &[f64]) -> Value {
+        Value::Matrix(vec![RangeValue {
+            labels: vec![],
+            samples: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| Sample::new((i as i64 + 1) * 10_000_000, *v))
+                .collect(),
+            exemplars: None,
+            time_window: Some(TimeWindow::new(60_000_000, Duration::from_secs(60))),
+        }])
+    }
+
+    #[test]
+    fn test_holt_winters() {
+        // expected values are computed with the same double exponential smoothing
+        // as Prometheus: the level starts at the first sample and the trend at
+        // the difference between the first two samples
+        let cases = [
+            (vec![1.0, 2.0, 4.0, 7.0, 11.0, 16.0], 0.5, 0.5, 13.9921875),
+            (
+                vec![10.0, 12.0, 11.0, 15.0, 14.0, 18.0],
+                0.3,
+                0.7,
+                16.9607429,
+            ),
+        ];
+        for (values, sf, tf, expected) in cases {
+            let result = holt_winters(&range_value(&values), sf, tf).unwrap();
+            let result = result.get_vector().unwrap();
+            assert_eq!(result.len(), 1);
+            assert!(
+                (result[0].sample.value - expected).abs() < 1e-6,
+                "got {}, expected {expected}",
+                result[0].sample.value
+            );
+        }
+    }
+
+    #[test]
+    fn test_holt_winters_invalid_factors() {
+        let data = range_value(&[1.0, 2.0, 3.0]);
+        for (sf, tf) in [
+            (0.0, 0.5),
+            (1.0, 0.5),
+            (0.5, 0.0),
+            (0.5, 1.0),
+            (-0.1, 1.2),
+            (f64::NAN, 0.5),
+        ] {
+            let err = holt_winters(&data, sf, tf).unwrap_err();
+            assert!(matches!(err, DataFusionError::Plan(_)));
+        }
+    }
+}
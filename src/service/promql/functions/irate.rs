@@ -38,3 +38,35 @@ fn exec(data: &RangeValue) -> Option<f64> {
     };
     Some(dt_value / dt_seconds)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::service::promql::value::{Sample, TimeWindow};
+
+    #[test]
+    fn test_irate_uses_last_two_samples() {
+        let samples = [(50, 0.0), (60, 10.0), (70, 20.0), (80, 100.0)]
+            .into_iter()
+            .map(|(ts, v)| Sample::new(ts * 1_000_000, v))
+            .collect();
+        let data = Value::Matrix(vec![RangeValue {
+            labels: vec![],
+            samples,
+            exemplars: None,
+            time_window: Some(TimeWindow::new(80_000_000, Duration::from_secs(40))),
+        }]);
+
+        let irate = irate(&data).unwrap();
+        let irate = irate.get_vector().unwrap();
+        assert_eq!(irate.len(), 1);
+        assert_eq!(irate[0].sample.value, 8.0);
+
+        let rate = super::super::rate(&data).unwrap();
+        let rate = rate.get_vector().unwrap();
+        assert_eq!(rate.len(), 1);
+        assert_ne!(rate[0].sample.value, irate[0].sample.value);
+    }
+}
@@ -13,15 +13,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::HashSet, sync::Arc};
-
-use config::meta::promql::NAME_LABEL;
 use datafusion::error::{DataFusionError, Result};
 use itertools::Itertools;
 
-use crate::service::promql::value::{InstantValue, Label, Value};
+use crate::service::promql::value::{InstantValue, LabelsExt, Value};
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#label_join
+///
+/// The values of the source labels are joined in the given order, a missing
+/// source label contributes an empty string. An existing destination label is
+/// overwritten.
 pub(crate) fn label_join(
     data: &Value,
     dest_label: &str,
@@ -38,22 +39,16 @@ pub(crate) fn label_join(
         }
     };
 
-    let keep_source_labels: HashSet<String> = HashSet::from_iter(source_labels);
     let rate_values: Vec<InstantValue> = data
         .iter()
         .map(|instant| {
-            let new_label = instant
-                .labels
+            let new_label = source_labels
                 .iter()
-                .filter(|l| l.name != NAME_LABEL && keep_source_labels.contains(&l.name))
-                .map(|label| label.value.clone())
+                .map(|name| instant.labels.get_value(name))
                 .join(separator);
 
-            let mut new_labels = instant.labels.clone();
-            new_labels.push(Arc::new(Label {
-                name: dest_label.to_string(),
-                value: new_label,
-            }));
+            let mut new_labels = instant.labels.without_label(dest_label);
+            new_labels.set(dest_label, &new_label);
             InstantValue {
                 labels: new_labels,
                 sample: instant.sample.clone(),
@@ -62,3 +57,51 @@ pub(crate) fn label_join(
         .collect();
     Ok(Value::Vector(rate_values))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::{Label, Sample};
+
+    fn input() -> Value {
+        Value::Vector(vec![InstantValue {
+            labels: vec![
+                Arc::new(Label::new("__name__", "up")),
+                Arc::new(Label::new("job", "api")),
+                Arc::new(Label::new("instance", "host-1")),
+                Arc::new(Label::new("dst", "old")),
+            ],
+            sample: Sample::new(1_000_000, 1.0),
+        }])
+    }
+
+    fn join(src: &[&str]) -> Vec<InstantValue> {
+        let src = src.iter().map(|s| s.to_string()).collect();
+        let out = label_join(&input(), "dst", ",", src).unwrap();
+        out.get_vector().unwrap().clone()
+    }
+
+    #[test]
+    fn test_label_join() {
+        let out = join(&["instance", "job", "__name__"]);
+        assert_eq!(out[0].labels.get_value("dst"), "host-1,api,up");
+        // the destination label is overwritten, not duplicated
+        assert_eq!(out[0].labels.iter().filter(|l| l.name == "dst").count(), 1);
+        assert_eq!(out[0].labels.len(), 4);
+    }
+
+    #[test]
+    fn test_label_join_missing_source_label() {
+        let out = join(&["job", "missing", "instance"]);
+        assert_eq!(out[0].labels.get_value("dst"), "api,,host-1");
+    }
+
+    #[test]
+    fn test_label_join_no_source_labels() {
+        let out = join(&[]);
+        assert_eq!(out[0].labels.get_value("dst"), "");
+        assert_eq!(out[0].labels.len(), 4);
+    }
+}
@@ -13,8 +13,6 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
-
 use datafusion::error::{DataFusionError, Result};
 use rayon::prelude::*;
 use regex::Regex;
@@ -22,6 +20,11 @@ use regex::Regex;
 use crate::service::promql::value::{InstantValue, Label, LabelsExt, Value};
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#label_replace
+///
+/// The regex is anchored and matched against the value of `source_label`. On a
+/// match the destination label is set to the expanded replacement, or removed
+/// when the replacement expands to an empty string. Series without the source
+/// label are returned unchanged.
 pub(crate) fn label_replace(
     data: &Value,
     dest_label: &str,
@@ -46,26 +49,25 @@ pub(crate) fn label_replace(
         )));
     }
 
-    let re = Regex::new(regex)
-        .map_err(|_e| DataFusionError::NotImplemented("Invalid regex found".into()))?;
+    let re = Regex::new(&format!("^(?:{regex})$"))
+        .map_err(|e| DataFusionError::Plan(format!("label_replace: invalid regex {regex}: {e}")))?;
 
     let rate_values: Vec<InstantValue> = data
         .par_iter()
         .map(|instant| {
-            let labels = if replacement.is_empty() {
-                instant.labels.without_label(dest_label)
-            } else {
-                let label_value = instant.labels.get_value(source_label);
-                let output_value = re.replace_all(&label_value, replacement);
-                let mut new_labels = instant.labels.clone();
-                if output_value != label_value {
-                    new_labels.push(Arc::new(Label {
-                        name: dest_label.to_string(),
-                        value: output_value.to_string(),
-                    }));
-                }
-                new_labels
+            if !instant.labels.iter().any(|l| l.name == source_label) {
+                return instant.clone();
+            }
+            let label_value = instant.labels.get_value(source_label);
+            let Some(captures) = re.captures(&label_value) else {
+                return instant.clone();
             };
+            let mut output_value = String::new();
+            captures.expand(replacement, &mut output_value);
+            let mut labels = instant.labels.without_label(dest_label);
+            if !output_value.is_empty() {
+                labels.set(dest_label, &output_value);
+            }
             InstantValue {
                 labels,
                 sample: instant.sample.clone(),
@@ -74,3 +76,57 @@ pub(crate) fn label_replace(
         .collect();
     Ok(Value::Vector(rate_values))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::Sample;
+
+    fn input() -> Value {
+        Value::Vector(vec![InstantValue {
+            labels: vec![
+                Arc::new(Label::new("job", "api")),
+                Arc::new(Label::new("instance", "host-1:9090")),
+            ],
+            sample: Sample::new(1_000_000, 1.0),
+        }])
+    }
+
+    fn replace(dest: &str, replacement: &str, src: &str, regex: &str) -> InstantValue {
+        let out = label_replace(&input(), dest, replacement, src, regex).unwrap();
+        out.get_vector().unwrap()[0].clone()
+    }
+
+    #[test]
+    fn test_label_replace() {
+        let out = replace("host", "$1", "instance", "(.*):.*");
+        assert_eq!(out.labels.get_value("host"), "host-1");
+        assert_eq!(out.labels.len(), 3);
+
+        // existing labels are overwritten
+        let out = replace("job", "${1}-job", "job", "(.*)");
+        assert_eq!(out.labels.get_value("job"), "api-job");
+        assert_eq!(out.labels.len(), 2);
+
+        // empty replacement removes the destination label
+        let out = replace("job", "", "instance", ".*");
+        assert!(!out.labels.iter().any(|l| l.name == "job"));
+    }
+
+    #[test]
+    fn test_label_replace_no_match() {
+        // the regex is anchored, so a partial match is not a match
+        let out = replace("host", "$1", "instance", "host");
+        assert_eq!(out.labels.len(), 2);
+        let out = replace("host", "$1", "missing", "(.*)");
+        assert_eq!(out.labels.len(), 2);
+    }
+
+    #[test]
+    fn test_label_replace_invalid_regex() {
+        let err = label_replace(&input(), "host", "$1", "instance", "(.*").unwrap_err();
+        assert!(matches!(err, DataFusionError::Plan(_)));
+    }
+}
@@ -33,3 +33,25 @@ fn exec(data: &RangeValue) -> Option<f64> {
     }
     Some(data.samples.last().unwrap().value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::promql::functions::test_utils::{matrix, samples};
+
+    #[test]
+    fn test_last_over_time() {
+        let five_points = samples(10, &[3.0, 1.0, 4.0, 1.0, 5.0]);
+        let cases = [
+            (five_points.clone(), Some(5.0)),
+            (five_points[2..3].to_vec(), Some(4.0)),
+            (vec![], None),
+        ];
+        for (points, expected) in cases {
+            let out = last_over_time(&matrix(points)).unwrap();
+            let out = out.get_vector().unwrap();
+            assert_eq!(out.first().map(|v| v.sample.value), expected);
+            assert!(out.len() <= 1);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use datafusion::error::Result;
+
+use crate::service::promql::{
+    common::quantile,
+    value::{RangeValue, Value},
+};
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#aggregation_over_time
+pub(crate) fn mad_over_time(data: &Value) -> Result<Value> {
+    super::eval_idelta(data, "mad_over_time", exec, false)
+}
+
+fn exec(data: &RangeValue) -> Option<f64> {
+    let values: Vec<f64> = data.samples.iter().map(|s| s.value).collect();
+    let median = quantile(&values, 0.5)?;
+    let deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+    quantile(&deviations, 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::service::promql::value::{Sample, TimeWindow};
+
+    #[test]
+    fn test_mad_over_time() {
+        let cases = [
+            // symmetric
+            (vec![1.0, 2.0, 3.0, 4.0, 5.0], Some(1.0)),
+            (vec![10.0, 20.0, 30.0, 40.0], Some(10.0)),
+            // asymmetric
+            (vec![1.0, 1.0, 2.0, 2.0, 4.0, 6.0, 9.0], Some(1.0)),
+            (vec![1.0, 2.0, 3.0, 10.0], Some(1.0)),
+            (vec![7.0], Some(0.0)),
+            (vec![], None),
+        ];
+        for (values, expected) in cases {
+            let data = Value::Matrix(vec![RangeValue {
+                labels: vec![],
+                samples: values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| Sample::new((i as i64 + 1) * 1_000_000, *v))
+                    .collect(),
+                exemplars: None,
+                time_window: Some(TimeWindow::new(60_000_000, Duration::from_secs(60))),
+            }]);
+            let out = mad_over_time(&data).unwrap();
+            let out = out.get_vector().unwrap();
+            assert_eq!(out.first().map(|v| v.sample.value), expected, "{values:?}");
+        }
+    }
+}
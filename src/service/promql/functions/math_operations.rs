@@ -22,6 +22,7 @@ use crate::service::promql::value::{InstantValue, LabelsExt, Sample, Value};
 pub enum MathOperationsType {
     Abs,
     Ceil,
+    Cos,
     Exp,
     Floor,
     Ln,
@@ -29,6 +30,7 @@ pub enum MathOperationsType {
     Log2,
     Round,
     Sgn,
+    Sin,
     Sqrt,
 }
 
@@ -38,12 +40,16 @@ impl MathOperationsType {
         match self {
             Self::Abs => input.abs(),
             Self::Ceil => input.ceil(),
+            Self::Cos => input.cos(),
             Self::Exp => input.exp(),
             Self::Floor => input.floor(),
             Self::Ln => input.ln(),
             Self::Log2 => input.log2(),
             Self::Log10 => input.log10(),
+            // f64::signum returns 1.0 for 0.0 and -1.0 for -0.0
+            Self::Sgn if input == 0.0 => 0.0,
             Self::Sgn => input.signum(),
+            Self::Sin => input.sin(),
             Self::Sqrt => input.sqrt(),
             Self::Round => input.round(),
         }
@@ -90,6 +96,19 @@ pub(crate) fn sgn(data: &Value) -> Result<Value> {
     exec(data, &MathOperationsType::Sgn)
 }
 
+pub(crate) fn sin(data: &Value) -> Result<Value> {
+    exec(data, &MathOperationsType::Sin)
+}
+
+pub(crate) fn cos(data: &Value) -> Result<Value> {
+    exec(data, &MathOperationsType::Cos)
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#trigonometric-functions
+pub(crate) fn pi() -> Result<Value> {
+    Ok(Value::Float(std::f64::consts::PI))
+}
+
 fn exec(data: &Value, op: &MathOperationsType) -> Result<Value> {
     match &data {
         Value::Vector(v) => {
@@ -105,6 +124,7 @@ fn exec(data: &Value, op: &MathOperationsType) -> Result<Value> {
                 .collect();
             Ok(Value::Vector(out))
         }
+        Value::Float(val) => Ok(Value::Float(op.apply(*val))),
         Value::None => Ok(Value::None),
         _ => Err(DataFusionError::NotImplemented(format!(
             "Invalid input for minute value: {:?}",
@@ -112,3 +132,51 @@ fn exec(data: &Value, op: &MathOperationsType) -> Result<Value> {
         ))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sgn() {
+        let data = Value::Vector(
+            [-3.5, -0.0, 0.0, 2.0]
+                .into_iter()
+                .map(|v| InstantValue {
+                    labels: vec![],
+                    sample: Sample::new(1_000_000, v),
+                })
+                .collect(),
+        );
+        let out = sgn(&data).unwrap();
+        let out: Vec<f64> = out
+            .get_vector()
+            .unwrap()
+            .iter()
+            .map(|v| v.sample.value)
+            .collect();
+        assert_eq!(out, vec![-1.0, 0.0, 0.0, 1.0]);
+        assert!(out[1].is_sign_positive());
+    }
+
+    #[test]
+    fn test_pi() {
+        let pi = pi().unwrap();
+        assert_eq!(pi.get_float(), Some(std::f64::consts::PI));
+        let sin_pi = sin(&pi).unwrap().get_float().unwrap();
+        assert!(sin_pi.abs() < 1e-12);
+        assert_eq!(cos(&pi).unwrap().get_float(), Some(-1.0));
+    }
+
+    #[test]
+    fn test_sgn_scalar() {
+        assert_eq!(sgn(&Value::Float(-2.0)).unwrap().get_float(), Some(-1.0));
+        assert_eq!(sgn(&Value::Float(0.0)).unwrap().get_float(), Some(0.0));
+        assert_eq!(sgn(&Value::Float(7.0)).unwrap().get_float(), Some(1.0));
+        assert!(sgn(&Value::Float(f64::NAN))
+            .unwrap()
+            .get_float()
+            .unwrap()
+            .is_nan());
+    }
+}
@@ -27,6 +27,7 @@ mod clamp;
 mod count_over_time;
 mod delta;
 mod deriv;
+mod first_over_time;
 mod histogram;
 mod holt_winters;
 mod idelta;
@@ -35,16 +36,22 @@ mod irate;
 mod label_join;
 mod label_replace;
 mod last_over_time;
+mod mad_over_time;
 mod math_operations;
 mod max_over_time;
 mod min_over_time;
 mod predict_linear;
+mod present_over_time;
 mod quantile_over_time;
 mod rate;
 mod resets;
+mod scalar;
+mod sort;
 mod stddev_over_time;
 mod stdvar_over_time;
 mod sum_over_time;
+#[cfg(test)]
+mod test_utils;
 mod time_operations;
 mod vector;
 
@@ -52,10 +59,11 @@ pub(crate) use absent::absent;
 pub(crate) use absent_over_time::absent_over_time;
 pub(crate) use avg_over_time::avg_over_time;
 pub(crate) use changes::changes;
-pub(crate) use clamp::clamp;
+pub(crate) use clamp::{clamp, clamp_max, clamp_min};
 pub(crate) use count_over_time::count_over_time;
 pub(crate) use delta::delta;
 pub(crate) use deriv::deriv;
+pub(crate) use first_over_time::first_over_time;
 pub(crate) use histogram::histogram_quantile;
 pub(crate) use holt_winters::holt_winters;
 pub(crate) use idelta::idelta;
@@ -64,13 +72,17 @@ pub(crate) use irate::irate;
 pub(crate) use label_join::label_join;
 pub(crate) use label_replace::label_replace;
 pub(crate) use last_over_time::last_over_time;
+pub(crate) use mad_over_time::mad_over_time;
 pub(crate) use math_operations::*;
 pub(crate) use max_over_time::max_over_time;
 pub(crate) use min_over_time::min_over_time;
 pub(crate) use predict_linear::predict_linear;
+pub(crate) use present_over_time::present_over_time;
 pub(crate) use quantile_over_time::quantile_over_time;
 pub(crate) use rate::rate;
 pub(crate) use resets::resets;
+pub(crate) use scalar::scalar;
+pub(crate) use sort::{sort, sort_desc};
 pub(crate) use stddev_over_time::stddev_over_time;
 pub(crate) use stdvar_over_time::stdvar_over_time;
 pub(crate) use sum_over_time::sum_over_time;
@@ -87,6 +99,7 @@ pub(crate) enum Func {
     AvgOverTime,
     Ceil,
     Changes,
+    Cos,
     Clamp,
     ClampMax,
     ClampMin,
@@ -98,6 +111,7 @@ pub(crate) enum Func {
     Delta,
     Deriv,
     Exp,
+    FirstOverTime,
     Floor,
     HistogramCount,
     HistogramFraction,
@@ -114,17 +128,21 @@ pub(crate) enum Func {
     Ln,
     Log10,
     Log2,
+    MadOverTime,
     MaxOverTime,
     MinOverTime,
     Minute,
     Month,
+    Pi,
     PredictLinear,
+    PresentOverTime,
     QuantileOverTime,
     Rate,
     Resets,
     Round,
     Scalar,
     Sgn,
+    Sin,
     Sort,
     SortDesc,
     Sqrt,
@@ -52,3 +52,51 @@ fn exec(data: &Value, duration: f64) -> Result<Value> {
     }
     Ok(Value::Vector(rate_values))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::service::promql::value::{RangeValue, TimeWindow};
+
+    fn range_value(samples: Vec<Sample>) -> Value {
+        Value::Matrix(vec![RangeValue {
+            labels: vec![],
+            samples,
+            exemplars: None,
+            time_window: Some(TimeWindow::new(60_000_000, Duration::from_secs(60))),
+        }])
+    }
+
+    #[test]
+    fn test_predict_linear() {
+        // y = 2 * t + 5, with t in seconds
+        let samples = (1..=6)
+            .map(|i| {
+                let t = i * 10;
+                Sample::new(t * 1_000_000, 2.0 * t as f64 + 5.0)
+            })
+            .collect();
+        let data = range_value(samples);
+
+        let result = predict_linear(&data, 0.0).unwrap();
+        let result = result.get_vector().unwrap();
+        assert_eq!(result.len(), 1);
+        assert!((result[0].sample.value - 125.0).abs() < 1e-9);
+        assert_eq!(result[0].sample.timestamp, 60_000_000);
+
+        let result = predict_linear(&data, 30.0).unwrap();
+        let result = result.get_vector().unwrap();
+        assert!((result[0].sample.value - 185.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_predict_linear_single_sample() {
+        let data = range_value(vec![Sample::new(50_000_000, 42.0)]);
+        let result = predict_linear(&data, 600.0).unwrap();
+        let result = result.get_vector().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].sample.value, 42.0);
+    }
+}
@@ -23,6 +23,11 @@ use crate::service::promql::{
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#quantile_over_time
 pub(crate) fn quantile_over_time(timestamp: i64, phi_quantile: f64, data: &Value) -> Result<Value> {
+    if !(0.0..=1.0).contains(&phi_quantile) {
+        return Err(DataFusionError::Plan(format!(
+            "quantile_over_time: quantile must be between 0 and 1, got {phi_quantile}"
+        )));
+    }
     eval(data, phi_quantile, timestamp, false)
 }
 
@@ -67,3 +72,46 @@ pub(crate) fn eval(
     }
     Ok(Value::Vector(rate_values))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::service::promql::value::{RangeValue, TimeWindow};
+
+    fn input() -> Value {
+        Value::Matrix(vec![RangeValue {
+            labels: vec![],
+            samples: [3.0, 1.0, 5.0, 2.0, 4.0]
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| Sample::new((i as i64 + 1) * 1_000_000, v))
+                .collect(),
+            exemplars: None,
+            time_window: Some(TimeWindow::new(60_000_000, Duration::from_secs(60))),
+        }])
+    }
+
+    #[test]
+    fn test_quantile_over_time() {
+        for (phi, expected) in [(0.0, 1.0), (0.5, 3.0), (0.9, 4.6), (1.0, 5.0)] {
+            let out = quantile_over_time(60_000_000, phi, &input()).unwrap();
+            let out = out.get_vector().unwrap();
+            assert_eq!(out.len(), 1);
+            assert!(
+                (out[0].sample.value - expected).abs() < 1e-9,
+                "phi {phi}: got {}, expected {expected}",
+                out[0].sample.value
+            );
+        }
+    }
+
+    #[test]
+    fn test_quantile_over_time_invalid_phi() {
+        for phi in [-0.1, 1.1, f64::NAN] {
+            let err = quantile_over_time(60_000_000, phi, &input()).unwrap_err();
+            assert!(matches!(err, DataFusionError::Plan(_)));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use datafusion::error::{DataFusionError, Result};
+
+use crate::service::promql::value::Value;
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#scalar
+pub(crate) fn scalar(data: &Value) -> Result<Value> {
+    match data {
+        Value::Vector(v) if v.len() == 1 => Ok(Value::Float(v[0].sample.value)),
+        Value::Vector(_) | Value::None => Ok(Value::Float(f64::NAN)),
+        Value::Float(_) => Ok(data.clone()),
+        v => Err(DataFusionError::Plan(format!(
+            "scalar: vector argument expected but got {}",
+            v.get_type()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::promql::value::{InstantValue, Sample};
+
+    fn instant(value: f64) -> InstantValue {
+        InstantValue {
+            labels: vec![],
+            sample: Sample::new(1_000_000, value),
+        }
+    }
+
+    #[test]
+    fn test_scalar() {
+        let out = scalar(&Value::Vector(vec![instant(3.25)])).unwrap();
+        assert_eq!(out.get_float(), Some(3.25));
+    }
+
+    #[test]
+    fn test_scalar_nan() {
+        for data in [
+            Value::None,
+            Value::Vector(vec![]),
+            Value::Vector(vec![instant(1.0), instant(2.0)]),
+        ] {
+            assert!(scalar(&data).unwrap().get_float().unwrap().is_nan());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+
+use datafusion::error::{DataFusionError, Result};
+
+use crate::service::promql::value::{InstantValue, LabelsExt, Value};
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#sort
+pub(crate) fn sort(data: &Value) -> Result<Value> {
+    exec(data, "sort", false)
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#sort_desc
+pub(crate) fn sort_desc(data: &Value) -> Result<Value> {
+    exec(data, "sort_desc", true)
+}
+
+fn exec(data: &Value, fn_name: &str, desc: bool) -> Result<Value> {
+    let data = match data {
+        Value::Vector(v) => v,
+        Value::None => return Ok(Value::None),
+        v => {
+            return Err(DataFusionError::Plan(format!(
+                "{fn_name}: vector argument expected but got {}",
+                v.get_type()
+            )));
+        }
+    };
+
+    // ties are broken by the label set signature so the order is the same
+    // across calls, NaN values always go last
+    let mut values: Vec<(InstantValue, _)> = data
+        .iter()
+        .map(|v| (v.clone(), v.labels.signature()))
+        .collect();
+    values.sort_by(|(a, a_sig), (b, b_sig)| {
+        let (a, b) = (a.sample.value, b.sample.value);
+        let ord = match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if desc => b.total_cmp(&a),
+            (false, false) => a.total_cmp(&b),
+        };
+        ord.then_with(|| a_sig.cmp(b_sig))
+    });
+    Ok(Value::Vector(values.into_iter().map(|(v, _)| v).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::{Label, Sample};
+
+    fn input() -> Value {
+        Value::Vector(
+            [
+                ("a", 3.0),
+                ("b", f64::NAN),
+                ("c", 1.0),
+                ("d", 3.0),
+                ("e", 2.0),
+            ]
+            .into_iter()
+            .map(|(job, v)| InstantValue {
+                labels: vec![Arc::new(Label::new("job", job))],
+                sample: Sample::new(1_000_000, v),
+            })
+            .collect(),
+        )
+    }
+
+    fn jobs(data: &Value) -> Vec<String> {
+        data.get_vector()
+            .unwrap()
+            .iter()
+            .map(|v| v.labels.get_value("job"))
+            .collect()
+    }
+
+    #[test]
+    fn test_sort() {
+        let out = sort(&input()).unwrap();
+        let jobs = jobs(&out);
+        assert_eq!(jobs[..2], ["c", "e"]);
+        assert_eq!(jobs[4], "b");
+    }
+
+    #[test]
+    fn test_sort_desc() {
+        let out = sort_desc(&input()).unwrap();
+        let jobs = jobs(&out);
+        assert_eq!(jobs[2..], ["e", "c", "b"]);
+    }
+
+    #[test]
+    fn test_sort_ties_are_deterministic() {
+        let mut reversed = input();
+        if let Value::Vector(v) = &mut reversed {
+            v.reverse();
+        }
+        for f in [sort, sort_desc] {
+            let out = jobs(&f(&input()).unwrap());
+            assert_eq!(out, jobs(&f(&reversed).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Builders of the range vectors shared by the tests of the functions.
+
+use std::time::Duration;
+
+use crate::service::promql::value::{RangeValue, Sample, TimeWindow, Value};
+
+/// The evaluation timestamp of the range vectors, in microseconds.
+const EVAL_TS: i64 = 60_000_000;
+
+/// Returns a matrix of one series without labels evaluated at [`EVAL_TS`]
+/// over the last 60 seconds.
+pub(super) fn matrix(samples: Vec<Sample>) -> Value {
+    Value::Matrix(vec![RangeValue {
+        labels: vec![],
+        samples,
+        exemplars: None,
+        time_window: Some(TimeWindow::new(EVAL_TS, Duration::from_secs(60))),
+    }])
+}
+
+/// Returns the `values` as samples `step_secs` seconds apart, the first one at
+/// `step_secs`.
+pub(super) fn samples(step_secs: i64, values: &[f64]) -> Vec<Sample> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| Sample::new((i as i64 + 1) * step_secs * 1_000_000, *v))
+        .collect()
+}
@@ -14,6 +14,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use chrono::{Datelike, NaiveDate, Timelike};
+use chrono_tz::Tz;
 use config::utils::time::parse_i64_to_timestamp_micros;
 use datafusion::error::{DataFusionError, Result};
 use rayon::prelude::*;
@@ -36,10 +37,18 @@ pub enum TimeOperationType {
 impl TimeOperationType {
     /// Given a timestamp, get the TimeOperationType component from it
     /// for e.g. month(), year(), day() etc.
-    pub fn get_component_from_ts(&self, timestamp: i64) -> u32 {
+    ///
+    /// The components are extracted in the given timezone, or in UTC if `tz`
+    /// is `None`.
+    pub fn get_component_from_ts(&self, timestamp: i64, tz: Option<&Tz>) -> Result<u32> {
         let timestamp = parse_i64_to_timestamp_micros(timestamp);
-        let naive_datetime = chrono::DateTime::from_timestamp_micros(timestamp).unwrap();
-        match self {
+        let utc_datetime = chrono::DateTime::from_timestamp_micros(timestamp)
+            .ok_or_else(|| DataFusionError::Plan(format!("Invalid timestamp: {timestamp}")))?;
+        let naive_datetime = match tz {
+            Some(tz) => utc_datetime.with_timezone(tz).naive_local(),
+            None => utc_datetime.naive_utc(),
+        };
+        Ok(match self {
             Self::Minute => naive_datetime.minute(),
             Self::Hour => naive_datetime.hour(),
             Self::Month => naive_datetime.month(),
@@ -60,45 +69,86 @@ impl TimeOperationType {
                     .signed_duration_since(NaiveDate::from_ymd_opt(cur_year, cur_month, 1).unwrap())
                     .num_days() as u32
             }
-        }
+        })
     }
 }
 
-pub(crate) fn minute(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::Minute)
+pub(crate) fn minute(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::Minute, tz)
+}
+
+pub(crate) fn hour(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::Hour, tz)
+}
+
+pub(crate) fn month(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::Month, tz)
 }
 
-pub(crate) fn hour(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::Hour)
+pub(crate) fn year(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::Year, tz)
 }
 
-pub(crate) fn month(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::Month)
+pub(crate) fn day_of_month(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::DayOfMonth, tz)
 }
 
-pub(crate) fn year(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::Year)
+pub(crate) fn day_of_week(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::DayOfWeek, tz)
 }
 
-pub(crate) fn day_of_month(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::DayOfMonth)
+pub(crate) fn day_of_year(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::DayOfYear, tz)
 }
 
-pub(crate) fn day_of_week(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::DayOfWeek)
+pub(crate) fn days_in_month(data: &Value, tz: Option<&str>) -> Result<Value> {
+    exec(data, &TimeOperationType::DaysInMonth, tz)
 }
 
-pub(crate) fn day_of_year(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::DayOfYear)
+/// Returns the timestamp of each sample in seconds since the Unix epoch.
+pub(crate) fn timestamp(data: &Value) -> Result<Value> {
+    let instant_values = match data {
+        Value::Vector(v) => v,
+        _ => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Unexpected input to timestamp function: {:?}",
+                data
+            )));
+        }
+    };
+
+    let out = instant_values
+        .par_iter()
+        .map(|instant| InstantValue {
+            labels: instant.labels.without_metric_name(),
+            sample: Sample::new(
+                instant.sample.timestamp,
+                instant.sample.timestamp as f64 / 1_000_000.0,
+            ),
+        })
+        .collect();
+    Ok(Value::Vector(out))
 }
 
-pub(crate) fn days_in_month(data: &Value) -> Result<Value> {
-    exec(data, &TimeOperationType::DaysInMonth)
+/// Parses an IANA timezone, e.g. `America/New_York`
+fn parse_tz(tz: Option<&str>) -> Result<Option<Tz>> {
+    tz.map(|tz| {
+        tz.parse::<Tz>()
+            .map_err(|e| DataFusionError::Plan(format!("Invalid timezone {tz}: {e}")))
+    })
+    .transpose()
 }
 
-fn exec(data: &Value, op: &TimeOperationType) -> Result<Value> {
+fn exec(data: &Value, op: &TimeOperationType, tz: Option<&str>) -> Result<Value> {
+    let tz = parse_tz(tz)?;
+    let tz = tz.as_ref();
     let instant_values = match data {
         Value::Vector(v) => v,
+        Value::Float(ts) => {
+            // scalar input is a unix timestamp in seconds
+            let ts = op.get_component_from_ts((ts * 1_000_000.0) as i64, tz)?;
+            return Ok(Value::Float(ts as f64));
+        }
         _ => {
             return Err(DataFusionError::NotImplemented(format!(
                 "Invalid input for minute value: {:?}",
@@ -110,13 +160,13 @@ fn exec(data: &Value, op: &TimeOperationType) -> Result<Value> {
     let out = instant_values
         .par_iter()
         .map(|instant| {
-            let ts = op.get_component_from_ts(instant.sample.value as i64);
-            InstantValue {
+            let ts = op.get_component_from_ts(instant.sample.value as i64, tz)?;
+            Ok(InstantValue {
                 labels: instant.labels.without_metric_name(),
                 sample: Sample::new(instant.sample.timestamp, ts as f64),
-            }
+            })
         })
-        .collect();
+        .collect::<Result<Vec<_>>>()?;
     Ok(Value::Vector(out))
 }
 
@@ -130,9 +180,10 @@ mod tests {
     fn test_get_component_from_ts() {
         let timestamp_micros = 1688379261000000; // Mon Jul 03 2023 10:14:21 GMT+0000
 
-        let expected_outputs = [14, 10, 1, 3, 184, 31, 7]; // Strict ordering based on TimeOperationType
+        // Strict ordering based on TimeOperationType
+        let expected_outputs = [14, 10, 1, 3, 184, 31, 7, 2023];
         for (op, expected) in std::iter::zip(TimeOperationType::iter(), expected_outputs) {
-            let got = op.get_component_from_ts(timestamp_micros);
+            let got = op.get_component_from_ts(timestamp_micros, None).unwrap();
             assert!(
                 got == expected,
                 "operation type: {:?} expected {} got {}",
@@ -142,4 +193,76 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp_micros = 1688379261000000; // Mon Jul 03 2023 10:14:21 GMT+0000
+        let data = Value::Vector(vec![InstantValue {
+            labels: vec![],
+            sample: Sample::new(timestamp_micros, 42.0),
+        }]);
+
+        let got = timestamp(&data).unwrap();
+        let got = got.get_vector().unwrap();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].sample.timestamp, timestamp_micros);
+        assert_eq!(got[0].sample.value, 1688379261.0);
+
+        // keeps the sub-second precision, and the timestamps before 1970
+        let data = Value::Vector(vec![
+            InstantValue {
+                labels: vec![],
+                sample: Sample::new(1688379261500000, 42.0),
+            },
+            InstantValue {
+                labels: vec![],
+                sample: Sample::new(-1500000, 42.0),
+            },
+        ]);
+        let got = timestamp(&data).unwrap();
+        let got = got.get_vector().unwrap();
+        assert_eq!(got[0].sample.value, 1688379261.5);
+        assert_eq!(got[1].sample.value, -1.5);
+    }
+
+    #[test]
+    fn test_exec_with_scalar() {
+        let data = Value::Float(1688379261.0); // Mon Jul 03 2023 10:14:21 GMT+0000
+        assert_eq!(minute(&data, None).unwrap().get_float(), Some(14.0));
+        assert_eq!(hour(&data, None).unwrap().get_float(), Some(10.0));
+        assert_eq!(year(&data, None).unwrap().get_float(), Some(2023.0));
+    }
+
+    #[test]
+    fn test_get_component_from_ts_with_timezone() {
+        let midnight_utc = 1688342400000000; // Mon Jul 03 2023 00:00:00 GMT+0000
+        let utc = TimeOperationType::Hour.get_component_from_ts(midnight_utc, None);
+        assert_eq!(utc.unwrap(), 0);
+        let utc = TimeOperationType::Hour.get_component_from_ts(midnight_utc, Some(&Tz::UTC));
+        assert_eq!(utc.unwrap(), 0);
+
+        // UTC-5 is the previous day at 19:00
+        let tz = Some(&Tz::Etc__GMTPlus5);
+        let hour = TimeOperationType::Hour.get_component_from_ts(midnight_utc, tz);
+        assert_eq!(hour.unwrap(), 19);
+        let day = TimeOperationType::DayOfMonth.get_component_from_ts(midnight_utc, tz);
+        assert_eq!(day.unwrap(), 2);
+        let day = TimeOperationType::DayOfWeek.get_component_from_ts(midnight_utc, tz);
+        assert_eq!(day.unwrap(), 0);
+
+        // New York is on daylight saving time (UTC-4) in July
+        let data = Value::Float(1688342400.0);
+        let got = hour(&data, Some("America/New_York")).unwrap();
+        assert_eq!(got.get_float(), Some(20.0));
+    }
+
+    #[test]
+    fn test_parse_tz() {
+        assert_eq!(parse_tz(None).unwrap(), None);
+        assert_eq!(parse_tz(Some("UTC")).unwrap(), Some(Tz::UTC));
+        let got = parse_tz(Some("Mars/Base"));
+        assert!(matches!(got, Err(DataFusionError::Plan(_))));
+        let got = hour(&Value::Float(1688342400.0), Some("Mars/Base"));
+        assert!(matches!(got, Err(DataFusionError::Plan(_))));
+    }
 }
@@ -36,3 +36,24 @@ pub(crate) fn vector(data: &Value, eval_ts: i64) -> Result<Value> {
     };
     Ok(Value::Vector(vec![instant]))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector() {
+        let out = vector(&Value::Float(4.2), 1_000_000).unwrap();
+        let out = out.get_vector().unwrap();
+        assert_eq!(out.len(), 1);
+        assert!(out[0].labels.is_empty());
+        assert_eq!(out[0].sample.timestamp, 1_000_000);
+        assert_eq!(out[0].sample.value, 4.2);
+    }
+
+    #[test]
+    fn test_vector_non_scalar_input() {
+        let input = Value::Vector(vec![]);
+        assert!(vector(&input, 1_000_000).is_err());
+    }
+}
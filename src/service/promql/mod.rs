@@ -70,6 +70,9 @@ pub struct MetricsQueryRequest {
     pub step: i64,
     pub query_exemplars: bool,
     pub no_cache: Option<bool>,
+    /// IANA timezone of the time functions, e.g. `America/New_York`, UTC if
+    /// not set.
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -221,6 +221,9 @@ pub async fn search_inner(
         query.query_exemplars,
         timeout,
     );
+    if !query.timezone.is_empty() {
+        ctx.timezone = Some(query.timezone.clone());
+    }
 
     let (value, result_type, mut scan_stats) = if query.query_exemplars {
         ctx.query_exemplars(&trace_id, eval_stmt).await?
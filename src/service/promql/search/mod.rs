@@ -90,11 +90,16 @@ async fn search_in_cluster(
         end,
         step,
         query_exemplars,
+        ref timezone,
     } = req.query.as_ref().unwrap();
 
-    // cache disabled if result cache is disabled or no_cache is true or start == end or step == 0
-    let cache_disabled =
-        !cfg.common.metrics_cache_enabled || req.no_cache || start == end || step == 0;
+    // cache disabled if result cache is disabled or no_cache is true or start == end or step == 0,
+    // the cached results are keyed without the timezone of the time functions
+    let cache_disabled = !cfg.common.metrics_cache_enabled
+        || req.no_cache
+        || start == end
+        || step == 0
+        || !timezone.is_empty();
     // adjust start and end time
     let (start, end) = adjust_start_end(start, end, step, cache_disabled);
 
@@ -138,7 +138,7 @@ impl LabelsExt for Labels {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct Label {
     pub name: String,
     pub value: String,
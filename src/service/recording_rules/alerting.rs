@@ -0,0 +1,411 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use config::{
+    meta::promql::NAME_LABEL,
+    utils::{
+        json,
+        time::{now_micros, parse_milliseconds},
+    },
+};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+
+use super::{notify, template};
+use crate::{
+    common::meta::recording_rule::{Receiver, Rule, RuleGroup},
+    service::{promql::value::Labels, silences},
+};
+
+/// The label holding the name of the alert
+pub const ALERT_NAME_LABEL: &str = "alertname";
+
+// {org_id}/{group_name}/{rule index}/{alert} -> alerts of the rule, the index
+// tells apart the rules of a group sharing the same alert name
+static ACTIVE_ALERTS: Lazy<Mutex<HashMap<String, RuleAlerts>>> = Lazy::new(Default::default);
+
+// {org_id}/{group_name}/{rule index}/{alert} -> the last delivery of the rule,
+// the next delivery waits for it so the receivers get the alerts in order
+static DELIVERIES: Lazy<Mutex<HashMap<String, (u64, JoinHandle<()>)>>> =
+    Lazy::new(Default::default);
+static DELIVERY_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+struct RuleAlerts {
+    /// The receivers of the rule, kept to resolve the alerts of a deleted rule
+    receivers: Vec<Receiver>,
+    /// labels of the alert -> alert
+    alerts: HashMap<String, ActiveAlert>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlertState {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveAlert {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    pub value: f64,
+    pub state: AlertState,
+    /// When the series showed up in the result
+    pub active_at: i64,
+    /// When the alert fired, 0 while pending
+    pub starts_at: i64,
+    /// When the alert resolved, 0 until then
+    pub ends_at: i64,
+    /// Whether the receivers were told the alert fired, not while silenced
+    pub notified: bool,
+}
+
+/// The key of the alerts of a rule, `group_key` is `{org_id}/{group_name}`
+/// and `rule_idx` the index of the rule in the group.
+pub fn rule_key(group_key: &str, rule_idx: usize, rule: &Rule) -> String {
+    format!("{group_key}/{rule_idx}/{}", rule.alert)
+}
+
+/// Updates the alerts of an alerting rule with the result of its expression
+/// and hands the alerts which fired or resolved to the receivers of the
+/// rule, unless the alerts are silenced. The deliveries run in the
+/// background so a slow receiver doesn't hold up the evaluation.
+pub fn update(
+    org_id: &str,
+    group: &RuleGroup,
+    rule_idx: usize,
+    rule: &Rule,
+    samples: Vec<(Labels, f64)>,
+    eval_ts: i64,
+) {
+    let key = rule_key(&format!("{org_id}/{}", group.name), rule_idx, rule);
+    let for_micros = rule
+        .for_duration
+        .as_deref()
+        .and_then(|v| parse_milliseconds(v).ok())
+        .unwrap_or_default() as i64
+        * 1000;
+    let alerts = samples
+        .iter()
+        .map(|(labels, value)| new_alert(rule, labels, *value, eval_ts))
+        .collect();
+    let changed = {
+        let mut active = ACTIVE_ALERTS.lock();
+        let rule_alerts = active.entry(key.clone()).or_default();
+        rule_alerts.receivers = rule.receivers.clone();
+        transition(
+            &mut rule_alerts.alerts,
+            alerts,
+            eval_ts,
+            for_micros,
+            |alert| silences::is_silenced(org_id, &alert.labels, eval_ts),
+        )
+    };
+    deliver(key, org_id, rule.receivers.clone(), changed);
+}
+
+/// Forgets the alerts of the rules which aren't in `local`, the rules
+/// evaluated by this node. The firing alerts of the rules which aren't in
+/// `existing` either, the deleted rules, are resolved. The alerts of a rule
+/// moved to another node are dropped silently, that node takes them over.
+pub fn prune(local: &HashSet<String>, existing: &HashSet<String>) {
+    let removed = {
+        let mut active = ACTIVE_ALERTS.lock();
+        let keys = active
+            .keys()
+            .filter(|key| !local.contains(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| active.remove(&key).map(|alerts| (key, alerts)))
+            .collect::<Vec<_>>()
+    };
+    let now = now_micros();
+    for (key, rule_alerts) in removed {
+        if existing.contains(&key) {
+            continue;
+        }
+        let resolved = resolve_all(rule_alerts.alerts, now);
+        let org_id = key.split('/').next().unwrap_or_default().to_string();
+        deliver(key, &org_id, rule_alerts.receivers, resolved);
+    }
+}
+
+/// Resolves the notified firing alerts of a deleted rule
+fn resolve_all(alerts: HashMap<String, ActiveAlert>, now: i64) -> Vec<ActiveAlert> {
+    alerts
+        .into_values()
+        .filter(|alert| alert.state == AlertState::Firing && alert.notified)
+        .map(|mut alert| {
+            alert.state = AlertState::Resolved;
+            alert.ends_at = now;
+            alert
+        })
+        .collect()
+}
+
+/// Sends the alerts to the receivers in a spawned task, after the last
+/// delivery of the rule
+fn deliver(key: String, org_id: &str, receivers: Vec<Receiver>, alerts: Vec<ActiveAlert>) {
+    if alerts.is_empty() || receivers.is_empty() {
+        return;
+    }
+    let org_id = org_id.to_string();
+    let id = DELIVERY_ID.fetch_add(1, Ordering::Relaxed);
+    let mut deliveries = DELIVERIES.lock();
+    let prev = deliveries.remove(&key);
+    let task_key = key.clone();
+    let handle = tokio::task::spawn(async move {
+        if let Some((_, prev)) = prev {
+            _ = prev.await;
+        }
+        for alert in alerts.iter() {
+            for receiver in receivers.iter() {
+                if let Err(e) = notify::send(&org_id, receiver, alert).await {
+                    log::error!("[RECORDING RULES] send alert: {task_key}, err: {e}");
+                }
+            }
+        }
+        let mut deliveries = DELIVERIES.lock();
+        if deliveries
+            .get(&task_key)
+            .is_some_and(|(last, _)| *last == id)
+        {
+            deliveries.remove(&task_key);
+        }
+    });
+    deliveries.insert(key, (id, handle));
+}
+
+/// A pending alert of a series of the result, labeled with the series labels
+/// and the rule labels
+fn new_alert(rule: &Rule, labels: &Labels, value: f64, eval_ts: i64) -> ActiveAlert {
+    let mut alert_labels = labels
+        .iter()
+        .filter(|l| l.name != NAME_LABEL)
+        .map(|l| (l.name.clone(), l.value.clone()))
+        .collect::<BTreeMap<_, _>>();
+    for (name, value) in rule.labels.iter() {
+        alert_labels.insert(name.clone(), value.clone());
+    }
+    alert_labels.insert(ALERT_NAME_LABEL.to_string(), rule.alert.clone());
+    let annotations = rule
+        .annotations
+        .iter()
+        .map(|(name, tpl)| (name.clone(), template::expand(tpl, &alert_labels, value)))
+        .collect();
+    ActiveAlert {
+        name: rule.alert.clone(),
+        labels: alert_labels,
+        annotations,
+        value,
+        state: AlertState::Pending,
+        active_at: eval_ts,
+        starts_at: 0,
+        ends_at: 0,
+        notified: false,
+    }
+}
+
+/// Moves the alerts of a rule from pending to firing once they have been in
+/// the result for `for_micros`, and from firing to resolved once they are no
+/// longer in the result. Returns the alerts to notify: the firing alerts not
+/// notified yet which aren't silenced, and the resolved alerts which were
+/// notified.
+fn transition(
+    active: &mut HashMap<String, ActiveAlert>,
+    alerts: Vec<ActiveAlert>,
+    eval_ts: i64,
+    for_micros: i64,
+    is_silenced: impl Fn(&ActiveAlert) -> bool,
+) -> Vec<ActiveAlert> {
+    let mut changed = Vec::new();
+    let mut seen = HashSet::with_capacity(alerts.len());
+    for alert in alerts {
+        let key = json::to_string(&alert.labels).unwrap();
+        seen.insert(key.clone());
+        let entry = active.entry(key).or_insert_with(|| alert.clone());
+        entry.value = alert.value;
+        entry.annotations = alert.annotations;
+        if entry.state == AlertState::Pending && eval_ts - entry.active_at >= for_micros {
+            entry.state = AlertState::Firing;
+            entry.starts_at = eval_ts;
+        }
+        // a silenced alert is notified once the silence ends
+        if entry.state == AlertState::Firing && !entry.notified && !is_silenced(entry) {
+            entry.notified = true;
+            changed.push(entry.clone());
+        }
+    }
+    active.retain(|key, alert| {
+        if seen.contains(key) {
+            return true;
+        }
+        // a pending or never notified alert is dropped silently
+        if alert.state == AlertState::Firing && alert.notified {
+            alert.state = AlertState::Resolved;
+            alert.ends_at = eval_ts;
+            changed.push(alert.clone());
+        }
+        false
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::Label;
+
+    fn rule() -> Rule {
+        Rule {
+            record: "".to_string(),
+            alert: "HighLatency".to_string(),
+            expr: "latency > 1".to_string(),
+            for_duration: Some("2m".to_string()),
+            labels: [("severity".to_string(), "page".to_string())].into(),
+            annotations: [(
+                "summary".to_string(),
+                "{{ $labels.job }} latency is {{$value}}s".to_string(),
+            )]
+            .into(),
+            receivers: vec![],
+        }
+    }
+
+    fn alert(job: &str, eval_ts: i64) -> ActiveAlert {
+        let labels = vec![
+            Arc::new(Label::new(NAME_LABEL, "latency")),
+            Arc::new(Label::new("job", job)),
+        ];
+        new_alert(&rule(), &labels, 1.5, eval_ts)
+    }
+
+    #[test]
+    fn test_new_alert() {
+        let alert = alert("api", 10);
+        assert_eq!(alert.labels.len(), 3);
+        assert_eq!(alert.labels[ALERT_NAME_LABEL], "HighLatency");
+        assert_eq!(alert.labels["severity"], "page");
+        assert!(!alert.labels.contains_key(NAME_LABEL));
+        assert_eq!(alert.annotations["summary"], "api latency is 1.5s");
+        assert_eq!(alert.state, AlertState::Pending);
+    }
+
+    #[test]
+    fn test_transition() {
+        let minute = 60_000_000;
+        let mut active = HashMap::new();
+        let mut eval = |job: &[&str], ts: i64| {
+            let alerts = job.iter().map(|job| alert(job, ts)).collect();
+            transition(&mut active, alerts, ts, 2 * minute, |_| false)
+        };
+        // pending until the alert has been active for 2m
+        assert!(eval(&["api"], 0).is_empty());
+        assert!(eval(&["api"], minute).is_empty());
+        let fired = eval(&["api"], 2 * minute);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].state, AlertState::Firing);
+        assert_eq!(fired[0].active_at, 0);
+        assert_eq!(fired[0].starts_at, 2 * minute);
+        // firing alerts are sent once
+        assert!(eval(&["api"], 3 * minute).is_empty());
+
+        // a pending alert disappears silently, the firing one resolves
+        assert!(eval(&["api", "db"], 4 * minute).is_empty());
+        let resolved = eval(&[], 5 * minute);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].labels["job"], "api");
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert!(active.is_empty());
+    }
+
+    #[test]
+    fn test_transition_silenced() {
+        let mut active = HashMap::new();
+        assert!(transition(&mut active, vec![alert("api", 0)], 0, 0, |_| true).is_empty());
+        // resolving a silenced alert isn't notified either
+        assert!(transition(&mut active, vec![], 10, 0, |_| true).is_empty());
+
+        assert!(transition(&mut active, vec![alert("api", 20)], 20, 0, |_| true).is_empty());
+        // notified once the silence ends
+        let fired = transition(&mut active, vec![alert("api", 30)], 30, 0, |_| false);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].starts_at, 20);
+        let resolved = transition(&mut active, vec![], 40, 0, |_| true);
+        assert_eq!(resolved.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        let mut active = HashMap::new();
+        transition(&mut active, vec![alert("api", 0)], 0, 0, |_| false);
+        transition(
+            &mut active,
+            vec![alert("api", 0), alert("db", 0)],
+            0,
+            10,
+            |_| false,
+        );
+        let resolved = resolve_all(active, 20);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].labels["job"], "api");
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert_eq!(resolved[0].ends_at, 20);
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let key = "default/prune/0/HighLatency".to_string();
+        ACTIVE_ALERTS
+            .lock()
+            .insert(key.clone(), RuleAlerts::default());
+        let moved = "default/prune/1/HighLatency".to_string();
+        ACTIVE_ALERTS
+            .lock()
+            .insert(moved.clone(), RuleAlerts::default());
+
+        let local = HashSet::from([key.clone()]);
+        prune(&local, &HashSet::from([key.clone(), moved.clone()]));
+        assert!(ACTIVE_ALERTS.lock().contains_key(&key));
+        assert!(!ACTIVE_ALERTS.lock().contains_key(&moved));
+
+        prune(&HashSet::new(), &HashSet::new());
+        assert!(!ACTIVE_ALERTS.lock().contains_key(&key));
+    }
+
+    #[test]
+    fn test_transition_resolve() {
+        let mut active = HashMap::new();
+        let fired = transition(&mut active, vec![alert("api", 0)], 0, 0, |_| false);
+        assert_eq!(fired.len(), 1);
+        let resolved = transition(&mut active, vec![], 10, 0, |_| false);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert_eq!(resolved[0].starts_at, 0);
+        assert_eq!(resolved[0].ends_at, 10);
+        assert!(active.is_empty());
+    }
+}
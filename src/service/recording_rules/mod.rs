@@ -0,0 +1,391 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use config::{
+    get_config,
+    meta::promql::{NAME_LABEL, TYPE_LABEL, VALUE_LABEL},
+    utils::{json, time::parse_milliseconds},
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    common::meta::recording_rule::{Receiver, Rule, RuleFile, RuleGroup},
+    service::{
+        db, metrics,
+        promql::{
+            self,
+            value::{Labels, Value},
+        },
+    },
+};
+
+mod alerting;
+mod notify;
+mod template;
+
+static RE_METRIC_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-zA-Z_:][a-zA-Z0-9_:]*$").unwrap());
+
+/// Parses a Prometheus rule file.
+pub fn parse(yaml: &[u8]) -> Result<Vec<RuleGroup>, anyhow::Error> {
+    let file: RuleFile = serde_yaml::from_slice(yaml)?;
+    let mut names = HashSet::new();
+    let mut groups = Vec::with_capacity(file.groups.len());
+    for group in file.groups {
+        if group.name.is_empty() {
+            return Err(anyhow::anyhow!("rule group name is empty"));
+        }
+        if group.name.contains('/') {
+            return Err(anyhow::anyhow!(
+                "rule group name [{}] contains '/'",
+                group.name
+            ));
+        }
+        if !names.insert(group.name.clone()) {
+            return Err(anyhow::anyhow!("duplicate rule group [{}]", group.name));
+        }
+        if let Some(interval) = group.interval.as_deref() {
+            if parse_milliseconds(interval)? < 1000 {
+                return Err(anyhow::anyhow!(
+                    "rule group [{}] interval must be at least 1s",
+                    group.name
+                ));
+            }
+        }
+        for rule in group.rules.iter() {
+            validate_rule(rule)?;
+        }
+        groups.push(group);
+    }
+    Ok(groups)
+}
+
+fn validate_rule(rule: &Rule) -> Result<(), anyhow::Error> {
+    let name = match (rule.record.is_empty(), rule.alert.is_empty()) {
+        (false, true) => &rule.record,
+        (true, false) => &rule.alert,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "a rule must have one of record and alert, expr: {}",
+                rule.expr
+            ));
+        }
+    };
+    if !rule.record.is_empty() {
+        if !RE_METRIC_NAME.is_match(&rule.record) {
+            return Err(anyhow::anyhow!(
+                "invalid recording rule metric name [{}]",
+                rule.record
+            ));
+        }
+        if rule.for_duration.is_some() || !rule.annotations.is_empty() {
+            return Err(anyhow::anyhow!(
+                "recording rule [{}] can't have for or annotations",
+                rule.record
+            ));
+        }
+        if !rule.receivers.is_empty() {
+            return Err(anyhow::anyhow!(
+                "recording rule [{}] can't have receivers",
+                rule.record
+            ));
+        }
+    }
+    if let Err(e) = promql_parser::parser::parse(&rule.expr) {
+        return Err(anyhow::anyhow!("invalid expression of rule [{name}]: {e}"));
+    }
+    if let Some(v) = rule.for_duration.as_deref() {
+        parse_milliseconds(v)
+            .map_err(|e| anyhow::anyhow!("invalid for of alerting rule [{name}]: {e}"))?;
+    }
+    for receiver in rule.receivers.iter() {
+        let valid = match receiver {
+            Receiver::Pagerduty {
+                routing_key,
+                severity,
+            } => {
+                !routing_key.is_empty()
+                    && severity
+                        .as_deref()
+                        .map_or(true, notify::is_pagerduty_severity)
+            }
+            Receiver::Slack { webhook_url } => url::Url::parse(webhook_url).is_ok(),
+            Receiver::Email { to } => !to.is_empty(),
+        };
+        if !valid {
+            return Err(anyhow::anyhow!(
+                "invalid receiver of alerting rule [{name}]: {receiver:?}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Saves the groups of a rule file, a group replaces the saved group of the
+/// same name. Returns the names of the saved groups.
+pub async fn save(org_id: &str, yaml: &[u8]) -> Result<Vec<String>, anyhow::Error> {
+    let groups = parse(yaml)?;
+    for group in groups.iter() {
+        db::recording_rules::set(org_id, group).await?;
+    }
+    Ok(groups.into_iter().map(|g| g.name).collect())
+}
+
+pub fn list(org_id: &str) -> Vec<RuleGroup> {
+    db::recording_rules::list(org_id)
+}
+
+pub async fn delete(org_id: &str, name: &str) -> Result<(), anyhow::Error> {
+    db::recording_rules::delete(org_id, name).await
+}
+
+/// The evaluation interval of the group in microseconds
+pub fn interval_micros(group: &RuleGroup) -> i64 {
+    let millis = group
+        .interval
+        .as_deref()
+        .and_then(|v| parse_milliseconds(v).ok())
+        .filter(|v| *v >= 1000)
+        .unwrap_or(get_config().common.recording_rules_default_interval * 1000);
+    millis as i64 * 1000
+}
+
+/// Evaluates the rules of the group in order at `eval_ts`, so a rule can use
+/// the result of the rules before it. The results of the recording rules are
+/// ingested, the results of the alerting rules update the alerts.
+pub async fn evaluate(org_id: &str, group: &RuleGroup, eval_ts: i64) -> Result<(), anyhow::Error> {
+    let step = interval_micros(group);
+    for (idx, rule) in group.rules.iter().enumerate() {
+        let req = promql::MetricsQueryRequest {
+            query: rule.expr.clone(),
+            start: eval_ts,
+            end: eval_ts,
+            step,
+            query_exemplars: false,
+            no_cache: Some(true),
+            timezone: None,
+        };
+        let value = promql::search::search("", org_id, &req, "", 0)
+            .await
+            .map_err(|e| anyhow::anyhow!("evaluate rule [{}]: {e}", rule_name(rule)))?;
+        let samples = to_samples(rule, value)?;
+        if rule.record.is_empty() {
+            alerting::update(org_id, group, idx, rule, samples, eval_ts);
+        } else {
+            let records = to_records(rule, samples, eval_ts);
+            metrics::ingest_records(org_id, records).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forgets the alerts of the rules which aren't evaluated by this node any
+/// more and resolves the alerts of the deleted rules. `groups` are the
+/// `{org_id}/{group_name}` keys and the groups of all the organizations.
+pub fn prune_alerts(groups: &[(String, RuleGroup)], is_local: impl Fn(&str) -> bool) {
+    let mut local = HashSet::new();
+    let mut existing = HashSet::new();
+    for (key, group) in groups {
+        let is_local = is_local(key);
+        for (idx, rule) in group.rules.iter().enumerate() {
+            if !rule.record.is_empty() {
+                continue;
+            }
+            let rule_key = alerting::rule_key(key, idx, rule);
+            if is_local {
+                local.insert(rule_key.clone());
+            }
+            existing.insert(rule_key);
+        }
+    }
+    alerting::prune(&local, &existing);
+}
+
+fn rule_name(rule: &Rule) -> &str {
+    if rule.record.is_empty() {
+        &rule.alert
+    } else {
+        &rule.record
+    }
+}
+
+/// The labels and the value of every series of the result
+fn to_samples(rule: &Rule, value: Value) -> Result<Vec<(Labels, f64)>, anyhow::Error> {
+    Ok(match value {
+        Value::Vector(v) => v
+            .into_iter()
+            .map(|v| (v.labels, v.sample.value))
+            .collect::<Vec<_>>(),
+        Value::Matrix(v) => v
+            .into_iter()
+            .filter_map(|v| v.samples.last().map(|s| (v.labels.clone(), s.value)))
+            .collect(),
+        Value::Float(v) => vec![(Default::default(), v)],
+        Value::None => vec![],
+        v => {
+            return Err(anyhow::anyhow!(
+                "rule [{}] returned unexpected value: {v:?}",
+                rule_name(rule)
+            ));
+        }
+    })
+}
+
+/// Every series of the result becomes a sample of the `record` metric at the
+/// evaluation time, the same as Prometheus writes a recording rule.
+fn to_records(rule: &Rule, samples: Vec<(Labels, f64)>, eval_ts: i64) -> Vec<json::Value> {
+    let timestamp = &get_config().common.column_timestamp;
+    let mut records = Vec::with_capacity(samples.len());
+    for (labels, value) in samples {
+        // json has no NaN nor infinity
+        if !value.is_finite() {
+            continue;
+        }
+        let mut record = json::Map::with_capacity(labels.len() + rule.labels.len() + 4);
+        for label in labels.iter() {
+            if label.name != NAME_LABEL {
+                record.insert(label.name.clone(), label.value.clone().into());
+            }
+        }
+        for (name, value) in rule.labels.iter() {
+            record.insert(name.clone(), value.clone().into());
+        }
+        record.insert(NAME_LABEL.to_string(), rule.record.clone().into());
+        record.insert(TYPE_LABEL.to_string(), "gauge".into());
+        record.insert(timestamp.to_string(), eval_ts.into());
+        record.insert(VALUE_LABEL.to_string(), value.into());
+        records.push(json::Value::Object(record));
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::service::promql::value::{InstantValue, Label, Sample};
+
+    const RULE_FILE: &str = r#"
+groups:
+  - name: http
+    interval: 30s
+    rules:
+      - record: job:http_requests:rate5m
+        expr: sum by (job) (rate(http_requests_total[5m]))
+        labels:
+          team: web
+      - alert: HighErrorRate
+        expr: job:http_requests:rate5m > 100
+        for: 10m
+        labels:
+          severity: critical
+        annotations:
+          summary: "{{ $labels.job }} error rate is high"
+        receivers:
+          - type: pagerduty
+            routing_key: abc
+          - type: slack
+            webhook_url: https://hooks.slack.com/services/a/b/c
+          - type: email
+            to: [oncall@example.com]
+  - name: cpu
+    rules:
+      - record: instance:cpu:avg
+        expr: avg by (instance) (cpu_usage)
+"#;
+
+    #[test]
+    fn test_parse() {
+        let groups = parse(RULE_FILE.as_bytes()).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "http");
+        assert_eq!(groups[0].rules.len(), 2);
+        assert_eq!(groups[0].rules[0].labels["team"], "web");
+        let alert = &groups[0].rules[1];
+        assert_eq!(alert.alert, "HighErrorRate");
+        assert_eq!(alert.for_duration.as_deref(), Some("10m"));
+        assert_eq!(alert.receivers.len(), 3);
+        assert_eq!(
+            alert.receivers[0],
+            Receiver::Pagerduty {
+                routing_key: "abc".to_string(),
+                severity: None,
+            }
+        );
+        assert_eq!(interval_micros(&groups[0]), 30_000_000);
+        assert_eq!(
+            interval_micros(&groups[1]),
+            get_config().common.recording_rules_default_interval as i64 * 1_000_000
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let bad_expr = "groups:\n  - name: a\n    rules:\n      - record: a\n        expr: sum(\n";
+        assert!(parse(bad_expr.as_bytes()).is_err());
+        let bad_name = "groups:\n  - name: a\n    rules:\n      - record: a-b\n        expr: up\n";
+        assert!(parse(bad_name.as_bytes()).is_err());
+        let duplicate = "groups:\n  - name: a\n    rules: []\n  - name: a\n    rules: []\n";
+        assert!(parse(duplicate.as_bytes()).is_err());
+        let bad_interval = "groups:\n  - name: a\n    interval: 10ms\n    rules: []\n";
+        assert!(parse(bad_interval.as_bytes()).is_err());
+        let both = "groups:\n  - name: a\n    rules:\n      - record: a\n        alert: a\n        expr: up\n";
+        assert!(parse(both.as_bytes()).is_err());
+        let bad_severity = "groups:\n  - name: a\n    rules:\n      - alert: a\n        expr: up\n        receivers:\n          - type: pagerduty\n            routing_key: abc\n            severity: page\n";
+        assert!(parse(bad_severity.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_to_records() {
+        let rule = Rule {
+            record: "job:up:sum".to_string(),
+            alert: "".to_string(),
+            expr: "sum by (job) (up)".to_string(),
+            for_duration: None,
+            labels: [("env".to_string(), "prod".to_string())].into(),
+            annotations: Default::default(),
+            receivers: vec![],
+        };
+        let value = Value::Vector(vec![
+            InstantValue {
+                labels: vec![
+                    Arc::new(Label::new(NAME_LABEL, "up")),
+                    Arc::new(Label::new("job", "api")),
+                    Arc::new(Label::new("env", "dev")),
+                ],
+                sample: Sample::new(1, 3.0),
+            },
+            InstantValue {
+                labels: vec![Arc::new(Label::new("job", "db"))],
+                sample: Sample::new(1, f64::NAN),
+            },
+        ]);
+        let records = to_records(&rule, to_samples(&rule, value).unwrap(), 10);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0][NAME_LABEL], "job:up:sum");
+        assert_eq!(records[0]["job"], "api");
+        assert_eq!(records[0]["env"], "prod");
+        assert_eq!(records[0][VALUE_LABEL], 3.0);
+        assert_eq!(records[0]["_timestamp"], 10);
+
+        let samples = to_samples(&rule, Value::Float(1.0)).unwrap();
+        assert_eq!(to_records(&rule, samples, 10).len(), 1);
+        assert!(to_samples(&rule, Value::String("a".to_string())).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use config::{
+    get_config,
+    utils::{
+        hash::{gxhash, Sum64},
+        json,
+    },
+    SMTP_CLIENT,
+};
+use lettre::{message::SinglePart, AsyncTransport, Message};
+use once_cell::sync::Lazy;
+
+use super::alerting::{ActiveAlert, AlertState};
+use crate::common::meta::recording_rule::Receiver;
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+const PAGERDUTY_SEVERITIES: [&str; 4] = ["critical", "error", "warning", "info"];
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times a failed delivery is sent, waiting twice as long before
+/// every retry
+const SEND_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(SEND_TIMEOUT)
+        .build()
+        .unwrap()
+});
+
+/// A failed delivery, `retryable` unless the receiver rejected the request
+#[derive(Debug)]
+struct SendError {
+    err: anyhow::Error,
+    retryable: bool,
+}
+
+impl<E: Into<anyhow::Error>> From<E> for SendError {
+    fn from(e: E) -> Self {
+        Self {
+            err: e.into(),
+            retryable: true,
+        }
+    }
+}
+
+pub fn is_pagerduty_severity(severity: &str) -> bool {
+    PAGERDUTY_SEVERITIES.contains(&severity)
+}
+
+/// Sends a fired or resolved alert to the receiver, retrying failed
+/// deliveries with a backoff
+pub async fn send(
+    org_id: &str,
+    receiver: &Receiver,
+    alert: &ActiveAlert,
+) -> Result<(), anyhow::Error> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match send_once(org_id, receiver, alert).await {
+            Ok(()) => return Ok(()),
+            Err(e) if !e.retryable || attempt >= SEND_ATTEMPTS => return Err(e.err),
+            Err(e) => {
+                log::warn!(
+                    "[RECORDING RULES] send alert {} attempt {attempt} failed, retry in {}s, err: {}",
+                    alert.name,
+                    backoff.as_secs(),
+                    e.err
+                );
+            }
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+async fn send_once(
+    org_id: &str,
+    receiver: &Receiver,
+    alert: &ActiveAlert,
+) -> Result<(), SendError> {
+    match receiver {
+        Receiver::Pagerduty {
+            routing_key,
+            severity,
+        } => {
+            let event = pagerduty_event(org_id, routing_key, severity.as_deref(), alert);
+            post_json(PAGERDUTY_EVENTS_URL, &event).await
+        }
+        Receiver::Slack { webhook_url } => {
+            post_json(webhook_url, &json::json!({ "text": message(alert) })).await
+        }
+        Receiver::Email { to } => Ok(send_email(to, alert).await?),
+    }
+}
+
+async fn post_json(url: &str, body: &json::Value) -> Result<(), SendError> {
+    let resp = CLIENT
+        .post(url)
+        .header("Content-type", "application/json")
+        .body(json::to_string(body)?)
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(SendError {
+            err: anyhow::anyhow!("sent error status: {status}, err: {body}"),
+            // a rejected request fails again, unless it was throttled
+            retryable: status.is_server_error() || status.as_u16() == 429,
+        });
+    }
+    Ok(())
+}
+
+async fn send_email(to: &[String], alert: &ActiveAlert) -> Result<(), anyhow::Error> {
+    let cfg = get_config();
+    if !cfg.smtp.smtp_enabled {
+        return Err(anyhow::anyhow!("SMTP configuration not enabled"));
+    }
+    let mut email = Message::builder()
+        .from(cfg.smtp.smtp_from_email.parse()?)
+        .subject(title(alert));
+    for recipient in to {
+        email = email.to(recipient.parse()?);
+    }
+    if !cfg.smtp.smtp_reply_to.is_empty() {
+        email = email.reply_to(cfg.smtp.smtp_reply_to.parse()?);
+    }
+    let email = email.singlepart(SinglePart::plain(message(alert)))?;
+    match SMTP_CLIENT.as_ref().unwrap().send(email).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!("Error sending email: {e}")),
+    }
+}
+
+/// An event of the PagerDuty Events API v2, the alert is resolved by the
+/// same dedup key it was triggered with
+fn pagerduty_event(
+    org_id: &str,
+    routing_key: &str,
+    severity: Option<&str>,
+    alert: &ActiveAlert,
+) -> json::Value {
+    let severity = severity
+        .or_else(|| {
+            alert
+                .labels
+                .get("severity")
+                .map(|v| v.as_str())
+                .filter(|v| is_pagerduty_severity(v))
+        })
+        .unwrap_or("error");
+    let labels = json::to_string(&alert.labels).unwrap();
+    let dedup_key = format!("{:x}", gxhash::new().sum64(&format!("{org_id}/{labels}")));
+    let event_action = if alert.state == AlertState::Resolved {
+        "resolve"
+    } else {
+        "trigger"
+    };
+    json::json!({
+        "routing_key": routing_key,
+        "event_action": event_action,
+        "dedup_key": dedup_key,
+        "payload": {
+            "summary": summary(alert),
+            "source": format!("openobserve/{org_id}"),
+            "severity": severity,
+            "timestamp": rfc3339(alert.starts_at),
+            "custom_details": {
+                "labels": alert.labels,
+                "annotations": alert.annotations,
+                "value": alert.value,
+            },
+        },
+    })
+}
+
+fn title(alert: &ActiveAlert) -> String {
+    let state = if alert.state == AlertState::Resolved {
+        "RESOLVED"
+    } else {
+        "FIRING"
+    };
+    format!("[{state}] {}", alert.name)
+}
+
+fn summary(alert: &ActiveAlert) -> String {
+    match alert.annotations.get("summary") {
+        Some(summary) => summary.clone(),
+        None => format!("{} {}", alert.name, labels_text(alert)),
+    }
+}
+
+fn labels_text(alert: &ActiveAlert) -> String {
+    alert
+        .labels
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The plain text message of the alert for slack and email
+fn message(alert: &ActiveAlert) -> String {
+    let mut lines = vec![title(alert)];
+    for (name, value) in alert.annotations.iter() {
+        lines.push(format!("{name}: {value}"));
+    }
+    lines.push(format!("labels: {}", labels_text(alert)));
+    lines.push(format!("value: {}", alert.value));
+    lines.push(format!("started at: {}", rfc3339(alert.starts_at)));
+    if alert.state == AlertState::Resolved {
+        lines.push(format!("ended at: {}", rfc3339(alert.ends_at)));
+    }
+    lines.join("\n")
+}
+
+fn rfc3339(ts: i64) -> String {
+    Utc.timestamp_nanos(ts * 1000).to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(state: AlertState) -> ActiveAlert {
+        ActiveAlert {
+            name: "HighLatency".to_string(),
+            labels: [
+                ("alertname".to_string(), "HighLatency".to_string()),
+                ("job".to_string(), "api".to_string()),
+                ("severity".to_string(), "warning".to_string()),
+            ]
+            .into(),
+            annotations: [("summary".to_string(), "api is slow".to_string())].into(),
+            value: 1.5,
+            state,
+            active_at: 0,
+            starts_at: 60_000_000,
+            ends_at: 120_000_000,
+            notified: true,
+        }
+    }
+
+    #[test]
+    fn test_pagerduty_event() {
+        let event = pagerduty_event("default", "key", None, &alert(AlertState::Firing));
+        assert_eq!(event["event_action"], "trigger");
+        assert_eq!(event["payload"]["severity"], "warning");
+        assert_eq!(event["payload"]["summary"], "api is slow");
+        assert_eq!(event["payload"]["timestamp"], "1970-01-01T00:01:00+00:00");
+
+        let resolved = pagerduty_event(
+            "default",
+            "key",
+            Some("critical"),
+            &alert(AlertState::Resolved),
+        );
+        assert_eq!(resolved["event_action"], "resolve");
+        assert_eq!(resolved["payload"]["severity"], "critical");
+        // resolves the triggered incident
+        assert_eq!(resolved["dedup_key"], event["dedup_key"]);
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(&alert(AlertState::Firing)),
+            "[FIRING] HighLatency\n\
+             summary: api is slow\n\
+             labels: alertname=HighLatency, job=api, severity=warning\n\
+             value: 1.5\n\
+             started at: 1970-01-01T00:01:00+00:00"
+        );
+        let resolved = message(&alert(AlertState::Resolved));
+        assert!(resolved.starts_with("[RESOLVED] HighLatency\n"));
+        assert!(resolved.ends_with("ended at: 1970-01-01T00:02:00+00:00"));
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use config::get_config;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use tera::{Context, Tera, Value};
+
+// the variables of the Go templates of Prometheus aren't valid tera names
+static RE_VARIABLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$(labels|value|externalLabels)\b").unwrap());
+static RE_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").unwrap());
+static RE_LABEL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\blabels\.([a-zA-Z_][a-zA-Z0-9_]*)").unwrap());
+
+const LARGE_PREFIXES: [&str; 8] = ["k", "M", "G", "T", "P", "E", "Z", "Y"];
+const SMALL_PREFIXES: [&str; 8] = ["m", "u", "n", "p", "f", "a", "z", "y"];
+// the functions of tera, the annotations are written by the users so they
+// must not read the environment of the server or loop over large ranges
+const DISABLED_FUNCTIONS: [&str; 5] = ["get_env", "range", "throw", "now", "get_random"];
+
+/// Expands an alert annotation with the same variables as Prometheus:
+/// `$labels`, `$value` and `$externalLabels`, and the `humanize` and
+/// `humanizeDuration` filters, eg: `{{ $value | humanize }}`. The template
+/// is rendered by tera, so only the syntax shared by Go templates and tera
+/// is supported.
+pub fn expand(tpl: &str, labels: &BTreeMap<String, String>, value: f64) -> String {
+    if !tpl.contains("{{") && !tpl.contains("{%") {
+        return tpl.to_string();
+    }
+    let tpl = RE_TAG.replace_all(tpl, |caps: &Captures| {
+        RE_VARIABLE
+            .replace_all(&caps[0], |caps: &Captures| match &caps[1] {
+                "externalLabels" => "external_labels".to_string(),
+                name => name.to_string(),
+            })
+            .into_owned()
+    });
+
+    // a missing label is empty, as in Prometheus
+    let mut labels = labels.clone();
+    for caps in RE_LABEL.captures_iter(&tpl) {
+        labels.entry(caps[1].to_string()).or_default();
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("labels", &labels);
+    ctx.insert("value", &value);
+    ctx.insert("external_labels", &external_labels());
+
+    let mut tera = Tera::default();
+    tera.register_filter("humanize", humanize_filter);
+    tera.register_filter("humanizeDuration", humanize_duration_filter);
+    for name in DISABLED_FUNCTIONS {
+        tera.register_function(name, disabled_function);
+    }
+    match tera.render_str(&tpl, &ctx) {
+        Ok(v) => v,
+        Err(e) => format!("<error expanding template: {e}>"),
+    }
+}
+
+/// `ZO_RECORDING_RULES_EXTERNAL_LABELS`, as `name=value,name=value`
+fn external_labels() -> HashMap<String, String> {
+    get_config()
+        .common
+        .recording_rules_external_labels
+        .split(',')
+        .filter_map(|v| v.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn disabled_function(_: &HashMap<String, Value>) -> tera::Result<Value> {
+    Err(tera::Error::msg("function is not supported in annotations"))
+}
+
+fn to_float(value: &Value) -> tera::Result<f64> {
+    match value {
+        Value::Number(v) => v.as_f64(),
+        Value::String(v) => v.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| tera::Error::msg(format!("can't convert {value} to a number")))
+}
+
+fn humanize_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(humanize(to_float(value)?)))
+}
+
+fn humanize_duration_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(humanize_duration(to_float(value)?)))
+}
+
+/// Formats with SI prefixes, eg: `1.235M`
+fn humanize(mut v: f64) -> String {
+    if v == 0.0 || !v.is_finite() {
+        return format_g4(v);
+    }
+    let mut prefix = "";
+    if v.abs() >= 1.0 {
+        for p in LARGE_PREFIXES {
+            if v.abs() < 1000.0 {
+                break;
+            }
+            prefix = p;
+            v /= 1000.0;
+        }
+    } else {
+        for p in SMALL_PREFIXES {
+            if v.abs() >= 1.0 {
+                break;
+            }
+            prefix = p;
+            v *= 1000.0;
+        }
+    }
+    format!("{}{prefix}", format_g4(v))
+}
+
+/// Formats seconds as a duration, eg: `1d 2h 3m 4s`
+fn humanize_duration(mut v: f64) -> String {
+    if !v.is_finite() {
+        return format_g4(v);
+    }
+    if v == 0.0 {
+        return "0s".to_string();
+    }
+    if v.abs() >= 1.0 {
+        let sign = if v < 0.0 { "-" } else { "" };
+        v = v.abs();
+        let duration = v as i64;
+        let seconds = duration % 60;
+        let minutes = (duration / 60) % 60;
+        let hours = (duration / 60 / 60) % 24;
+        let days = duration / 60 / 60 / 24;
+        return if days != 0 {
+            format!("{sign}{days}d {hours}h {minutes}m {seconds}s")
+        } else if hours != 0 {
+            format!("{sign}{hours}h {minutes}m {seconds}s")
+        } else if minutes != 0 {
+            format!("{sign}{minutes}m {seconds}s")
+        } else {
+            format!("{sign}{}s", format_g4(v))
+        };
+    }
+    let mut prefix = "";
+    for p in SMALL_PREFIXES {
+        if v.abs() >= 1.0 {
+            break;
+        }
+        prefix = p;
+        v *= 1000.0;
+    }
+    format!("{}{prefix}s", format_g4(v))
+}
+
+/// The `%.4g` format of Go
+fn format_g4(v: f64) -> String {
+    if v.is_nan() {
+        return "NaN".to_string();
+    }
+    if v.is_infinite() {
+        return if v > 0.0 { "+Inf" } else { "-Inf" }.to_string();
+    }
+    if v == 0.0 {
+        return "0".to_string();
+    }
+    // the exponent after rounding to 4 significant digits
+    let sci = format!("{v:.3e}");
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    if !(-4..4).contains(&exp) {
+        let sign = if exp < 0 { '-' } else { '+' };
+        return format!("{}e{sign}{:02}", trim_zeros(mantissa), exp.abs());
+    }
+    trim_zeros(&format!("{v:.*}", (3 - exp) as usize)).to_string()
+}
+
+fn trim_zeros(v: &str) -> &str {
+    if v.contains('.') {
+        v.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_humanize() {
+        // the outputs of Prometheus
+        for (v, expected) in [
+            (0.0, "0"),
+            (999.0, "999"),
+            (1000.0, "1k"),
+            (1234567.0, "1.235M"),
+            (12345678901.0, "12.35G"),
+            (-1500.0, "-1.5k"),
+            (0.0012, "1.2m"),
+            (0.000_001_5, "1.5u"),
+            (1e30, "1e+06Y"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "+Inf"),
+        ] {
+            assert_eq!(humanize(v), expected, "humanize({v})");
+        }
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        for (v, expected) in [
+            (0.0, "0s"),
+            (1.5, "1.5s"),
+            (59.99, "59.99s"),
+            (61.0, "1m 1s"),
+            (3661.0, "1h 1m 1s"),
+            (90061.0, "1d 1h 1m 1s"),
+            (-61.0, "-1m 1s"),
+            (0.05, "50ms"),
+            (0.000_123, "123us"),
+        ] {
+            assert_eq!(humanize_duration(v), expected, "humanizeDuration({v})");
+        }
+    }
+
+    #[test]
+    fn test_format_g4() {
+        assert_eq!(format_g4(1.0), "1");
+        assert_eq!(format_g4(1234.5), "1234");
+        assert_eq!(format_g4(9999.5), "1e+04");
+        assert_eq!(format_g4(12345.0), "1.234e+04");
+        assert_eq!(format_g4(0.000123), "0.000123");
+        assert_eq!(format_g4(0.0000125), "1.25e-05");
+    }
+
+    #[test]
+    fn test_expand() {
+        let labels = [("job".to_string(), "api".to_string())].into();
+        assert_eq!(
+            expand(
+                "{{ $labels.job }} has {{ $value | humanize }} errors",
+                &labels,
+                1234567.0
+            ),
+            "api has 1.235M errors"
+        );
+        assert_eq!(
+            expand("down for {{ $value | humanizeDuration }}", &labels, 3661.0),
+            "down for 1h 1m 1s"
+        );
+        assert_eq!(
+            expand("{{ $labels.job }}/{{ $labels.none }}", &labels, 1.0),
+            "api/"
+        );
+        assert_eq!(
+            expand(
+                "{% if $value > 1 %}high{% else %}low{% endif %}",
+                &labels,
+                2.0
+            ),
+            "high"
+        );
+        assert_eq!(expand("no template", &labels, 1.0), "no template");
+        assert!(expand("{{ .Value }}", &labels, 1.0).starts_with("<error expanding template"));
+    }
+
+    #[test]
+    fn test_expand_disabled_functions() {
+        let labels = BTreeMap::new();
+        for tpl in [
+            r#"{{ get_env(name="ZO_ROOT_USER_PASSWORD") }}"#,
+            r#"{{ get_env(name="PATH", default="") }}"#,
+            "{% for i in range(end=10) %}{{ i }}{% endfor %}",
+            r#"{{ throw(message="boom") }}"#,
+        ] {
+            assert!(
+                expand(tpl, &labels, 1.0).starts_with("<error expanding template"),
+                "{tpl}"
+            );
+        }
+    }
+}
@@ -56,6 +56,66 @@ pub(crate) fn get_request_columns_limit_error(
     )
 }
 
+/// Rejects the records when `ZO_INGEST_STRICT_SCHEMA` is enabled and they
+/// would change the data type of existing fields of the stream, the error
+/// lists every incompatible field.
+pub async fn check_schema_compatibility(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    record_vals: Vec<&Map<String, Value>>,
+) -> Result<()> {
+    if !get_config().common.ingest_strict_schema {
+        return Ok(());
+    }
+    let schema = infra::schema::get_cache(org_id, stream_name, stream_type).await?;
+    if schema.schema().fields().is_empty() {
+        return Ok(());
+    }
+    let inferred_schema = infer_json_schema_from_map(record_vals.into_iter(), stream_type)?;
+    let incompatible_fields = get_incompatible_fields(&schema, &inferred_schema);
+    if incompatible_fields.is_empty() {
+        return Ok(());
+    }
+    metrics::INGEST_ERRORS
+        .with_label_values(&[
+            org_id,
+            stream_type.as_str(),
+            stream_name,
+            SCHEMA_CONFORMANCE_FAILED,
+        ])
+        .inc();
+    Err(anyhow::anyhow!(
+        "Schema of stream {stream_name} is incompatible with the records: {}",
+        incompatible_fields.join(", ")
+    ))
+}
+
+// returns the fields whose data type would be widened by the records
+fn get_incompatible_fields(schema: &SchemaCache, inferred_schema: &Schema) -> Vec<String> {
+    inferred_schema
+        .fields()
+        .iter()
+        .filter_map(|item| {
+            let idx = schema.fields_map().get(item.name())?;
+            let existing_field = &schema.schema().fields()[*idx];
+            (existing_field.data_type() != item.data_type()
+                && infra::schema::is_widening_conversion(
+                    existing_field.data_type(),
+                    item.data_type(),
+                ))
+            .then(|| {
+                format!(
+                    "field {} expected {} but got {}",
+                    item.name(),
+                    existing_field.data_type(),
+                    item.data_type()
+                )
+            })
+        })
+        .collect()
+}
+
 pub async fn check_for_schema(
     org_id: &str,
     stream_name: &str,
@@ -565,4 +625,30 @@ mod tests {
         let value_iter = record_val.into_iter();
         infer_json_schema_from_map(value_iter, stream_type).unwrap();
     }
+
+    #[test]
+    fn test_get_incompatible_fields() {
+        let schema = SchemaCache::new(Schema::new(vec![
+            Field::new("Year", DataType::Int64, false),
+            Field::new("Score", DataType::Float64, false),
+            Field::new("City", DataType::Utf8, false),
+        ]));
+
+        // same types, a narrower type which is cast and a new field
+        let inferred_schema = Schema::new(vec![
+            Field::new("Year", DataType::Int64, true),
+            Field::new("Score", DataType::Int64, true),
+            Field::new("Country", DataType::Utf8, true),
+        ]);
+        assert!(get_incompatible_fields(&schema, &inferred_schema).is_empty());
+
+        let inferred_schema = Schema::new(vec![
+            Field::new("Year", DataType::Utf8, true),
+            Field::new("City", DataType::Utf8, true),
+        ]);
+        assert_eq!(
+            get_incompatible_fields(&schema, &inferred_schema),
+            vec!["field Year expected Int64 but got Utf8".to_string()]
+        );
+    }
 }
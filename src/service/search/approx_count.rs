@@ -0,0 +1,260 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use config::{
+    get_config,
+    meta::stream::{FileKey, FileMeta, StreamType},
+    utils::sql::is_aggregate_query,
+};
+use infra::schema::{get_settings, unwrap_partition_time_level};
+use parquet::file::{metadata::ParquetMetaDataReader, statistics::Statistics};
+use sqlparser::{
+    ast::{GroupByExpr, SetExpr, Statement, TableFactor},
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+};
+
+use super::datafusion::storage::parquet_meta_cache::GLOBAL_CACHE;
+
+/// `_timestamp` min, max and number of rows of a row group
+type RowGroupStats = (i64, i64, usize);
+
+/// Estimates a lower bound of the total hits of `sql` in
+/// `[start_time, end_time)` from the file list and the row group statistics
+/// of the parquet footers, without reading any data page.
+///
+/// Returns `None` if the query can't be estimated this way, only queries
+/// which select from one stream without any filter, grouping or aggregation
+/// are supported. The records in the wal are not counted.
+pub async fn total_hits_lower_bound(
+    org_id: &str,
+    stream_type: StreamType,
+    sql: &str,
+    start_time: i64,
+    end_time: i64,
+) -> Option<usize> {
+    let stream_name = countable_stream(sql)?;
+    let settings = get_settings(org_id, &stream_name, stream_type).await;
+    let time_level =
+        unwrap_partition_time_level(settings.and_then(|s| s.partition_time_level), stream_type);
+    let files = crate::service::file_list::query(
+        org_id,
+        &stream_name,
+        stream_type,
+        time_level,
+        start_time,
+        end_time,
+    )
+    .await
+    .ok()?;
+
+    let mut total = 0;
+    for file in files.iter() {
+        let row_groups = if is_partial(&file.meta, start_time, end_time) {
+            read_row_groups(file).await
+        } else {
+            None
+        };
+        total += file_lower_bound(&file.meta, row_groups.as_deref(), start_time, end_time);
+    }
+    Some(total)
+}
+
+/// Returns the stream of a query which selects from one stream without any
+/// filter, grouping or aggregation.
+fn countable_stream(sql: &str) -> Option<String> {
+    let statement = Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?.pop()?;
+    let Statement::Query(query) = statement else {
+        return None;
+    };
+    if query.with.is_some() {
+        return None;
+    }
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return None;
+    };
+    if select.distinct.is_some()
+        || select.selection.is_some()
+        || select.having.is_some()
+        || !matches!(select.group_by, GroupByExpr::Expressions(ref expr, _) if expr.is_empty())
+        || select.from.len() != 1
+        || !select.from[0].joins.is_empty()
+    {
+        return None;
+    }
+    let TableFactor::Table { name, .. } = &select.from[0].relation else {
+        return None;
+    };
+    if is_aggregate_query(sql).unwrap_or(true) {
+        return None;
+    }
+    name.0.last().map(|ident| ident.value.clone())
+}
+
+/// The file overlaps the time range but is not fully inside it.
+fn is_partial(meta: &FileMeta, start_time: i64, end_time: i64) -> bool {
+    meta.max_ts >= start_time
+        && meta.min_ts < end_time
+        && (meta.min_ts < start_time || meta.max_ts >= end_time)
+}
+
+/// Number of records of a file which are certainly in
+/// `[start_time, end_time)`, a partially overlapping file only counts its
+/// row groups which are fully inside the time range.
+fn file_lower_bound(
+    meta: &FileMeta,
+    row_groups: Option<&[RowGroupStats]>,
+    start_time: i64,
+    end_time: i64,
+) -> usize {
+    if meta.max_ts < start_time || meta.min_ts >= end_time {
+        return 0;
+    }
+    if !is_partial(meta, start_time, end_time) {
+        return meta.records.max(0) as usize;
+    }
+    row_groups
+        .unwrap_or_default()
+        .iter()
+        .filter(|(min, max, _)| *min >= start_time && *max < end_time)
+        .map(|(_, _, rows)| rows)
+        .sum()
+}
+
+/// Reads the `_timestamp` statistics of the row groups from the parquet
+/// footer, the footer is shared with the query through [`GLOBAL_CACHE`].
+async fn read_row_groups(file: &FileKey) -> Option<Vec<RowGroupStats>> {
+    let meta = match GLOBAL_CACHE.get(&file.key) {
+        Some(meta) => meta,
+        None => {
+            let size = file.meta.compressed_size as usize;
+            if size < 8 {
+                return None;
+            }
+            let footer = infra::storage::get_range(&file.key, size - 8..size)
+                .await
+                .ok()?;
+            let len =
+                ParquetMetaDataReader::decode_footer(footer.as_ref().try_into().ok()?).ok()?;
+            if len + 8 > size {
+                return None;
+            }
+            let data = infra::storage::get_range(&file.key, size - 8 - len..size - 8)
+                .await
+                .ok()?;
+            let meta = Arc::new(ParquetMetaDataReader::decode_metadata(&data).ok()?);
+            GLOBAL_CACHE.insert(file.key.clone(), meta.clone());
+            meta
+        }
+    };
+    let column_timestamp = &get_config().common.column_timestamp;
+    let idx = meta
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .position(|c| c.name() == column_timestamp)?;
+    meta.row_groups()
+        .iter()
+        .map(|rg| match rg.column(idx).statistics()? {
+            Statistics::Int64(s) => Some((*s.min_opt()?, *s.max_opt()?, rg.num_rows() as usize)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countable_stream() {
+        assert_eq!(
+            countable_stream("SELECT * FROM \"default\" ORDER BY _timestamp DESC"),
+            Some("default".to_string())
+        );
+        assert_eq!(
+            countable_stream("SELECT * FROM \"default\" WHERE a = 1"),
+            None
+        );
+        assert_eq!(countable_stream("SELECT count(*) FROM \"default\""), None);
+        assert_eq!(
+            countable_stream("SELECT a, count(*) FROM \"default\" GROUP BY a"),
+            None
+        );
+        assert_eq!(countable_stream("SELECT DISTINCT a FROM \"default\""), None);
+    }
+
+    #[test]
+    fn test_file_lower_bound_not_above_actual() {
+        // 3 files of 2 row groups, each row group has 10 records one second apart
+        let mut files = Vec::new();
+        let mut timestamps = Vec::new();
+        for f in 0..3 {
+            let mut row_groups = Vec::new();
+            for rg in 0..2 {
+                let start = (f * 2 + rg) * 10_000_000;
+                let ts = (0..10).map(|i| start + i * 1_000_000).collect::<Vec<i64>>();
+                row_groups.push((ts[0], ts[9], ts.len()));
+                timestamps.extend(ts);
+            }
+            let meta = FileMeta {
+                min_ts: row_groups[0].0,
+                max_ts: row_groups[1].1,
+                records: 20,
+                ..Default::default()
+            };
+            files.push((meta, row_groups));
+        }
+
+        let ranges = [
+            (0, 60_000_000),
+            (5_000_000, 55_000_000),
+            (10_000_000, 30_000_000),
+            (15_000_000, 16_000_000),
+            (60_000_000, 70_000_000),
+        ];
+        for (start, end) in ranges {
+            let actual = timestamps
+                .iter()
+                .filter(|ts| **ts >= start && **ts < end)
+                .count();
+            let estimate = files
+                .iter()
+                .map(|(meta, rg)| file_lower_bound(meta, Some(rg), start, end))
+                .sum::<usize>();
+            let without_footer = files
+                .iter()
+                .map(|(meta, _)| file_lower_bound(meta, None, start, end))
+                .sum::<usize>();
+            assert!(estimate <= actual, "{start}..{end}: {estimate} > {actual}");
+            assert!(without_footer <= estimate);
+        }
+
+        // exact if the range covers whole row groups
+        let estimate = files
+            .iter()
+            .map(|(meta, rg)| file_lower_bound(meta, Some(rg), 10_000_000, 30_000_000))
+            .sum::<usize>();
+        assert_eq!(estimate, 20);
+        let estimate = files
+            .iter()
+            .map(|(meta, rg)| file_lower_bound(meta, Some(rg), 0, 60_000_000))
+            .sum::<usize>();
+        assert_eq!(estimate, 60);
+    }
+}
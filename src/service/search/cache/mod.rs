@@ -30,6 +30,7 @@ use infra::{
     errors::Error,
 };
 use proto::cluster_rpc::SearchQuery;
+use query_cache::{QueryCache, QUERY_CACHE};
 use result_utils::get_ts_value;
 use tracing::Instrument;
 
@@ -46,6 +47,7 @@ use crate::{
 
 pub mod cacher;
 pub mod multi;
+pub mod query_cache;
 pub mod result_utils;
 
 #[tracing::instrument(name = "service:search:cacher:search", skip_all)]
@@ -74,6 +76,19 @@ pub async fn search(
         }
     };
 
+    let query_cache_key = QUERY_CACHE
+        .is_enabled()
+        .then(|| QueryCache::hash_key(org_id, stream_type, in_req));
+    if let Some(key) = query_cache_key.as_deref() {
+        if let Some(mut res) = QUERY_CACHE.get(key).await {
+            log::info!("[trace_id {trace_id}] query cache hit for {org_id}/{stream_type}");
+            res.trace_id = trace_id.to_string();
+            res.took = start.elapsed().as_millis() as usize;
+            res.result_cache_ratio = 100;
+            return Ok(res);
+        }
+    }
+
     let mut req = in_req.clone();
     let mut query_fn = req
         .query
@@ -380,6 +395,19 @@ pub async fn search(
     }
     // result cache save changes Ends
 
+    if let Some(key) = query_cache_key {
+        if !res.is_partial {
+            let streams = all_streams
+                .split(',')
+                .chain(in_req.streams.iter().map(|s| s.as_str()))
+                .map(|s| format!("{org_id}/{stream_type}/{s}"))
+                .collect();
+            QUERY_CACHE
+                .set(key, streams, req.query.start_time, req.query.end_time, &res)
+                .await;
+        }
+    }
+
     Ok(res)
 }
 
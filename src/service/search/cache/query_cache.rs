@@ -0,0 +1,412 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use config::{
+    get_config,
+    meta::{
+        search,
+        stream::{FileMeta, StreamType},
+    },
+    utils::{json, parquet::parse_file_key_columns, time::now_micros},
+};
+use hashlink::lru_cache::LruCache;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+pub static QUERY_CACHE: Lazy<QueryCache> = Lazy::new(QueryCache::default);
+
+struct Entry {
+    // stream keys, eg: `default/logs/olympics`
+    streams: Vec<String>,
+    start_time: i64,
+    end_time: i64,
+    expires_at: i64,
+    // size of the file on disk
+    size: usize,
+}
+
+struct CacheInner {
+    data: LruCache<String, Entry>,
+    size: usize,
+}
+
+impl CacheInner {
+    fn remove(&mut self, key: &str) -> bool {
+        match self.data.remove(key) {
+            Some(entry) => {
+                self.size -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Whole responses of search requests, stored as json files under
+/// `{data_cache_dir}/query_cache/` and keyed by the blake3 hash of the
+/// request. Unlike the result cache it doesn't merge partial results, an entry is
+/// served as is until it expires, a new file in its time range is added to
+/// the file list of one of its streams, or it is evicted as the least
+/// recently used one when the files exceed `max_size` bytes.
+pub struct QueryCache {
+    dir: PathBuf,
+    ttl: i64,
+    max_size: usize,
+    inner: Mutex<CacheInner>,
+}
+
+impl QueryCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl_secs: u64, max_size: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: ttl_secs as i64 * 1_000_000,
+            max_size,
+            inner: Mutex::new(CacheInner {
+                data: LruCache::new_unbounded(),
+                size: 0,
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ttl > 0 && self.max_size > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().data.is_empty()
+    }
+
+    /// Returns the size in bytes of the cached files.
+    pub fn size(&self) -> usize {
+        self.inner.lock().size
+    }
+
+    /// Removes the files left by a previous run, their entries are only kept
+    /// in memory so they could never be served nor invalidated.
+    pub async fn init(&self) {
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Ok(()) => log::info!("[query_cache] removed the cached responses of the last run"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::error!("[query_cache] remove dir {:?} error: {e}", self.dir),
+        }
+    }
+
+    /// Hashes everything in the request which changes the response, each
+    /// field is prefixed with its length so the fields can't run into each
+    /// other.
+    pub fn hash_key(org_id: &str, stream_type: StreamType, req: &search::Request) -> String {
+        let body = [
+            org_id.to_string(),
+            stream_type.to_string(),
+            req.query.sql.clone(),
+            req.query.start_time.to_string(),
+            req.query.end_time.to_string(),
+            req.query.from.to_string(),
+            req.query.size.to_string(),
+            req.query.sort_by.clone().unwrap_or_default(),
+            req.query.quick_mode.to_string(),
+            req.query.query_type.clone(),
+            req.query.track_total_hits.to_string(),
+            req.query.uses_zo_fn.to_string(),
+            req.query.query_fn.clone().unwrap_or_default(),
+            req.query.skip_wal.to_string(),
+            req.encoding.to_string(),
+            req.timeout.to_string(),
+            req.streams.join(","),
+            req.regions.join(","),
+            req.clusters.join(","),
+            req.cursor.clone().unwrap_or_default(),
+            req.use_cursor.to_string(),
+            req.approximate_count.to_string(),
+            req.use_fts.to_string(),
+        ];
+        let mut hasher = blake3::Hasher::new();
+        for field in body.iter() {
+            hasher.update(&(field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    fn file_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// Removes the files of the dropped entries, outside of the lock.
+    async fn remove_files(&self, keys: Vec<String>) {
+        for key in keys {
+            if let Err(e) = tokio::fs::remove_file(self.file_path(&key)).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::error!("[query_cache] remove cached response {key} error: {e}");
+                }
+            }
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        if self.inner.lock().remove(key) {
+            self.remove_files(vec![key.to_string()]).await;
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<search::Response> {
+        let expires_at = self.inner.lock().data.get(key)?.expires_at;
+        if now_micros() > expires_at {
+            self.remove(key).await;
+            return None;
+        }
+        let data = tokio::fs::read(self.file_path(key)).await.ok()?;
+        match json::from_slice(&data) {
+            Ok(res) => Some(res),
+            Err(e) => {
+                log::error!("[query_cache] parse cached response {key} error: {e}");
+                self.remove(key).await;
+                None
+            }
+        }
+    }
+
+    pub async fn set(
+        &self,
+        key: String,
+        streams: Vec<String>,
+        start_time: i64,
+        end_time: i64,
+        res: &search::Response,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let data = match json::to_vec(res) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("[query_cache] serialize response {key} error: {e}");
+                return;
+            }
+        };
+        let size = data.len();
+        if size > self.max_size {
+            return;
+        }
+        if let Err(e) = tokio::fs::create_dir_all(&self.dir).await {
+            log::error!("[query_cache] create dir {:?} error: {e}", self.dir);
+            return;
+        }
+        if let Err(e) = tokio::fs::write(self.file_path(&key), data).await {
+            log::error!("[query_cache] write cached response {key} error: {e}");
+            return;
+        }
+
+        // replace the old entry and evict the least recently used ones
+        let mut evicted = Vec::new();
+        {
+            let mut inner = self.inner.lock();
+            inner.remove(&key);
+            while inner.size + size > self.max_size {
+                let Some((k, entry)) = inner.data.remove_lru() else {
+                    break;
+                };
+                inner.size -= entry.size;
+                evicted.push(k);
+            }
+            inner.size += size;
+            inner.data.insert(
+                key,
+                Entry {
+                    streams,
+                    start_time,
+                    end_time,
+                    expires_at: now_micros() + self.ttl,
+                    size,
+                },
+            );
+        }
+        self.remove_files(evicted).await;
+    }
+
+    /// Drops the entries of the stream whose time range overlaps with the
+    /// time range of a newly added file.
+    pub async fn invalidate(&self, stream_key: &str, min_ts: i64, max_ts: i64) {
+        let keys = {
+            let mut inner = self.inner.lock();
+            if inner.data.is_empty() {
+                return;
+            }
+            let keys = inner
+                .data
+                .iter()
+                .filter(|(_, e)| {
+                    e.start_time <= max_ts
+                        && e.end_time >= min_ts
+                        && e.streams.iter().any(|s| s == stream_key)
+                })
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<_>>();
+            for key in keys.iter() {
+                inner.remove(key);
+            }
+            keys
+        };
+        self.remove_files(keys).await;
+    }
+
+    /// Drops the entries which don't include a newly added file, eg:
+    /// `files/default/logs/olympics/2022/10/03/10/xxx.parquet`.
+    pub async fn invalidate_file(&self, file_key: &str, meta: &FileMeta) {
+        if let Ok((stream_key, ..)) = parse_file_key_columns(file_key) {
+            self.invalidate(&stream_key, meta.min_ts, meta.max_ts).await;
+        }
+    }
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        let cfg = get_config();
+        Self::new(
+            format!("{}query_cache", cfg.common.data_cache_dir),
+            cfg.common.query_cache_ttl,
+            cfg.common.query_cache_max_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_response(total: usize) -> search::Response {
+        search::Response {
+            total,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_cache_hash_key() {
+        let req: search::Request =
+            json::from_str(r#"{"query":{"sql":"SELECT * FROM t","size":10}}"#).unwrap();
+        let key = QueryCache::hash_key("default", StreamType::Logs, &req);
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, QueryCache::hash_key("default", StreamType::Logs, &req));
+        assert_ne!(key, QueryCache::hash_key("other", StreamType::Logs, &req));
+
+        let mut other = req.clone();
+        other.use_cursor = true;
+        assert_ne!(
+            key,
+            QueryCache::hash_key("default", StreamType::Logs, &other)
+        );
+
+        // the fields can't be shifted into each other
+        let mut a = req.clone();
+        a.regions = vec!["a\nb".to_string()];
+        let mut b = req.clone();
+        b.regions = vec!["a".to_string()];
+        b.clusters = vec!["b".to_string()];
+        assert_ne!(
+            QueryCache::hash_key("default", StreamType::Logs, &a),
+            QueryCache::hash_key("default", StreamType::Logs, &b)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_invalidate() {
+        let dir = std::env::temp_dir().join("test_query_cache_invalidate");
+        let cache = QueryCache::new(&dir, 60, 1024 * 1024);
+        let streams = vec!["default/logs/olympics".to_string()];
+        cache
+            .set("1".to_string(), streams.clone(), 100, 200, &new_response(1))
+            .await;
+        cache
+            .set("2".to_string(), streams, 300, 400, &new_response(2))
+            .await;
+        assert_eq!(cache.get("1").await.unwrap().total, 1);
+        assert_eq!(cache.get("2").await.unwrap().total, 2);
+
+        // other stream
+        cache.invalidate("default/logs/other", 150, 160).await;
+        assert_eq!(cache.len(), 2);
+        // out of the time range
+        cache.invalidate("default/logs/olympics", 210, 290).await;
+        assert_eq!(cache.len(), 2);
+        cache.invalidate("default/logs/olympics", 150, 160).await;
+        assert!(cache.get("1").await.is_none());
+        assert!(cache.get("2").await.is_some());
+        assert!(!dir.join("1.json").exists());
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_ttl() {
+        let dir = std::env::temp_dir().join("test_query_cache_ttl");
+        let cache = QueryCache::new(&dir, 60, 1024 * 1024);
+        cache
+            .set("1".to_string(), vec![], 100, 200, &new_response(1))
+            .await;
+        cache.inner.lock().data.get_mut("1").unwrap().expires_at = now_micros() - 1;
+        assert!(cache.get("1").await.is_none());
+        assert!(cache.is_empty());
+
+        let cache = QueryCache::new(&dir, 0, 1024 * 1024);
+        cache
+            .set("1".to_string(), vec![], 100, 200, &new_response(1))
+            .await;
+        assert!(cache.get("1").await.is_none());
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_lru() {
+        let dir = std::env::temp_dir().join("test_query_cache_lru");
+        let size = json::to_vec(&new_response(1)).unwrap().len();
+        let cache = QueryCache::new(&dir, 60, size * 2);
+        cache
+            .set("1".to_string(), vec![], 100, 200, &new_response(1))
+            .await;
+        cache
+            .set("2".to_string(), vec![], 100, 200, &new_response(2))
+            .await;
+        assert_eq!(cache.size(), size * 2);
+        // touch 1, then 2 is the least recently used one
+        assert!(cache.get("1").await.is_some());
+        cache
+            .set("3".to_string(), vec![], 100, 200, &new_response(3))
+            .await;
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), size * 2);
+        assert!(cache.get("2").await.is_none());
+        assert!(!dir.join("2.json").exists());
+        assert!(cache.get("1").await.is_some());
+        assert!(cache.get("3").await.is_some());
+
+        // replacing an entry doesn't count its old size
+        cache
+            .set("3".to_string(), vec![], 100, 200, &new_response(4))
+            .await;
+        assert_eq!(cache.size(), size * 2);
+        assert_eq!(cache.get("3").await.unwrap().total, 4);
+
+        // restart
+        let cache = QueryCache::new(&dir, 60, size * 2);
+        cache.init().await;
+        assert!(!dir.exists());
+    }
+}
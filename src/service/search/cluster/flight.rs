@@ -32,6 +32,7 @@ use config::{
 };
 use datafusion::{
     common::{tree_node::TreeNode, TableReference},
+    datasource::TableProvider,
     error::DataFusionError,
     physical_plan::{displayable, visit_execution_plan, ExecutionPlan},
     prelude::SessionContext,
@@ -58,7 +59,10 @@ use crate::{
             },
             exec::{prepare_datafusion_context, register_udf},
             optimizer::generate_optimizer_rules,
-            table_provider::{catalog::StreamTypeProvider, empty_table::NewEmptyTable},
+            table_provider::{
+                catalog::StreamTypeProvider, derived_table::with_derived_fields,
+                empty_table::NewEmptyTable,
+            },
         },
         generate_filter_from_equal_items,
         request::Request,
@@ -261,6 +265,7 @@ pub async fn search(
         stream_type = sql.stream_type.to_string(),
     );
 
+    let org_id = sql.org_id.clone();
     let trace_id_move = trace_id.to_string();
     let query_task = DATAFUSION_RUNTIME.spawn(async move {
         run_datafusion(
@@ -290,7 +295,12 @@ pub async fn search(
         _ = tokio::time::sleep(tokio::time::Duration::from_secs(timeout)) => {
             query_task.abort();
             log::error!("[trace_id {trace_id}] flight->search: search timeout");
-            Err(DataFusionError::ResourcesExhausted("flight->search: search timeout".to_string()))
+            metrics::QUERY_TIMEOUT_NUMS
+                .with_label_values(&[&org_id])
+                .inc();
+            Ok(Err(Error::ErrorCode(ErrorCodes::SearchTimeout(format!(
+                "[trace_id {trace_id}] flight->search: search timeout after {timeout}s"
+            )))))
         },
         _ = async {
             #[cfg(feature = "enterprise")]
@@ -769,11 +779,15 @@ pub async fn register_table(ctx: &SessionContext, sql: &Sql) -> Result<()> {
     for (stream, schema) in &sql.schemas {
         let schema = schema.schema().as_ref().clone();
         let stream_name = stream.to_quoted_string();
-        let table = Arc::new(
+        let table: Arc<dyn TableProvider> = Arc::new(
             NewEmptyTable::new(&stream_name, Arc::new(schema))
                 .with_partitions(ctx.state().config().target_partitions())
                 .with_sorted_by_time(sql.sorted_by_time),
         );
+        let table = match sql.derived_fields.get(stream) {
+            Some(fields) => with_derived_fields(&ctx.state(), &stream_name, table, fields)?,
+            None => table,
+        };
         ctx.register_table(&stream_name, table)?;
     }
 
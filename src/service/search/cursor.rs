@@ -0,0 +1,168 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use config::{get_config, utils::json, ID_COL_NAME};
+use serde::{Deserialize, Serialize};
+
+const NONCE_LEN: usize = 12;
+
+/// Position of the next page of a search which is sorted by `_timestamp` desc
+/// and `_o2_id` desc, the next page reads the records with
+/// `(_timestamp, _o2_id) < (timestamp, id)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub timestamp: i64,
+    pub id: String,
+}
+
+impl Cursor {
+    /// Encrypts the cursor into an opaque token, the sql and the start time are
+    /// authenticated with it, so it only works for the same query.
+    pub fn encode(&self, sql: &str, start_time: i64) -> String {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let payload = json::to_vec(self).unwrap();
+        let aad = format!("{start_time}:{sql}");
+        // only fails if the payload is larger than 256 GiB
+        let ciphertext = cipher()
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &payload,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .expect("search cursor encryption failed");
+        URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub fn decode(token: &str, sql: &str, start_time: i64) -> Result<Self, anyhow::Error> {
+        let invalid = || anyhow::anyhow!("Invalid search cursor");
+        let token = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        if token.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = token.split_at(NONCE_LEN);
+        let aad = format!("{start_time}:{sql}");
+        let payload = cipher()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| invalid())?;
+        json::from_slice(&payload).map_err(|_| invalid())
+    }
+
+    /// Returns the cursor of the page after `hits`, `None` if it is the last
+    /// page or the hits have no `_timestamp` or `_o2_id`.
+    pub fn next(hits: &[json::Value], size: i64) -> Option<Self> {
+        if hits.is_empty() || (hits.len() as i64) < size {
+            return None;
+        }
+        let last = hits.last()?;
+        let timestamp = last.get(&get_config().common.column_timestamp)?.as_i64()?;
+        let id = last.get(ID_COL_NAME)?.as_str()?.to_string();
+        Some(Self { timestamp, id })
+    }
+}
+
+fn cipher() -> ChaCha20Poly1305 {
+    let cfg = get_config();
+    let secret = if cfg.common.search_cursor_secret.is_empty() {
+        &cfg.auth.root_user_password
+    } else {
+        &cfg.common.search_cursor_secret
+    };
+    let key = blake3::derive_key("openobserve search cursor", secret.as_bytes());
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor() -> Cursor {
+        Cursor {
+            timestamp: 1700000000000000,
+            id: "7113456789012345678".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cursor_encode_decode() {
+        let sql = "SELECT * FROM default";
+        let token = cursor().encode(sql, 0);
+        assert_eq!(Cursor::decode(&token, sql, 0).unwrap(), cursor());
+        // the cursor only works for the same query
+        assert!(Cursor::decode(&token, "SELECT * FROM other", 0).is_err());
+        assert!(Cursor::decode(&token, sql, 1).is_err());
+    }
+
+    #[test]
+    fn test_cursor_encrypted() {
+        let sql = "SELECT * FROM default";
+        let token = cursor().encode(sql, 0);
+        let raw = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(!raw.contains("1700000000000000"));
+        assert!(!raw.contains("7113456789012345678"));
+        // a new nonce every time
+        assert_ne!(token, cursor().encode(sql, 0));
+    }
+
+    #[test]
+    fn test_cursor_tampered() {
+        let sql = "SELECT * FROM default";
+        let token = cursor().encode(sql, 0);
+        let mut raw = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        let last = raw.len() - 1;
+        raw[last] ^= 1;
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode(&raw), sql, 0).is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode(&raw[..4]), sql, 0).is_err());
+        assert!(Cursor::decode("not a cursor", sql, 0).is_err());
+        let plain = URL_SAFE_NO_PAD.encode(json::to_vec(&cursor()).unwrap());
+        assert!(Cursor::decode(&plain, sql, 0).is_err());
+    }
+
+    #[test]
+    fn test_cursor_next() {
+        let hits = |rows: &[(i64, &str)]| {
+            rows.iter()
+                .map(|(ts, id)| json::json!({"_timestamp": ts, "_o2_id": id}))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(Cursor::next(&hits(&[(5, "b"), (4, "a")]), 3), None);
+        assert_eq!(
+            Cursor::next(&hits(&[(5, "c"), (4, "b"), (4, "a")]), 3),
+            Some(Cursor {
+                timestamp: 4,
+                id: "a".to_string()
+            })
+        );
+        // no `_o2_id` to break the ties of `_timestamp`
+        let hits = [5, 4, 3]
+            .iter()
+            .map(|ts| json::json!({"_timestamp": ts}))
+            .collect::<Vec<_>>();
+        assert_eq!(Cursor::next(&hits, 3), None);
+    }
+}
@@ -16,5 +16,6 @@
 pub mod file_list;
 pub mod file_statistics_cache;
 pub mod memory;
+pub mod parquet_meta_cache;
 pub mod tmpfs;
 pub mod wal;
@@ -0,0 +1,285 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{ops::Range, sync::Arc};
+
+use bytes::Bytes;
+use config::{metrics, utils::time::now_micros};
+use datafusion::{
+    datasource::physical_plan::{
+        parquet::{DefaultParquetFileReaderFactory, ParquetFileReaderFactory},
+        FileMeta,
+    },
+    physical_plan::metrics::ExecutionPlanMetricsSet,
+};
+use futures::future::BoxFuture;
+use hashlink::lru_cache::LruCache;
+use object_store::ObjectStore;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use parquet::{arrow::async_reader::AsyncFileReader, file::metadata::ParquetMetaData};
+
+pub static GLOBAL_CACHE: Lazy<Arc<ParquetMetaCache>> =
+    Lazy::new(|| Arc::new(ParquetMetaCache::default()));
+
+/// Parquet footers (schema + row group statistics) of the files read by
+/// queries, so a file's footer is fetched at most once per ttl.
+/// Files are immutable once uploaded, so entries are only expired by ttl or
+/// evicted as least recently used once the footers exceed `max_size` bytes.
+pub struct ParquetMetaCache {
+    inner: Mutex<CacheInner>,
+    ttl: i64,
+    max_size: usize,
+}
+
+struct CacheInner {
+    // file key -> (footer, inserted time in microseconds), ordered by last access
+    data: LruCache<String, (Arc<ParquetMetaData>, i64)>,
+    size: usize,
+}
+
+impl CacheInner {
+    fn remove(&mut self, key: &str) {
+        if let Some((meta, _)) = self.data.remove(key) {
+            self.size -= entry_size(key, &meta);
+        }
+    }
+}
+
+fn entry_size(key: &str, meta: &ParquetMetaData) -> usize {
+    key.len() + meta.memory_size()
+}
+
+impl ParquetMetaCache {
+    pub fn new(max_size: usize, ttl_secs: u64) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                data: LruCache::new_unbounded(),
+                size: 0,
+            }),
+            ttl: ttl_secs as i64 * 1_000_000,
+            max_size,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_size > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().data.is_empty()
+    }
+
+    /// Memory in bytes used by the cached footers.
+    pub fn size(&self) -> usize {
+        self.inner.lock().size
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<ParquetMetaData>> {
+        let mut inner = self.inner.lock();
+        let (meta, inserted_at) = inner.data.get(key)?;
+        if self.ttl > 0 && now_micros() - *inserted_at > self.ttl {
+            inner.remove(key);
+            return None;
+        }
+        Some(meta.clone())
+    }
+
+    pub fn insert(&self, key: String, meta: Arc<ParquetMetaData>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let size = entry_size(&key, &meta);
+        if size > self.max_size {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        inner.remove(&key);
+        inner.data.insert(key, (meta, now_micros()));
+        inner.size += size;
+        while inner.size > self.max_size {
+            match inner.data.remove_lru() {
+                Some((k, (m, _))) => inner.size -= entry_size(&k, &m),
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.data.clear();
+        inner.size = 0;
+    }
+
+    /// Only the files from the file list, eg: `/{trace_id}/$$/files/...`, are
+    /// cached, keyed by the storage file key.
+    fn format_key(location: &str) -> Option<String> {
+        location.find("/$$/").map(|p| location[p + 4..].to_string())
+    }
+}
+
+impl Default for ParquetMetaCache {
+    fn default() -> Self {
+        let cfg = config::get_config();
+        Self::new(
+            cfg.limit.datafusion_parquet_meta_cache_max_size,
+            cfg.limit.datafusion_parquet_meta_cache_ttl,
+        )
+    }
+}
+
+/// A [`ParquetFileReaderFactory`] which serves the parquet footer from
+/// [`GLOBAL_CACHE`] and reads everything else from the object store.
+#[derive(Debug)]
+pub struct CachedParquetFileReaderFactory {
+    inner: DefaultParquetFileReaderFactory,
+}
+
+impl CachedParquetFileReaderFactory {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            inner: DefaultParquetFileReaderFactory::new(store),
+        }
+    }
+}
+
+impl ParquetFileReaderFactory for CachedParquetFileReaderFactory {
+    fn create_reader(
+        &self,
+        partition_index: usize,
+        file_meta: FileMeta,
+        metadata_size_hint: Option<usize>,
+        metrics: &ExecutionPlanMetricsSet,
+    ) -> datafusion::error::Result<Box<dyn AsyncFileReader + Send>> {
+        let key = ParquetMetaCache::format_key(file_meta.location().as_ref());
+        let inner =
+            self.inner
+                .create_reader(partition_index, file_meta, metadata_size_hint, metrics)?;
+        match key {
+            Some(key) => Ok(Box::new(CachedParquetFileReader { key, inner })),
+            None => Ok(inner),
+        }
+    }
+}
+
+struct CachedParquetFileReader {
+    key: String,
+    inner: Box<dyn AsyncFileReader + Send>,
+}
+
+impl AsyncFileReader for CachedParquetFileReader {
+    fn get_bytes(&mut self, range: Range<usize>) -> BoxFuture<'_, parquet::errors::Result<Bytes>> {
+        self.inner.get_bytes(range)
+    }
+
+    fn get_byte_ranges(
+        &mut self,
+        ranges: Vec<Range<usize>>,
+    ) -> BoxFuture<'_, parquet::errors::Result<Vec<Bytes>>> {
+        self.inner.get_byte_ranges(ranges)
+    }
+
+    fn get_metadata(&mut self) -> BoxFuture<'_, parquet::errors::Result<Arc<ParquetMetaData>>> {
+        Box::pin(async move {
+            if let Some(meta) = GLOBAL_CACHE.get(&self.key) {
+                metrics::QUERY_PARQUET_META_CACHE_HITS
+                    .with_label_values(&[])
+                    .inc();
+                return Ok(meta);
+            }
+            metrics::QUERY_PARQUET_META_CACHE_MISSES
+                .with_label_values(&[])
+                .inc();
+            let meta = self.inner.get_metadata().await?;
+            GLOBAL_CACHE.insert(self.key.clone(), meta.clone());
+            Ok(meta)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::{
+        file::metadata::FileMetaData,
+        schema::types::{SchemaDescriptor, Type},
+    };
+
+    use super::*;
+
+    fn new_meta() -> Arc<ParquetMetaData> {
+        let schema = Type::group_type_builder("schema").build().unwrap();
+        let schema = Arc::new(SchemaDescriptor::new(Arc::new(schema)));
+        let file_meta = FileMetaData::new(1, 0, None, None, schema, None);
+        Arc::new(ParquetMetaData::new(file_meta, vec![]))
+    }
+
+    #[test]
+    fn test_parquet_meta_cache_format_key() {
+        assert_eq!(
+            ParquetMetaCache::format_key("/trace_id/schema=123/$$/files/default/logs/a.parquet"),
+            Some("files/default/logs/a.parquet".to_string())
+        );
+        assert_eq!(ParquetMetaCache::format_key("/trace_id/a.parquet"), None);
+    }
+
+    #[test]
+    fn test_parquet_meta_cache_lru() {
+        let entry = entry_size("a", &new_meta());
+        let cache = ParquetMetaCache::new(entry * 2, 3600);
+        cache.insert("a".to_string(), new_meta());
+        cache.insert("b".to_string(), new_meta());
+        assert_eq!(cache.size(), entry * 2);
+        assert!(cache.get("a").is_some());
+        // b is the least recently used
+        cache.insert("c".to_string(), new_meta());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), entry * 2);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+        // replacing an entry does not count it twice
+        cache.insert("c".to_string(), new_meta());
+        assert_eq!(cache.size(), entry * 2);
+    }
+
+    #[test]
+    fn test_parquet_meta_cache_too_large() {
+        let cache = ParquetMetaCache::new(1, 3600);
+        cache.insert("a".to_string(), new_meta());
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_parquet_meta_cache_ttl() {
+        let cache = ParquetMetaCache::new(1024 * 1024, 3600);
+        cache.insert("a".to_string(), new_meta());
+        cache.inner.lock().data.get_mut("a").unwrap().1 = now_micros() - 3601 * 1_000_000;
+        assert!(cache.get("a").is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_parquet_meta_cache_disabled() {
+        let cache = ParquetMetaCache::new(0, 3600);
+        cache.insert("a".to_string(), new_meta());
+        assert!(cache.get("a").is_none());
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use config::meta::stream::DerivedField;
+use datafusion::{
+    common::{Column, DFSchema, Result, TableReference},
+    datasource::{provider_as_source, TableProvider, ViewTable},
+    execution::context::SessionState,
+    logical_expr::{Expr, LogicalPlanBuilder},
+};
+
+/// Wraps the table of a stream in a view which appends the derived fields of
+/// the stream as computed columns. The view is inlined into the logical plan,
+/// so the derived fields can be selected and filtered like stored columns.
+///
+/// Derived fields which can't be planned, eg: the expression uses a column
+/// which isn't in the schema yet, are skipped.
+pub fn with_derived_fields(
+    state: &SessionState,
+    name: &str,
+    table: Arc<dyn TableProvider>,
+    fields: &[DerivedField],
+) -> Result<Arc<dyn TableProvider>> {
+    let table_ref = TableReference::from(name);
+    let schema = table.schema();
+    let df_schema = DFSchema::try_from_qualified_schema(table_ref.clone(), &schema)?;
+    let mut exprs = schema
+        .fields()
+        .iter()
+        .map(|f| Expr::Column(Column::new(Some(table_ref.clone()), f.name())))
+        .collect::<Vec<_>>();
+    for field in fields {
+        if schema.field_with_name(&field.name).is_ok() {
+            continue;
+        }
+        match state.create_logical_expr(&field.expression, &df_schema) {
+            Ok(expr) => exprs.push(expr.alias(&field.name)),
+            Err(e) => log::warn!("skip derived field [{}] of {name}: {e}", field.name),
+        }
+    }
+    let plan = LogicalPlanBuilder::scan(table_ref, provider_as_source(table), None)?
+        .project(exprs)?
+        .build()?;
+    Ok(Arc::new(ViewTable::try_new(plan, None)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Int64Array, StringArray},
+        record_batch::RecordBatch,
+    };
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::{datasource::MemTable, prelude::SessionContext};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_derived_fields() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("latency_us", DataType::Int64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+                Arc::new(Int64Array::from(vec![1000, 5000, 9000])),
+            ],
+        )
+        .unwrap();
+        let table = Arc::new(MemTable::try_new(schema, vec![vec![batch]]).unwrap());
+        let fields = vec![
+            DerivedField {
+                name: "response_time_ms".to_string(),
+                expression: "latency_us / 1000".to_string(),
+            },
+            DerivedField {
+                name: "missing".to_string(),
+                expression: "no_such_column + 1".to_string(),
+            },
+        ];
+
+        let ctx = SessionContext::new();
+        let table = with_derived_fields(&ctx.state(), "t", table, &fields).unwrap();
+        assert!(table.schema().field_with_name("missing").is_err());
+        ctx.register_table("t", table).unwrap();
+
+        let batches = ctx
+            .sql("SELECT name, response_time_ms FROM t WHERE response_time_ms > 2 ORDER BY name")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        let values = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(values.values(), &[5, 9]);
+    }
+}
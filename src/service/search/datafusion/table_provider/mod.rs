@@ -39,9 +39,10 @@ use datafusion::{
     catalog::Session,
     common::{plan_err, project_schema, Result, Statistics, ToDFSchema},
     datasource::{
+        file_format::parquet::ParquetFormat,
         get_statistics_with_limit,
         listing::{ListingOptions, ListingTableConfig, ListingTableUrl, PartitionedFile},
-        physical_plan::FileScanConfig,
+        physical_plan::{parquet::ParquetExecBuilder, FileScanConfig},
         TableProvider,
     },
     error::DataFusionError,
@@ -62,9 +63,13 @@ use helpers::*;
 use object_store::ObjectStore;
 use tokio::sync::Semaphore;
 
-use crate::service::search::index::IndexCondition;
+use crate::service::search::{
+    datafusion::storage::parquet_meta_cache::{self, CachedParquetFileReaderFactory},
+    index::IndexCondition,
+};
 
 pub mod catalog;
+pub mod derived_table;
 pub mod empty_table;
 mod helpers;
 pub mod memtable;
@@ -365,21 +370,46 @@ impl TableProvider for NewListingTable {
         let filter_projection = filter_projection.as_ref();
 
         // create the execution plan
-        let parquet_exec = self
-            .options
-            .format
-            .create_physical_plan(
-                session_state,
-                FileScanConfig::new(object_store_url, Arc::clone(&self.file_schema))
-                    .with_file_groups(partitioned_file_lists)
-                    .with_statistics(statistics)
-                    .with_projection(parquet_projection.cloned())
-                    .with_limit(limit)
-                    .with_output_ordering(output_ordering)
-                    .with_table_partition_cols(table_partition_cols),
-                filters.as_ref(),
-            )
-            .await?;
+        let file_scan_config =
+            FileScanConfig::new(object_store_url.clone(), Arc::clone(&self.file_schema))
+                .with_file_groups(partitioned_file_lists)
+                .with_statistics(statistics)
+                .with_projection(parquet_projection.cloned())
+                .with_limit(limit)
+                .with_output_ordering(output_ordering)
+                .with_table_partition_cols(table_partition_cols);
+        let parquet_format = self.options.format.as_any().downcast_ref::<ParquetFormat>();
+        let parquet_exec = match parquet_format {
+            Some(format) if parquet_meta_cache::GLOBAL_CACHE.is_enabled() => {
+                // same as ParquetFormat::create_physical_plan, but reads the
+                // parquet footer through the parquet metadata cache
+                let store = session_state
+                    .runtime_env()
+                    .object_store(&object_store_url)?;
+                let mut builder = ParquetExecBuilder::new_with_options(
+                    file_scan_config,
+                    format.options().clone(),
+                )
+                .with_parquet_file_reader_factory(Arc::new(
+                    CachedParquetFileReaderFactory::new(store),
+                ));
+                if format.enable_pruning() {
+                    if let Some(filters) = filters {
+                        builder = builder.with_predicate(filters);
+                    }
+                }
+                if let Some(size_hint) = format.metadata_size_hint() {
+                    builder = builder.with_metadata_size_hint(size_hint);
+                }
+                builder.build_arc()
+            }
+            _ => {
+                self.options
+                    .format
+                    .create_physical_plan(session_state, file_scan_config, filters.as_ref())
+                    .await?
+            }
+        };
 
         let projection_exec = apply_projection(
             &self.schema(),
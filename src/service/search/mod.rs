@@ -64,13 +64,16 @@ use crate::{
     handler::grpc::request::search::Searcher,
 };
 
+pub(crate) mod approx_count;
 pub(crate) mod cache;
 pub(crate) mod cluster;
+pub(crate) mod cursor;
 pub(crate) mod datafusion;
 pub(crate) mod grpc;
 pub(crate) mod grpc_search;
 pub(crate) mod index;
 pub(crate) mod request;
+pub(crate) mod scan_budget;
 pub(crate) mod sql;
 #[cfg(feature = "enterprise")]
 pub(crate) mod super_cluster;
@@ -1334,4 +1337,83 @@ mod tests {
             );
         }
     }
+
+    async fn match_files(
+        files: &[FileKey],
+        partition_keys: &[StreamPartition],
+        equal_items: &[(&str, &str)],
+    ) -> Vec<usize> {
+        let equal_items = equal_items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        let mut matched = Vec::new();
+        for (i, file) in files.iter().enumerate() {
+            if match_file(
+                "default",
+                StreamType::Logs,
+                "web",
+                None,
+                file,
+                partition_keys,
+                &equal_items,
+            )
+            .await
+            {
+                matched.push(i);
+            }
+        }
+        matched
+    }
+
+    #[tokio::test]
+    async fn test_match_file_by_partition_key() {
+        let keys = vec![
+            StreamPartition::new("host"),
+            StreamPartition::new_hash("trace_id", 8),
+        ];
+        let bucket = keys[1].get_partition_value("abc");
+        let file = |host: &str, bucket: &str| FileKey {
+            key: format!(
+                "files/default/logs/web/2024/01/01/00/host={host}/trace_id={bucket}/1.parquet"
+            ),
+            ..Default::default()
+        };
+        let files = vec![
+            file("web1", &bucket),
+            file("web2", &bucket),
+            file("web1", "99"),
+        ];
+
+        assert_eq!(match_files(&files, &keys, &[]).await, vec![0, 1, 2]);
+        assert_eq!(
+            match_files(&files, &keys, &[("host", "web1")]).await,
+            vec![0, 2]
+        );
+        // the value is formatted the same way as the partition path
+        assert_eq!(
+            match_files(&files, &keys, &[("host", "web.1")]).await,
+            vec![0, 2]
+        );
+        assert_eq!(
+            match_files(&files, &keys, &[("host", "web1"), ("host", "web2")]).await,
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            match_files(&files, &keys, &[("trace_id", "abc")]).await,
+            vec![0, 1]
+        );
+        assert_eq!(
+            match_files(&files, &keys, &[("host", "web2"), ("trace_id", "abc")]).await,
+            vec![1]
+        );
+        assert!(match_files(&files, &keys, &[("host", "web3")])
+            .await
+            .is_empty());
+        // not a partition key
+        assert_eq!(
+            match_files(&files, &keys, &[("level", "error")]).await,
+            vec![0, 1, 2]
+        );
+    }
 }
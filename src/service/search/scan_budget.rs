@@ -0,0 +1,96 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::meta::stream::{FileMeta, StreamType};
+use infra::{
+    errors,
+    schema::{get_settings, unwrap_partition_time_level},
+};
+
+/// Estimates the bytes a query of `stream_names` in `[start_time, end_time)`
+/// would scan, by summing the original size of the files overlapping the time
+/// range in the file list. The records in the wal are not counted.
+pub async fn estimate_scan_bytes(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_names: &[String],
+    start_time: i64,
+    end_time: i64,
+) -> errors::Result<i64> {
+    let mut total = 0;
+    for stream_name in stream_names.iter() {
+        let settings = get_settings(org_id, stream_name, stream_type).await;
+        let time_level =
+            unwrap_partition_time_level(settings.and_then(|s| s.partition_time_level), stream_type);
+        let files = crate::service::file_list::query(
+            org_id,
+            stream_name,
+            stream_type,
+            time_level,
+            start_time,
+            end_time,
+        )
+        .await?;
+        total += files
+            .iter()
+            .filter(|file| overlaps(&file.meta, start_time, end_time))
+            .map(|file| file.meta.original_size)
+            .sum::<i64>();
+    }
+    Ok(total)
+}
+
+/// Checks the estimated scan bytes against the limit, a limit of 0 means
+/// unlimited.
+pub fn check_scan_budget(scan_bytes: i64, limit: i64) -> Result<(), String> {
+    if limit > 0 && scan_bytes > limit {
+        return Err(format!(
+            "query would scan {scan_bytes} bytes, limit is {limit}"
+        ));
+    }
+    Ok(())
+}
+
+fn overlaps(meta: &FileMeta, start_time: i64, end_time: i64) -> bool {
+    meta.max_ts >= start_time && meta.min_ts < end_time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_scan_budget() {
+        assert!(check_scan_budget(100, 0).is_ok());
+        assert!(check_scan_budget(100, 100).is_ok());
+        assert_eq!(
+            check_scan_budget(101, 100),
+            Err("query would scan 101 bytes, limit is 100".to_string())
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let meta = FileMeta {
+            min_ts: 10,
+            max_ts: 20,
+            ..Default::default()
+        };
+        assert!(overlaps(&meta, 0, 11));
+        assert!(overlaps(&meta, 20, 30));
+        assert!(!overlaps(&meta, 0, 10));
+        assert!(!overlaps(&meta, 21, 30));
+    }
+}
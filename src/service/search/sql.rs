@@ -22,9 +22,9 @@ use config::{
     meta::{
         inverted_index::InvertedIndexOptimizeMode,
         sql::{resolve_stream_names_with_type, OrderBy, Sql as MetaSql, TableReferenceExt},
-        stream::StreamType,
+        stream::{DerivedField, StreamType},
     },
-    utils::sql::AGGREGATE_UDF_LIST,
+    utils::sql::{is_aggregate_expression, AGGREGATE_UDF_LIST},
     ID_COL_NAME, ORIGINAL_DATA_COL_NAME,
 };
 use datafusion::{arrow::datatypes::Schema, common::TableReference};
@@ -42,15 +42,17 @@ use proto::cluster_rpc::SearchQuery;
 use regex::Regex;
 use sqlparser::{
     ast::{
-        BinaryOperator, DuplicateTreatment, Expr, Function, FunctionArg, FunctionArgExpr,
-        FunctionArgumentList, FunctionArguments, GroupByExpr, Ident, ObjectName, Query, Select,
-        SelectItem, SetExpr, Statement, TableFactor, TableWithJoins, VisitMut, VisitorMut,
+        visit_relations_mut, BinaryOperator, DuplicateTreatment, Expr, Function, FunctionArg,
+        FunctionArgExpr, FunctionArgumentList, FunctionArguments, GroupByExpr, Ident, ObjectName,
+        OrderByExpr, Query, Select, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins,
+        Value, VisitMut, VisitorMut,
     },
     dialect::PostgreSqlDialect,
     parser::Parser,
 };
 
 use super::{
+    cursor::Cursor,
     datafusion::udf::match_all_udf::{
         FUZZY_MATCH_ALL_UDF_NAME, MATCH_ALL_RAW_IGNORE_CASE_UDF_NAME, MATCH_ALL_RAW_UDF_NAME,
         MATCH_ALL_UDF_NAME,
@@ -59,6 +61,7 @@ use super::{
     request::Request,
     utils::{is_field, is_value, split_conjunction, trim_quotes},
 };
+use crate::{common::infra::config::STREAM_ALIASES, service::stream};
 
 pub static RE_ONLY_SELECT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)select[ ]+\*").unwrap());
 pub static RE_SELECT_FROM: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)SELECT (.*) FROM").unwrap());
@@ -90,6 +93,7 @@ pub struct Sql {
     pub use_inverted_index: bool, // if can use inverted index
     pub index_condition: Option<IndexCondition>, // use for tantivy index
     pub index_optimize_mode: Option<InvertedIndexOptimizeMode>,
+    pub derived_fields: HashMap<TableReference, Vec<DerivedField>>, // computed at query time
 }
 
 impl Sql {
@@ -103,7 +107,7 @@ impl Sql {
         stream_type: StreamType,
     ) -> Result<Sql, Error> {
         let cfg = get_config();
-        let sql = query.sql.clone();
+        let sql = resolve_stream_aliases(org_id, stream_type, &query.sql)?;
         let limit = query.size as i64;
         let offset = query.from as i64;
 
@@ -116,12 +120,18 @@ impl Sql {
             ));
         }
         let mut total_schemas = HashMap::with_capacity(stream_names.len());
+        let mut derived_fields = HashMap::new();
         for stream in stream_names.iter() {
             let stream_name = stream.stream_name();
             let stream_type = stream.get_stream_type(stream_type);
             let schema = infra::schema::get(org_id, &stream_name, stream_type)
                 .await
                 .unwrap_or_else(|_| Schema::empty());
+            if let Some(settings) = unwrap_stream_settings(&schema) {
+                if !settings.derived_fields.is_empty() {
+                    derived_fields.insert(stream.clone(), settings.derived_fields);
+                }
+            }
             total_schemas.insert(stream.clone(), Arc::new(SchemaCache::new(schema)));
         }
 
@@ -140,7 +150,19 @@ impl Sql {
         let mut column_visitor = ColumnVisitor::new(&total_schemas);
         statement.visit(&mut column_visitor);
 
-        let columns = column_visitor.columns.clone();
+        let mut columns = column_visitor.columns.clone();
+        // read the columns used by the derived fields as well
+        for (stream, fields) in derived_fields.iter() {
+            let stream_columns = if column_visitor.is_wildcard {
+                columns.entry(stream.clone()).or_default()
+            } else {
+                match columns.get_mut(stream) {
+                    Some(stream_columns) => stream_columns,
+                    None => continue,
+                }
+            };
+            stream_columns.extend(fields.iter().flat_map(|f| f.columns()));
+        }
         let aliases = column_visitor
             .columns_alias
             .iter()
@@ -294,6 +316,7 @@ impl Sql {
             use_inverted_index,
             index_condition,
             index_optimize_mode,
+            derived_fields,
         })
     }
 }
@@ -1533,6 +1556,279 @@ pub fn convert_histogram_interval_to_seconds(interval: &str) -> Result<i64, Erro
     seconds.map_err(|_| Error::Message("Invalid number format".to_string()))
 }
 
+/// Rewrites the sql of one stream to query the `streams` together, the stream
+/// in the sql is replaced by the `UNION ALL` of the streams and the columns
+/// missing in a stream are filled with `NULL`.
+pub fn generate_federated_sql(sql: &str, schemas: &[(String, Schema)]) -> Result<String, Error> {
+    let not_supported =
+        || Error::Message("Query multiple streams only supports the sql of one stream".to_string());
+    let mut statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .pop()
+        .ok_or_else(not_supported)?;
+    let Statement::Query(query) = &mut statement else {
+        return Err(not_supported());
+    };
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return Err(not_supported());
+    };
+    if select.from.len() != 1 || !select.from[0].joins.is_empty() {
+        return Err(not_supported());
+    }
+    let TableFactor::Table { name, alias, .. } = &select.from[0].relation else {
+        return Err(not_supported());
+    };
+    // keep the table name, so the columns qualified by it still work
+    let alias = match alias {
+        Some(alias) => alias.name.value.clone(),
+        None => name.0.last().map(|v| v.value.clone()).unwrap_or_default(),
+    };
+
+    let columns = schemas
+        .iter()
+        .flat_map(|(_, schema)| schema.fields().iter().map(|f| f.name().as_str()))
+        .collect::<std::collections::BTreeSet<_>>();
+    // quote the identifiers with `Ident`, which doubles the `"` in the names
+    let quoted = |name: &str| Ident::with_quote('"', name).to_string();
+    let union_sql = schemas
+        .iter()
+        .map(|(stream_name, schema)| {
+            let fields = columns
+                .iter()
+                .map(|c| {
+                    if schema.field_with_name(c).is_ok() {
+                        quoted(c)
+                    } else {
+                        format!("NULL AS {}", quoted(c))
+                    }
+                })
+                .join(", ");
+            format!("SELECT {fields} FROM {}", quoted(stream_name))
+        })
+        .join(" UNION ALL ");
+    let derived_sql = format!("SELECT * FROM ({union_sql}) AS {}", quoted(&alias));
+    let Some(Statement::Query(mut derived)) =
+        Parser::parse_sql(&PostgreSqlDialect {}, &derived_sql)
+            .map_err(|e| Error::Message(e.to_string()))?
+            .pop()
+    else {
+        return Err(not_supported());
+    };
+    let SetExpr::Select(derived) = derived.body.as_mut() else {
+        return Err(not_supported());
+    };
+    select.from[0].relation = derived.from.remove(0).relation;
+    Ok(statement.to_string())
+}
+
+/// Rewrites the sql of a search paginated by a cursor. The sql must be ordered
+/// by `_timestamp DESC`, or have no `ORDER BY` at all. `_o2_id DESC` is added
+/// to the order to break the ties of `_timestamp`, and if there is a cursor the
+/// records up to it are filtered out by `(_timestamp, _o2_id) < (timestamp,
+/// id)`.
+///
+/// Returns `None` if the sql is not ordered by `_timestamp DESC`.
+pub fn generate_cursor_sql(sql: &str, cursor: Option<&Cursor>) -> Result<Option<String>, Error> {
+    let column_timestamp = &get_config().common.column_timestamp;
+    let mut statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .pop()
+        .ok_or_else(|| Error::Message("Invalid sql".to_string()))?;
+    let Statement::Query(query) = &mut statement else {
+        return Ok(None);
+    };
+    let is_column_desc = |order: &OrderByExpr, name: &str| {
+        let column = match &order.expr {
+            Expr::Identifier(ident) => ident,
+            Expr::CompoundIdentifier(idents) => match idents.last() {
+                Some(ident) => ident,
+                None => return false,
+            },
+            _ => return false,
+        };
+        column.value == name && order.asc == Some(false)
+    };
+    let order_by = query
+        .order_by
+        .get_or_insert_with(|| sqlparser::ast::OrderBy {
+            exprs: vec![],
+            interpolate: None,
+        });
+    if order_by.exprs.is_empty() {
+        order_by
+            .exprs
+            .push(order_by_desc(Ident::new(column_timestamp.as_str())));
+    }
+    match order_by.exprs.as_slice() {
+        [ts] if is_column_desc(ts, column_timestamp) => {
+            order_by.exprs.push(order_by_desc(Ident::new(ID_COL_NAME)));
+        }
+        [ts, id] if is_column_desc(ts, column_timestamp) && is_column_desc(id, ID_COL_NAME) => {}
+        _ => return Ok(None),
+    }
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return Ok(None);
+    };
+    // the aggregates return one row per group, there is nothing to page
+    let has_aggregate = select.projection.iter().any(|item| {
+        matches!(item, SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. }
+            if is_aggregate_expression(expr))
+    });
+    if !matches!(&select.group_by, GroupByExpr::Expressions(v, _) if v.is_empty())
+        || select.distinct.is_some()
+        || has_aggregate
+    {
+        return Ok(None);
+    }
+
+    if let Some(cursor) = cursor {
+        let ts_col = Ident::with_quote('"', column_timestamp.as_str());
+        let id_col = Ident::with_quote('"', ID_COL_NAME);
+        let id = Value::SingleQuotedString(cursor.id.clone());
+        let ts = cursor.timestamp;
+        let predicate = Parser::new(&PostgreSqlDialect {})
+            .try_with_sql(&format!(
+                "{ts_col} < {ts} OR ({ts_col} = {ts} AND {id_col} < {id})"
+            ))
+            .and_then(|mut p| p.parse_expr())
+            .map_err(|e| Error::Message(e.to_string()))?;
+        let predicate = Expr::Nested(Box::new(predicate));
+        select.selection = Some(match select.selection.take() {
+            Some(selection) => Expr::BinaryOp {
+                left: Box::new(predicate),
+                op: BinaryOperator::And,
+                right: Box::new(Expr::Nested(Box::new(selection))),
+            },
+            None => predicate,
+        });
+    }
+    Ok(Some(statement.to_string()))
+}
+
+fn order_by_desc(column: Ident) -> OrderByExpr {
+    OrderByExpr {
+        expr: Expr::Identifier(column),
+        asc: Some(false),
+        nulls_first: None,
+        with_fill: None,
+    }
+}
+
+/// Returns true if the sql is `SHOW STREAMS`, which lists the streams instead
+/// of querying one.
+pub fn is_show_streams(sql: &str) -> bool {
+    let words = sql
+        .trim()
+        .trim_end_matches(';')
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    matches!(words.as_slice(), [show, streams]
+        if show.eq_ignore_ascii_case("show") && streams.eq_ignore_ascii_case("streams"))
+}
+
+/// Returns the stream name if the sql is `DESCRIBE STREAM <name>`, which
+/// returns the schema of the stream instead of querying it.
+pub fn get_describe_stream(sql: &str) -> Option<String> {
+    let words = sql
+        .trim()
+        .trim_end_matches(';')
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    match words.as_slice() {
+        [describe, stream, name]
+            if describe.eq_ignore_ascii_case("describe")
+                && stream.eq_ignore_ascii_case("stream") =>
+        {
+            Some(name.trim_matches('"').to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Adds `match_all('*keyword*')` to the `field LIKE '%keyword%'` filters on the
+/// full text search fields, so the files are picked up by the inverted index
+/// before they are scanned. The `LIKE` filter is kept to check the rows.
+///
+/// Only the lowercase ascii alphanumeric keywords in the top level `AND` of the
+/// where clause are rewritten, such a keyword can't cross the boundaries of the
+/// indexed tokens. The rewrite is lossy for the tokens longer than 40 chars,
+/// which are not indexed, see `search::Request::use_fts`.
+pub fn generate_fts_sql(sql: &str, fts_fields: &[String]) -> Result<String, Error> {
+    let mut statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .pop()
+        .ok_or_else(|| Error::Message("Invalid sql".to_string()))?;
+    let Statement::Query(query) = &mut statement else {
+        return Ok(sql.to_string());
+    };
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return Ok(sql.to_string());
+    };
+    let Some(selection) = select.selection.as_mut() else {
+        return Ok(sql.to_string());
+    };
+    if !add_fts_match_all(selection, fts_fields) {
+        return Ok(sql.to_string());
+    }
+    Ok(statement.to_string())
+}
+
+fn add_fts_match_all(expr: &mut Expr, fts_fields: &[String]) -> bool {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let left = add_fts_match_all(left, fts_fields);
+            let right = add_fts_match_all(right, fts_fields);
+            left || right
+        }
+        Expr::Nested(expr) => add_fts_match_all(expr, fts_fields),
+        Expr::Like {
+            negated: false,
+            expr: field,
+            pattern,
+            escape_char: None,
+        } => {
+            let Expr::Identifier(field) = field.as_ref() else {
+                return false;
+            };
+            let Expr::Value(Value::SingleQuotedString(pattern)) = pattern.as_ref() else {
+                return false;
+            };
+            if !fts_fields.contains(&field.value) {
+                return false;
+            }
+            let Some(keyword) = pattern
+                .strip_prefix('%')
+                .and_then(|p| p.strip_suffix('%'))
+                .filter(|k| {
+                    !k.is_empty()
+                        && k.chars()
+                            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                })
+            else {
+                return false;
+            };
+            let Ok(match_all) = Parser::new(&PostgreSqlDialect {})
+                .try_with_sql(&format!("{MATCH_ALL_UDF_NAME}('*{keyword}*')"))
+                .and_then(|mut p| p.parse_expr())
+            else {
+                return false;
+            };
+            let like = std::mem::replace(expr, Expr::Value(Value::Null));
+            *expr = Expr::Nested(Box::new(Expr::BinaryOp {
+                left: Box::new(match_all),
+                op: BinaryOperator::And,
+                right: Box::new(like),
+            }));
+            true
+        }
+        _ => false,
+    }
+}
+
 pub fn pickup_where(sql: &str, meta: Option<MetaSql>) -> Result<Option<String>, Error> {
     let meta = match meta {
         Some(v) => v,
@@ -1563,6 +1859,57 @@ pub fn pickup_where(sql: &str, meta: Option<MetaSql>) -> Result<Option<String>,
     Ok(Some(where_str))
 }
 
+/// Replaces the stream aliases in the sql with the streams they point to.
+pub fn resolve_stream_aliases(
+    org_id: &str,
+    stream_type: StreamType,
+    sql: &str,
+) -> Result<String, Error> {
+    if STREAM_ALIASES.is_empty() {
+        return Ok(sql.to_string());
+    }
+    let mut statement = Parser::parse_sql(&PostgreSqlDialect {}, sql)
+        .map_err(|e| Error::Message(e.to_string()))?
+        .pop()
+        .unwrap();
+    // the names of common table expressions are not streams
+    let cte_names = match &statement {
+        Statement::Query(query) => query
+            .with
+            .as_ref()
+            .map(|with| {
+                with.cte_tables
+                    .iter()
+                    .map(|cte| cte.alias.name.value.clone())
+                    .collect::<HashSet<_>>()
+            })
+            .unwrap_or_default(),
+        _ => HashSet::new(),
+    };
+    let mut changed = false;
+    let _ = visit_relations_mut(&mut statement, |table| {
+        let stream_type = match table.0.as_slice() {
+            [schema, _] => StreamType::from(schema.value.as_str()),
+            _ => stream_type,
+        };
+        if let Some(name) = table.0.last_mut() {
+            if !cte_names.contains(&name.value) {
+                let stream_name = stream::resolve_alias(org_id, stream_type, &name.value);
+                if stream_name != name.value {
+                    name.value = stream_name;
+                    changed = true;
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(if changed {
+        statement.to_string()
+    } else {
+        sql.to_string()
+    })
+}
+
 fn o2_id_is_needed(schemas: &HashMap<TableReference, Arc<SchemaCache>>) -> bool {
     schemas.values().any(|schema| {
         let stream_setting = unwrap_stream_settings(schema.schema());
@@ -1577,6 +1924,32 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_resolve_stream_aliases() {
+        STREAM_ALIASES.insert("sql_org/logs/logs".to_string(), "k8s_logs".to_string());
+        let sql = "SELECT * FROM logs WHERE a = 1";
+        assert_eq!(
+            resolve_stream_aliases("sql_org", StreamType::Logs, sql).unwrap(),
+            "SELECT * FROM k8s_logs WHERE a = 1"
+        );
+        let sql = r#"SELECT * FROM "logs"."logs" JOIN "traces"."logs" ON 1 = 1"#;
+        assert_eq!(
+            resolve_stream_aliases("sql_org", StreamType::Metrics, sql).unwrap(),
+            r#"SELECT * FROM "logs"."k8s_logs" JOIN "traces"."logs" ON 1 = 1"#
+        );
+        // a common table expression shadows the alias
+        let sql = "WITH logs AS (SELECT * FROM logs) SELECT * FROM logs";
+        assert_eq!(
+            resolve_stream_aliases("sql_org", StreamType::Logs, sql).unwrap(),
+            sql
+        );
+        let sql = "SELECT * FROM logs";
+        assert_eq!(
+            resolve_stream_aliases("other_org", StreamType::Logs, sql).unwrap(),
+            sql
+        );
+    }
+
     #[test]
     fn test_index_visitor1() {
         let sql = "SELECT * FROM t WHERE name = 'a' AND age = 1 AND (name = 'b' OR (match_all('good') AND match_all('bar'))) AND (match_all('foo') OR age = 2)";
@@ -1817,4 +2190,125 @@ mod tests {
             .unwrap();
         assert_eq!(is_simple_count_query(&mut statement), false);
     }
+
+    #[test]
+    fn test_is_show_streams() {
+        assert!(is_show_streams("SHOW STREAMS"));
+        assert!(is_show_streams(" show  streams;\n"));
+        assert!(!is_show_streams("SHOW TABLES"));
+        assert!(!is_show_streams("SELECT * FROM streams"));
+    }
+
+    #[test]
+    fn test_get_describe_stream() {
+        assert_eq!(
+            get_describe_stream("DESCRIBE STREAM k8s_logs"),
+            Some("k8s_logs".to_string())
+        );
+        assert_eq!(
+            get_describe_stream("describe stream \"my-logs\";"),
+            Some("my-logs".to_string())
+        );
+        assert_eq!(get_describe_stream("DESCRIBE k8s_logs"), None);
+        assert_eq!(get_describe_stream("SELECT * FROM k8s_logs"), None);
+    }
+
+    #[test]
+    fn test_generate_fts_sql() {
+        let fts_fields = vec!["log".to_string(), "message".to_string()];
+        let sql = "SELECT * FROM logs WHERE log LIKE '%timeout%' AND code = 500";
+        assert_eq!(
+            generate_fts_sql(sql, &fts_fields).unwrap(),
+            "SELECT * FROM logs WHERE (match_all('*timeout*') AND log LIKE '%timeout%') AND code = 500"
+        );
+        // not a full text search field, uppercase or several tokens, or under OR
+        for sql in [
+            "SELECT * FROM logs WHERE host LIKE '%timeout%'",
+            "SELECT * FROM logs WHERE log LIKE '%Timeout%'",
+            "SELECT * FROM logs WHERE log LIKE '%read timeout%'",
+            "SELECT * FROM logs WHERE log LIKE 'timeout%'",
+            "SELECT * FROM logs WHERE log NOT LIKE '%timeout%'",
+            "SELECT * FROM logs WHERE log LIKE '%timeout%' OR code = 500",
+        ] {
+            assert_eq!(generate_fts_sql(sql, &fts_fields).unwrap(), sql);
+        }
+    }
+
+    #[test]
+    fn test_generate_cursor_sql() {
+        let cursor = Cursor {
+            timestamp: 1700000000000000,
+            id: "711'3".to_string(),
+        };
+        let sql = "SELECT * FROM logs WHERE code = 500 OR code = 503 ORDER BY _timestamp DESC";
+        assert_eq!(
+            generate_cursor_sql(sql, Some(&cursor)).unwrap().unwrap(),
+            "SELECT * FROM logs WHERE (\"_timestamp\" < 1700000000000000 OR (\"_timestamp\" = 1700000000000000 AND \"_o2_id\" < '711''3')) AND (code = 500 OR code = 503) ORDER BY _timestamp DESC, _o2_id DESC"
+        );
+        // the first page only gets the tie break
+        assert_eq!(
+            generate_cursor_sql("SELECT * FROM logs", None)
+                .unwrap()
+                .unwrap(),
+            "SELECT * FROM logs ORDER BY _timestamp DESC, _o2_id DESC"
+        );
+        for sql in [
+            "SELECT * FROM logs ORDER BY _timestamp",
+            "SELECT * FROM logs ORDER BY _timestamp ASC",
+            "SELECT * FROM logs ORDER BY code DESC",
+            "SELECT * FROM logs ORDER BY _timestamp DESC, code DESC",
+            "SELECT code, count(*) FROM logs GROUP BY code",
+            "SELECT count(*) FROM logs",
+            "SELECT avg(took) AS took FROM logs",
+        ] {
+            assert_eq!(
+                generate_cursor_sql(sql, Some(&cursor)).unwrap(),
+                None,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_federated_sql() {
+        use arrow_schema::{DataType, Field};
+
+        let schemas = vec![
+            (
+                "logs".to_string(),
+                Schema::new(vec![
+                    Field::new("_timestamp", DataType::Int64, false),
+                    Field::new("message", DataType::Utf8, true),
+                ]),
+            ),
+            (
+                "audit_logs".to_string(),
+                Schema::new(vec![
+                    Field::new("_timestamp", DataType::Int64, false),
+                    Field::new("user", DataType::Utf8, true),
+                ]),
+            ),
+        ];
+        let sql = "SELECT * FROM logs WHERE user = 'root' ORDER BY _timestamp DESC";
+        let sql = generate_federated_sql(sql, &schemas).unwrap();
+        assert!(sql.contains(
+            r#"SELECT "_timestamp", "message", NULL AS "user" FROM "logs" UNION ALL SELECT "_timestamp", NULL AS "message", "user" FROM "audit_logs""#
+        ));
+        assert!(sql.contains(r#"AS "logs" WHERE user = 'root'"#));
+        let stream_names = config::meta::sql::resolve_stream_names(&sql).unwrap();
+        assert!(stream_names.contains(&"logs".to_string()));
+        assert!(stream_names.contains(&"audit_logs".to_string()));
+
+        // joins are not supported
+        let sql = "SELECT * FROM logs JOIN users ON logs.user = users.name";
+        assert!(generate_federated_sql(sql, &schemas).is_err());
+
+        // quotes in the names are escaped instead of closing the identifier
+        let schemas = vec![(
+            r#"logs" UNION SELECT * FROM "secrets"#.to_string(),
+            Schema::new(vec![Field::new(r#"a"b"#, DataType::Utf8, true)]),
+        )];
+        let sql = generate_federated_sql("SELECT * FROM logs", &schemas).unwrap();
+        assert!(sql.contains(r#"SELECT "a""b" FROM "logs"" UNION SELECT * FROM ""secrets""#));
+    }
 }
@@ -20,6 +20,7 @@ use async_recursion::async_recursion;
 use config::{
     get_config,
     meta::{cluster::NodeInfo, search::ScanStats, sql::TableReferenceExt},
+    metrics,
     utils::json,
 };
 use datafusion::{
@@ -114,6 +115,7 @@ pub async fn search(
         stream_name = trace_stream_name,
     );
 
+    let org_id = sql.org_id.clone();
     let trace_id_move = trace_id.to_string();
     let query_task = DATAFUSION_RUNTIME.spawn(async move {
         run_datafusion(trace_id_move, req, sql, nodes)
@@ -135,7 +137,12 @@ pub async fn search(
         _ = tokio::time::sleep(tokio::time::Duration::from_secs(timeout)) => {
             query_task.abort();
             log::error!("[trace_id {trace_id}] super cluster leader: search timeout");
-            Err(DataFusionError::ResourcesExhausted("super cluster leader: search timeout".to_string()))
+            metrics::QUERY_TIMEOUT_NUMS
+                .with_label_values(&[&org_id])
+                .inc();
+            Ok(Err(Error::ErrorCode(ErrorCodes::SearchTimeout(format!(
+                "[trace_id {trace_id}] super cluster leader: search timeout after {timeout}s"
+            )))))
         },
         _ = abort_receiver => {
             query_task.abort();
@@ -0,0 +1,104 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use config::{cluster::LOCAL_NODE, get_config};
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+
+use crate::{
+    common::infra::{cluster, wal},
+    job::files::parquet,
+    service::self_reporting,
+};
+
+/// Set when the drain starts, the gRPC, Vector and Flight SQL servers stop
+/// on it. Each server holds a receiver until it stopped, so the drain knows
+/// when their in-flight requests are done.
+static LISTENERS: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+/// Registers a server which accepts requests besides the http server, the
+/// server must hold the receiver until it stopped.
+pub fn listener() -> watch::Receiver<bool> {
+    LISTENERS.subscribe()
+}
+
+/// Resolves when the drain starts, for `serve_with_shutdown`.
+pub async fn listener_stopped(mut listener: watch::Receiver<bool>) {
+    listener.wait_for(|stop| *stop).await.ok();
+}
+
+/// Stops the registered servers and waits until they stopped.
+async fn stop_listeners() {
+    LISTENERS.send_replace(true);
+    LISTENERS.closed().await;
+}
+
+/// Drains the node before it stops, so no data is left in the wal of an
+/// ingester which may not come back:
+///
+/// 1. mark the node offline, so no new requests are routed to it
+/// 2. stop the http, gRPC, Vector and Flight SQL servers, waiting for the in-flight requests, so
+///    nothing is written to the memtables anymore
+/// 3. flush the memtables and move the wal parquet files to storage
+/// 4. leave the cluster
+///
+/// Steps 1-3 are bounded by `ZO_SHUTDOWN_DRAIN_TIMEOUT`, the node always
+/// leaves the cluster even if the drain times out.
+pub async fn drain(server: ServerHandle) {
+    let timeout = get_config().limit.shutdown_drain_timeout;
+    if tokio::time::timeout(Duration::from_secs(timeout), drain_inner(server))
+        .await
+        .is_err()
+    {
+        log::error!(
+            "[SHUTDOWN] drain timed out after {} seconds, wal files left on the local disk will be moved to storage when the node restarts",
+            timeout
+        );
+    }
+
+    if let Err(e) = cluster::leave().await {
+        log::error!("[SHUTDOWN] leave cluster failed: {}", e);
+    }
+    log::info!("[SHUTDOWN] Node left cluster");
+}
+
+async fn drain_inner(server: ServerHandle) {
+    if let Err(e) = cluster::set_offline(true).await {
+        log::error!("[SHUTDOWN] set offline failed: {}", e);
+    }
+    log::info!("[SHUTDOWN] Node is offline");
+
+    server.stop(true).await;
+    stop_listeners().await;
+    log::info!("[SHUTDOWN] In-flight requests are done");
+
+    // usage reports are ingested into the local node, flush them before the wal
+    self_reporting::flush().await;
+
+    if LOCAL_NODE.is_ingester() {
+        wal::flush_all_to_disk().await;
+        // move whatever was persisted even if some memtables failed
+        if let Err(e) = ingester::flush_all_to_parquet().await {
+            log::error!("[SHUTDOWN] flush memtables failed: {}", e);
+        }
+        if let Err(e) = parquet::move_all_files_to_storage().await {
+            log::error!("[SHUTDOWN] move wal files to storage failed: {}", e);
+        }
+        log::info!("[SHUTDOWN] Wal files are moved to storage");
+    }
+}
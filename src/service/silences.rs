@@ -0,0 +1,201 @@
+// Copyright 2024 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use config::{ider, utils::time::now_micros};
+use regex::Regex;
+
+use crate::{
+    common::{
+        infra::config::SILENCES,
+        meta::silence::{Silence, SilenceMatcher},
+    },
+    service::db,
+};
+
+pub async fn create(
+    org_id: &str,
+    mut silence: Silence,
+    user_email: &str,
+) -> Result<Silence, anyhow::Error> {
+    validate(&silence)?;
+    silence.id = ider::uuid();
+    silence.created_by = user_email.to_string();
+    db::silences::set(org_id, &silence).await?;
+    Ok(silence)
+}
+
+fn validate(silence: &Silence) -> Result<(), anyhow::Error> {
+    if silence.matchers.is_empty() {
+        return Err(anyhow::anyhow!("silence must have at least one matcher"));
+    }
+    for matcher in silence.matchers.iter() {
+        if matcher.name.is_empty() {
+            return Err(anyhow::anyhow!("silence matcher name is empty"));
+        }
+        if matcher.is_regex {
+            matcher_regex(&matcher.value)?;
+        }
+    }
+    if silence.ends_at <= silence.starts_at {
+        return Err(anyhow::anyhow!("silence must end after it starts"));
+    }
+    if silence.ends_at <= now_micros() {
+        return Err(anyhow::anyhow!("silence has already ended"));
+    }
+    Ok(())
+}
+
+pub fn get(org_id: &str, id: &str) -> Option<Silence> {
+    SILENCES
+        .get(&format!("{org_id}/{id}"))
+        .map(|v| v.value().clone())
+}
+
+pub fn list(org_id: &str) -> Vec<Silence> {
+    db::silences::list(org_id)
+}
+
+pub async fn delete(org_id: &str, id: &str) -> Result<(), anyhow::Error> {
+    db::silences::delete(org_id, id).await
+}
+
+/// Whether an alert with the labels is silenced at `ts` by any silence of
+/// the organization
+pub fn is_silenced(org_id: &str, labels: &BTreeMap<String, String>, ts: i64) -> bool {
+    let prefix = format!("{org_id}/");
+    SILENCES.iter().any(|v| {
+        v.key().starts_with(&prefix) && is_active(v.value(), ts) && matches(v.value(), labels)
+    })
+}
+
+fn is_active(silence: &Silence, ts: i64) -> bool {
+    silence.starts_at <= ts && ts < silence.ends_at
+}
+
+/// All the matchers must match, a missing label matches an empty value
+fn matches(silence: &Silence, labels: &BTreeMap<String, String>) -> bool {
+    silence.matchers.iter().all(|matcher| {
+        let value = labels.get(&matcher.name).map(|v| v.as_str()).unwrap_or("");
+        match_value(matcher, value)
+    })
+}
+
+fn match_value(matcher: &SilenceMatcher, value: &str) -> bool {
+    if !matcher.is_regex {
+        return matcher.value == value;
+    }
+    matcher_regex(&matcher.value).is_ok_and(|re| re.is_match(value))
+}
+
+/// The regular expressions of matchers are anchored, as in PromQL
+fn matcher_regex(value: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{value})$"))
+}
+
+/// Deletes the silences which have ended
+pub async fn cleanup() -> Result<(), anyhow::Error> {
+    let now = now_micros();
+    let expired = SILENCES
+        .iter()
+        .filter(|v| v.value().ends_at <= now)
+        .map(|v| v.key().clone())
+        .collect::<Vec<_>>();
+    for key in expired {
+        let Some((org_id, id)) = key.split_once('/') else {
+            continue;
+        };
+        db::silences::delete(org_id, id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(name: &str, value: &str, is_regex: bool) -> SilenceMatcher {
+        SilenceMatcher {
+            name: name.to_string(),
+            value: value.to_string(),
+            is_regex,
+        }
+    }
+
+    fn silence(matchers: Vec<SilenceMatcher>) -> Silence {
+        Silence {
+            id: "".to_string(),
+            matchers,
+            starts_at: 10,
+            ends_at: 20,
+            comment: "deploy".to_string(),
+            created_by: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let labels = [
+            ("alertname".to_string(), "HighLatency".to_string()),
+            ("job".to_string(), "api-1".to_string()),
+        ]
+        .into();
+        assert!(matches(
+            &silence(vec![matcher("alertname", "HighLatency", false)]),
+            &labels
+        ));
+        assert!(matches(
+            &silence(vec![
+                matcher("alertname", "HighLatency", false),
+                matcher("job", "api-.*", true),
+            ]),
+            &labels
+        ));
+        // regexes are anchored
+        assert!(!matches(
+            &silence(vec![matcher("job", "api", true)]),
+            &labels
+        ));
+        assert!(!matches(
+            &silence(vec![matcher("job", "db", false)]),
+            &labels
+        ));
+        // a missing label is empty
+        assert!(matches(&silence(vec![matcher("env", "", false)]), &labels));
+    }
+
+    #[test]
+    fn test_is_active() {
+        let s = silence(vec![matcher("job", "api", false)]);
+        assert!(!is_active(&s, 9));
+        assert!(is_active(&s, 10));
+        assert!(!is_active(&s, 20));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut s = silence(vec![matcher("job", "api", false)]);
+        s.ends_at = now_micros() + 60_000_000;
+        assert!(validate(&s).is_ok());
+        s.matchers = vec![matcher("job", "(", true)];
+        assert!(validate(&s).is_err());
+        s.matchers = vec![];
+        assert!(validate(&s).is_err());
+        let s = silence(vec![matcher("job", "api", false)]);
+        // already ended
+        assert!(validate(&s).is_err());
+    }
+}
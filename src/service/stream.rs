@@ -20,7 +20,7 @@ use arrow_schema::DataType;
 use config::{
     is_local_disk_storage,
     meta::{
-        promql,
+        promql, search,
         stream::{
             DistinctField, StreamParams, StreamSettings, StreamStats, StreamType,
             UpdateStreamSettings,
@@ -30,7 +30,7 @@ use config::{
     SIZE_IN_MB, SQL_FULL_TEXT_SEARCH_FIELDS,
 };
 use datafusion::arrow::datatypes::Schema;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use infra::{
     cache::stats,
     schema::{
@@ -41,16 +41,21 @@ use infra::{
 };
 
 use crate::{
-    common::meta::{
-        authz::Authz,
-        http::HttpResponse as MetaHttpResponse,
-        stream::{Stream, StreamProperty},
+    common::{
+        infra::config::STREAM_ALIASES,
+        meta::{
+            authz::Authz,
+            http::HttpResponse as MetaHttpResponse,
+            stream::{Stream, StreamFileStats, StreamProperty},
+        },
     },
     service::{db, db::distinct_values, metrics::get_prom_metadata_from_schema},
 };
 
 const LOCAL: &str = "disk";
 const S3: &str = "s3";
+// aliases are checked for cycles when created, this only guards the lookup
+const MAX_ALIAS_DEPTH: usize = 16;
 
 pub async fn get_stream(
     org_id: &str,
@@ -135,6 +140,76 @@ pub async fn get_streams(
     indices_res
 }
 
+/// Returns the streams as the hits of `SHOW STREAMS`, one hit per stream with
+/// the columns `org, stream_type, stream_name, schema_version, record_count`.
+pub async fn show_streams(org_id: &str, streams: &[Stream]) -> search::Response {
+    let mut res = search::Response::new(0, streams.len() as i64);
+    res.columns = [
+        "org",
+        "stream_type",
+        "stream_name",
+        "schema_version",
+        "record_count",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    for stream in streams {
+        let schema_version =
+            infra::schema::get_versions(org_id, &stream.name, stream.stream_type, None)
+                .await
+                .map(|versions| versions.len())
+                .unwrap_or_default();
+        res.add_hit(&json::json!({
+            "org": org_id,
+            "stream_type": stream.stream_type.to_string(),
+            "stream_name": stream.name,
+            "schema_version": schema_version,
+            "record_count": stream.stats.doc_num,
+        }));
+    }
+    res
+}
+
+/// Returns the schema of the stream as the hits of `DESCRIBE STREAM`, one hit
+/// per column with `column_name, data_type, nullable, is_partition_key`, or
+/// None if the stream doesn't exist.
+pub async fn describe_stream(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Option<search::Response> {
+    let schema = infra::schema::get(org_id, stream_name, stream_type)
+        .await
+        .ok()?;
+    if schema.fields().is_empty() {
+        return None;
+    }
+    let partition_keys = unwrap_stream_settings(&schema)
+        .map(|s| {
+            s.partition_keys
+                .into_iter()
+                .filter(|p| !p.disabled)
+                .map(|p| p.field)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+    let mut res = search::Response::new(0, schema.fields().len() as i64);
+    res.columns = ["column_name", "data_type", "nullable", "is_partition_key"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    for field in schema.fields() {
+        res.add_hit(&json::json!({
+            "column_name": field.name(),
+            "data_type": field.data_type().to_string(),
+            "nullable": field.is_nullable(),
+            "is_partition_key": partition_keys.contains(field.name()),
+        }));
+    }
+    Some(res)
+}
+
 pub fn stream_res(
     stream_name: &str,
     stream_type: StreamType,
@@ -237,6 +312,7 @@ pub async fn save_stream_settings(
         }
     }
 
+    settings.apply_custom_partition_key();
     for key in settings.partition_keys.iter() {
         if SQL_FULL_TEXT_SEARCH_FIELDS.contains(&key.field) || key.field == cfg.common.column_all {
             return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
@@ -280,11 +356,48 @@ pub async fn save_stream_settings(
         }
     }
 
+    // the ingest transform is compiled for every write, reject invalid programs here
+    if let Some(func) = settings.ingest_transform.as_ref() {
+        if let Err(e) = crate::service::ingestion::compile_vrl_function(func, org_id) {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                format!("invalid ingest transform: {e}"),
+            )));
+        }
+    }
+
+    if let Some(script) = settings.ingest_lua_transform.as_ref() {
+        if let Err(e) = crate::service::ingestion::lua::LuaTransform::new(script) {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                format!("invalid ingest lua transform: {e}"),
+            )));
+        }
+    }
+
+    // derived fields are computed at query time and can't shadow stored fields
+    let mut derived_names = HashSet::with_capacity(settings.derived_fields.len());
+    for field in settings.derived_fields.iter() {
+        if let Err(e) = field.validate() {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                e,
+            )));
+        }
+        if schema_fields.contains_key(&field.name) || !derived_names.insert(&field.name) {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                format!("derived field [{}] already exists", field.name),
+            )));
+        }
+    }
+
     // we need to keep the old partition information, because the hash bucket num can't be changed
     // get old settings and then update partition_keys
-    let mut old_partition_keys = unwrap_stream_settings(&schema)
-        .unwrap_or_default()
-        .partition_keys;
+    let old_settings = unwrap_stream_settings(&schema).unwrap_or_default();
+    // only the rollup job marks rollup streams
+    settings.rollup_stream = old_settings.rollup_stream;
+    let mut old_partition_keys = old_settings.partition_keys;
     // first disable all old partition keys
     for v in old_partition_keys.iter_mut() {
         v.disabled = true;
@@ -521,6 +634,7 @@ pub async fn update_stream_settings(
                     .partition_keys
                     .extend(new_settings.partition_keys.add);
             }
+            settings.custom_partition_key = new_settings.custom_partition_key;
 
             if !new_settings.partition_keys.remove.is_empty() {
                 settings
@@ -531,6 +645,31 @@ pub async fn update_stream_settings(
             if let Some(partition_time_level) = new_settings.partition_time_level {
                 settings.partition_time_level = Some(partition_time_level);
             }
+
+            if let Some(func) = new_settings.ingest_transform {
+                settings.ingest_transform = (!func.trim().is_empty()).then_some(func);
+            }
+            if let Some(script) = new_settings.ingest_lua_transform {
+                settings.ingest_lua_transform = (!script.trim().is_empty()).then_some(script);
+            }
+            if let Some(field) = new_settings.geoip_field {
+                settings.geoip_field = (!field.trim().is_empty()).then_some(field);
+            }
+
+            // a derived field is replaced when added again with the same name
+            for field in new_settings.derived_fields.add {
+                settings.derived_fields.retain(|f| f.name != field.name);
+                settings.derived_fields.push(field);
+            }
+            if !new_settings.derived_fields.remove.is_empty() {
+                settings.derived_fields.retain(|field| {
+                    !new_settings
+                        .derived_fields
+                        .remove
+                        .iter()
+                        .any(|f| f.name == field.name)
+                });
+            }
             save_stream_settings(org_id, stream_name, stream_type, settings).await
         }
         None => Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
@@ -675,6 +814,138 @@ pub async fn delete_fields(
     Ok(())
 }
 
+/// Returns the file statistics of a stream, they are kept up to date from the
+/// file list by the stats job.
+#[tracing::instrument]
+pub async fn get_stream_file_stats(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<HttpResponse, Error> {
+    if !stream_exists(org_id, stream_name, stream_type).await {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            "stream not found".to_string(),
+        )));
+    }
+    let stats = stats::get_stream_stats(org_id, stream_name, stream_type);
+    Ok(HttpResponse::Ok().json(StreamFileStats::from(&stats)))
+}
+
+#[tracing::instrument]
+pub async fn create_alias(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    alias: &str,
+) -> Result<HttpResponse, Error> {
+    let bad_request = |msg: String| {
+        Ok(HttpResponse::BadRequest()
+            .json(MetaHttpResponse::error(StatusCode::BAD_REQUEST.into(), msg)))
+    };
+    if alias.is_empty() {
+        return bad_request("alias can not be empty".to_string());
+    }
+    if stream_exists(org_id, alias, stream_type).await {
+        return bad_request(format!("stream [{alias}] already exists"));
+    }
+    if !stream_exists(org_id, stream_name, stream_type).await
+        && !STREAM_ALIASES.contains_key(&alias_key(org_id, stream_type, stream_name))
+    {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            "stream not found".to_string(),
+        )));
+    }
+    if has_alias_cycle(alias, stream_name, |name| {
+        STREAM_ALIASES
+            .get(&alias_key(org_id, stream_type, name))
+            .map(|v| v.value().clone())
+    }) {
+        return bad_request(format!(
+            "alias [{alias}] -> [{stream_name}] would create a cycle"
+        ));
+    }
+
+    if let Err(e) = db::stream_alias::set(org_id, stream_type, alias, stream_name).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR.into(),
+                e.to_string(),
+            )),
+        );
+    }
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        StatusCode::OK.into(),
+        "alias created".to_string(),
+    )))
+}
+
+#[tracing::instrument]
+pub async fn delete_alias(
+    org_id: &str,
+    stream_type: StreamType,
+    alias: &str,
+) -> Result<HttpResponse, Error> {
+    if !STREAM_ALIASES.contains_key(&alias_key(org_id, stream_type, alias)) {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            "alias not found".to_string(),
+        )));
+    }
+    if let Err(e) = db::stream_alias::delete(org_id, stream_type, alias).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR.into(),
+                e.to_string(),
+            )),
+        );
+    }
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        StatusCode::OK.into(),
+        "alias deleted".to_string(),
+    )))
+}
+
+/// Returns the stream the name points to if it is an alias, otherwise the name
+/// itself.
+pub fn resolve_alias(org_id: &str, stream_type: StreamType, name: &str) -> String {
+    let mut name = name.to_string();
+    for _ in 0..MAX_ALIAS_DEPTH {
+        match STREAM_ALIASES.get(&alias_key(org_id, stream_type, &name)) {
+            Some(target) => name = target.value().clone(),
+            None => break,
+        }
+    }
+    name
+}
+
+fn alias_key(org_id: &str, stream_type: StreamType, alias: &str) -> String {
+    format!("{org_id}/{stream_type}/{alias}")
+}
+
+async fn stream_exists(org_id: &str, stream_name: &str, stream_type: StreamType) -> bool {
+    infra::schema::get(org_id, stream_name, stream_type)
+        .await
+        .map(|schema| !schema.fields().is_empty())
+        .unwrap_or_default()
+}
+
+/// Checks if pointing `alias` at `target` would create a cycle, `lookup`
+/// returns the target of an existing alias. Every alias has a single target,
+/// so the depth first search only has one path to follow.
+fn has_alias_cycle(alias: &str, target: &str, lookup: impl Fn(&str) -> Option<String>) -> bool {
+    let mut visited = HashSet::new();
+    let mut next = Some(target.to_string());
+    while let Some(name) = next {
+        if name == alias || !visited.insert(name.clone()) {
+            return true;
+        }
+        next = lookup(&name);
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use datafusion::arrow::datatypes::{DataType, Field};
@@ -688,4 +959,41 @@ mod tests {
         let res = stream_res("Test", StreamType::Logs, schema, Some(stats.clone()));
         assert_eq!(res.stats, stats);
     }
+
+    #[test]
+    fn test_has_alias_cycle() {
+        let aliases = HashMap::from([
+            ("b".to_string(), "c".to_string()),
+            ("c".to_string(), "d".to_string()),
+        ]);
+        let lookup = |name: &str| aliases.get(name).cloned();
+        assert!(!has_alias_cycle("a", "b", lookup));
+        assert!(has_alias_cycle("a", "a", lookup));
+        // d -> b -> c -> d
+        assert!(has_alias_cycle("d", "b", lookup));
+        assert!(!has_alias_cycle("x", "d", lookup));
+    }
+
+    #[test]
+    fn test_resolve_alias() {
+        STREAM_ALIASES.insert(
+            alias_key("org", StreamType::Logs, "logs"),
+            "k8s_logs".to_string(),
+        );
+        STREAM_ALIASES.insert(
+            alias_key("org", StreamType::Logs, "old_logs"),
+            "logs".to_string(),
+        );
+        assert_eq!(resolve_alias("org", StreamType::Logs, "logs"), "k8s_logs");
+        assert_eq!(
+            resolve_alias("org", StreamType::Logs, "old_logs"),
+            "k8s_logs"
+        );
+        assert_eq!(
+            resolve_alias("org", StreamType::Logs, "k8s_logs"),
+            "k8s_logs"
+        );
+        assert_eq!(resolve_alias("org", StreamType::Traces, "logs"), "logs");
+        assert_eq!(resolve_alias("other", StreamType::Logs, "logs"), "logs");
+    }
 }
@@ -56,13 +56,14 @@ use crate::{
     service::{
         alerts::alert::AlertExt,
         db, format_stream_name,
-        ingestion::{evaluate_trigger, grpc::get_val, write_file, TriggerAlertData},
+        ingestion::{self, evaluate_trigger, grpc::get_val, write_file, TriggerAlertData},
         metadata::{
             distinct_values::DvItem, trace_list_index::TraceListItem, write, MetadataItem,
             MetadataType,
         },
         schema::{check_for_schema, stream_schema_exists},
         self_reporting::report_request_usage_stats,
+        stream,
     },
 };
 
@@ -190,6 +191,7 @@ pub async fn handle_otlp_request(
         Some(name) => format_stream_name(name),
         None => "default".to_owned(),
     };
+    let traces_stream_name = stream::resolve_alias(org_id, StreamType::Traces, &traces_stream_name);
     let min_ts = (Utc::now()
         - Duration::try_hours(cfg.limit.ingest_allowed_upto)
             .expect("configuration error: too large ingest_allowed_upto"))
@@ -505,6 +507,9 @@ pub async fn handle_otlp_request(
         }
     }
 
+    // reject the spans which conflict with the stream schema
+    reject_incompatible_spans(org_id, &mut partial_success, &mut json_data_by_stream).await;
+
     // if no data, fast return
     if json_data_by_stream.is_empty() {
         return format_response(partial_success, req_type);
@@ -579,6 +584,7 @@ pub async fn ingest_json(
             )),
         );
     }
+    let traces_stream_name = &stream::resolve_alias(org_id, StreamType::Traces, traces_stream_name);
 
     if !db::file_list::BLOCKED_ORGS.is_empty()
         && db::file_list::BLOCKED_ORGS.contains(&org_id.to_string())
@@ -661,6 +667,9 @@ pub async fn ingest_json(
         ts_data.push((timestamp, record_val));
     }
 
+    // reject the spans which conflict with the stream schema
+    reject_incompatible_spans(org_id, &mut partial_success, &mut json_data_by_stream).await;
+
     // if no data, fast return
     if json_data_by_stream.is_empty() {
         return format_response(partial_success, req_type);
@@ -750,6 +759,26 @@ fn format_response(
     }
 }
 
+/// Drops the spans of the streams whose schema conflicts with them, see
+/// [`ingestion::reject_incompatible_records`], and counts them as rejected.
+async fn reject_incompatible_spans(
+    org_id: &str,
+    partial_success: &mut ExportTracePartialSuccess,
+    json_data_by_stream: &mut HashMap<String, O2IngestJsonData>,
+) {
+    ingestion::reject_incompatible_records(
+        org_id,
+        StreamType::Traces,
+        json_data_by_stream,
+        |(json_data, _)| json_data.iter().map(|(_, v)| v).collect(),
+        |_, (json_data, _), e| {
+            partial_success.rejected_spans += json_data.len() as i64;
+            partial_success.error_message = e.to_string();
+        },
+    )
+    .await;
+}
+
 async fn write_traces_by_stream(
     org_id: &str,
     time_stats: (i64, &Instant),
@@ -360,6 +360,7 @@ pub async fn update_user(
                                             );
                                             update_user_role(&old_str, &new_str, email, org_id)
                                                 .await;
+                                            crate::handler::http::auth::validator::invalidate_permission_cache();
                                         }
                                     }
                                 }
@@ -19,7 +19,12 @@ mod tests {
     use std::{env, fs, net::SocketAddr, str, sync::Once, thread};
 
     use actix_web::{http::header::ContentType, test, web, App};
-    use arrow_flight::flight_service_server::FlightServiceServer;
+    use arrow::array::{Array, RecordBatch, StringArray};
+    use arrow_flight::{
+        flight_service_server::FlightServiceServer,
+        sql::{client::FlightSqlServiceClient, CommandGetTables},
+        FlightInfo,
+    };
     use bytes::{Bytes, BytesMut};
     use chrono::{Duration, Utc};
     use config::{
@@ -34,16 +39,24 @@ mod tests {
         },
         utils::json,
     };
+    use futures::TryStreamExt;
     use infra::scheduler::Trigger;
     use openobserve::{
         handler::{
-            grpc::{auth::check_auth, flight::FlightServiceImpl},
+            grpc::{auth::check_auth, flight::FlightServiceImpl, flight_sql::FlightSqlServiceImpl},
             http::router::*,
         },
         service::{alerts::scheduler::handle_triggers, search::SEARCH_SERVER},
     };
+    use opentelemetry_proto::tonic::{
+        collector::logs::v1::{ExportLogsServiceRequest, ExportLogsServiceResponse},
+        common::v1::{any_value::Value::StringValue, AnyValue, KeyValue},
+        logs::v1::{LogRecord, ResourceLogs, ScopeLogs},
+        resource::v1::Resource,
+    };
     use prost::Message;
     use proto::{cluster_rpc::search_server::SearchServer, prometheus_rpc};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tonic::codec::CompressionEncoding;
 
     static START: Once = Once::new();
@@ -60,6 +73,8 @@ mod tests {
             env::set_var("ZO_RESULT_CACHE_ENABLED", "false");
             env::set_var("ZO_PRINT_KEY_SQL", "true");
             env::set_var("ZO_SMTP_ENABLED", "true");
+            env::set_var("ZO_FLUENT_FORWARD_ENABLED", "true");
+            env::set_var("ZO_FLUENT_FORWARD_ORG", "e2e");
 
             env_logger::init_from_env(
                 env_logger::Env::new().default_filter_or(&get_config().log.level),
@@ -99,6 +114,18 @@ mod tests {
         Ok(())
     }
 
+    async fn init_flight_sql_server() -> Result<(), anyhow::Error> {
+        let addr: SocketAddr = format!("0.0.0.0:{}", get_config().grpc.flight_sql_port).parse()?;
+        log::info!("starting Flight SQL server at {}", addr);
+        tonic::transport::Server::builder()
+            .layer(tonic::service::interceptor(check_auth))
+            .add_service(FlightServiceServer::new(FlightSqlServiceImpl))
+            .serve(addr)
+            .await
+            .expect("Flight SQL server init failed");
+        Ok(())
+    }
+
     async fn e2e_100_tear_down() {
         log::info!("Tear Down Invoked");
         fs::remove_dir_all("./data").expect("Delete local dir failed");
@@ -118,6 +145,11 @@ mod tests {
                 .await
                 .expect("router gRPC server init failed");
         });
+        tokio::task::spawn(async move {
+            init_flight_sql_server()
+                .await
+                .expect("Flight SQL server init failed");
+        });
 
         // register node
         openobserve::common::infra::cluster::register_and_keep_alive()
@@ -142,8 +174,11 @@ mod tests {
         // ingest
         e2e_post_json().await;
         e2e_post_multi().await;
+        e2e_ingest_rate_limit().await;
         e2e_post_trace().await;
+        e2e_post_otlp_logs().await;
         e2e_post_metrics().await;
+        e2e_fluent_forward().await;
         // e2e_post_kinesis_data().await;
 
         // streams
@@ -151,6 +186,7 @@ mod tests {
         e2e_get_stream_schema().await;
         e2e_get_org_summary().await;
         e2e_post_stream_settings().await;
+        e2e_stream_alias().await;
         e2e_get_org().await;
 
         // functions
@@ -161,6 +197,7 @@ mod tests {
         // search
         e2e_search().await;
         e2e_search_around().await;
+        e2e_flight_sql().await;
 
         // users
         e2e_post_user().await;
@@ -337,6 +374,125 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    async fn e2e_ingest_rate_limit() {
+        let auth = setup();
+        let thread_id: usize = 0;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().limit(get_config().limit.req_json_limit))
+                .app_data(web::PayloadConfig::new(
+                    get_config().limit.req_payload_limit,
+                ))
+                .app_data(web::Data::new(thread_id))
+                .configure(get_service_routes)
+                .configure(get_basic_routes),
+        )
+        .await;
+        let set_limit = |records: u32| {
+            test::TestRequest::post()
+                .uri(&format!("/api/{}/settings", "e2e_rate_limit"))
+                .insert_header(ContentType::json())
+                .append_header(auth)
+                .set_payload(format!("{{\"ingestion_rate_limit_records\":{records}}}"))
+                .to_request()
+        };
+        let post_json = |records: usize| {
+            let body = vec!["{\"Year\": 1896, \"City\": \"Athens\"}"; records].join(",");
+            test::TestRequest::post()
+                .uri(&format!("/api/{}/{}/_json", "e2e_rate_limit", "olympics"))
+                .insert_header(ContentType::json())
+                .append_header(auth)
+                .set_payload(format!("[{body}]"))
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, set_limit(5)).await;
+        assert!(resp.status().is_success());
+
+        // below the limit
+        let resp = test::call_service(&app, post_json(2)).await;
+        assert_eq!(resp.status(), 200);
+
+        // above the remaining tokens
+        let resp = test::call_service(&app, post_json(4)).await;
+        assert_eq!(resp.status(), 429);
+        assert!(resp.headers().contains_key("Retry-After"));
+
+        // above the limit of one second, retrying can't help
+        let resp = test::call_service(&app, post_json(10)).await;
+        assert_eq!(resp.status(), 413);
+
+        // remove the limit
+        let resp = test::call_service(&app, set_limit(0)).await;
+        assert!(resp.status().is_success());
+        let resp = test::call_service(&app, post_json(10)).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    async fn e2e_fluent_forward() {
+        let auth = setup();
+        let now = Utc::now();
+        let entries = (0..3)
+            .map(|i| {
+                rmpv::Value::Array(vec![
+                    rmpv::Value::from(now.timestamp()),
+                    rmpv::Value::Map(vec![(
+                        rmpv::Value::from("log"),
+                        rmpv::Value::from(format!("line {i}")),
+                    )]),
+                ])
+            })
+            .collect();
+        let message = rmpv::Value::Array(vec![
+            rmpv::Value::from("fluent_forward"),
+            rmpv::Value::Array(entries),
+            rmpv::Value::Map(vec![(rmpv::Value::from("chunk"), rmpv::Value::from("e2e"))]),
+        ]);
+        let mut buf = Vec::new();
+        rmpv::encode::write_value(&mut buf, &message).unwrap();
+
+        // the chunk is acked once the records are written
+        let addr = format!("127.0.0.1:{}", get_config().tcp.fluent_forward_port);
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+        let mut ack = vec![0u8; 64];
+        let n = stream.read(&mut ack).await.unwrap();
+        let ack = rmpv::decode::read_value(&mut &ack[..n]).unwrap();
+        assert_eq!(ack["ack"].as_str(), Some("e2e"));
+
+        // the records are in the wal of the stream named by the tag
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().limit(get_config().limit.req_json_limit))
+                .app_data(web::PayloadConfig::new(
+                    get_config().limit.req_payload_limit,
+                ))
+                .configure(get_service_routes)
+                .configure(get_basic_routes),
+        )
+        .await;
+        let body = json::json!({
+            "query": {
+                "sql": "select count(*) as cnt from fluent_forward",
+                "from": 0,
+                "size": 10,
+                "start_time": (now - Duration::hours(1)).timestamp_micros(),
+                "end_time": (now + Duration::hours(1)).timestamp_micros(),
+            }
+        });
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/{}/_search", "e2e"))
+            .insert_header(ContentType::json())
+            .append_header(auth)
+            .set_payload(body.to_string())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body = test::read_body(resp).await;
+        let body = json::from_slice::<json::Value>(&body).unwrap();
+        assert_eq!(body["hits"][0]["cnt"], 3);
+    }
+
     async fn e2e_get_stream() {
         let auth = setup();
         let app = test::init_service(
@@ -413,6 +569,84 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    async fn e2e_stream_alias() {
+        let auth = setup();
+        let thread_id: usize = 0;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().limit(get_config().limit.req_json_limit))
+                .app_data(web::PayloadConfig::new(
+                    get_config().limit.req_payload_limit,
+                ))
+                .app_data(web::Data::new(thread_id))
+                .configure(get_service_routes)
+                .configure(get_basic_routes),
+        )
+        .await;
+        let create_alias = |stream: &str, alias: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/api/{}/streams/{}/alias", "e2e", stream))
+                .insert_header(ContentType::json())
+                .append_header(auth)
+                .set_payload(format!("{{\"alias\":\"{alias}\"}}"))
+                .to_request()
+        };
+
+        // creation
+        let resp =
+            test::call_service(&app, create_alias("olympics_schema", "olympics_alias")).await;
+        assert_eq!(resp.status(), 200);
+        let resp =
+            test::call_service(&app, create_alias("olympics_alias", "olympics_alias2")).await;
+        assert_eq!(resp.status(), 200);
+        // an alias can not shadow a stream
+        let resp =
+            test::call_service(&app, create_alias("olympics_alias", "olympics_schema")).await;
+        assert_eq!(resp.status(), 400);
+        let resp = test::call_service(&app, create_alias("no_such_stream", "alias_x")).await;
+        assert_eq!(resp.status(), 404);
+
+        // cycles: olympics_alias -> olympics_alias2 -> olympics_alias
+        let resp =
+            test::call_service(&app, create_alias("olympics_alias2", "olympics_alias")).await;
+        assert_eq!(resp.status(), 400);
+
+        // resolution in ingestion and search
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/{}/{}/_json", "e2e", "olympics_alias2"))
+            .insert_header(ContentType::json())
+            .append_header(auth)
+            .set_payload(r#"[{"Year": 1896, "City": "Athens"}]"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body = test::read_body(resp).await;
+        let body = json::from_slice::<json::Value>(&body).unwrap();
+        assert_eq!(body["status"][0]["name"], "olympics_schema");
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/{}/_search", "e2e"))
+            .insert_header(ContentType::json())
+            .append_header(auth)
+            .set_payload(
+                r#"{"query": {"sql": "select * from olympics_alias", "from": 0, "size": 10, "start_time": 1714857600000, "end_time": 1714944000000}}"#,
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        for alias in ["olympics_alias2", "olympics_alias"] {
+            let req = test::TestRequest::delete()
+                .uri(&format!(
+                    "/api/{}/streams/{}/alias/{}",
+                    "e2e", "olympics_schema", alias
+                ))
+                .append_header(auth)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200);
+        }
+    }
+
     async fn e2e_delete_stream() {
         let auth = setup();
         let app = test::init_service(
@@ -555,6 +789,103 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    async fn e2e_flight_sql_fetch(
+        client: &mut FlightSqlServiceClient<tonic::transport::Channel>,
+        info: FlightInfo,
+    ) -> Vec<RecordBatch> {
+        let mut batches = Vec::new();
+        for endpoint in info.endpoint {
+            let stream = client.do_get(endpoint.ticket.unwrap()).await.unwrap();
+            batches.extend(stream.try_collect::<Vec<_>>().await.unwrap());
+        }
+        batches
+    }
+
+    async fn e2e_flight_sql() {
+        setup();
+        let cfg = get_config();
+        let channel = tonic::transport::Endpoint::from_shared(format!(
+            "http://127.0.0.1:{}",
+            cfg.grpc.flight_sql_port
+        ))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+        let mut client = FlightSqlServiceClient::new(channel);
+        client.set_header(cfg.grpc.org_header_key.as_str(), "e2e");
+        client
+            .handshake("root@example.com", "Complexpass#123")
+            .await
+            .unwrap();
+        assert!(client.token().is_some());
+
+        let info = client.get_table_types().await.unwrap();
+        let batches = e2e_flight_sql_fetch(&mut client, info).await;
+        let table_types = batches[0]
+            .column_by_name("table_type")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap()
+            .clone();
+        assert_eq!(table_types.value(0), "TABLE");
+
+        let info = client
+            .get_tables(CommandGetTables {
+                catalog: None,
+                db_schema_filter_pattern: None,
+                table_name_filter_pattern: None,
+                table_types: vec![],
+                include_schema: false,
+            })
+            .await
+            .unwrap();
+        let batches = e2e_flight_sql_fetch(&mut client, info).await;
+        let tables = batches
+            .iter()
+            .flat_map(|batch| {
+                let names = batch
+                    .column_by_name("table_name")
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                (0..names.len())
+                    .map(|i| names.value(i).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert!(tables.contains(&"olympics_schema".to_string()));
+
+        // the schema of the flight info is the one of the fetched batches
+        let info = client
+            .execute("SELECT * FROM olympics_schema LIMIT 10".to_string(), None)
+            .await
+            .unwrap();
+        let schema = info.clone().try_decode_schema().unwrap();
+        assert!(schema.field_with_name("Athlete").is_ok());
+        let batches = e2e_flight_sql_fetch(&mut client, info).await;
+        let num_rows = batches.iter().map(|b| b.num_rows()).sum::<usize>();
+        assert!(num_rows > 0 && num_rows <= 10);
+        for batch in batches.iter() {
+            assert_eq!(batch.schema().fields(), schema.fields());
+        }
+
+        // the statements are only run for authenticated users
+        let channel = tonic::transport::Endpoint::from_shared(format!(
+            "http://127.0.0.1:{}",
+            cfg.grpc.flight_sql_port
+        ))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+        let mut client = FlightSqlServiceClient::new(channel);
+        client.set_header(cfg.grpc.org_header_key.as_str(), "e2e");
+        assert!(client.handshake("root@example.com", "wrong").await.is_err());
+    }
+
     async fn e2e_search_around() {
         let auth = setup();
 
@@ -993,6 +1324,92 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    async fn e2e_post_otlp_logs() {
+        let auth = setup();
+
+        let log_rec = LogRecord {
+            time_unix_nano: Utc::now().timestamp_nanos_opt().unwrap() as u64,
+            severity_number: 9,
+            severity_text: "Info".to_string(),
+            body: Some(AnyValue {
+                value: Some(StringValue("This is an otlp log message".to_string())),
+            }),
+            attributes: vec![KeyValue {
+                key: "app".to_string(),
+                value: Some(AnyValue {
+                    value: Some(StringValue("server".to_string())),
+                }),
+            }],
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            ..Default::default()
+        };
+        let ex_req = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(Resource {
+                    attributes: vec![KeyValue {
+                        key: "service.name".to_string(),
+                        value: Some(AnyValue {
+                            value: Some(StringValue("e2e".to_string())),
+                        }),
+                    }],
+                    dropped_attributes_count: 0,
+                }),
+                scope_logs: vec![ScopeLogs {
+                    log_records: vec![log_rec],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let mut out = BytesMut::with_capacity(ex_req.encoded_len());
+        ex_req.encode(&mut out).expect("Out of memory");
+        let body: Bytes = out.into();
+
+        // app
+        let thread_id: usize = 0;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().limit(get_config().limit.req_json_limit))
+                .app_data(web::PayloadConfig::new(
+                    get_config().limit.req_payload_limit,
+                ))
+                .app_data(web::Data::new(thread_id))
+                .configure(get_service_routes)
+                .configure(get_basic_routes),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/{}/v1/logs", "e2e"))
+            .insert_header(("Content-Type", "application/x-protobuf"))
+            .insert_header((get_config().grpc.stream_header_key.as_str(), "otlp_logs"))
+            .append_header(auth)
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let resp = ExportLogsServiceResponse::decode(test::read_body(resp).await).unwrap();
+        assert!(resp.partial_success.is_none());
+
+        // the record is written to the wal and its memtable
+        let batches = ingester::read_from_memtable("e2e", "logs", "otlp_logs", None, &[])
+            .await
+            .unwrap();
+        let (schema, entries) = batches.first().expect("no records in the wal");
+        for field in [
+            "body",
+            "severity",
+            "trace_id",
+            "span_id",
+            "service_name",
+            "app",
+        ] {
+            assert!(schema.field_with_name(field).is_ok(), "missing {field}");
+        }
+        let num_rows: usize = entries.iter().map(|e| e.data.num_rows()).sum();
+        assert_eq!(num_rows, 1);
+    }
+
     async fn e2e_post_metrics() {
         let auth = setup();
 
@@ -0,0 +1,124 @@
+#!/usr/bin/env python3
+"""Writes the small MaxMind DB files used by the geoip ingestion tests.
+
+The records follow the GeoLite2 test databases of
+https://github.com/maxmind/MaxMind-DB/tree/main/test-data
+"""
+import ipaddress
+import os
+import struct
+
+def ctrl(type_, size):
+    out = bytearray()
+    if size < 29:
+        first, ext = size, b""
+    elif size < 285:
+        first, ext = 29, bytes([size - 29])
+    elif size < 65821:
+        first, ext = 30, (size - 285).to_bytes(2, "big")
+    else:
+        first, ext = 31, (size - 65821).to_bytes(3, "big")
+    if type_ <= 7:
+        out.append((type_ << 5) | first)
+    else:
+        out.append(first)
+        out.append(type_ - 7)
+    return bytes(out) + ext
+
+def uint(type_, v):
+    b = v.to_bytes((v.bit_length() + 7) // 8, "big") if v else b""
+    return ctrl(type_, len(b)) + b
+
+def enc(v):
+    if isinstance(v, bool):
+        return ctrl(14, 1 if v else 0)
+    if isinstance(v, str):
+        b = v.encode()
+        return ctrl(2, len(b)) + b
+    if isinstance(v, float):
+        return ctrl(3, 8) + struct.pack(">d", v)
+    if isinstance(v, tuple):  # (type, unsigned integer)
+        return uint(v[0], v[1])
+    if isinstance(v, int):
+        return uint(6, v)
+    if isinstance(v, list):
+        return ctrl(11, len(v)) + b"".join(enc(x) for x in v)
+    if isinstance(v, dict):
+        return ctrl(7, len(v)) + b"".join(enc(k) + enc(x) for k, x in v.items())
+    raise TypeError(v)
+
+def write_db(path, db_type, networks):
+    # networks: [(cidr, record)]
+    data = bytearray()
+    offsets = []
+    for _, record in networks:
+        offsets.append(len(data))
+        data += enc(record)
+    nodes = [[None, None]]
+    for (cidr, _), offset in zip(networks, offsets):
+        net = ipaddress.ip_network(cidr)
+        # ipv4 networks are stored under ::/96
+        addr = int(net.network_address)
+        bits = net.prefixlen + (96 if net.version == 4 else 0)
+        node = 0
+        for i in range(bits):
+            bit = (addr >> (127 - i)) & 1
+            if i == bits - 1:
+                nodes[node][bit] = ("data", offset)
+            else:
+                nxt = nodes[node][bit]
+                if nxt is None:
+                    nodes.append([None, None])
+                    nxt = ("node", len(nodes) - 1)
+                    nodes[node][bit] = nxt
+                node = nxt[1]
+    count = len(nodes)
+    tree = bytearray()
+    for left, right in nodes:
+        for rec in (left, right):
+            if rec is None:
+                v = count
+            elif rec[0] == "node":
+                v = rec[1]
+            else:
+                v = count + 16 + rec[1]
+            tree += v.to_bytes(3, "big")
+    metadata = {
+        "binary_format_major_version": (5, 2),
+        "binary_format_minor_version": (5, 0),
+        "build_epoch": (9, 1700000000),
+        "database_type": db_type,
+        "description": {"en": db_type + " test database for openobserve"},
+        "ip_version": (5, 6),
+        "languages": ["en"],
+        "node_count": (6, count),
+        "record_size": (5, 24),
+    }
+    with open(path, "wb") as f:
+        f.write(tree + bytes(16) + data + b"\xab\xcd\xefMaxMind.com" + enc(metadata))
+
+def names(en):
+    return {"en": en}
+
+here = os.path.dirname(os.path.abspath(__file__))
+write_db(os.path.join(here, "GeoLite2-City-Test.mmdb"), "GeoLite2-City", [
+    ("81.2.69.142/31", {
+        "city": {"geoname_id": 2643743, "names": names("London")},
+        "continent": {"code": "EU", "geoname_id": 6255148, "names": names("Europe")},
+        "country": {"geoname_id": 2635167, "iso_code": "GB", "names": names("United Kingdom")},
+        "location": {"accuracy_radius": (5, 10), "latitude": 51.5142, "longitude": -0.0931,
+                     "time_zone": "Europe/London"},
+    }),
+    ("2001:218::/32", {
+        "continent": {"code": "AS", "geoname_id": 6255147, "names": names("Asia")},
+        "country": {"geoname_id": 1861060, "iso_code": "JP", "names": names("Japan")},
+        "location": {"accuracy_radius": (5, 100), "latitude": 35.68536, "longitude": 139.75309,
+                     "time_zone": "Asia/Tokyo"},
+    }),
+])
+write_db(os.path.join(here, "GeoLite2-ASN-Test.mmdb"), "GeoLite2-ASN", [
+    ("1.128.0.0/11", {"autonomous_system_number": 1221,
+                      "autonomous_system_organization": "Telstra Pty Ltd"}),
+    ("2600:6000::/20", {"autonomous_system_number": 237,
+                        "autonomous_system_organization": "Merit Network Inc."}),
+])